@@ -0,0 +1,50 @@
+#![no_main]
+
+use joycon_sys::{
+    input::{InputReportEnum, SubcommandReplyEnum, UseSPIColors},
+    spi::*,
+    InputReport,
+};
+use libfuzzer_sys::fuzz_target;
+use std::convert::TryFrom;
+
+fn spi_result<S: SPI + std::fmt::Debug>(result: SPIReadResult) {
+    if let Ok(value) = S::try_from(result) {
+        let _ = format!("{:?}", value);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut report = InputReport::new();
+    let raw = report.as_bytes_mut();
+    let len = raw.len().min(data.len());
+    raw[..len].copy_from_slice(&data[..len]);
+
+    let _ = format!("{:?}", report);
+    let _ = report.standard();
+    let _ = report.imu_frames();
+    if let Some(reply) = report.subcmd_reply() {
+        let _ = reply.is_spi_write_success();
+        if let Some(&result) = reply.spi_read_result() {
+            spi_result::<SticksCalibration>(result);
+            spi_result::<UserSticksCalibration>(result);
+            spi_result::<UserStickCalibration<LeftOrder>>(result);
+            spi_result::<UserStickCalibration<RightOrder>>(result);
+            spi_result::<SensorCalibration>(result);
+            spi_result::<UserSensorCalibration>(result);
+            spi_result::<PairingInfo>(result);
+            spi_result::<SerialNumber>(result);
+            spi_result::<ControllerColor>(result);
+            spi_result::<UseSPIColors>(result);
+        }
+        if let Ok(reply) = SubcommandReplyEnum::try_from(*reply) {
+            let _ = format!("{:?}", reply);
+        }
+    }
+    if let Some(mcu) = report.mcu_report() {
+        let _ = format!("{:?}", mcu);
+    }
+    if let Ok(report) = InputReportEnum::try_from(report) {
+        let _ = format!("{:?}", report);
+    }
+});
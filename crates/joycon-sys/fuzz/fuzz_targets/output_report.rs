@@ -0,0 +1,25 @@
+#![no_main]
+
+use joycon_sys::{
+    output::{OutputReportEnum, SubcommandRequestEnum},
+    OutputReport,
+};
+use libfuzzer_sys::fuzz_target;
+use std::convert::TryFrom;
+
+fuzz_target!(|data: &[u8]| {
+    let mut report = OutputReport::new();
+    let raw = report.as_bytes_mut();
+    let len = raw.len().min(data.len());
+    raw[..len].copy_from_slice(&data[..len]);
+
+    let _ = format!("{:?}", report);
+    if let Some(&subcmd) = report.rumble_subcmd() {
+        if let Ok(subcmd) = SubcommandRequestEnum::try_from(subcmd) {
+            let _ = format!("{:?}", subcmd);
+        }
+    }
+    if let Ok(report) = OutputReportEnum::try_from(report) {
+        let _ = format!("{:?}", report);
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use joycon_sys::input::ReportBuffer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut buffer = ReportBuffer::new();
+    // Feed in two chunks to exercise the reports split across reads.
+    let (first, second) = data.split_at(data.len() / 2);
+    for chunk in [first, second].iter() {
+        for report in buffer.feed(chunk) {
+            let _ = format!("{:?}", report);
+        }
+    }
+});
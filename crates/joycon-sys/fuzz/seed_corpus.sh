@@ -0,0 +1,24 @@
+#!/bin/sh
+# Build the corpus from the Bluetooth captures in trace/, see trace/README.md.
+set -e
+cd "$(dirname "$0")"
+mkdir -p corpus/input_report corpus/output_report corpus/report_stream
+
+# Write each line of hex from stdin into its own file in the directory $1.
+split_reports() {
+    while read -r hex; do
+        echo "$hex" | xxd -r -p > "$1/$(echo "$hex" | sha1sum | cut -c1-40)"
+    done
+}
+
+tmp=$(mktemp -d)
+for log in ../../../trace/*.log; do
+    # Skip the HID transaction byte, 0xa1 for input and 0xa2 for output.
+    grep '^>' "$log" | cut -d' ' -f3 | cut -c3- > "$tmp/input"
+    grep '^<' "$log" | cut -d' ' -f3 | cut -c3- > "$tmp/output"
+    # The standard input reports are most of the captures, keep the others.
+    grep -v '^30' "$tmp/input" | sort -u | head -n 200 | split_reports corpus/input_report
+    sort -u "$tmp/output" | head -n 200 | split_reports corpus/output_report
+    head -n 64 "$tmp/input" | xxd -r -p > "corpus/report_stream/$(basename "$log" .log)"
+done
+rm -r "$tmp"
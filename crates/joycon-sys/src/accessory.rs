@@ -1,6 +1,14 @@
-use std::fmt;
+use std::{collections::VecDeque, fmt, time::Duration};
 
-use crate::{RawId, U16LE};
+use crate::{
+    clock::Timestamp,
+    external::{ExternalDevice, ExternalDeviceType},
+    input::DeviceType,
+    mcu::{MCUConfig, MCUMode},
+    output::{SubcommandRequest, SubcommandRequestEnum},
+    poll::REPLY_TIMEOUT,
+    InputReport, OutputReport, RawId, Wire, U16LE,
+};
 
 // subcommand id 0x58
 //
@@ -39,6 +47,7 @@ pub enum RingconItemId {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug)]
 #[allow(dead_code)]
 pub struct AccessoryCommand {
@@ -49,6 +58,8 @@ pub struct AccessoryCommand {
     maybe_arg_size: u8,
     raw: [u8; 18],
 }
+
+unsafe impl Wire for AccessoryCommand {}
 impl AccessoryCommand {
     pub fn get_offline_steps() -> Self {
         AccessoryCommand {
@@ -91,6 +102,7 @@ impl AccessoryCommand {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 pub struct AccessoryResponse {
     //254: nothing connected
@@ -100,6 +112,8 @@ pub struct AccessoryResponse {
     u: AccessoryResponseUnion,
 }
 
+unsafe impl Wire for AccessoryResponse {}
+
 impl AccessoryResponse {
     fn check_error(&self) -> Result<(), Error> {
         match self.error {
@@ -109,6 +123,15 @@ impl AccessoryResponse {
         }
     }
 
+    /// Whether an accessory answered, `None` for an unknown error.
+    pub fn is_connected(&self) -> Option<bool> {
+        match self.check_error() {
+            Ok(()) => Some(true),
+            Err(Error::NoAccessoryConnected) => Some(false),
+            Err(Error::Other(_)) => None,
+        }
+    }
+
     pub fn offline_steps(&self) -> Result<OfflineSteps, Error> {
         self.check_error()?;
         Ok(unsafe { self.u.offline_steps })
@@ -132,6 +155,7 @@ union AccessoryResponseUnion {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug)]
 #[allow(dead_code)]
 pub struct OfflineSteps {
@@ -140,6 +164,18 @@ pub struct OfflineSteps {
     maybe_crc: u8,
 }
 
+unsafe impl Wire for OfflineSteps {}
+
+impl OfflineSteps {
+    pub fn steps(&self) -> u16 {
+        self.steps.into()
+    }
+
+    pub fn set_steps(&mut self, steps: u16) {
+        self.steps = steps.into();
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Error {
     NoAccessoryConnected,
@@ -156,3 +192,257 @@ impl fmt::Display for Error {
         }
     }
 }
+
+/// Default delay between two accessory probes.
+pub const ACCESSORY_PROBE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Subcommands enabling the ringcon, as sent by the Switch.
+///
+/// The MCU can take a few tries to switch to the ringcon mode: the state in
+/// the reply to the second subcommand should be checked, and the subcommand
+/// sent again until it is `MCUMode::MaybeRingcon`.
+/// [`ExternalDevice::ringcon`] sends them that way.
+pub fn ringcon_bring_up() -> Vec<SubcommandRequest> {
+    let mut requests = vec![
+        SubcommandRequestEnum::SetMCUState(MCUMode::Standby.into()).into(),
+        MCUConfig::Mode(MCUMode::MaybeRingcon).into(),
+        MCUConfig::Ringcon { enable: true }.into(),
+        SubcommandRequest::subcmd_0x59(),
+    ];
+    requests.extend(ExternalDeviceType::Ringcon.configure());
+    requests.push(SubcommandRequest::subcmd_0x5a());
+    requests
+}
+
+/// Subcommands disabling the ringcon, the reverse of [`ringcon_bring_up`].
+pub fn ringcon_shutdown() -> Vec<SubcommandRequest> {
+    let mut requests = vec![SubcommandRequest::subcmd_0x5b()];
+    requests.extend(ExternalDeviceType::Ringcon.unconfigure());
+    requests.push(MCUConfig::Ringcon { enable: false }.into());
+    requests
+}
+
+/// An accessory was plugged or unplugged.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AttachmentChanged {
+    pub attached: bool,
+}
+
+impl AttachmentChanged {
+    /// Subcommands adapting the controller to the change.
+    pub fn subcommands(&self) -> Vec<SubcommandRequest> {
+        if self.attached {
+            ringcon_bring_up()
+        } else {
+            ringcon_shutdown()
+        }
+    }
+}
+
+/// Follows the attachment of the ringcon during a session.
+///
+/// The device type in the input reports changes when an accessory is
+/// plugged, and the accessory subcommand fails when none is. Both are
+/// watched, probing with the subcommand periodically, the probe reply taking
+/// precedence. When the ringcon is plugged, it is brought up by an
+/// [`ExternalDevice`] checking each reply; when unplugged, the subcommands of
+/// [`ringcon_shutdown`] are sent. These subcommands are sent before the next
+/// probes, one at a time:
+///
+/// ```ignore
+/// let mut watch = AccessoryWatch::new(ACCESSORY_PROBE_INTERVAL);
+/// loop {
+///     if let Some(mut report) = watch.poll(clock.now()) {
+///         send(&mut report)?;
+///     }
+///     let report = recv()?;
+///     if let Some(change) = watch.handle(&report) {
+///         println!("ringcon attached: {}", change.attached);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AccessoryWatch {
+    interval: Duration,
+    attached: Option<bool>,
+    device: Option<ExternalDevice>,
+    pending: VecDeque<SubcommandRequest>,
+    last_probe: Option<Timestamp>,
+    waiting_reply: Option<Timestamp>,
+}
+
+impl AccessoryWatch {
+    pub fn new(interval: Duration) -> AccessoryWatch {
+        AccessoryWatch {
+            interval,
+            attached: None,
+            device: None,
+            pending: VecDeque::new(),
+            last_probe: None,
+            waiting_reply: None,
+        }
+    }
+
+    /// `None` until the first probe reply or accessory report.
+    pub fn is_attached(&self) -> Option<bool> {
+        self.attached
+    }
+
+    /// Bring-up of the plugged ringcon, eg. to check it succeeded.
+    pub fn device(&self) -> Option<&ExternalDevice> {
+        self.device.as_ref()
+    }
+
+    /// The next subcommand to send, if one is due at `now`.
+    pub fn poll(&mut self, now: Timestamp) -> Option<OutputReport> {
+        if let Some(sent) = self.waiting_reply {
+            if now.saturating_duration_since(sent) < REPLY_TIMEOUT {
+                return None;
+            }
+        }
+        // Sent again after a timeout.
+        let bring_up = self.device.as_ref().and_then(|d| d.next_request());
+        let subcmd = match bring_up.or_else(|| self.pending.pop_front()) {
+            Some(subcmd) => subcmd,
+            None => {
+                if let Some(last) = self.last_probe {
+                    if now.saturating_duration_since(last) < self.interval {
+                        return None;
+                    }
+                }
+                self.last_probe = Some(now);
+                AccessoryCommand::get_offline_steps().into()
+            }
+        };
+        self.waiting_reply = Some(now);
+        Some(subcmd.into())
+    }
+
+    /// Look for an attachment change in `report`.
+    ///
+    /// The first state seen is reported as a change too, so the bring-up is
+    /// done for an accessory already plugged.
+    pub fn handle(&mut self, report: &InputReport) -> Option<AttachmentChanged> {
+        let mut attached = None;
+        if let Some(reply) = report.subcmd_reply() {
+            self.waiting_reply = None;
+            attached = reply.maybe_accessory().and_then(|r| r.is_connected());
+            if let Some(device) = &mut self.device {
+                if let Err(e) = device.push(report) {
+                    trace!("ringcon bring-up failed: {:?}", e);
+                }
+            }
+        }
+        if let (None, Some(std)) = (attached, report.standard()) {
+            match std.info.device_type() {
+                DeviceType::MaybeAccessory | DeviceType::MaybeInitializingAccessory => {
+                    attached = Some(true)
+                }
+                // Only the probe tells when a JoyCon has no accessory.
+                _ => {}
+            }
+        }
+        let attached = attached?;
+        if self.attached == Some(attached) || (self.attached.is_none() && !attached) {
+            self.attached = Some(attached);
+            return None;
+        }
+        self.attached = Some(attached);
+        let change = AttachmentChanged { attached };
+        trace!("accessory attachment: {:?}", change);
+        if attached {
+            self.device = Some(ExternalDevice::ringcon());
+            self.pending.clear();
+        } else {
+            self.device = None;
+            self.pending = ringcon_shutdown().into();
+        }
+        Some(change)
+    }
+}
+
+impl Default for AccessoryWatch {
+    fn default() -> Self {
+        AccessoryWatch::new(ACCESSORY_PROBE_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn accessory_hot_plug() {
+    use crate::{common::SubcommandId, external::ExternalDeviceState, InputReportId};
+
+    let reply = |id: u8, ack: u8, data: &[u8]| {
+        let mut reply = InputReport::new();
+        let raw = reply.as_bytes_mut();
+        raw[0] = InputReportId::StandardAndSubcmd as u8;
+        // JoyCon
+        raw[2] = 0x6;
+        raw[13] = ack;
+        raw[14] = id;
+        raw[15..15 + data.len()].copy_from_slice(data);
+        reply
+    };
+    let probe_reply = |error: u8| reply(SubcommandId::MaybeAccessory as u8, 0xd8, &[error]);
+
+    let start = Timestamp::from_micros(0);
+    let mut watch = AccessoryWatch::default();
+    let probe = watch.poll(start).unwrap();
+    assert_eq!(probe.as_bytes()[10], 0x58);
+    assert!(watch.poll(start + Duration::from_millis(50)).is_none());
+    // Nothing plugged at start, nothing to do.
+    assert_eq!(watch.handle(&probe_reply(254)), None);
+    assert_eq!(watch.is_attached(), Some(false));
+    assert!(watch.poll(start + Duration::from_secs(1)).is_none());
+
+    let probe = watch.poll(start + ACCESSORY_PROBE_INTERVAL).unwrap();
+    assert_eq!(probe.as_bytes()[10], 0x58);
+    assert_eq!(
+        watch.handle(&probe_reply(0)),
+        Some(AttachmentChanged { attached: true })
+    );
+    let mut now = start + ACCESSORY_PROBE_INTERVAL;
+    let mut sent = Vec::new();
+    let mut mcu_replies = 0;
+    while watch.device().unwrap().next_request().is_some() {
+        now = now + Duration::from_millis(5);
+        let id = watch.poll(now).unwrap().as_bytes()[10];
+        sent.push(id);
+        let report = match id {
+            0x21 => {
+                // The MCU switches to the ringcon mode on the second try.
+                mcu_replies += 1;
+                let state = if mcu_replies == 1 { 1 } else { 3 };
+                reply(id, 0xa0, &[0x01, 0, 0, 0, 0, 0, 0, state])
+            }
+            0x59 => reply(id, 0xd9, &[0, 0x20]),
+            _ => reply(id, 0x80, &[]),
+        };
+        watch.handle(&report);
+    }
+    assert_eq!(sent, [0x22, 0x21, 0x21, 0x21, 0x59, 0x40, 0x5c, 0x5a]);
+    assert_eq!(
+        watch.device().unwrap().state(),
+        ExternalDeviceState::Attached
+    );
+
+    // The device type alone doesn't tell when the accessory is unplugged.
+    let mut report = InputReport::new();
+    report.as_bytes_mut()[0] = InputReportId::StandardFull as u8;
+    report.as_bytes_mut()[2] = 0x2;
+    assert_eq!(watch.handle(&report), None);
+    // The probe reply takes precedence over the device type.
+    let mut report = probe_reply(254);
+    report.as_bytes_mut()[2] = 0x2;
+    assert_eq!(
+        watch.handle(&report),
+        Some(AttachmentChanged { attached: false })
+    );
+    assert_eq!(
+        watch
+            .poll(now + Duration::from_millis(5))
+            .unwrap()
+            .as_bytes()[10],
+        0x5b
+    );
+}
@@ -0,0 +1,91 @@
+//! Decoding of the standard input reports shared by the C, Python and
+//! JavaScript bindings, each exposing [`FlatInputState`] in its own types.
+
+use crate::{
+    input::{BatteryLevel, ConnectionInfo},
+    state::ButtonSet,
+    InputReport,
+};
+
+/// Standard input report flattened to plain values.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct FlatInputState {
+    pub report_id: u8,
+    pub timer: u8,
+    pub battery_level: BatteryLevel,
+    pub charging: bool,
+    pub connection: ConnectionInfo,
+    pub buttons: ButtonSet,
+    /// Raw 12-bit stick values, to be calibrated.
+    pub left_stick: (u16, u16),
+    pub right_stick: (u16, u16),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum FlatInputError {
+    /// The data isn't a valid report.
+    Invalid,
+    /// The report doesn't contain the standard input data.
+    NotStandard,
+}
+
+impl FlatInputState {
+    /// Parse a raw input report, report id included.
+    pub fn parse(data: &[u8]) -> Result<FlatInputState, FlatInputError> {
+        let report = InputReport::from_slice(data).ok_or(FlatInputError::Invalid)?;
+        let std = report.standard().ok_or(FlatInputError::NotStandard)?;
+        let (left_stick, right_stick, info) = (std.left_stick, std.right_stick, std.info);
+        Ok(FlatInputState {
+            report_id: data[0],
+            timer: std.timer,
+            battery_level: info.battery_level(),
+            charging: info.charging(),
+            connection: info.connection_info(),
+            buttons: ButtonSet::from(std.buttons),
+            left_stick: (left_stick.x(), left_stick.y()),
+            right_stick: (right_stick.x(), right_stick.y()),
+        })
+    }
+
+    /// 0 (empty) to 4 (full).
+    #[cfg(any(feature = "ffi", feature = "wasm-bindgen"))]
+    pub fn battery_code(&self) -> u8 {
+        match self.battery_level {
+            BatteryLevel::Empty => 0,
+            BatteryLevel::Critical => 1,
+            BatteryLevel::Low => 2,
+            BatteryLevel::Medium => 3,
+            BatteryLevel::Full => 4,
+        }
+    }
+
+    #[cfg(feature = "pyo3")]
+    pub fn battery_name(&self) -> &'static str {
+        match self.battery_level {
+            BatteryLevel::Empty => "empty",
+            BatteryLevel::Critical => "critical",
+            BatteryLevel::Low => "low",
+            BatteryLevel::Medium => "medium",
+            BatteryLevel::Full => "full",
+        }
+    }
+
+    /// 0: Bluetooth, 1: USB, 2: attached to the console rail.
+    #[cfg(any(feature = "ffi", feature = "wasm-bindgen"))]
+    pub fn connection_code(&self) -> u8 {
+        match self.connection {
+            ConnectionInfo::Bluetooth => 0,
+            ConnectionInfo::Usb => 1,
+            ConnectionInfo::RailAttached => 2,
+        }
+    }
+
+    #[cfg(feature = "pyo3")]
+    pub fn connection_name(&self) -> &'static str {
+        match self.connection {
+            ConnectionInfo::Bluetooth => "bluetooth",
+            ConnectionInfo::Usb => "usb",
+            ConnectionInfo::RailAttached => "rail",
+        }
+    }
+}
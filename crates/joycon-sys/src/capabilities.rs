@@ -0,0 +1,101 @@
+//! Subcommands supported by each firmware version.
+//!
+//! Older firmwares don't reply to the subcommands added later, so a driver
+//! sending them waits for a timeout. Checking the firmware version of the
+//! device info first avoids it:
+//!
+//! ```ignore
+//! let capabilities = Capabilities::for_firmware(device_info.firmware_version);
+//! if capabilities.supports(SubcommandId::SetUnknownData) {
+//!     send_subcommand(request)?;
+//! }
+//! ```
+
+use crate::{
+    common::SubcommandId,
+    input::FirmwareVersion,
+    output::{SubcommandRequest, SubcommandRequestEnum},
+};
+
+/// First firmware version replying to the subcommands added after the
+/// launch firmware, as documented by dekuNukem's subcommand notes.
+///
+/// The versions are BCD, firmware 3.86 reporting `[0x03, 0x86]`. The
+/// subcommands not listed are assumed to be supported by every firmware.
+pub const SUBCOMMAND_MIN_FIRMWARE: &[(SubcommandId, FirmwareVersion)] = &[
+    // "Set unknown data (fw 3.86 and up)".
+    (SubcommandId::SetUnknownData, FirmwareVersion([0x03, 0x86])),
+];
+
+/// What a controller supports given its firmware version.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Capabilities {
+    firmware: FirmwareVersion,
+}
+
+impl Capabilities {
+    pub fn for_firmware(firmware: FirmwareVersion) -> Capabilities {
+        Capabilities { firmware }
+    }
+
+    pub fn firmware(&self) -> FirmwareVersion {
+        self.firmware
+    }
+
+    /// First firmware version supporting `id`, `None` if every version does.
+    pub fn min_firmware(id: SubcommandId) -> Option<FirmwareVersion> {
+        SUBCOMMAND_MIN_FIRMWARE
+            .iter()
+            .find(|(known, _)| *known == id)
+            .map(|(_, version)| *version)
+    }
+
+    pub fn supports(&self, id: SubcommandId) -> bool {
+        match Capabilities::min_firmware(id) {
+            Some(min) => self.firmware >= min,
+            None => true,
+        }
+    }
+
+    /// Whether the controller will reply to `request`, `false` for unknown
+    /// subcommands.
+    pub fn supports_request(&self, request: &SubcommandRequest) -> bool {
+        match request.id().try_into() {
+            Some(id) => self.supports(id),
+            None => false,
+        }
+    }
+
+    /// Every known subcommand supported, by id.
+    pub fn supported(self) -> impl Iterator<Item = SubcommandId> {
+        SubcommandRequestEnum::VARIANTS
+            .iter()
+            .cloned()
+            .filter(move |id| self.supports(*id))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn capabilities_by_firmware() {
+    let launch = Capabilities::for_firmware(FirmwareVersion([0x03, 0x48]));
+    assert!(launch.supports(SubcommandId::SPIRead));
+    assert!(!launch.supports(SubcommandId::SetUnknownData));
+    assert!(launch.supports_request(&crate::common::HCIState::Disconnect.into()));
+    assert_eq!(
+        launch.supported().count(),
+        SubcommandRequestEnum::VARIANTS.len() - SUBCOMMAND_MIN_FIRMWARE.len()
+    );
+
+    let recent = Capabilities::for_firmware(FirmwareVersion([0x04, 0x07]));
+    assert!(recent.supports(SubcommandId::SetUnknownData));
+    assert_eq!(
+        recent.supported().count(),
+        SubcommandRequestEnum::VARIANTS.len()
+    );
+    // BCD: 3.60 is older than 3.86.
+    assert!(!Capabilities::for_firmware(FirmwareVersion([0x03, 0x60]))
+        .supports(SubcommandId::SetUnknownData));
+    assert!(Capabilities::for_firmware(FirmwareVersion([0x03, 0x86]))
+        .supports(SubcommandId::SetUnknownData));
+}
@@ -0,0 +1,272 @@
+//! Reports extracted from Bluetooth captures.
+//!
+//! [`BtsnoopReader`] reads the btsnoop logs written by Android (the HCI snoop
+//! log) and by Wireshark, and yields the reports exchanged with the
+//! controllers, to analyze existing captures with the decoders of this crate:
+//!
+//! ```ignore
+//! for captured in BtsnoopReader::new(File::open("btsnoop_hci.log")?)? {
+//!     let captured = captured?;
+//!     if let CapturedReport::Input(report) = captured.report {
+//!         println!("{:?} {:?}", captured.timestamp, report.subcmd_reply());
+//!     }
+//! }
+//! ```
+//!
+//! The vendor id is only exchanged over SDP, so the HID interrupt payloads are
+//! recognized by their report id and length instead: the payloads of other
+//! HID devices using the same ids are yielded too.
+
+use crate::{
+    clock::Timestamp,
+    input::{InputReport, HIDP_INPUT_HEADER},
+    output::{OutputReport, HIDP_OUTPUT_HEADER},
+};
+use std::{collections::HashMap, fmt, io};
+
+const MAGIC: &[u8; 8] = b"btsnoop\0";
+/// HCI packets without type byte, the type given by the record flags.
+const DATALINK_HCI: u32 = 1001;
+/// HCI packets prefixed by their UART (H4) type byte.
+const DATALINK_H4: u32 = 1002;
+const H4_ACL: u8 = 0x02;
+/// Record flag set for commands and events, unset for data packets.
+const FLAG_COMMAND_EVENT: u32 = 1 << 1;
+/// ACL packet boundary flag of the continuing fragments.
+const ACL_CONTINUATION: u16 = 0b01;
+/// Largest record accepted, an HCI packet never reaching it.
+const MAX_RECORD_LEN: u32 = 0x10000;
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(io::Error),
+    /// Not a btsnoop file.
+    InvalidHeader,
+    UnsupportedDatalink(u32),
+    /// Record longer than [`MAX_RECORD_LEN`] or than the original packet.
+    InvalidRecord(u32),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Io(e) => write!(f, "capture read error: {}", e),
+            CaptureError::InvalidHeader => f.write_str("not a btsnoop capture"),
+            CaptureError::UnsupportedDatalink(datalink) => {
+                write!(f, "unsupported btsnoop datalink type {}", datalink)
+            }
+            CaptureError::InvalidRecord(len) => {
+                write!(f, "invalid btsnoop record of {} bytes", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CaptureError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CaptureError {
+    fn from(e: io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Copy, Clone, Debug)]
+pub enum CapturedReport {
+    Input(InputReport),
+    Output(OutputReport),
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Captured {
+    /// Capture time, in microseconds since the year 0.
+    pub timestamp: Timestamp,
+    /// ACL connection the report was exchanged on, one per controller.
+    pub handle: u16,
+    pub report: CapturedReport,
+}
+
+/// Iterator over the reports of a btsnoop capture.
+pub struct BtsnoopReader<R> {
+    reader: R,
+    datalink: u32,
+    /// L2CAP frames split across several ACL packets, by connection handle.
+    fragments: HashMap<u16, Vec<u8>>,
+}
+
+impl<R: io::Read> BtsnoopReader<R> {
+    /// Check the file header.
+    pub fn new(mut reader: R) -> Result<BtsnoopReader<R>, CaptureError> {
+        let mut header = [0; 16];
+        reader.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(CaptureError::InvalidHeader);
+        }
+        let datalink = be_u32(&header[12..]);
+        if datalink != DATALINK_HCI && datalink != DATALINK_H4 {
+            return Err(CaptureError::UnsupportedDatalink(datalink));
+        }
+        Ok(BtsnoopReader {
+            reader,
+            datalink,
+            fragments: HashMap::new(),
+        })
+    }
+
+    /// Next record, `None` at the end of the file.
+    fn next_record(&mut self) -> Result<Option<(u32, u64, Vec<u8>)>, CaptureError> {
+        let mut header = [0; 24];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let original_len = be_u32(&header[0..]);
+        let included_len = be_u32(&header[4..]);
+        if included_len > MAX_RECORD_LEN || included_len > original_len {
+            return Err(CaptureError::InvalidRecord(included_len));
+        }
+        let flags = be_u32(&header[8..]);
+        let timestamp = u64::from(be_u32(&header[16..])) << 32 | u64::from(be_u32(&header[20..]));
+        let mut data = vec![0; included_len as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some((flags, timestamp, data)))
+    }
+
+    /// Reassemble the L2CAP frame of an ACL packet, once complete.
+    fn l2cap_frame(&mut self, acl: &[u8]) -> Option<(u16, Vec<u8>)> {
+        let header = u16::from_le_bytes([*acl.first()?, *acl.get(1)?]);
+        let handle = header & 0x0fff;
+        let data = acl.get(4..)?;
+        let frame = if header >> 12 & 0b11 == ACL_CONTINUATION {
+            let frame = self.fragments.get_mut(&handle)?;
+            frame.extend_from_slice(data);
+            frame
+        } else {
+            self.fragments.insert(handle, data.to_vec());
+            self.fragments.get_mut(&handle)?
+        };
+        let len = u16::from_le_bytes([*frame.first()?, *frame.get(1)?]) as usize;
+        if frame.len() < 4 + len {
+            return None;
+        }
+        let mut frame = self.fragments.remove(&handle)?;
+        frame.truncate(4 + len);
+        Some((handle, frame.split_off(4)))
+    }
+}
+
+impl<R: io::Read> Iterator for BtsnoopReader<R> {
+    type Item = Result<Captured, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (flags, timestamp, data) = match self.next_record() {
+                Ok(record) => record?,
+                Err(e) => return Some(Err(e)),
+            };
+            let acl = match self.datalink {
+                DATALINK_H4 if data.first() == Some(&H4_ACL) => &data[1..],
+                DATALINK_HCI if flags & FLAG_COMMAND_EVENT == 0 => &data[..],
+                _ => continue,
+            };
+            let (handle, payload) = match self.l2cap_frame(acl) {
+                Some(frame) => frame,
+                None => continue,
+            };
+            let report = match payload.split_first() {
+                Some((&HIDP_INPUT_HEADER, report)) => {
+                    InputReport::from_slice(report).map(CapturedReport::Input)
+                }
+                Some((&HIDP_OUTPUT_HEADER, report)) => {
+                    OutputReport::from_slice(report).map(CapturedReport::Output)
+                }
+                _ => None,
+            };
+            if let Some(report) = report {
+                return Some(Ok(Captured {
+                    timestamp: Timestamp::from_micros(timestamp),
+                    handle,
+                    report,
+                }));
+            }
+        }
+    }
+}
+
+impl<R> fmt::Debug for BtsnoopReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BtsnoopReader")
+            .field("datalink", &self.datalink)
+            .finish()
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+#[test]
+fn btsnoop_reader() {
+    fn record(out: &mut Vec<u8>, flags: u32, data: &[u8]) {
+        for field in &[data.len() as u32, data.len() as u32, flags, 0, 0, 42] {
+            out.extend_from_slice(&field.to_be_bytes());
+        }
+        out.extend_from_slice(data);
+    }
+    fn acl(handle: u16, first: bool, data: &[u8]) -> Vec<u8> {
+        let flags = if first { 0x2000 } else { 0x1000 };
+        let mut out = vec![H4_ACL];
+        out.extend_from_slice(&(handle | flags).to_le_bytes());
+        out.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    let mut l2cap = vec![13, 0, 0x41, 0, 0xa1, 0x3f, 0x00, 0x00, 0x08];
+    l2cap.extend_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80]);
+    let mut capture = b"btsnoop\0\0\0\0\x01\0\0\x03\xea".to_vec();
+    // HCI event, ignored.
+    record(
+        &mut capture,
+        3,
+        &[0x04, 0x13, 0x05, 0x01, 0x0b, 0x00, 0x01, 0x00],
+    );
+    record(&mut capture, 1, &acl(0x0b, true, &l2cap[..6]));
+    record(&mut capture, 1, &acl(0x0b, false, &l2cap[6..]));
+    let rumble = [
+        11, 0, 0x41, 0, 0xa2, 0x10, 0x01, 0, 1, 0x40, 0x40, 0, 1, 0x40, 0x40,
+    ];
+    record(&mut capture, 0, &acl(0x0b, true, &rumble[..]));
+
+    let captured: Vec<_> = BtsnoopReader::new(&capture[..])
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(captured.len(), 2);
+    assert_eq!(captured[0].handle, 0x0b);
+    assert_eq!(captured[0].timestamp, Timestamp::from_micros(42));
+    match captured[0].report {
+        CapturedReport::Input(report) => assert_eq!(report.as_bytes()[3], 0x08),
+        report => panic!("unexpected {:?}", report),
+    }
+    assert!(matches!(captured[1].report, CapturedReport::Output(_)));
+    assert!(BtsnoopReader::new(&b"btsnoop\0\0\0\0\x01\0\0\0\x01"[..]).is_err());
+
+    let mut corrupt = b"btsnoop\0\0\0\0\x01\0\0\x03\xea".to_vec();
+    for field in &[0x10u32, 0xffff_fff0, 0, 0, 0, 0] {
+        corrupt.extend_from_slice(&field.to_be_bytes());
+    }
+    assert!(matches!(
+        BtsnoopReader::new(&corrupt[..]).unwrap().next(),
+        Some(Err(CaptureError::InvalidRecord(0xffff_fff0)))
+    ));
+}
@@ -0,0 +1,127 @@
+//! Host clock abstraction for the timing helpers.
+//!
+//! The sans-io helpers, eg. [`LatencyTracker`](../link/struct.LatencyTracker.html)
+//! or [`RateGovernor`](../output/struct.RateGovernor.html), take the current
+//! [`Timestamp`] as argument instead of reading a clock themselves. A
+//! [`Clock`] provides it:
+//!
+//! ```ignore
+//! let clock = StdClock::new();
+//! governor.sent(kind, clock.now());
+//!
+//! // On a microcontroller, from a free-running 1 MHz timer.
+//! let clock = || timer.count_u64();
+//! governor.sent(kind, clock.now());
+//! ```
+
+use std::{
+    ops::{Add, Sub},
+    time::Duration,
+};
+
+/// Point in time, in microseconds since an arbitrary origin.
+///
+/// Only timestamps from the same [`Clock`] can be compared.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub const fn from_micros(micros: u64) -> Timestamp {
+        Timestamp(micros)
+    }
+
+    pub const fn as_micros(self) -> u64 {
+        self.0
+    }
+
+    /// Time elapsed since `earlier`, or zero if `earlier` is later.
+    pub fn saturating_duration_since(self, earlier: Timestamp) -> Duration {
+        Duration::from_micros(self.0.saturating_sub(earlier.0))
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, duration: Duration) -> Timestamp {
+        Timestamp(self.0 + duration.as_micros() as u64)
+    }
+}
+
+/// Saturates at the origin of the clock, a timestamp shortly after it
+/// minus a timeout being common.
+impl Sub<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, duration: Duration) -> Timestamp {
+        Timestamp(self.0.saturating_sub(duration.as_micros() as u64))
+    }
+}
+
+/// Monotonic clock.
+pub trait Clock {
+    fn now(&self) -> Timestamp;
+}
+
+/// Closure returning monotonic microseconds, eg. read from a hardware timer.
+impl<F: Fn() -> u64> Clock for F {
+    fn now(&self) -> Timestamp {
+        Timestamp(self())
+    }
+}
+
+/// Clock based on [`std::time::Instant`], counting from its creation.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug)]
+pub struct StdClock {
+    origin: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+    pub fn new() -> StdClock {
+        StdClock {
+            origin: std::time::Instant::now(),
+        }
+    }
+
+    /// Timestamp of `instant`, which must not be before the creation of the
+    /// clock.
+    pub fn timestamp(&self, instant: std::time::Instant) -> Timestamp {
+        Timestamp(instant.saturating_duration_since(self.origin).as_micros() as u64)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        StdClock::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now(&self) -> Timestamp {
+        self.timestamp(std::time::Instant::now())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn timestamps() {
+    let start = Timestamp::from_micros(1_000);
+    let later = start + Duration::from_millis(2);
+    assert_eq!(later.as_micros(), 3_000);
+    assert_eq!(
+        later.saturating_duration_since(start),
+        Duration::from_millis(2)
+    );
+    assert_eq!(
+        start.saturating_duration_since(later),
+        Duration::from_secs(0)
+    );
+    assert_eq!(later - Duration::from_millis(2), start);
+    assert_eq!(start - Duration::from_secs(1), Timestamp::from_micros(0));
+    let clock = || 42;
+    assert_eq!(clock.now(), Timestamp::from_micros(42));
+}
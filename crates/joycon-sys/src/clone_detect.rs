@@ -0,0 +1,214 @@
+//! Heuristics telling apart clone controllers from official ones.
+//!
+//! Clones misreport their device info and mishandle some subcommands, which
+//! explains why some features fail with them. [`ClonerDetect`] sends a short
+//! probe sequence and scores the answers:
+//!
+//! ```ignore
+//! let mut detect = ClonerDetect::new();
+//! for report in ClonerDetect::probes() {
+//!     detect.sent(&report, clock.now());
+//!     send(&report)?;
+//!     detect.received(&recv_reply()?, clock.now());
+//! }
+//! detect.check_timeouts(clock.now());
+//! if detect.score() > 0.5 {
+//!     warn!("probably a clone: {:?}", detect.hints());
+//! }
+//! ```
+
+use crate::{
+    clock::Timestamp,
+    common::SubcommandId,
+    imu::{RegistersRead, LSM6DS3_ID, REG_WHO_AM_I},
+    input::FirmwareVersion,
+    output::{SubcommandRequest, SubcommandRequestEnum},
+    spi::{SPIReadRequest, SerialNumber, SPI},
+    InputReport, OutputReport,
+};
+use std::{convert::TryFrom, time::Duration};
+
+/// Replies faster than this are suspicious, since the Bluetooth connection
+/// interval of official controllers is longer.
+pub const INSTANT_REPLY: Duration = Duration::from_millis(2);
+/// Probes unanswered after this are counted as unanswered.
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Weight of the timing check in the score.
+const TIMING_WEIGHT: u32 = 1;
+
+/// Answer to a probe not matching an official controller.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CloneHint {
+    /// Official firmwares are 3.x or 4.x.
+    UnexpectedFirmware(FirmwareVersion),
+    MissingSerialNumber,
+    /// The IMU isn't an LSM6DS3.
+    UnknownImu(u8),
+    Nack(SubcommandId),
+    NoReply(SubcommandId),
+    InstantReply(Duration),
+}
+
+impl CloneHint {
+    fn weight(self) -> u32 {
+        match self {
+            CloneHint::UnexpectedFirmware(_) => 1,
+            CloneHint::MissingSerialNumber => 1,
+            CloneHint::UnknownImu(_) => 2,
+            CloneHint::Nack(id) | CloneHint::NoReply(id) => probe_weight(id),
+            CloneHint::InstantReply(_) => TIMING_WEIGHT,
+        }
+    }
+}
+
+fn probe_weight(id: SubcommandId) -> u32 {
+    match id {
+        SubcommandId::ReadIMURegisters => 2,
+        _ => 1,
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ClonerDetect {
+    pending: Vec<(SubcommandId, Timestamp)>,
+    hints: Vec<CloneHint>,
+    checked_weight: u32,
+    fastest_reply: Option<Duration>,
+}
+
+impl ClonerDetect {
+    pub fn new() -> ClonerDetect {
+        ClonerDetect::default()
+    }
+
+    /// Reports to send, one after the other, each after the reply to the
+    /// previous one.
+    pub fn probes() -> [OutputReport; 3] {
+        let device_info: SubcommandRequest = SubcommandRequestEnum::RequestDeviceInfo(()).into();
+        let serial: SubcommandRequest = SPIReadRequest::new(SerialNumber::range()).into();
+        let imu: SubcommandRequest = RegistersRead {
+            address: REG_WHO_AM_I,
+            count: 1,
+        }
+        .into();
+        [device_info.into(), serial.into(), imu.into()]
+    }
+
+    /// Record that `report` is sent at `now`.
+    pub fn sent(&mut self, report: &OutputReport, now: Timestamp) {
+        if let Some(id) = report.rumble_subcmd().and_then(|s| s.id().try_into()) {
+            self.pending.push((id, now));
+        }
+    }
+
+    /// Check a report received at `now`, ignoring the ones not replying to
+    /// a probe.
+    pub fn received(&mut self, report: &InputReport, now: Timestamp) {
+        let reply = match report.subcmd_reply() {
+            Some(reply) => reply,
+            None => return,
+        };
+        let id = match reply.id().try_into() {
+            Some(id) => id,
+            None => return,
+        };
+        let index = match self.pending.iter().position(|&(sent, _)| sent == id) {
+            Some(index) => index,
+            None => return,
+        };
+        let (_, sent) = self.pending.remove(index);
+        let latency = now.saturating_duration_since(sent);
+        self.fastest_reply = Some(self.fastest_reply.map_or(latency, |l| l.min(latency)));
+        self.checked_weight += probe_weight(id);
+
+        if !reply.ack().is_ok() {
+            trace!("clone probe {:?} not acknowledged", id);
+            self.hints.push(CloneHint::Nack(id));
+            return;
+        }
+        let hint = if let Some(info) = reply.device_info() {
+            let version = info.firmware_version;
+            Some(CloneHint::UnexpectedFirmware(version))
+                .filter(|_| !(3..=4).contains(&version.0[0]))
+        } else if let Some(result) = reply.spi_read_result() {
+            let serial = SerialNumber::try_from(*result).ok();
+            Some(CloneHint::MissingSerialNumber)
+                .filter(|_| serial.as_ref().and_then(SerialNumber::as_str).is_none())
+        } else if let Some(registers) = reply.imu_registers() {
+            let id = registers.values().first().copied().unwrap_or(0);
+            Some(CloneHint::UnknownImu(id)).filter(|_| id != LSM6DS3_ID)
+        } else {
+            None
+        };
+        if let Some(hint) = hint {
+            trace!("clone hint {:?}", hint);
+            self.hints.push(hint);
+        }
+    }
+
+    /// Count the probes sent more than [`PROBE_TIMEOUT`] before `now` as
+    /// unanswered, and check the reply timing.
+    pub fn check_timeouts(&mut self, now: Timestamp) {
+        let hints = &mut self.hints;
+        let checked_weight = &mut self.checked_weight;
+        self.pending.retain(|&(id, sent)| {
+            if now.saturating_duration_since(sent) < PROBE_TIMEOUT {
+                return true;
+            }
+            *checked_weight += probe_weight(id);
+            hints.push(CloneHint::NoReply(id));
+            false
+        });
+        if let Some(fastest) = self.fastest_reply.take() {
+            self.checked_weight += TIMING_WEIGHT;
+            if fastest < INSTANT_REPLY {
+                self.hints.push(CloneHint::InstantReply(fastest));
+            }
+        }
+    }
+
+    pub fn hints(&self) -> &[CloneHint] {
+        &self.hints
+    }
+
+    /// Confidence that the controller is a clone, between 0 and 1, from the
+    /// checks done so far.
+    pub fn score(&self) -> f64 {
+        if self.checked_weight == 0 {
+            return 0.;
+        }
+        let suspicious: u32 = self.hints.iter().map(|hint| hint.weight()).sum();
+        (f64::from(suspicious) / f64::from(self.checked_weight)).min(1.)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn detect_clone() {
+    use crate::{input::WhichController, mock::MockController};
+
+    let run = |controller: &mut MockController| {
+        let mut detect = ClonerDetect::new();
+        let mut now = Timestamp::from_micros(0);
+        for report in ClonerDetect::probes().iter() {
+            detect.sent(report, now);
+            controller.send(report);
+            now = now + Duration::from_millis(15);
+            detect.received(&controller.recv(), now);
+        }
+        detect.check_timeouts(now);
+        detect
+    };
+
+    // The mock has no serial number.
+    let detect = run(&mut MockController::new(WhichController::ProController));
+    assert_eq!(detect.hints(), [CloneHint::MissingSerialNumber]);
+    assert!((detect.score() - 1. / 5.).abs() < 1e-9);
+
+    let mut controller = MockController::new(WhichController::ProController);
+    controller
+        .flash_mut()
+        .write(SerialNumber::range().offset(), b"XBW12345678901\0\0");
+    assert_eq!(run(&mut controller).score(), 0.);
+}
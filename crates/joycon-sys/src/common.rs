@@ -1,6 +1,6 @@
 use cgmath::Vector3;
 use num::{FromPrimitive, ToPrimitive};
-use std::{any::type_name, fmt, marker::PhantomData};
+use std::{any::type_name, fmt, marker::PhantomData, mem::size_of};
 
 pub const NINTENDO_VENDOR_ID: u16 = 1406;
 
@@ -37,16 +37,26 @@ pub enum SubcommandId {
     RequestDeviceInfo = 0x02,
     SetInputReportMode = 0x03,
     GetTriggerButtonsElapsedTime = 0x04,
+    SetHCIState = 0x06,
+    ResetPairingInfo = 0x07,
     SetShipmentMode = 0x08,
     SPIRead = 0x10,
     SPIWrite = 0x11,
     SetMCUConf = 0x21,
     SetMCUState = 0x22,
     SetUnknownData = 0x24,
+    // Set unknown NFC/IR MCU data
+    SetUnknownMCUData = 0x28,
+    // Get the data set by 0x28
+    GetUnknownMCUData = 0x29,
+    // Set GPIO pin output value
+    SetGPIOPinOutput = 0x2A,
     SetPlayerLights = 0x30,
     SetHomeLight = 0x38,
     SetIMUMode = 0x40,
     SetIMUSens = 0x41,
+    WriteIMURegister = 0x42,
+    ReadIMURegisters = 0x43,
     EnableVibration = 0x48,
 
     // arg [4,0,0,2], ret [0,8,0,0,0,0,0,44]
@@ -71,7 +81,25 @@ pub enum SubcommandId {
     Unknown0x5c = 0x5c,
 }
 
-#[derive(Copy, Clone, Default, Eq, PartialEq)]
+/// Argument of `SubcommandId::SetHCIState`.
+///
+/// The controller drops the connection right away, so no reply is sent.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum HCIState {
+    /// Disconnect and go to sleep. Pressing a button wakes the controller up
+    /// and makes it reconnect to the last paired host.
+    Disconnect = 0x00,
+    /// Reboot and reconnect to the last paired host (page mode).
+    RebootAndReconnect = 0x01,
+    /// Reboot and enter pairing mode (discoverable).
+    RebootAndPair = 0x02,
+    /// Reboot and reconnect to the last paired host (home mode).
+    RebootAndReconnectHome = 0x04,
+}
+
+#[cfg_attr(feature = "repr-c", repr(transparent))]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub struct U16LE([u8; 2]);
 
 impl From<u16> for U16LE {
@@ -98,7 +126,8 @@ impl fmt::Display for U16LE {
     }
 }
 
-#[derive(Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "repr-c", repr(transparent))]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub struct I16LE(pub [u8; 2]);
 
 impl From<i16> for I16LE {
@@ -119,7 +148,8 @@ impl fmt::Debug for I16LE {
     }
 }
 
-#[derive(Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "repr-c", repr(transparent))]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub struct U32LE([u8; 4]);
 
 impl From<u32> for U32LE {
@@ -145,6 +175,14 @@ pub(crate) fn offset_of<A, B>(a: &A, b: &B) -> usize {
     b as *const _ as usize - a as *const _ as usize
 }
 
+pub fn f32s_from_raw(raw: [I16LE; 3]) -> [f32; 3] {
+    [
+        i16::from(raw[0]) as f32,
+        i16::from(raw[1]) as f32,
+        i16::from(raw[2]) as f32,
+    ]
+}
+
 pub fn vector_from_raw(raw: [I16LE; 3]) -> Vector3<f64> {
     Vector3::new(
         i16::from(raw[0]) as f64,
@@ -153,6 +191,14 @@ pub fn vector_from_raw(raw: [I16LE; 3]) -> Vector3<f64> {
     )
 }
 
+pub fn i16s_from_raw(raw: [I16LE; 3]) -> [i16; 3] {
+    [raw[0].into(), raw[1].into(), raw[2].into()]
+}
+
+pub fn raw_from_i16s(values: [i16; 3]) -> [I16LE; 3] {
+    [values[0].into(), values[1].into(), values[2].into()]
+}
+
 pub fn raw_from_vector(v: Vector3<f64>) -> [I16LE; 3] {
     [
         (v.x as i16).into(),
@@ -162,7 +208,7 @@ pub fn raw_from_vector(v: Vector3<f64>) -> [I16LE; 3] {
 }
 
 #[repr(transparent)]
-#[derive(Copy, Clone, Default, PartialEq, Eq)]
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
 pub struct RawId<Id>(u8, PhantomData<Id>);
 
 impl<Id> RawId<Id> {
@@ -227,3 +273,111 @@ impl From<bool> for Bool {
         }
     }
 }
+
+/// Struct laid out exactly like on the wire, which can be converted from and
+/// to bytes without loss.
+///
+/// ```ignore
+/// let color = ControllerColor::from_bytes(&bytes).unwrap();
+/// assert_eq!(color.to_bytes(), bytes);
+/// ```
+///
+/// # Safety
+///
+/// The type must be `#[repr(packed)]` and made only of integers, byte arrays
+/// and other `Wire` types, so any bytes are a valid value.
+pub unsafe trait Wire: Copy {
+    fn to_bytes(&self) -> Vec<u8> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(self as *const Self as *const u8, size_of::<Self>())
+        };
+        bytes.to_vec()
+    }
+
+    /// Value from exactly `size_of::<Self>()` bytes.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != size_of::<Self>() {
+            return None;
+        }
+        Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const Self) })
+    }
+}
+
+unsafe impl Wire for U16LE {}
+unsafe impl Wire for I16LE {}
+unsafe impl Wire for U32LE {}
+unsafe impl<Id: Copy> Wire for RawId<Id> {}
+
+/// Error parsing a report from hexadecimal text, see
+/// [`InputReport::from_hex_str`](../input/struct.InputReport.html#method.from_hex_str).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HexError {
+    /// Not hexadecimal, or an odd number of digits.
+    InvalidDigit,
+    TooLong,
+    /// Unknown report id or truncated report.
+    InvalidReport,
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::InvalidDigit => f.write_str("invalid hexadecimal value"),
+            HexError::TooLong => f.write_str("too many bytes for a report"),
+            HexError::InvalidReport => f.write_str("invalid report"),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+/// Decode hexadecimal bytes into `out`, returning the decoded part.
+///
+/// Bytes are separated by whitespace or `:`, or not at all as in the
+/// Wireshark "Copy as Hex Stream" output. If the first byte is
+/// `header`, the Bluetooth HIDP header of the captures, it is skipped.
+pub(crate) fn parse_hex_report<'a>(
+    s: &str,
+    header: u8,
+    out: &'a mut [u8],
+) -> Result<&'a [u8], HexError> {
+    let mut len = 0;
+    for token in s.split(|c: char| c.is_whitespace() || c == ':') {
+        if token.len() % 2 != 0 || !token.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(HexError::InvalidDigit);
+        }
+        for digits in token.as_bytes().chunks(2) {
+            let digits = std::str::from_utf8(digits).map_err(|_| HexError::InvalidDigit)?;
+            let byte = u8::from_str_radix(digits, 16).map_err(|_| HexError::InvalidDigit)?;
+            if len == 0 && byte == header {
+                continue;
+            }
+            *out.get_mut(len).ok_or(HexError::TooLong)? = byte;
+            len += 1;
+        }
+    }
+    Ok(&out[..len])
+}
+
+#[cfg(test)]
+#[test]
+fn wire_round_trip() {
+    use crate::{
+        input::DeviceInfo,
+        output::{RumbleData, SubcommandRequest},
+        spi::ControllerColor,
+    };
+
+    fn round_trip<T: Wire>(bytes: &[u8]) {
+        let value = T::from_bytes(bytes).unwrap();
+        assert_eq!(value.to_bytes(), bytes);
+        assert!(T::from_bytes(&bytes[1..]).is_none());
+    }
+
+    let bytes: Vec<u8> = (0..=255).collect();
+    round_trip::<ControllerColor>(&bytes[..12]);
+    round_trip::<DeviceInfo>(&bytes[..12]);
+    round_trip::<RumbleData>(&bytes[..8]);
+    round_trip::<SubcommandRequest>(&bytes[..39]);
+    round_trip::<crate::InputReport>(&bytes.repeat(2)[..362]);
+}
@@ -0,0 +1,135 @@
+//! Which subcommands this crate can build and decode.
+//!
+//! ```ignore
+//! for coverage in subcommand_coverage() {
+//!     if coverage.reply == Support::Missing {
+//!         println!("{:?} replies are not decoded", coverage.id);
+//!     }
+//! }
+//! ```
+
+use crate::{common::SubcommandId, output::SubcommandRequestEnum};
+use std::fmt;
+
+/// How the data of a subcommand, or of its reply, is supported.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Support {
+    /// No data.
+    Empty,
+    /// Built or decoded with a dedicated type.
+    Typed,
+    /// Only raw bytes, the meaning being unknown.
+    Raw,
+    /// The protocol has data the crate doesn't handle.
+    Missing,
+}
+
+impl Support {
+    pub fn name(self) -> &'static str {
+        match self {
+            Support::Empty => "empty",
+            Support::Typed => "typed",
+            Support::Raw => "raw",
+            Support::Missing => "missing",
+        }
+    }
+}
+
+impl fmt::Display for Support {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SubcommandCoverage {
+    pub id: SubcommandId,
+    pub argument: Support,
+    pub reply: Support,
+}
+
+impl SubcommandCoverage {
+    pub fn of(id: SubcommandId) -> SubcommandCoverage {
+        use Support::*;
+        let (argument, reply) = match id {
+            SubcommandId::GetOnlyControllerState => (Empty, Typed),
+            SubcommandId::BluetoothManualPairing => (Typed, Typed),
+            SubcommandId::RequestDeviceInfo => (Empty, Typed),
+            SubcommandId::SetInputReportMode => (Typed, Empty),
+            SubcommandId::GetTriggerButtonsElapsedTime => (Empty, Typed),
+            SubcommandId::SetHCIState => (Typed, Empty),
+            SubcommandId::ResetPairingInfo => (Empty, Empty),
+            SubcommandId::SetShipmentMode => (Typed, Empty),
+            SubcommandId::SPIRead => (Typed, Typed),
+            SubcommandId::SPIWrite => (Typed, Typed),
+            SubcommandId::SetMCUConf => (Typed, Typed),
+            SubcommandId::SetMCUState => (Typed, Empty),
+            SubcommandId::SetUnknownData => (Raw, Empty),
+            SubcommandId::SetUnknownMCUData => (Raw, Empty),
+            SubcommandId::GetUnknownMCUData => (Empty, Raw),
+            SubcommandId::SetGPIOPinOutput => (Raw, Empty),
+            SubcommandId::SetPlayerLights => (Typed, Empty),
+            SubcommandId::SetHomeLight => (Typed, Empty),
+            SubcommandId::SetIMUMode => (Typed, Empty),
+            SubcommandId::SetIMUSens => (Typed, Empty),
+            SubcommandId::WriteIMURegister => (Typed, Empty),
+            SubcommandId::ReadIMURegisters => (Typed, Typed),
+            SubcommandId::EnableVibration => (Typed, Empty),
+            SubcommandId::MaybeAccessory => (Typed, Typed),
+            SubcommandId::Unknown0x59 => (Empty, Typed),
+            SubcommandId::Unknown0x5a => (Raw, Empty),
+            SubcommandId::Unknown0x5b => (Empty, Empty),
+            SubcommandId::Unknown0x5c => (Raw, Empty),
+        };
+        SubcommandCoverage {
+            id,
+            argument,
+            reply,
+        }
+    }
+
+    /// Both the argument and the reply are fully supported.
+    pub fn is_complete(&self) -> bool {
+        let complete = |support| support == Support::Empty || support == Support::Typed;
+        complete(self.argument) && complete(self.reply)
+    }
+}
+
+/// Row of a Markdown table, see [`markdown_table`].
+impl fmt::Display for SubcommandCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "| `0x{:02x}` | {:?} | {} | {} |",
+            self.id as u8, self.id, self.argument, self.reply
+        )
+    }
+}
+
+/// Coverage of every known subcommand, by id.
+pub fn subcommand_coverage() -> impl Iterator<Item = SubcommandCoverage> {
+    SubcommandRequestEnum::VARIANTS
+        .iter()
+        .cloned()
+        .map(SubcommandCoverage::of)
+}
+
+/// Coverage of every subcommand as a Markdown table, eg. for the README.
+pub fn markdown_table() -> String {
+    let mut table = String::from("| Id | Subcommand | Argument | Reply |\n|---|---|---|---|\n");
+    for coverage in subcommand_coverage() {
+        table += &format!("{}\n", coverage);
+    }
+    table
+}
+
+#[cfg(test)]
+#[test]
+fn coverage() {
+    let all: Vec<_> = subcommand_coverage().collect();
+    assert_eq!(all.len(), SubcommandRequestEnum::VARIANTS.len());
+    assert!(all.windows(2).all(|w| (w[0].id as u8) < (w[1].id as u8)));
+    assert!(SubcommandCoverage::of(SubcommandId::SPIRead).is_complete());
+    assert!(!SubcommandCoverage::of(SubcommandId::Unknown0x5a).is_complete());
+    assert!(markdown_table().contains("| `0x10` | SPIRead | typed | typed |\n"));
+}
@@ -0,0 +1,329 @@
+//! Attachment of the external devices, eg. the ringcon.
+//!
+//! Once the MCU is in the right mode, the Switch identifies the device with
+//! subcommand `0x59`, configures it with `0x5C` and starts polling it with
+//! `0x5A`. `0x5B` stops the polling before the device is unconfigured.
+//! [`ExternalDevice`] sends these subcommands, the configuration depending on
+//! the type of the device. [`ExternalDevice::ringcon`] switches the MCU to the
+//! ringcon mode first:
+//!
+//! ```ignore
+//! let mut device = ExternalDevice::new();
+//! while let Some(request) = device.next_request() {
+//!     send(&OutputReport::from(request))?;
+//!     device.push(&recv_reply()?)?;
+//! }
+//! println!("{:?} attached", device.device_type());
+//! ```
+
+use crate::{
+    common::{RawId, SubcommandId, Wire},
+    imu::IMUMode,
+    input::InputReport,
+    mcu::{MCUConfig, MCUMode},
+    output::{SubcommandRequest, SubcommandRequestEnum},
+};
+use std::{collections::VecDeque, fmt};
+
+/// Number of `0x59` requests sent before giving up on a device not ready.
+pub const MAX_INFO_TRIES: u8 = 5;
+
+/// Number of MCU mode switches sent before giving up on the MCU.
+pub const MAX_MCU_MODE_TRIES: u8 = 5;
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive, PartialEq, Eq, Hash)]
+pub enum ExternalDeviceType {
+    Ringcon = 0x20,
+}
+
+impl ExternalDeviceType {
+    /// Subcommands configuring the device, before its polling is enabled.
+    pub fn configure(self) -> Vec<SubcommandRequest> {
+        match self {
+            ExternalDeviceType::Ringcon => vec![
+                SubcommandRequestEnum::SetIMUMode(IMUMode::MaybeRingcon.into()).into(),
+                SubcommandRequest::subcmd_0x5c_6(),
+            ],
+        }
+    }
+
+    /// Subcommands restoring the controller, after the polling is disabled.
+    pub fn unconfigure(self) -> Vec<SubcommandRequest> {
+        match self {
+            ExternalDeviceType::Ringcon => vec![
+                SubcommandRequestEnum::SetIMUMode(IMUMode::_Unknown0x02.into()).into(),
+                SubcommandRequest::subcmd_0x5c_0(),
+            ],
+        }
+    }
+}
+
+/// Reply to subcommand `0x59`.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug)]
+pub struct ExternalDeviceInfo {
+    /// 0 once the device is identified.
+    status: u8,
+    device_type: RawId<ExternalDeviceType>,
+}
+
+unsafe impl Wire for ExternalDeviceInfo {}
+
+impl ExternalDeviceInfo {
+    pub fn is_ready(&self) -> bool {
+        self.status == 0
+    }
+
+    pub fn device_type(&self) -> RawId<ExternalDeviceType> {
+        self.device_type
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExternalDeviceError {
+    /// The MCU still not in the ringcon mode after [`MAX_MCU_MODE_TRIES`]
+    /// requests.
+    MCUNotReady,
+    /// No device identified after [`MAX_INFO_TRIES`] requests.
+    NotReady,
+    /// A device this crate doesn't know how to configure.
+    Unsupported(RawId<ExternalDeviceType>),
+    /// The controller refused a subcommand.
+    Nack(RawId<SubcommandId>),
+}
+
+impl fmt::Display for ExternalDeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalDeviceError::MCUNotReady => f.write_str("MCU not in the ringcon mode"),
+            ExternalDeviceError::NotReady => f.write_str("no external device identified"),
+            ExternalDeviceError::Unsupported(ty) => {
+                write!(f, "unsupported external device {:?}", ty)
+            }
+            ExternalDeviceError::Nack(id) => write!(f, "subcommand {:?} refused", id),
+        }
+    }
+}
+
+impl std::error::Error for ExternalDeviceError {}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExternalDeviceState {
+    /// Switching the MCU to the ringcon mode.
+    PreparingMCU,
+    /// Waiting for the `0x59` reply.
+    Identifying,
+    /// Configuring the device and enabling its polling.
+    Attaching,
+    Attached,
+    /// Disabling the polling and unconfiguring the device.
+    Detaching,
+    Detached,
+    Failed(ExternalDeviceError),
+}
+
+/// Sans-io state machine of the attachment of an external device.
+///
+/// Each subcommand is sent after the reply to the previous one, the MCU
+/// mode switch and the `0x59` request being repeated until they succeed.
+#[derive(Clone, Debug)]
+pub struct ExternalDevice {
+    state: ExternalDeviceState,
+    device_type: Option<ExternalDeviceType>,
+    pending: VecDeque<SubcommandRequest>,
+    tries: u8,
+    /// Whether the MCU was switched to the ringcon mode, and must be reset
+    /// when detaching.
+    mcu: bool,
+}
+
+impl ExternalDevice {
+    /// Attach a device, the MCU being already in the right mode.
+    pub fn new() -> ExternalDevice {
+        ExternalDevice {
+            state: ExternalDeviceState::Identifying,
+            device_type: None,
+            pending: vec![SubcommandRequest::subcmd_0x59()].into(),
+            tries: 0,
+            mcu: false,
+        }
+    }
+
+    /// Attach the ringcon, switching the MCU to its mode first as the Switch
+    /// does.
+    pub fn ringcon() -> ExternalDevice {
+        ExternalDevice {
+            state: ExternalDeviceState::PreparingMCU,
+            device_type: None,
+            pending: vec![
+                SubcommandRequestEnum::SetMCUState(MCUMode::Standby.into()).into(),
+                MCUConfig::Mode(MCUMode::MaybeRingcon).into(),
+                MCUConfig::Ringcon { enable: true }.into(),
+            ]
+            .into(),
+            tries: 0,
+            mcu: true,
+        }
+    }
+
+    pub fn state(&self) -> ExternalDeviceState {
+        self.state
+    }
+
+    /// Type of the device, once identified.
+    pub fn device_type(&self) -> Option<ExternalDeviceType> {
+        self.device_type
+    }
+
+    /// Subcommand to send, `None` while attached or once finished.
+    pub fn next_request(&self) -> Option<SubcommandRequest> {
+        self.pending.front().copied()
+    }
+
+    /// Start detaching an attached device.
+    pub fn detach(&mut self) {
+        if let (ExternalDeviceState::Attached, Some(ty)) = (self.state, self.device_type) {
+            self.state = ExternalDeviceState::Detaching;
+            self.pending = vec![SubcommandRequest::subcmd_0x5b()].into();
+            self.pending.extend(ty.unconfigure());
+            if self.mcu {
+                self.pending
+                    .push_back(MCUConfig::Ringcon { enable: false }.into());
+            }
+        }
+    }
+
+    fn fail(&mut self, error: ExternalDeviceError) -> Result<(), ExternalDeviceError> {
+        trace!("external device failed: {:?}", error);
+        self.state = ExternalDeviceState::Failed(error);
+        self.pending.clear();
+        Err(error)
+    }
+
+    /// Handle a report, other reports than the reply to the last subcommand
+    /// being ignored.
+    pub fn push(&mut self, report: &InputReport) -> Result<(), ExternalDeviceError> {
+        let (sent, reply) = match (self.pending.front(), report.subcmd_reply()) {
+            (Some(sent), Some(reply)) if reply.id() == sent.id() => (*sent, *reply),
+            _ => return Ok(()),
+        };
+        if !reply.ack().is_ok() {
+            return self.fail(ExternalDeviceError::Nack(sent.id()));
+        }
+        let mcu_state = reply
+            .mcu_report()
+            .and_then(|r| r.state_report())
+            .map(|s| s.state);
+        match mcu_state {
+            Some(state)
+                if self.state == ExternalDeviceState::PreparingMCU
+                    && state != MCUMode::MaybeRingcon =>
+            {
+                // The switch takes a few tries.
+                self.tries += 1;
+                if self.tries >= MAX_MCU_MODE_TRIES {
+                    return self.fail(ExternalDeviceError::MCUNotReady);
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+        if let Some(info) = reply.unknown0x59() {
+            if !info.is_ready() {
+                self.tries += 1;
+                if self.tries >= MAX_INFO_TRIES {
+                    return self.fail(ExternalDeviceError::NotReady);
+                }
+                return Ok(());
+            }
+            let ty = match info.device_type().try_into() {
+                Some(ty) => ty,
+                None => return self.fail(ExternalDeviceError::Unsupported(info.device_type())),
+            };
+            trace!("external device identified: {:?}", ty);
+            self.device_type = Some(ty);
+            self.state = ExternalDeviceState::Attaching;
+            self.tries = 0;
+            self.pending = ty.configure().into();
+            self.pending.push_back(SubcommandRequest::subcmd_0x5a());
+            return Ok(());
+        }
+        self.pending.pop_front();
+        if self.pending.is_empty() {
+            match self.state {
+                ExternalDeviceState::PreparingMCU => {
+                    self.state = ExternalDeviceState::Identifying;
+                    self.tries = 0;
+                    self.pending.push_back(SubcommandRequest::subcmd_0x59());
+                }
+                ExternalDeviceState::Attaching => self.state = ExternalDeviceState::Attached,
+                _ => self.state = ExternalDeviceState::Detached,
+            }
+            trace!("external device {:?}", self.state);
+        }
+        Ok(())
+    }
+}
+
+impl Default for ExternalDevice {
+    fn default() -> Self {
+        ExternalDevice::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn external_device_attach() {
+    use crate::{common::InputReportId, OutputReport};
+
+    let reply = |request: SubcommandRequest, ack: u8, data: &[u8]| {
+        let mut report = InputReport::new();
+        let raw = report.as_bytes_mut();
+        raw[0] = InputReportId::StandardAndSubcmd as u8;
+        raw[13] = ack;
+        raw[14] = OutputReport::from(request).as_bytes()[10];
+        raw[15..15 + data.len()].copy_from_slice(data);
+        report
+    };
+
+    let mut device = ExternalDevice::new();
+    let info = device.next_request().unwrap();
+    assert_eq!(info.id(), SubcommandId::Unknown0x59);
+    // Not identified yet, asked again.
+    device.push(&reply(info, 0xD9, &[1, 0])).unwrap();
+    assert_eq!(device.state(), ExternalDeviceState::Identifying);
+    device.push(&reply(info, 0xD9, &[0, 0x20])).unwrap();
+    assert_eq!(device.device_type(), Some(ExternalDeviceType::Ringcon));
+
+    let mut sent = Vec::new();
+    while let Some(request) = device.next_request() {
+        sent.push(request.id());
+        device.push(&reply(request, 0x80, &[])).unwrap();
+    }
+    assert_eq!(
+        sent,
+        [
+            SubcommandId::SetIMUMode,
+            SubcommandId::Unknown0x5c,
+            SubcommandId::Unknown0x5a
+        ]
+    );
+    assert_eq!(device.state(), ExternalDeviceState::Attached);
+
+    device.detach();
+    let stop = device.next_request().unwrap();
+    assert_eq!(stop.id(), SubcommandId::Unknown0x5b);
+    assert_eq!(
+        device.push(&reply(stop, 0, &[])),
+        Err(ExternalDeviceError::Nack(SubcommandId::Unknown0x5b.into()))
+    );
+    assert!(device.next_request().is_none());
+
+    let mut device = ExternalDevice::new();
+    assert_eq!(
+        device.push(&reply(info, 0xD9, &[0, 0x21])),
+        Err(ExternalDeviceError::Unsupported(RawId::new(0x21)))
+    );
+}
@@ -0,0 +1,262 @@
+//! C interface to the report parsing and building functions.
+//!
+//! Enabled by the `ffi` feature. Build a shared library with
+//! `cargo rustc -p joycon-sys --features ffi --crate-type cdylib`.
+//!
+//! All the functions return a negative `JOYCON_ERR_*` code on error. Builders
+//! return the number of bytes written into `buf`, to be sent as-is to the
+//! controller over Bluetooth.
+
+use crate::{
+    bindings::{FlatInputError, FlatInputState},
+    imu::IMUMode,
+    light::PlayerLights,
+    output::{RumbleData, RumbleSide, SubcommandRequest, SubcommandRequestEnum, TransportKind},
+    InputReportId, OutputReport, RawId,
+};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+
+pub const JOYCON_OK: i32 = 0;
+/// A pointer argument is null.
+pub const JOYCON_ERR_NULL: i32 = -1;
+/// The data isn't a valid report.
+pub const JOYCON_ERR_INVALID: i32 = -2;
+/// The report doesn't contain the standard input data.
+pub const JOYCON_ERR_NOT_STANDARD: i32 = -3;
+pub const JOYCON_ERR_BUFFER_TOO_SMALL: i32 = -4;
+/// Unexpected internal error.
+pub const JOYCON_ERR_INTERNAL: i32 = -5;
+
+/// Flat view of a standard input report.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct JoyconInputState {
+    pub report_id: u8,
+    pub timer: u8,
+    /// 0 (empty) to 4 (full).
+    pub battery_level: u8,
+    pub charging: bool,
+    /// 0: Bluetooth, 1: USB, 2: attached to the console rail.
+    pub connection: u8,
+    /// Pressed buttons, bit `n` being the button of index `n` in
+    /// [`Button::ALL`](../input/enum.Button.html).
+    pub buttons: u32,
+    /// Raw 12-bit stick values, to be calibrated.
+    pub left_stick_x: u16,
+    pub left_stick_y: u16,
+    pub right_stick_x: u16,
+    pub right_stick_y: u16,
+}
+
+/// Panics must not unwind into C code.
+fn guard(f: impl FnOnce() -> isize) -> isize {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(JOYCON_ERR_INTERNAL as isize)
+}
+
+/// Parse a raw input report of `len` bytes into `out`.
+///
+/// # Safety
+///
+/// `data` must be valid for `len` bytes and `out` must point to a writable
+/// `JoyconInputState`.
+#[no_mangle]
+pub unsafe extern "C" fn joycon_parse_input_report(
+    data: *const u8,
+    len: usize,
+    out: *mut JoyconInputState,
+) -> i32 {
+    if data.is_null() || out.is_null() {
+        return JOYCON_ERR_NULL;
+    }
+    let data = slice::from_raw_parts(data, len);
+    let out = &mut *out;
+    guard(move || {
+        let state = match FlatInputState::parse(data) {
+            Ok(state) => state,
+            Err(FlatInputError::Invalid) => return JOYCON_ERR_INVALID as isize,
+            Err(FlatInputError::NotStandard) => return JOYCON_ERR_NOT_STANDARD as isize,
+        };
+        *out = JoyconInputState {
+            report_id: state.report_id,
+            timer: state.timer,
+            battery_level: state.battery_code(),
+            charging: state.charging,
+            connection: state.connection_code(),
+            buttons: state.buttons.bits(),
+            left_stick_x: state.left_stick.0,
+            left_stick_y: state.left_stick.1,
+            right_stick_x: state.right_stick.0,
+            right_stick_y: state.right_stick.1,
+        };
+        JOYCON_OK as isize
+    }) as i32
+}
+
+unsafe fn write_report(report: OutputReport, buf: *mut u8, len: usize) -> isize {
+    if buf.is_null() {
+        return JOYCON_ERR_NULL as isize;
+    }
+    let buf = slice::from_raw_parts_mut(buf, len);
+    match report.write_into(buf, TransportKind::Bluetooth) {
+        Ok(written) => written as isize,
+        Err(_) => JOYCON_ERR_BUFFER_TOO_SMALL as isize,
+    }
+}
+
+unsafe fn write_subcmd(
+    subcmd: impl Into<SubcommandRequest>,
+    packet_counter: u8,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    let mut report = OutputReport::from(subcmd.into());
+    *report.packet_counter() = packet_counter;
+    write_report(report, buf, len)
+}
+
+/// Build a rumble-only report, playing the same vibration on both sides.
+///
+/// Frequencies are in Hz and amplitudes between 0 and 1.
+///
+/// # Safety
+///
+/// `buf` must be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn joycon_build_rumble(
+    packet_counter: u8,
+    high_freq: f32,
+    high_amp: f32,
+    low_freq: f32,
+    low_amp: f32,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    guard(move || {
+        let side = RumbleSide::from_freq(high_freq, high_amp, low_freq, low_amp);
+        let mut report = OutputReport::set_rumble(RumbleData {
+            left: side,
+            right: side,
+        });
+        *report.packet_counter() = packet_counter;
+        write_report(report, buf, len)
+    })
+}
+
+/// Build the subcommand changing the input report mode, usually to `0x30`
+/// (standard full mode).
+///
+/// # Safety
+///
+/// `buf` must be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn joycon_build_set_input_report_mode(
+    packet_counter: u8,
+    mode: u8,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    guard(move || {
+        let mode = match RawId::<InputReportId>::new(mode).try_into() {
+            Some(mode) => mode,
+            None => return JOYCON_ERR_INVALID as isize,
+        };
+        let subcmd = SubcommandRequestEnum::SetInputReportMode(mode.into());
+        write_subcmd(subcmd, packet_counter, buf, len)
+    })
+}
+
+/// Build the subcommand setting the player lights.
+///
+/// Bits 0 to 3 turn on the lights, bits 4 to 7 make them blink.
+///
+/// # Safety
+///
+/// `buf` must be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn joycon_build_set_player_lights(
+    packet_counter: u8,
+    lights: u8,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    guard(move || {
+        let lights = PlayerLights::from_bits(lights);
+        write_subcmd(lights, packet_counter, buf, len)
+    })
+}
+
+/// Build the subcommand enabling or disabling the IMU.
+///
+/// # Safety
+///
+/// `buf` must be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn joycon_build_enable_imu(
+    packet_counter: u8,
+    enabled: bool,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    guard(move || {
+        let mode = if enabled {
+            IMUMode::GyroAccel
+        } else {
+            IMUMode::Disabled
+        };
+        let subcmd = SubcommandRequestEnum::SetIMUMode(mode.into());
+        write_subcmd(subcmd, packet_counter, buf, len)
+    })
+}
+
+/// Build the subcommand requesting the device info.
+///
+/// # Safety
+///
+/// `buf` must be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn joycon_build_request_device_info(
+    packet_counter: u8,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    guard(move || {
+        let subcmd = SubcommandRequestEnum::RequestDeviceInfo(());
+        write_subcmd(subcmd, packet_counter, buf, len)
+    })
+}
+
+#[cfg(test)]
+#[test]
+fn parse_and_build() {
+    let mut raw = [0u8; 49];
+    raw[0] = 0x30;
+    raw[1] = 7;
+    raw[2] = 0x8e;
+    raw[3] = 0b1000;
+    raw[6..9].copy_from_slice(&[0x00, 0x08, 0x80]);
+    let mut state = JoyconInputState::default();
+    unsafe {
+        assert_eq!(
+            joycon_parse_input_report(raw.as_ptr(), raw.len(), &mut state),
+            JOYCON_OK
+        );
+    }
+    assert_eq!(state.timer, 7);
+    assert_eq!(state.battery_level, 4);
+    assert_eq!(state.buttons, 1 << crate::input::Button::E.index());
+    assert_eq!((state.left_stick_x, state.left_stick_y), (0x800, 0x800));
+
+    let mut buf = [0; 64];
+    let written =
+        unsafe { joycon_build_set_player_lights(3, 0b0010_0001, buf.as_mut_ptr(), buf.len()) };
+    assert_eq!(written, 49);
+    assert_eq!(buf[0], 0x01);
+    assert_eq!(buf[1], 3);
+    assert_eq!(buf[10], 0x30);
+    assert_eq!(buf[11], 0b0010_0001);
+    assert_eq!(
+        unsafe { joycon_build_request_device_info(0, buf.as_mut_ptr(), 10) },
+        JOYCON_ERR_BUFFER_TOO_SMALL as isize
+    );
+}
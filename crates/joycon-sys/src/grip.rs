@@ -0,0 +1,157 @@
+//! Helpers for the JoyCon charging grip.
+//!
+//! When plugged over USB, the grip enumerates as a single product
+//! ([`JOYCON_CHARGING_GRIP`](../constant.JOYCON_CHARGING_GRIP.html)) exposing one HID
+//! interface per attached JoyCon. Each interface then speaks the usual protocol.
+
+use crate::{
+    input::WhichController,
+    output::{RumbleData, RumbleSide},
+    OutputReport,
+};
+
+/// HID interface used by the left JoyCon slot of the charging grip.
+pub const GRIP_INTERFACE_LEFT: i32 = 0;
+/// HID interface used by the right JoyCon slot of the charging grip.
+pub const GRIP_INTERFACE_RIGHT: i32 = 1;
+
+/// Usage page of the controller interfaces (Generic Desktop).
+pub const HID_USAGE_PAGE_GENERIC_DESKTOP: u16 = 0x01;
+/// Usage of the controller interfaces (Joystick).
+pub const HID_USAGE_JOYSTICK: u16 = 0x04;
+
+/// Slot of the charging grip a HID interface is connected to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GripSlot {
+    Left,
+    Right,
+}
+
+impl GripSlot {
+    /// Behaviour differences of a JoyCon when accessed through the grip, the
+    /// same for both slots.
+    pub const QUIRKS: GripQuirks = GripQuirks {
+        imu_disabled_by_default: true,
+        needs_usb_handshake: true,
+    };
+
+    /// Find which slot a HID interface of the grip belongs to.
+    ///
+    /// Some platforms also expose vendor specific interfaces, so the usage
+    /// page is checked when it is reported (ie. non-zero).
+    pub fn from_interface(interface_number: i32, usage_page: u16) -> Option<GripSlot> {
+        if usage_page != 0 && usage_page != HID_USAGE_PAGE_GENERIC_DESKTOP {
+            return None;
+        }
+        match interface_number {
+            GRIP_INTERFACE_LEFT => Some(GripSlot::Left),
+            GRIP_INTERFACE_RIGHT => Some(GripSlot::Right),
+            _ => None,
+        }
+    }
+
+    pub fn which_controller(self) -> WhichController {
+        match self {
+            GripSlot::Left => WhichController::LeftJoyCon,
+            GripSlot::Right => WhichController::RightJoyCon,
+        }
+    }
+
+    pub fn interface_number(self) -> i32 {
+        match self {
+            GripSlot::Left => GRIP_INTERFACE_LEFT,
+            GripSlot::Right => GRIP_INTERFACE_RIGHT,
+        }
+    }
+}
+
+/// Differences from a Bluetooth connection when talking through the grip.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GripQuirks {
+    /// The IMU doesn't send any data until explicitly enabled with
+    /// `SetIMUMode`, even in the full report mode.
+    pub imu_disabled_by_default: bool,
+    /// The JoyCon only answers to HID reports after the USB `0x80` handshake
+    /// sequence has been sent on its interface.
+    pub needs_usb_handshake: bool,
+}
+
+/// Splits rumble commands between the two JoyCons of the grip.
+///
+/// Each interface is a separate controller with its own packet counter, so
+/// one logical rumble command becomes one report per slot.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GripRumbleRouter {
+    counters: [u8; 2],
+}
+
+impl GripRumbleRouter {
+    pub fn new() -> GripRumbleRouter {
+        GripRumbleRouter::default()
+    }
+
+    /// Set the packet counter of a report sent to `slot` outside of the
+    /// router, eg. a subcommand, so the counters stay consistent.
+    pub fn stamp(&mut self, slot: GripSlot, report: &mut OutputReport) {
+        let counter = &mut self.counters[slot as usize];
+        *report.packet_counter() = *counter;
+        *counter = (*counter + 1) & 0xf;
+    }
+
+    /// Reports playing `rumble.left` on the left JoyCon and `rumble.right`
+    /// on the right one.
+    pub fn route(&mut self, rumble: RumbleData) -> [(GripSlot, OutputReport); 2] {
+        let mut left = OutputReport::set_rumble(RumbleData {
+            left: rumble.left,
+            right: RumbleSide::default(),
+        });
+        let mut right = OutputReport::set_rumble(RumbleData {
+            left: RumbleSide::default(),
+            right: rumble.right,
+        });
+        self.stamp(GripSlot::Left, &mut left);
+        self.stamp(GripSlot::Right, &mut right);
+        [(GripSlot::Left, left), (GripSlot::Right, right)]
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn interface_routing() {
+    assert_eq!(
+        GripSlot::from_interface(0, HID_USAGE_PAGE_GENERIC_DESKTOP),
+        Some(GripSlot::Left)
+    );
+    assert_eq!(GripSlot::from_interface(1, 0), Some(GripSlot::Right));
+    assert_eq!(GripSlot::from_interface(1, 0xff00), None);
+    assert_eq!(GripSlot::from_interface(2, 0), None);
+}
+
+#[cfg(test)]
+#[test]
+fn rumble_routing() {
+    let mut router = GripRumbleRouter::new();
+    let mut subcmd = OutputReport::keepalive(0);
+    router.stamp(GripSlot::Right, &mut subcmd);
+    let rumble = RumbleData {
+        left: RumbleSide::from_freq(320., 0.5, 160., 0.),
+        right: RumbleSide::from_freq(320., 0., 160., 0.8),
+    };
+    let [(left_slot, left), (right_slot, right)] = router.route(rumble);
+    assert_eq!((left_slot, right_slot), (GripSlot::Left, GripSlot::Right));
+    assert_eq!(left.as_bytes()[1], 0);
+    assert_eq!(right.as_bytes()[1], 1);
+    let neutral = RumbleData::default();
+    assert_eq!(
+        left.as_bytes()[2..6],
+        OutputReport::set_rumble(rumble).as_bytes()[2..6]
+    );
+    assert_eq!(
+        left.as_bytes()[6..10],
+        OutputReport::set_rumble(neutral).as_bytes()[6..10]
+    );
+    assert_eq!(
+        right.as_bytes()[6..10],
+        OutputReport::set_rumble(rumble).as_bytes()[6..10]
+    );
+}
@@ -0,0 +1,130 @@
+//! Human readable description of a controller, for device pickers.
+
+use crate::{
+    input::{DeviceInfo, WhichController},
+    spi::{Color, ControllerColor, SerialNumber},
+};
+use std::fmt::{self, Write};
+
+/// Body colors of the official controllers.
+pub const COLOR_PRESETS: &[(Color, &str)] = &[
+    (Color::new(0x82, 0x82, 0x82), "Grey"),
+    (Color::new(0x0a, 0xb9, 0xe6), "Neon Blue"),
+    (Color::new(0xff, 0x3c, 0x28), "Neon Red"),
+    (Color::new(0xe6, 0xff, 0x00), "Neon Yellow"),
+    (Color::new(0xff, 0x32, 0x78), "Neon Pink"),
+    (Color::new(0x1e, 0xdc, 0x00), "Neon Green"),
+    (Color::new(0xb4, 0x00, 0xe6), "Neon Purple"),
+    (Color::new(0xfa, 0xa0, 0x05), "Neon Orange"),
+    (Color::new(0xe1, 0x0f, 0x00), "Red"),
+    (Color::new(0x46, 0x55, 0xf5), "Blue"),
+    (Color::new(0x32, 0x32, 0x32), "Black"),
+];
+
+/// Name of a known body color.
+pub fn color_name(color: Color) -> Option<&'static str> {
+    COLOR_PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == color)
+        .map(|(_, name)| *name)
+}
+
+/// What is known about a controller, gathered from the device info and the
+/// SPI flash.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ControllerIdentity {
+    pub controller: Option<WhichController>,
+    pub color: Option<ControllerColor>,
+    pub serial_number: Option<SerialNumber>,
+}
+
+impl ControllerIdentity {
+    pub fn new(
+        info: &DeviceInfo,
+        color: Option<ControllerColor>,
+        serial_number: Option<SerialNumber>,
+    ) -> ControllerIdentity {
+        ControllerIdentity {
+            controller: info.which_controller.try_into(),
+            color,
+            serial_number,
+        }
+    }
+
+    pub fn product_name(&self) -> &'static str {
+        match self.controller {
+            Some(WhichController::LeftJoyCon) => "Joy-Con (L)",
+            Some(WhichController::RightJoyCon) => "Joy-Con (R)",
+            Some(WhichController::ProController) => "Pro Controller",
+            None => "Unknown controller",
+        }
+    }
+
+    /// Render the description, eg. `Joy-Con (R), Neon Red, SN XAW10012345678`,
+    /// into `buf` without allocating.
+    ///
+    /// The description is truncated if it doesn't fit.
+    pub fn describe<'a>(&self, buf: &'a mut [u8]) -> &'a str {
+        let mut writer = SliceWriter { buf, len: 0 };
+        // An error only means the description was truncated.
+        let _ = write!(writer, "{}", self);
+        let SliceWriter { buf, len } = writer;
+        std::str::from_utf8(&buf[..len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ControllerIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.product_name())?;
+        if let Some(color) = self.color {
+            let body = color.body;
+            match color_name(body) {
+                Some(name) => write!(f, ", {}", name)?,
+                None => write!(f, ", {}", body)?,
+            }
+        }
+        if let Some(serial) = self.serial_number.as_ref().and_then(SerialNumber::as_str) {
+            write!(f, ", SN {}", serial)?;
+        }
+        Ok(())
+    }
+}
+
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Write for SliceWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let available = self.buf.len() - self.len;
+        let mut n = s.len().min(available);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        if n < s.len() {
+            Err(fmt::Error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn describe_controller() {
+    let identity = ControllerIdentity {
+        controller: Some(WhichController::RightJoyCon),
+        color: Some(ControllerColor {
+            body: Color::new(0xff, 0x3c, 0x28),
+            ..Default::default()
+        }),
+        serial_number: None,
+    };
+    let mut buf = [0; 64];
+    assert_eq!(identity.describe(&mut buf), "Joy-Con (R), Neon Red");
+    let mut small = [0; 9];
+    assert_eq!(identity.describe(&mut small), "Joy-Con (");
+}
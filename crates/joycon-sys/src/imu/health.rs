@@ -0,0 +1,245 @@
+//! Sanity checks of the IMU samples.
+//!
+//! A working sensor always has some noise, even at rest. Clone controllers
+//! and damaged units often report an axis stuck at a fixed value, or noise far
+//! above the one of an LSM6DS3. [`ImuHealth`] keeps a short window of frames
+//! and reports these problems:
+//!
+//! ```ignore
+//! let mut health = ImuHealth::new(DEFAULT_HEALTH_WINDOW);
+//! for frame in report.imu_frames().unwrap() {
+//!     health.push(frame);
+//! }
+//! if let Some(report) = health.report() {
+//!     for issue in report.issues() {
+//!         warn!("IMU: {:?}", issue);
+//!     }
+//! }
+//! ```
+//!
+//! The checks expect the controller to be at rest on a table while the window
+//! is filled, movement being reported as noise.
+
+use super::Frame;
+use std::collections::VecDeque;
+
+/// Frames in the window by default, half a second of samples.
+pub const DEFAULT_HEALTH_WINDOW: usize = 100;
+/// Accelerometer variance, in squared raw units, above which an axis is
+/// reported as noisy.
+pub const ACCEL_NOISY_VARIANCE: f64 = 2500.;
+/// Gyroscope variance, in squared raw units, above which an axis is reported
+/// as noisy.
+pub const GYRO_NOISY_VARIANCE: f64 = 400.;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ImuAxis {
+    AccelX,
+    AccelY,
+    AccelZ,
+    GyroX,
+    GyroY,
+    GyroZ,
+}
+
+impl ImuAxis {
+    pub const ALL: [ImuAxis; 6] = [
+        ImuAxis::AccelX,
+        ImuAxis::AccelY,
+        ImuAxis::AccelZ,
+        ImuAxis::GyroX,
+        ImuAxis::GyroY,
+        ImuAxis::GyroZ,
+    ];
+
+    pub fn is_gyro(self) -> bool {
+        matches!(self, ImuAxis::GyroX | ImuAxis::GyroY | ImuAxis::GyroZ)
+    }
+
+    fn noisy_variance(self) -> f64 {
+        if self.is_gyro() {
+            GYRO_NOISY_VARIANCE
+        } else {
+            ACCEL_NOISY_VARIANCE
+        }
+    }
+}
+
+/// Problem found on an axis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImuIssue {
+    /// Always zero, the sensor doesn't report this axis.
+    Dead(ImuAxis),
+    /// Always the same non-zero value.
+    Stuck(ImuAxis, i16),
+    /// Variance above the threshold of the axis.
+    Noisy(ImuAxis, f64),
+}
+
+/// Statistics of an axis over the window, in raw units.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AxisStats {
+    pub mean: f64,
+    pub variance: f64,
+    pub min: i16,
+    pub max: i16,
+}
+
+impl AxisStats {
+    fn new(values: impl Iterator<Item = i16> + Clone) -> AxisStats {
+        let count = values.clone().count() as f64;
+        let mean = values.clone().map(f64::from).sum::<f64>() / count;
+        let variance = values
+            .clone()
+            .map(|v| (f64::from(v) - mean) * (f64::from(v) - mean))
+            .sum::<f64>()
+            / count;
+        AxisStats {
+            mean,
+            variance,
+            min: values.clone().min().unwrap_or(0),
+            max: values.max().unwrap_or(0),
+        }
+    }
+
+    fn issue(&self, axis: ImuAxis) -> Option<ImuIssue> {
+        if self.min == self.max {
+            if self.min == 0 {
+                Some(ImuIssue::Dead(axis))
+            } else {
+                Some(ImuIssue::Stuck(axis, self.min))
+            }
+        } else if self.variance > axis.noisy_variance() {
+            Some(ImuIssue::Noisy(axis, self.variance))
+        } else {
+            None
+        }
+    }
+}
+
+/// Result of the checks on a full window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HealthReport {
+    axes: [AxisStats; 6],
+    issues: Vec<ImuIssue>,
+}
+
+impl HealthReport {
+    /// Statistics of `axis`.
+    pub fn axis(&self, axis: ImuAxis) -> AxisStats {
+        self.axes[axis as usize]
+    }
+
+    pub fn issues(&self) -> &[ImuIssue] {
+        &self.issues
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Sliding window of IMU frames checked for dead, stuck or noisy axes.
+#[derive(Clone, Debug)]
+pub struct ImuHealth {
+    window: usize,
+    frames: VecDeque<[i16; 6]>,
+}
+
+impl ImuHealth {
+    /// Check the last `window` frames, at least 2.
+    pub fn new(window: usize) -> ImuHealth {
+        let window = window.max(2);
+        ImuHealth {
+            window,
+            frames: VecDeque::with_capacity(window),
+        }
+    }
+
+    pub fn push(&mut self, frame: &Frame) {
+        if self.frames.len() == self.window {
+            self.frames.pop_front();
+        }
+        let accel = frame.raw_accel_i16();
+        let gyro = frame.raw_gyro_i16();
+        self.frames
+            .push_back([accel[0], accel[1], accel[2], gyro[0], gyro[1], gyro[2]]);
+    }
+
+    /// Whether enough frames were pushed for a report.
+    pub fn is_ready(&self) -> bool {
+        self.frames.len() == self.window
+    }
+
+    /// Forget the frames, for example when the controller was moved.
+    pub fn reset(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Checks of the window, `None` until it is full.
+    pub fn report(&self) -> Option<HealthReport> {
+        if !self.is_ready() {
+            return None;
+        }
+        let mut axes = [AxisStats {
+            mean: 0.,
+            variance: 0.,
+            min: 0,
+            max: 0,
+        }; 6];
+        let mut issues = Vec::new();
+        for (i, &axis) in ImuAxis::ALL.iter().enumerate() {
+            axes[i] = AxisStats::new(self.frames.iter().map(move |f| f[i]));
+            issues.extend(axes[i].issue(axis));
+        }
+        trace!("IMU health issues: {:?}", issues);
+        Some(HealthReport { axes, issues })
+    }
+}
+
+impl Default for ImuHealth {
+    fn default() -> Self {
+        ImuHealth::new(DEFAULT_HEALTH_WINDOW)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn imu_health() {
+    use cgmath::vec3;
+
+    let mut health = ImuHealth::new(4);
+    let samples = [
+        (vec3(10., 4096., 0.), vec3(-3., 2., 500.)),
+        (vec3(12., 4090., 0.), vec3(-2., 1., -500.)),
+        (vec3(9., 4100., 0.), vec3(-4., 3., 500.)),
+        (vec3(11., 4094., 0.), vec3(-3., 2., -500.)),
+    ];
+    for (accel, gyro) in &samples[..3] {
+        health.push(&Frame::new(*accel, *gyro));
+    }
+    assert_eq!(health.report(), None);
+    health.push(&Frame::new(samples[3].0, samples[3].1));
+
+    let report = health.report().unwrap();
+    assert_eq!(report.axis(ImuAxis::AccelX).mean, 10.5);
+    assert_eq!(report.axis(ImuAxis::AccelX).variance, 1.25);
+    assert_eq!(report.axis(ImuAxis::GyroZ).min, -500);
+    assert_eq!(
+        report.issues(),
+        [
+            ImuIssue::Dead(ImuAxis::AccelZ),
+            ImuIssue::Noisy(ImuAxis::GyroZ, 250000.)
+        ]
+    );
+    assert!(!report.is_healthy());
+
+    health.reset();
+    for _ in 0..4 {
+        health.push(&Frame::new(vec3(1., 2., 3.), vec3(7., 7., 7.)));
+    }
+    assert_eq!(
+        health.report().unwrap().issues()[3],
+        ImuIssue::Stuck(ImuAxis::GyroX, 7)
+    );
+}
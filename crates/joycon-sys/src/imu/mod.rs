@@ -0,0 +1,340 @@
+use crate::common::*;
+use cgmath::{Array, ElementWise, Vector3};
+use std::fmt;
+
+mod health;
+mod rate;
+
+pub use health::*;
+pub use rate::*;
+
+pub const IMU_SAMPLE_DURATION: f64 = 0.005;
+pub const IMU_SAMPLES_PER_SECOND: u32 = 200;
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum IMUMode {
+    Disabled = 0,
+    GyroAccel = 1,
+    _Unknown0x02 = 2,
+    MaybeRingcon = 3,
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone)]
+pub struct Frame {
+    raw_accel: [I16LE; 3],
+    raw_gyro: [I16LE; 3],
+}
+
+unsafe impl Wire for Frame {}
+
+impl Frame {
+    /// Build a frame from raw sensor values, eg. for synthesized samples.
+    pub fn new(raw_accel: Vector3<f64>, raw_gyro: Vector3<f64>) -> Frame {
+        Frame {
+            raw_accel: raw_from_vector(raw_accel),
+            raw_gyro: raw_from_vector(raw_gyro),
+        }
+    }
+
+    pub fn raw_ringcon(&self) -> u16 {
+        let raw_self = unsafe {
+            std::slice::from_raw_parts(self as *const _ as *const u8, std::mem::size_of_val(self))
+        };
+        u16::from_le_bytes([raw_self[2], raw_self[3]])
+    }
+    pub fn raw_accel(&self) -> Vector3<f64> {
+        vector_from_raw(self.raw_accel)
+    }
+
+    pub fn raw_gyro(&self) -> Vector3<f64> {
+        vector_from_raw(self.raw_gyro)
+    }
+
+    /// Accelerometer sample as sent, without conversion.
+    pub fn raw_accel_i16(&self) -> [i16; 3] {
+        i16s_from_raw(self.raw_accel)
+    }
+
+    pub fn raw_gyro_i16(&self) -> [i16; 3] {
+        i16s_from_raw(self.raw_gyro)
+    }
+
+    /// Calculation from <https://github.com/dekuNukem/Nintendo_Switch_Reverse_Engineering/blob/master/imu_sensor_notes.md#accelerometer---acceleration-in-g>
+    pub fn accel_g(&self, offset: Vector3<f64>, _sens: AccSens) -> Vector3<f64> {
+        // TODO: handle sens
+        (self.raw_accel() * 4.).div_element_wise(Vector3::from_value(16383.) - offset)
+    }
+
+    /// The rotation described in this frame.
+    /// <https://github.com/dekuNukem/Nintendo_Switch_Reverse_Engineering/blob/master/imu_sensor_notes.md#gyroscope-calibrated---rotation-in-degreess---dps>
+    pub fn rotation_dps(&self, offset: Vector3<f64>, sens: GyroSens) -> Vector3<f64> {
+        (self.raw_gyro() - offset) * sens.factor()
+    }
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("imu::Frame")
+            .field("accel", &self.raw_accel())
+            .field("gyro", &self.raw_gyro())
+            .finish()
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Sensitivity {
+    pub gyro_sens: RawId<GyroSens>,
+    pub acc_sens: RawId<AccSens>,
+    pub gyro_perf_rate: RawId<GyroPerfRate>,
+    pub acc_anti_aliasing: RawId<AccAntiAliasing>,
+}
+
+unsafe impl Wire for Sensitivity {}
+
+/// Sensitivity range of the gyroscope.
+///
+/// If using DPS2000 for example, the gyroscope can measure values of
+/// up to +-2000 degree per second for a total range of 4000 DPS over
+/// the 16 bit raw value.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive)]
+pub enum GyroSens {
+    DPS250 = 0,
+    DPS500 = 1,
+    DPS1000 = 2,
+    DPS2000 = 3,
+}
+
+impl GyroSens {
+    pub fn range_dps(self) -> u16 {
+        match self {
+            GyroSens::DPS250 => 500,
+            GyroSens::DPS500 => 1000,
+            GyroSens::DPS1000 => 2000,
+            GyroSens::DPS2000 => 4000,
+        }
+    }
+
+    /// factor from raw unit to dps
+    pub fn factor(self) -> f64 {
+        self.range_dps() as f64 * 1.147 / u16::MAX as f64
+    }
+}
+
+impl Default for GyroSens {
+    fn default() -> Self {
+        GyroSens::DPS2000
+    }
+}
+
+/// Sensitivity range of the accelerometer.
+///
+/// If using G4 for example, the accelerometer can measure values of
+/// up to +-4G for a total range of 8G over the 16 bit raw value.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive)]
+pub enum AccSens {
+    G8 = 0,
+    G4 = 1,
+    G2 = 2,
+    G16 = 3,
+}
+
+impl AccSens {
+    pub fn range_g(self) -> u16 {
+        match self {
+            AccSens::G8 => 16,
+            AccSens::G4 => 8,
+            AccSens::G2 => 4,
+            AccSens::G16 => 32,
+        }
+    }
+}
+
+impl Default for AccSens {
+    fn default() -> Self {
+        AccSens::G8
+    }
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive)]
+pub enum GyroPerfRate {
+    Hz833 = 0,
+    Hz208 = 1,
+}
+
+impl Default for GyroPerfRate {
+    fn default() -> Self {
+        GyroPerfRate::Hz208
+    }
+}
+
+/// Anti-aliasing setting of the accelerometer.
+///
+/// Accelerations frequencies above the value are ignored using a low-pass filter.
+///
+/// See <https://blog.endaq.com/filter-selection-for-shock-and-vibration-applications>.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive)]
+pub enum AccAntiAliasing {
+    Hz200 = 0,
+    Hz100 = 1,
+}
+
+impl Default for AccAntiAliasing {
+    fn default() -> Self {
+        AccAntiAliasing::Hz100
+    }
+}
+
+/// Argument of the `WriteIMURegister` subcommand, writing one register of the
+/// LSM6DS3 IMU.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RegisterWrite {
+    pub address: u8,
+    _constant: u8,
+    pub value: u8,
+}
+
+unsafe impl Wire for RegisterWrite {}
+
+impl RegisterWrite {
+    pub const fn new(address: u8, value: u8) -> RegisterWrite {
+        RegisterWrite {
+            address,
+            _constant: 1,
+            value,
+        }
+    }
+}
+
+/// Argument of the `ReadIMURegisters` subcommand.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug)]
+pub struct RegistersRead {
+    pub address: u8,
+    /// At most 0x20.
+    pub count: u8,
+}
+
+unsafe impl Wire for RegistersRead {}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug)]
+pub struct RegistersReadResult {
+    pub address: u8,
+    pub count: u8,
+    values: [u8; 0x20],
+}
+
+unsafe impl Wire for RegistersReadResult {}
+
+impl RegistersReadResult {
+    pub fn new(address: u8, values: &[u8]) -> RegistersReadResult {
+        assert!(values.len() <= 0x20);
+        let mut raw = [0; 0x20];
+        raw[..values.len()].copy_from_slice(values);
+        RegistersReadResult {
+            address,
+            count: values.len() as u8,
+            values: raw,
+        }
+    }
+
+    pub fn values(&self) -> &[u8] {
+        &self.values[..(self.count as usize).min(0x20)]
+    }
+}
+
+/// Identification register, reading [`LSM6DS3_ID`].
+pub const REG_WHO_AM_I: u8 = 0x0F;
+/// Value of [`REG_WHO_AM_I`] on the LSM6DS3.
+pub const LSM6DS3_ID: u8 = 0x69;
+/// Accelerometer control register: output data rate, full scale and anti-aliasing.
+pub const REG_CTRL1_XL: u8 = 0x10;
+/// Gyroscope control register: output data rate and full scale.
+pub const REG_CTRL2_G: u8 = 0x11;
+/// Accelerometer high-performance mode, disabled when bit 4 is set.
+pub const REG_CTRL6_C: u8 = 0x15;
+/// Gyroscope high-performance mode, disabled when bit 7 is set.
+pub const REG_CTRL7_G: u8 = 0x16;
+
+/// Tuned register configurations of the IMU.
+///
+/// The full scale set by a profile must be used to convert the raw values,
+/// see [`IMUProfile::sensitivity`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IMUProfile {
+    /// 833Hz output rate with the widest anti-aliasing filter, ±8G and ±2000dps.
+    LowLatency,
+    /// 833Hz output rate with the narrowest anti-aliasing filter, ±2G and
+    /// ±250dps for slow and precise movements.
+    HighResolution,
+    /// 208Hz output rate with the high-performance modes disabled, ±8G and
+    /// ±2000dps.
+    PowerSave,
+}
+
+impl IMUProfile {
+    /// Registers to write, in order.
+    pub fn registers(self) -> &'static [RegisterWrite] {
+        // CTRL1_XL and CTRL2_G: output data rate in the high nibble, then the
+        // full scale and the anti-aliasing bandwidth (accelerometer only).
+        const LOW_LATENCY: &[RegisterWrite] = &[
+            RegisterWrite::new(REG_CTRL6_C, 0x00),
+            RegisterWrite::new(REG_CTRL7_G, 0x00),
+            RegisterWrite::new(REG_CTRL1_XL, 0b0111_1100),
+            RegisterWrite::new(REG_CTRL2_G, 0b0111_1100),
+        ];
+        const HIGH_RESOLUTION: &[RegisterWrite] = &[
+            RegisterWrite::new(REG_CTRL6_C, 0x00),
+            RegisterWrite::new(REG_CTRL7_G, 0x00),
+            RegisterWrite::new(REG_CTRL1_XL, 0b0111_0011),
+            RegisterWrite::new(REG_CTRL2_G, 0b0111_0000),
+        ];
+        const POWER_SAVE: &[RegisterWrite] = &[
+            RegisterWrite::new(REG_CTRL1_XL, 0b0101_1101),
+            RegisterWrite::new(REG_CTRL2_G, 0b0101_1100),
+            RegisterWrite::new(REG_CTRL6_C, 0x10),
+            RegisterWrite::new(REG_CTRL7_G, 0x80),
+        ];
+        match self {
+            IMUProfile::LowLatency => LOW_LATENCY,
+            IMUProfile::HighResolution => HIGH_RESOLUTION,
+            IMUProfile::PowerSave => POWER_SAVE,
+        }
+    }
+
+    /// Full scale of the sensors once the profile is applied.
+    pub fn sensitivity(self) -> (GyroSens, AccSens) {
+        match self {
+            IMUProfile::LowLatency | IMUProfile::PowerSave => (GyroSens::DPS2000, AccSens::G8),
+            IMUProfile::HighResolution => (GyroSens::DPS250, AccSens::G2),
+        }
+    }
+
+    /// Subcommands applying the profile, to be sent one after the other.
+    pub fn subcommands(self) -> impl Iterator<Item = crate::output::SubcommandRequest> {
+        self.registers().iter().map(|&write| write.into())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn imu_profile_subcommands() {
+    let subcmds: Vec<_> = IMUProfile::HighResolution.subcommands().collect();
+    assert_eq!(subcmds.len(), 4);
+    let last = subcmds[3].write_imu_register().unwrap();
+    assert_eq!(last.address, REG_CTRL2_G);
+    assert_eq!(last.value, 0x70);
+}
@@ -0,0 +1,172 @@
+//! Choice of the report mode and IMU configuration for a sample rate.
+//!
+//! In standard full mode each report carries the last three IMU frames,
+//! sampled every [`IMU_SAMPLE_DURATION`], for 200 samples per second. Lower
+//! rates use only the latest frame of each report. The actual interval
+//! between two frames is measured with the report timer, which advances once
+//! per sample.
+//!
+//! ```ignore
+//! let plan = ImuRatePlan::new(60);
+//! for subcmd in plan.subcommands() {
+//!     joycon.call_subcmd_wait(subcmd)?;
+//! }
+//! let mut frames = ImuFrames::new(plan);
+//! for timed in frames.push(&report) {
+//!     fusion.update(timed.frame, timed.dt);
+//! }
+//! ```
+
+use super::{Frame, IMUMode, IMUProfile, IMU_SAMPLES_PER_SECOND, IMU_SAMPLE_DURATION};
+use crate::{
+    common::InputReportId,
+    input::InputReport,
+    output::{SubcommandRequest, SubcommandRequestEnum},
+};
+use std::time::Duration;
+
+/// Number of IMU frames carried by a standard full report.
+pub const FRAMES_PER_REPORT: usize = 3;
+
+/// Report mode and IMU configuration giving a sample rate.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ImuRatePlan {
+    /// `StandardFull`, or `StandardFullMCU` when the MCU is used too.
+    pub report_mode: InputReportId,
+    pub profile: IMUProfile,
+    /// Number of frames used in each report, the most recent ones.
+    pub frames_per_report: usize,
+}
+
+impl ImuRatePlan {
+    /// Plan for the lowest rate at or above `hz`, or the highest rate if
+    /// `hz` isn't achievable.
+    pub fn new(hz: u32) -> ImuRatePlan {
+        let reports_per_second = IMU_SAMPLES_PER_SECOND / FRAMES_PER_REPORT as u32;
+        let (profile, frames_per_report) = if hz <= reports_per_second {
+            (IMUProfile::PowerSave, 1)
+        } else if hz <= IMU_SAMPLES_PER_SECOND {
+            (IMUProfile::PowerSave, FRAMES_PER_REPORT)
+        } else {
+            // Can't go faster, but the higher output data rate of the IMU
+            // lowers the latency of each sample.
+            (IMUProfile::LowLatency, FRAMES_PER_REPORT)
+        };
+        ImuRatePlan {
+            report_mode: InputReportId::StandardFull,
+            profile,
+            frames_per_report,
+        }
+    }
+
+    pub fn with_report_mode(mut self, report_mode: InputReportId) -> ImuRatePlan {
+        assert!(
+            report_mode == InputReportId::StandardFull
+                || report_mode == InputReportId::StandardFullMCU,
+            "report mode without IMU frames"
+        );
+        self.report_mode = report_mode;
+        self
+    }
+
+    /// Nominal interval between two frames.
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f64(IMU_SAMPLE_DURATION)
+            * (FRAMES_PER_REPORT / self.frames_per_report) as u32
+    }
+
+    /// Nominal number of frames per second.
+    pub fn sample_rate(&self) -> f64 {
+        1. / self.frame_interval().as_secs_f64()
+    }
+
+    /// Subcommands applying the plan, to be sent one after the other.
+    pub fn subcommands(&self) -> Vec<SubcommandRequest> {
+        let mut subcmds = vec![
+            SubcommandRequestEnum::SetInputReportMode(self.report_mode.into()).into(),
+            SubcommandRequestEnum::SetIMUMode(IMUMode::GyroAccel.into()).into(),
+        ];
+        subcmds.extend(self.profile.subcommands());
+        subcmds
+    }
+}
+
+/// IMU frame with the time elapsed since the previous one.
+#[derive(Copy, Clone, Debug)]
+pub struct TimedFrame {
+    pub frame: Frame,
+    pub dt: Duration,
+}
+
+/// Extracts the frames of an [`ImuRatePlan`] from the input reports.
+#[derive(Copy, Clone, Debug)]
+pub struct ImuFrames {
+    plan: ImuRatePlan,
+    last_timer: Option<u8>,
+}
+
+impl ImuFrames {
+    pub fn new(plan: ImuRatePlan) -> ImuFrames {
+        ImuFrames {
+            plan,
+            last_timer: None,
+        }
+    }
+
+    /// Forget the previous report, for example after a mode change.
+    pub fn reset(&mut self) {
+        self.last_timer = None;
+    }
+
+    /// Frames of `report` used by the plan, oldest first.
+    ///
+    /// The interval before the first frame is measured with the report timer,
+    /// so it includes the frames lost with dropped reports.
+    pub fn push(&mut self, report: &InputReport) -> Vec<TimedFrame> {
+        let (frames, timer) = match (report.imu_frames(), report.standard()) {
+            (Some(frames), Some(std)) => (frames, std.timer),
+            _ => return Vec::new(),
+        };
+        let used = self.plan.frames_per_report.clamp(1, FRAMES_PER_REPORT);
+        let sample = Duration::from_secs_f64(IMU_SAMPLE_DURATION);
+        let first_dt = match self.last_timer {
+            Some(last) => {
+                let ticks = u32::from(timer.wrapping_sub(last));
+                sample * ticks.saturating_sub(used as u32 - 1).max(1)
+            }
+            None => self.plan.frame_interval(),
+        };
+        self.last_timer = Some(timer);
+        frames[FRAMES_PER_REPORT - used..]
+            .iter()
+            .enumerate()
+            .map(|(i, &frame)| TimedFrame {
+                frame,
+                dt: if i == 0 { first_dt } else { sample },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn imu_rate_plan() {
+    let slow = ImuRatePlan::new(60);
+    assert_eq!(slow.frames_per_report, 1);
+    assert_eq!(slow.frame_interval(), Duration::from_millis(15));
+    let fast = ImuRatePlan::new(1000);
+    assert_eq!(fast.frames_per_report, 3);
+    assert_eq!(fast.profile, IMUProfile::LowLatency);
+    assert_eq!(fast.subcommands().len(), 2 + fast.profile.registers().len());
+
+    let mut report = InputReport::new();
+    report.as_bytes_mut()[0] = InputReportId::StandardFull as u8;
+    let mut frames = ImuFrames::new(ImuRatePlan::new(200));
+    assert_eq!(frames.push(&report)[0].dt, Duration::from_millis(5));
+    // One report dropped.
+    report.as_bytes_mut()[1] = 6;
+    let timed = frames.push(&report);
+    assert_eq!(timed.len(), 3);
+    assert_eq!(timed[0].dt, Duration::from_millis(20));
+    assert_eq!(timed[1].dt, Duration::from_millis(5));
+}
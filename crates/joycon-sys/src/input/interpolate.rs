@@ -0,0 +1,145 @@
+//! Filling of the IMU frames lost with dropped reports.
+//!
+//! In [`InputReportId::StandardFull`] mode each report carries three IMU
+//! frames and the report timer advances by [`TIMER_TICKS_PER_REPORT`]. When
+//! the Bluetooth link drops reports, the timer jumps and sensor fusion sees a
+//! discontinuity. [`FrameInterpolator`] detects the jump and synthesizes the
+//! missing frames by linear interpolation between the last frame received and
+//! the first frame of the new report.
+//!
+//! ```ignore
+//! let mut interpolator = FrameInterpolator::new();
+//! for frame in interpolator.push(&report) {
+//!     fusion.update(frame.frame, frame.synthesized);
+//! }
+//! ```
+
+use crate::{
+    imu::{self, FRAMES_PER_REPORT},
+    input::InputReport,
+};
+
+/// Timer increase between two consecutive reports in standard full mode.
+pub const TIMER_TICKS_PER_REPORT: u8 = 3;
+
+/// Frame returned by [`FrameInterpolator::push`].
+#[derive(Copy, Clone, Debug)]
+pub struct InterpolatedFrame {
+    pub frame: imu::Frame,
+    /// The frame was not sent by the controller but estimated to fill a gap.
+    pub synthesized: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct FrameInterpolator {
+    last: Option<(u8, imu::Frame)>,
+    max_dropped_reports: usize,
+}
+
+impl FrameInterpolator {
+    pub fn new() -> FrameInterpolator {
+        FrameInterpolator {
+            last: None,
+            max_dropped_reports: 4,
+        }
+    }
+
+    /// Gaps longer than `reports` dropped reports are not filled, the
+    /// estimation being too far from reality.
+    pub fn with_max_dropped_reports(mut self, reports: usize) -> FrameInterpolator {
+        self.max_dropped_reports = reports;
+        self
+    }
+
+    /// Forget the previous report, for example after a mode change.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Frames of `report`, preceded by the synthesized frames if reports were
+    /// dropped since the previous call.
+    ///
+    /// Returns nothing if the report doesn't contain IMU data.
+    pub fn push(&mut self, report: &InputReport) -> Vec<InterpolatedFrame> {
+        let (timer, frames) = match (report.standard(), report.imu_frames()) {
+            (Some(std), Some(frames)) => (std.timer, *frames),
+            _ => return Vec::new(),
+        };
+        let mut out = Vec::with_capacity(FRAMES_PER_REPORT);
+        if let Some((last_timer, last_frame)) = self.last {
+            let dropped = self.dropped_reports(timer.wrapping_sub(last_timer));
+            if 0 < dropped && dropped <= self.max_dropped_reports {
+                let missing = dropped * FRAMES_PER_REPORT;
+                let (accel0, gyro0) = (last_frame.raw_accel(), last_frame.raw_gyro());
+                let (accel1, gyro1) = (frames[0].raw_accel(), frames[0].raw_gyro());
+                for i in 1..=missing {
+                    let t = i as f64 / (missing + 1) as f64;
+                    out.push(InterpolatedFrame {
+                        frame: imu::Frame::new(
+                            accel0 + (accel1 - accel0) * t,
+                            gyro0 + (gyro1 - gyro0) * t,
+                        ),
+                        synthesized: true,
+                    });
+                }
+            }
+        }
+        out.extend(frames.iter().map(|&frame| InterpolatedFrame {
+            frame,
+            synthesized: false,
+        }));
+        self.last = Some((timer, frames[FRAMES_PER_REPORT - 1]));
+        out
+    }
+
+    fn dropped_reports(&self, delta: u8) -> usize {
+        // Round to the nearest report to absorb the timer jitter.
+        let reports = (delta as usize + TIMER_TICKS_PER_REPORT as usize / 2)
+            / TIMER_TICKS_PER_REPORT as usize;
+        reports.saturating_sub(1)
+    }
+}
+
+impl Default for FrameInterpolator {
+    fn default() -> Self {
+        FrameInterpolator::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn fill_dropped_reports() {
+    use crate::{common::Wire, InputReportId};
+    use cgmath::Vector3;
+
+    let report = |timer: u8, accel_x: f64| {
+        let mut report = InputReport::new();
+        let raw = report.as_bytes_mut();
+        raw[0] = InputReportId::StandardFull as u8;
+        raw[1] = timer;
+        for i in 0..3 {
+            let frame = imu::Frame::new(
+                Vector3::new(accel_x + i as f64, 0., 0.),
+                Vector3::new(0., 0., 0.),
+            );
+            raw[13 + 12 * i..25 + 12 * i].copy_from_slice(&frame.to_bytes());
+        }
+        report
+    };
+
+    let mut interpolator = FrameInterpolator::new();
+    assert_eq!(interpolator.push(&report(254, 0.)).len(), 3);
+    let frames = interpolator.push(&report(1, 3.));
+    assert!(frames.iter().all(|f| !f.synthesized));
+
+    // One report dropped: timer 4 skipped.
+    let frames = interpolator.push(&report(7, 10.));
+    assert_eq!(frames.len(), 6);
+    assert!(frames[..3].iter().all(|f| f.synthesized));
+    assert_eq!(frames[1].frame.raw_accel().x, 7.);
+    assert_eq!(frames[3].frame.raw_accel().x, 10.);
+    assert!(!frames[3].synthesized);
+
+    // Gap too long to be filled.
+    assert_eq!(interpolator.push(&report(100, 0.)).len(), 3);
+}
@@ -1,5 +1,13 @@
 mod values;
 mod report;
+mod stream;
+mod reply_len;
+mod reassembly;
+mod interpolate;
 
 pub use values::*;
 pub use report::*;
+pub use stream::*;
+pub use reply_len::*;
+pub use reassembly::*;
+pub use interpolate::*;
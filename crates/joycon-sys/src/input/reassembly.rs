@@ -0,0 +1,140 @@
+//! Reassembly of subcommand replies spanning several reports.
+//!
+//! A `0x21` report carries at most [`REPLY_DATA_LEN`] bytes of reply data.
+//! Longer replies, eg. some accessory reads, continue in the data of the next
+//! replies to the same subcommand. [`ReplyAssembler`] concatenates them:
+//!
+//! ```ignore
+//! let mut assembler = ReplyAssembler::new();
+//! assembler.expect(SubcommandId::MaybeAccessory, 60);
+//! send(&SubcommandRequest::from(command).into())?;
+//! let reply = loop {
+//!     if let Some(reply) = assembler.push(&recv()?) {
+//!         break reply;
+//!     }
+//! };
+//! let header: AccessoryResponse = reply.parse().unwrap();
+//! ```
+
+use crate::{
+    common::{SubcommandId, Wire},
+    input::{Ack, InputReport},
+};
+use std::mem::size_of;
+
+/// Reply data in one `0x21` report.
+pub const REPLY_DATA_LEN: usize = 34;
+
+/// Complete reply data, see [`ReplyAssembler`].
+#[derive(Clone, Debug)]
+pub struct AssembledReply {
+    pub id: SubcommandId,
+    /// Acknowledgement of the last report, the data being incomplete if
+    /// negative.
+    pub ack: Ack,
+    pub data: Vec<u8>,
+}
+
+impl AssembledReply {
+    /// Interpret the start of the data as `T`.
+    pub fn parse<T: Wire>(&self) -> Option<T> {
+        T::from_bytes(self.data.get(..size_of::<T>())?)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Pending {
+    id: SubcommandId,
+    len: usize,
+    data: Vec<u8>,
+}
+
+/// Concatenates the replies of the subcommands expecting a long reply.
+#[derive(Clone, Debug, Default)]
+pub struct ReplyAssembler {
+    pending: Vec<Pending>,
+}
+
+impl ReplyAssembler {
+    pub fn new() -> ReplyAssembler {
+        ReplyAssembler::default()
+    }
+
+    /// Wait for `len` bytes of reply data to `id`, replacing the previous
+    /// expectation for the same subcommand.
+    pub fn expect(&mut self, id: SubcommandId, len: usize) {
+        self.cancel(id);
+        self.pending.push(Pending {
+            id,
+            len,
+            data: Vec::with_capacity(len),
+        });
+    }
+
+    /// Drop the data received for `id`, eg. after a timeout.
+    pub fn cancel(&mut self, id: SubcommandId) {
+        self.pending.retain(|p| p.id != id);
+    }
+
+    pub fn is_pending(&self, id: SubcommandId) -> bool {
+        self.pending.iter().any(|p| p.id == id)
+    }
+
+    /// Add the data of a received report, returning the reply once complete
+    /// or not acknowledged.
+    ///
+    /// The reports not replying to an expected subcommand are ignored.
+    pub fn push(&mut self, report: &InputReport) -> Option<AssembledReply> {
+        let reply = report.subcmd_reply()?;
+        let id = reply.id().try_into()?;
+        let index = self.pending.iter().position(|p| p.id == id)?;
+        let data = report.raw_reply()?;
+        let pending = &mut self.pending[index];
+        let missing = pending.len - pending.data.len();
+        pending
+            .data
+            .extend_from_slice(&data[..missing.min(data.len())]);
+        trace!(
+            "reply to {:?}: {:?}/{:?} bytes",
+            id,
+            pending.data.len(),
+            pending.len
+        );
+        if pending.data.len() < pending.len && reply.ack().is_ok() {
+            return None;
+        }
+        let Pending { data, .. } = self.pending.remove(index);
+        Some(AssembledReply {
+            id,
+            ack: *reply.ack(),
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn reassemble_reply() {
+    let reply = |data: &[u8]| {
+        let mut report = InputReport::new();
+        let raw = report.as_bytes_mut();
+        raw[0] = 0x21;
+        raw[13..15].copy_from_slice(&[0x80, 0x58]);
+        raw[15..15 + data.len()].copy_from_slice(data);
+        report
+    };
+    let mut assembler = ReplyAssembler::new();
+    assert!(assembler.push(&reply(&[1; 34])).is_none());
+    assembler.expect(SubcommandId::MaybeAccessory, 40);
+    assert!(assembler.push(&reply(&[1; 34])).is_none());
+    assert!(assembler.is_pending(SubcommandId::MaybeAccessory));
+    let assembled = assembler.push(&reply(&[2; 34])).unwrap();
+    assert!(assembled.ack.is_ok());
+    assert_eq!(assembled.data.len(), 40);
+    assert_eq!(assembled.data[33..35], [1, 2]);
+    assert_eq!(
+        assembled.parse::<crate::U16LE>().map(u16::from),
+        Some(0x0101)
+    );
+    assert!(!assembler.is_pending(SubcommandId::MaybeAccessory));
+}
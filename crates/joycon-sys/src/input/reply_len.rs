@@ -0,0 +1,207 @@
+//! Expected length of the reply of each subcommand.
+//!
+//! Some Bluetooth stacks truncate the `0x21` reports, or leave garbage after
+//! the reply. The report still parses, the missing or extra bytes showing up
+//! as wrong fields. [`check_reply_len`] detects both from the bytes received:
+//!
+//! ```ignore
+//! let nb_read = device.read(&mut buf)?;
+//! if let Err(warning) = check_reply_len(&buf[..nb_read]) {
+//!     log::warn!("{}", warning);
+//! }
+//! ```
+
+use crate::{
+    common::{InputReportId, RawId, SubcommandId},
+    input::{DeviceInfo, REPLY_DATA_LEN},
+};
+use std::{fmt, mem::size_of};
+
+/// Offset of the reply data in a `0x21` report, after the standard report,
+/// the ack byte and the subcommand id.
+const REPLY_DATA_OFFSET: usize = 15;
+
+/// Length of the data of a subcommand reply.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ReplyLen {
+    Fixed(usize),
+    /// A header of this size, its last byte being the size of the data
+    /// following it.
+    Sized(usize),
+    /// Not known, the reply isn't checked.
+    Unknown,
+}
+
+/// Length of the data of the reply to `id`.
+pub const fn reply_len(id: SubcommandId) -> ReplyLen {
+    match id {
+        SubcommandId::GetOnlyControllerState => ReplyLen::Fixed(0),
+        // Depends on the step.
+        SubcommandId::BluetoothManualPairing => ReplyLen::Unknown,
+        SubcommandId::RequestDeviceInfo => ReplyLen::Fixed(size_of::<DeviceInfo>()),
+        SubcommandId::SetInputReportMode => ReplyLen::Fixed(0),
+        SubcommandId::GetTriggerButtonsElapsedTime => ReplyLen::Fixed(14),
+        SubcommandId::SetHCIState => ReplyLen::Fixed(0),
+        SubcommandId::ResetPairingInfo => ReplyLen::Fixed(0),
+        SubcommandId::SetShipmentMode => ReplyLen::Fixed(0),
+        // Address and size.
+        SubcommandId::SPIRead => ReplyLen::Sized(5),
+        // Status.
+        SubcommandId::SPIWrite => ReplyLen::Fixed(1),
+        // The start of the MCU report, filling the rest of the report.
+        SubcommandId::SetMCUConf => ReplyLen::Fixed(REPLY_DATA_LEN),
+        SubcommandId::SetMCUState => ReplyLen::Fixed(0),
+        SubcommandId::SetUnknownData => ReplyLen::Fixed(0),
+        SubcommandId::SetUnknownMCUData => ReplyLen::Fixed(0),
+        SubcommandId::GetUnknownMCUData => ReplyLen::Unknown,
+        SubcommandId::SetGPIOPinOutput => ReplyLen::Fixed(0),
+        SubcommandId::SetPlayerLights => ReplyLen::Fixed(0),
+        SubcommandId::SetHomeLight => ReplyLen::Fixed(0),
+        SubcommandId::SetIMUMode => ReplyLen::Fixed(0),
+        SubcommandId::SetIMUSens => ReplyLen::Fixed(0),
+        SubcommandId::WriteIMURegister => ReplyLen::Fixed(0),
+        // Address and count.
+        SubcommandId::ReadIMURegisters => ReplyLen::Sized(2),
+        SubcommandId::EnableVibration => ReplyLen::Fixed(0),
+        // Depends on the accessory command.
+        SubcommandId::MaybeAccessory => ReplyLen::Unknown,
+        // Only the start, the status and the device type, is known.
+        SubcommandId::Unknown0x59 => ReplyLen::Unknown,
+        SubcommandId::Unknown0x5a => ReplyLen::Fixed(0),
+        SubcommandId::Unknown0x5b => ReplyLen::Fixed(0),
+        SubcommandId::Unknown0x5c => ReplyLen::Fixed(0),
+    }
+}
+
+/// Reply whose length doesn't match [`reply_len`], see [`check_reply_len`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ReplyLenWarning {
+    /// The report ends before the end of the reply data.
+    Truncated {
+        id: SubcommandId,
+        len: usize,
+        expected: usize,
+    },
+    /// Non-zero bytes follow the reply data, up to `len`.
+    Padded {
+        id: SubcommandId,
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for ReplyLenWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplyLenWarning::Truncated { id, len, expected } => write!(
+                f,
+                "{:?} reply truncated to {} bytes, expected {}",
+                id, len, expected
+            ),
+            ReplyLenWarning::Padded { id, len, expected } => write!(
+                f,
+                "{:?} reply padded to {} bytes, expected {}",
+                id, len, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplyLenWarning {}
+
+/// Check the length of the subcommand reply in `data`, a report as
+/// received, starting with its id.
+///
+/// Other reports, unknown subcommands and replies of [`ReplyLen::Unknown`]
+/// length are not checked.
+pub fn check_reply_len(data: &[u8]) -> Result<(), ReplyLenWarning> {
+    if data.len() < REPLY_DATA_OFFSET
+        || RawId::<InputReportId>::new(data[0]) != InputReportId::StandardAndSubcmd
+    {
+        return Ok(());
+    }
+    let id = match RawId::<SubcommandId>::new(data[REPLY_DATA_OFFSET - 1]).try_into() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+    let reply = &data[REPLY_DATA_OFFSET..];
+    let truncated = |expected| ReplyLenWarning::Truncated {
+        id,
+        len: reply.len(),
+        expected,
+    };
+    let expected = match reply_len(id) {
+        ReplyLen::Fixed(len) => len,
+        ReplyLen::Sized(header) => match reply.get(header - 1) {
+            Some(&size) => header + size as usize,
+            None => return Err(truncated(header)),
+        },
+        ReplyLen::Unknown => return Ok(()),
+    };
+    if reply.len() < expected {
+        trace!("{:?} reply truncated: {:?}", id, reply.len());
+        return Err(truncated(expected));
+    }
+    match reply.iter().rposition(|&b| b != 0) {
+        Some(last) if last >= expected => {
+            trace!("{:?} reply padded: {:?}", id, last + 1);
+            Err(ReplyLenWarning::Padded {
+                id,
+                len: last + 1,
+                expected,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn reply_len_check() {
+    use crate::coverage::{SubcommandCoverage, Support};
+
+    let mut report = [0; 49];
+    report[0] = InputReportId::StandardAndSubcmd as u8;
+    report[13] = 0x90;
+    report[14] = SubcommandId::SPIRead as u8;
+    report[15..20].copy_from_slice(&[0x50, 0x60, 0, 0, 3]);
+    report[20..23].copy_from_slice(&[1, 2, 3]);
+    assert_eq!(check_reply_len(&report), Ok(()));
+    // Zero padding, eg. of USB reports.
+    assert_eq!(check_reply_len(&[&report[..], &[0; 15]].concat()), Ok(()));
+
+    assert_eq!(
+        check_reply_len(&report[..21]),
+        Err(ReplyLenWarning::Truncated {
+            id: SubcommandId::SPIRead,
+            len: 6,
+            expected: 8,
+        })
+    );
+    assert!(check_reply_len(&report[..17]).is_err());
+    report[30] = 0xFF;
+    let warning = check_reply_len(&report).unwrap_err();
+    assert_eq!(
+        warning,
+        ReplyLenWarning::Padded {
+            id: SubcommandId::SPIRead,
+            len: 16,
+            expected: 8,
+        }
+    );
+    assert_eq!(
+        warning.to_string(),
+        "SPIRead reply padded to 16 bytes, expected 8"
+    );
+
+    report[14] = SubcommandId::SetMCUConf as u8;
+    assert_eq!(check_reply_len(&report), Ok(()));
+    assert!(check_reply_len(&[0x30; 49]).is_ok());
+
+    // Consistent with the subcommands known to reply without data.
+    for &id in crate::output::SubcommandRequestEnum::VARIANTS {
+        if SubcommandCoverage::of(id).reply == Support::Empty {
+            assert_eq!(reply_len(id), ReplyLen::Fixed(0), "{:?}", id);
+        }
+    }
+}
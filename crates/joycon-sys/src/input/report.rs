@@ -2,13 +2,20 @@
 //!
 //! <https://github.com/dekuNukem/Nintendo_Switch_Reverse_Engineering/blob/master/bluetooth_hid_notes.md#input-reports>
 
-use crate::{accessory::AccessoryResponse, common::*, imu, input::*, mcu::*, raw_enum, spi::*};
-use std::{fmt, mem::size_of_val};
+use crate::{
+    accessory::AccessoryResponse, common::*, external, imu, input::*, mcu::*, pairing, raw_enum,
+    spi::*,
+};
+use std::{
+    fmt,
+    mem::{size_of, size_of_val},
+};
 
 raw_enum! {
     #[id: InputReportId]
     #[union: InputReportUnion]
     #[struct: InputReport]
+    #[layout offset = 1, size = 362]
     pub enum InputReportEnum {
         normal normal_mut: Normal = NormalInputReport,
         standard_subcmd standard_subcmd_mut: StandardAndSubcmd = (
@@ -28,6 +35,9 @@ raw_enum! {
     }
 }
 
+/// Bluetooth HIDP header of the input reports, `DATA | Input`.
+pub(crate) const HIDP_INPUT_HEADER: u8 = 0xA1;
+
 // Describes a HID report from the JoyCon.
 //
 // ```ignore
@@ -47,12 +57,11 @@ impl InputReport {
                 .unwrap_or(true)
     }
 
+    /// Size of the report on the wire, the whole buffer for the ids of
+    /// unknown size.
     pub fn len(&self) -> usize {
         match self.id.try_into() {
-            Some(InputReportId::Normal) => 12,
-            Some(InputReportId::StandardAndSubcmd) | Some(InputReportId::StandardFull) => 49,
-            Some(InputReportId::StandardFullMCU) => 362,
-            Some(InputReportId::MCUFwUpdate) => unimplemented!(),
+            Some(id) => report_len(id as u8).unwrap_or_else(|| size_of_val(self)),
             None => size_of_val(self),
         }
     }
@@ -65,6 +74,30 @@ impl InputReport {
         unsafe { std::slice::from_raw_parts_mut(self as *mut _ as *mut u8, size_of_val(self)) }
     }
 
+    /// Copy a report received as a byte slice, checking its id and length.
+    pub(crate) fn from_slice(data: &[u8]) -> Option<InputReport> {
+        let len = report_len(*data.first()?)?;
+        let mut report = InputReport::new();
+        let bytes = report.as_bytes_mut();
+        if data.len() < len || data.len() > bytes.len() {
+            return None;
+        }
+        bytes[..data.len()].copy_from_slice(data);
+        Some(report)
+    }
+
+    /// Parse a report from its hexadecimal bytes, eg. copied from Wireshark,
+    /// without allocating.
+    ///
+    /// ```ignore
+    /// let report = InputReport::from_hex_str("a1 21 5e 8e 00 00 00 ...")?;
+    /// ```
+    pub fn from_hex_str(s: &str) -> Result<InputReport, HexError> {
+        let mut buf = [0; size_of::<InputReport>()];
+        let bytes = parse_hex_report(s, HIDP_INPUT_HEADER, &mut buf)?;
+        InputReport::from_slice(bytes).ok_or(HexError::InvalidReport)
+    }
+
     pub fn validate(&self) {
         match self.id.try_into() {
             Some(_) => {
@@ -79,6 +112,23 @@ impl InputReport {
         }
     }
 
+    /// Zero the identifiers in the report, the MAC address, the SPI data of
+    /// the serial number and pairing information, and the address and link
+    /// key of a manual pairing reply, before logging or sharing it.
+    pub fn redact(&mut self) {
+        if let Some((_, reply)) = self.standard_subcmd_mut() {
+            if let Some(info) = reply.device_info_mut() {
+                info.redact();
+            }
+            if let Some(result) = reply.spi_read_result_mut() {
+                result.redact();
+            }
+            if let Some(pairing) = reply.bluetooth_manual_pairing_mut() {
+                pairing.redact();
+            }
+        }
+    }
+
     pub fn standard(&self) -> Option<&StandardInputReport> {
         if self.id == InputReportId::StandardAndSubcmd
             || self.id == InputReportId::StandardFull
@@ -94,6 +144,24 @@ impl InputReport {
         self.standard_subcmd().map(|x| &x.1)
     }
 
+    /// Data of the subcommand reply, including the replies to subcommands
+    /// not modeled by [`SubcommandReplyEnum`].
+    pub fn raw_reply(&self) -> Option<&[u8]> {
+        if self.id != InputReportId::StandardAndSubcmd {
+            return None;
+        }
+        // Report id, then the ack and subcommand id of the reply.
+        self.as_bytes()
+            .get(1 + size_of::<StandardInputReport>() + 2..)
+    }
+
+    /// State sent in reply to a
+    /// [`ControllerStateRequest`](../output/struct.ControllerStateRequest.html).
+    pub fn controller_state(&self) -> Option<&StandardInputReport> {
+        self.subcmd_reply()?.controller_state()?;
+        self.standard()
+    }
+
     pub fn imu_frames(&self) -> Option<&[imu::Frame; 3]> {
         if self.id == InputReportId::StandardFull || self.id == InputReportId::StandardFullMCU {
             Some(unsafe { &self.u.standard_full.1 })
@@ -113,6 +181,7 @@ impl InputReport {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug, Default)]
 pub struct NormalInputReport {
     pub buttons: [u8; 2],
@@ -120,7 +189,70 @@ pub struct NormalInputReport {
     _filler: [u8; 8],
 }
 
+unsafe impl Wire for NormalInputReport {}
+
+impl NormalInputReport {
+    /// Report in the Pro Controller layout: the buttons, the d-pad as a hat
+    /// switch, and the 12 bits sticks scaled to 16 bits.
+    pub fn new(
+        buttons: &ButtonsStatus,
+        left_stick: Stick,
+        right_stick: Stick,
+    ) -> NormalInputReport {
+        let (left, middle, right) = (buttons.left, buttons.middle, buttons.right);
+        let buttons = [
+            right.b() as u8
+                | (right.a() as u8) << 1
+                | (right.y() as u8) << 2
+                | (right.x() as u8) << 3
+                | (left.l() as u8) << 4
+                | (right.r() as u8) << 5
+                | (left.zl() as u8) << 6
+                | (right.zr() as u8) << 7,
+            middle.minus() as u8
+                | (middle.plus() as u8) << 1
+                | (middle.lstick() as u8) << 2
+                | (middle.rstick() as u8) << 3
+                | (middle.home() as u8) << 4
+                | (middle.capture() as u8) << 5,
+        ];
+        // Clockwise from up, 8 when released.
+        let stick = match (left.up(), left.right(), left.down(), left.left()) {
+            (true, false, false, false) => 0,
+            (true, true, false, false) => 1,
+            (false, true, false, false) => 2,
+            (false, true, true, false) => 3,
+            (false, false, true, false) => 4,
+            (false, false, true, true) => 5,
+            (false, false, false, true) => 6,
+            (true, false, false, true) => 7,
+            _ => 8,
+        };
+        let mut sticks = [0; 8];
+        let axes = [
+            left_stick.x(),
+            left_stick.y(),
+            right_stick.x(),
+            right_stick.y(),
+        ];
+        for (out, axis) in sticks.chunks_mut(2).zip(axes.iter()) {
+            out.copy_from_slice(&(axis << 4).to_le_bytes());
+        }
+        NormalInputReport {
+            buttons,
+            stick,
+            _filler: sticks,
+        }
+    }
+
+    /// Button bits, the first byte being the low byte.
+    pub fn buttons_bits(&self) -> u16 {
+        u16::from_le_bytes(self.buttons)
+    }
+}
+
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug)]
 pub struct StandardInputReport {
     pub timer: u8,
@@ -131,31 +263,41 @@ pub struct StandardInputReport {
     pub vibrator: u8,
 }
 
+unsafe impl Wire for StandardInputReport {}
+
 raw_enum! {
     #[pre_id ack ack_mut: Ack]
     #[id: SubcommandId]
     #[union: SubcommandReplyUnion]
     #[struct: SubcommandReply]
     #[raw [u8; 39]]
+    #[layout offset = 2, size = 315]
     pub enum SubcommandReplyEnum {
         controller_state controller_state_mut: GetOnlyControllerState = (),
-        bluetooth_manual_pairing bluetooth_manual_pairing_mut: BluetoothManualPairing = (),
+        bluetooth_manual_pairing bluetooth_manual_pairing_mut: BluetoothManualPairing = pairing::PairingReply,
         device_info device_info_mut: RequestDeviceInfo = DeviceInfo,
         input_report_mode_result input_report_mode_result_mut: SetInputReportMode = (),
         trigger_buttons_elapsed_time trigger_buttons_elapsed_time_mut: GetTriggerButtonsElapsedTime = [U16LE; 7],
+        hci_state_result hci_state_result_mut: SetHCIState = (),
+        pairing_info_reset_result pairing_info_reset_result_mut: ResetPairingInfo = (),
         shipment_mode_result shipment_mode_result_mut: SetShipmentMode = (),
         spi_read_result spi_read_result_mut: SPIRead = SPIReadResult,
         spi_write_result spi_write_result_mut: SPIWrite = SPIWriteResult,
         mcu_report mcu_report_mut: SetMCUConf = MCUReport,
         mcu_state_result mcu_state_result_mut: SetMCUState = (),
         set_unknown_data set_unknown_data_mut: SetUnknownData = (),
+        set_unknown_mcu_data set_unknown_mcu_data_mut: SetUnknownMCUData = (),
+        unknown_mcu_data unknown_mcu_data_mut: GetUnknownMCUData = [u8; REPLY_DATA_LEN],
+        set_gpio_pin_output set_gpio_pin_output_mut: SetGPIOPinOutput = (),
         player_lights_result player_lights_result_mut: SetPlayerLights = (),
         home_light_result home_light_result_mut: SetHomeLight = (),
         imu_mode_result imu_mode_result_mut: SetIMUMode = (),
         imu_sens_result imu_sens_result_mut: SetIMUSens = (),
+        imu_register_write_result imu_register_write_result_mut: WriteIMURegister = (),
+        imu_registers imu_registers_mut: ReadIMURegisters = imu::RegistersReadResult,
         enable_vibration enable_vibration_mut: EnableVibration = (),
         maybe_accessory maybe_accessory_mut: MaybeAccessory = AccessoryResponse,
-        unknown0x59 unknown0x59_mut: Unknown0x59 = (),
+        unknown0x59 unknown0x59_mut: Unknown0x59 = external::ExternalDeviceInfo,
         unknown0x5a unknown0x5a_mut: Unknown0x5a = (),
         unknown0x5b unknown0x5b_mut: Unknown0x5b = (),
         unknown0x5c unknown0x5c_mut: Unknown0x5c = ()
@@ -171,6 +313,24 @@ impl SubcommandReply {
         )
     }
 
+    /// Reply without data to the subcommand `id`.
+    pub fn empty(id: RawId<SubcommandId>) -> SubcommandReply {
+        let mut reply = SubcommandReply::new();
+        reply.id = id;
+        reply
+    }
+
+    /// Elapsed times of `GetTriggerButtonsElapsedTime`, in units of 10ms: L,
+    /// R, ZL, ZR, SL, SR and Home.
+    pub fn trigger_elapsed_times(&self) -> Option<[u16; 7]> {
+        let times = *self.trigger_buttons_elapsed_time()?;
+        let mut out = [0; 7];
+        for (out, time) in out.iter_mut().zip(times.iter()) {
+            *out = (*time).into();
+        }
+        Some(out)
+    }
+
     pub fn is_spi_write_success(&self) -> Option<bool> {
         self.spi_write_result()
             .map(|r| self.ack.is_ok() && r.success())
@@ -178,10 +338,23 @@ impl SubcommandReply {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
 pub struct Ack(u8);
 
+unsafe impl Wire for Ack {}
+
 impl Ack {
+    /// Positive acknowledgement, `data` telling the kind of reply data.
+    pub fn ok(data: u8) -> Ack {
+        Ack(0x80 | data)
+    }
+
+    /// Negative acknowledgement, the subcommand failed.
+    pub fn nack() -> Ack {
+        Ack(0)
+    }
+
     pub fn is_ok(self) -> bool {
         (self.0 & 0x80) != 0
     }
@@ -203,7 +376,8 @@ impl fmt::Debug for Ack {
 }
 
 #[repr(packed)]
-#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct DeviceInfo {
     pub firmware_version: FirmwareVersion,
     // 1=Left Joy-Con, 2=Right Joy-Con, 3=Pro Controller
@@ -218,10 +392,38 @@ pub struct DeviceInfo {
     pub use_spi_colors: RawId<UseSPIColors>,
 }
 
+unsafe impl Wire for DeviceInfo {}
+
+impl DeviceInfo {
+    /// Zero the MAC address.
+    pub fn redact(&mut self) {
+        self.mac_address = MACAddress([0; 6]);
+    }
+
+    pub fn new(
+        firmware_version: FirmwareVersion,
+        which_controller: WhichController,
+        mac_address: MACAddress,
+        use_spi_colors: UseSPIColors,
+    ) -> DeviceInfo {
+        DeviceInfo {
+            firmware_version,
+            which_controller: which_controller.into(),
+            _something: 2,
+            mac_address,
+            _somethingelse: 1,
+            use_spi_colors: use_spi_colors.into(),
+        }
+    }
+}
+
 #[repr(packed)]
-#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct FirmwareVersion(pub [u8; 2]);
 
+unsafe impl Wire for FirmwareVersion {}
+
 impl fmt::Display for FirmwareVersion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}.{}", self.0[0], self.0[1])
@@ -229,9 +431,12 @@ impl fmt::Display for FirmwareVersion {
 }
 
 #[repr(packed)]
-#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct MACAddress(pub [u8; 6]);
 
+unsafe impl Wire for MACAddress {}
+
 impl fmt::Display for MACAddress {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -243,7 +448,7 @@ impl fmt::Display for MACAddress {
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive, Eq, PartialEq, Hash)]
 pub enum WhichController {
     LeftJoyCon = 1,
     RightJoyCon = 2,
@@ -265,7 +470,7 @@ impl fmt::Display for WhichController {
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive, Eq, PartialEq, Hash)]
 pub enum UseSPIColors {
     No = 0,
     WithoutGrip = 1,
@@ -285,3 +490,122 @@ fn check_layout() {
         assert_eq!(362, std::mem::size_of_val(&report));
     }
 }
+
+/// Offsets of the public fields, which must stay stable for FFI consumers.
+#[cfg(test)]
+#[test]
+fn wire_offsets() {
+    use std::mem::size_of;
+
+    let report = InputReport::new();
+    let std = unsafe { &report.u.standard_full.0 };
+    assert_eq!(1, offset_of(std, &std.info));
+    assert_eq!(2, offset_of(std, &std.buttons));
+    assert_eq!(5, offset_of(std, &std.left_stick));
+    assert_eq!(8, offset_of(std, &std.right_stick));
+    assert_eq!(11, offset_of(std, &std.vibrator));
+    assert_eq!(12, size_of::<StandardInputReport>());
+
+    let info = unsafe { &*(&report.u as *const _ as *const DeviceInfo) };
+    assert_eq!(2, offset_of(info, &info.which_controller));
+    assert_eq!(4, offset_of(info, &info.mac_address));
+    assert_eq!(11, offset_of(info, &info.use_spi_colors));
+    assert_eq!(12, size_of::<DeviceInfo>());
+
+    assert_eq!(12, size_of::<imu::Frame>());
+    assert_eq!(34, size_of::<SPIReadResult>());
+}
+
+#[cfg(test)]
+#[test]
+fn from_hex_str() {
+    use crate::output::{OutputReport, OutputReportId};
+
+    let normal = "a1 3f 00 00 08 00 80 00 80 00 80 00 80";
+    let report = InputReport::from_hex_str(normal).unwrap();
+    assert_eq!(report.id(), InputReportId::Normal);
+    assert_eq!(report.as_bytes()[3], 0x08);
+    assert_eq!(
+        InputReport::from_hex_str(&normal[3..]).unwrap().as_bytes(),
+        report.as_bytes()
+    );
+    assert_eq!(
+        InputReport::from_hex_str("3f 00 0").unwrap_err(),
+        HexError::InvalidDigit
+    );
+    assert_eq!(
+        InputReport::from_hex_str("3f:00:00").unwrap_err(),
+        HexError::InvalidReport
+    );
+
+    let output = OutputReport::from_hex_str("a2 10 05 00 01 40 40 00 01 40 40").unwrap();
+    assert_eq!(output.id(), OutputReportId::RumbleOnly);
+    assert_eq!(output.as_bytes()[1], 0x05);
+}
+
+#[cfg(test)]
+#[test]
+fn redact_reports() {
+    use crate::pairing::{BdAddr, PairingRequest, PairingStep};
+
+    let reply = |id: SubcommandId, data: &[u8]| {
+        let mut report = InputReport::new();
+        let raw = report.as_bytes_mut();
+        raw[0] = InputReportId::StandardAndSubcmd as u8;
+        raw[13] = 0x80;
+        raw[14] = id as u8;
+        raw[15..15 + data.len()].copy_from_slice(data);
+        report
+    };
+
+    let mut report = reply(
+        SubcommandId::RequestDeviceInfo,
+        &[4, 7, 3, 2, 1, 2, 3, 4, 5, 6],
+    );
+    report.redact();
+    assert_eq!(report.as_bytes()[15..25], [4, 7, 3, 2, 0, 0, 0, 0, 0, 0]);
+
+    // Serial number and the start of the hardware info.
+    let mut report = reply(SubcommandId::SPIRead, &[0x08, 0x60, 0, 0, 0x0c]);
+    report.as_bytes_mut()[20..32].copy_from_slice(&[0xaa; 12]);
+    report.redact();
+    assert_eq!(
+        report.as_bytes()[20..32],
+        [0, 0, 0, 0, 0, 0, 0, 0, 0xaa, 0xaa, 0xaa, 0xaa]
+    );
+
+    let mut write = crate::OutputReport::from(crate::output::SubcommandRequest::from(unsafe {
+        SPIWriteRequest::new(SPIRange::new(0x2000, 4), &[1, 2, 3, 4])
+    }));
+    write.redact();
+    assert_eq!(
+        write.rumble_subcmd().unwrap().spi_write().unwrap().data(),
+        [0; 4]
+    );
+
+    let mut report = reply(SubcommandId::BluetoothManualPairing, &[2, 0x12, 0x34]);
+    report.redact();
+    assert_eq!(report.as_bytes()[15..18], [2, 0, 0]);
+    let request = PairingRequest::new(PairingStep::SendHostAddress, BdAddr([1; 6]));
+    let mut request = crate::OutputReport::from(crate::output::SubcommandRequest::from(request));
+    request.redact();
+    assert_eq!(
+        request
+            .rumble_subcmd()
+            .unwrap()
+            .bluetooth_manual_pairing()
+            .unwrap()
+            .host_address(),
+        BdAddr([0; 6])
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn unsized_report_len() {
+    let mut report = InputReport::new();
+    report.as_bytes_mut()[0] = InputReportId::MCUFwUpdate as u8;
+    assert_eq!(report.len(), size_of_val(&report));
+    report.as_bytes_mut()[0] = InputReportId::StandardFull as u8;
+    assert_eq!(report.len(), 49);
+}
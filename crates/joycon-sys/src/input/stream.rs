@@ -0,0 +1,215 @@
+//! Splitting of raw byte streams into input reports.
+//!
+//! Some platforms deliver several HID reports in one read, or split one report
+//! across several reads. [`ReportBuffer`] keeps the incomplete tail between two
+//! reads and [`ReportStream`] yields the complete reports.
+//!
+//! ```ignore
+//! let mut buffer = ReportBuffer::new();
+//! for report in buffer.feed(&raw[..nb_read]) {
+//!     handle(report?);
+//! }
+//! ```
+
+use crate::{common::*, input::InputReport};
+use std::fmt;
+
+/// Size on the wire of a report with the given id, if known.
+///
+/// This is the only table of the report sizes, the `report_id_registry`
+/// test checking it against the variants of [`InputReportEnum`].
+///
+/// [`InputReportEnum`]: crate::input::InputReportEnum
+pub fn report_len(id: u8) -> Option<usize> {
+    match RawId::<InputReportId>::new(id).try_into()? {
+        InputReportId::Normal => Some(12),
+        InputReportId::StandardAndSubcmd | InputReportId::StandardFull => Some(49),
+        InputReportId::StandardFullMCU => Some(362),
+        // Unknown size
+        InputReportId::MCUFwUpdate => None,
+    }
+}
+
+/// Incomplete report kept between two calls to [`ReportBuffer::feed`].
+#[derive(Copy, Clone, Debug)]
+pub struct ReportBuffer {
+    report: InputReport,
+    len: usize,
+}
+
+impl ReportBuffer {
+    pub fn new() -> ReportBuffer {
+        ReportBuffer {
+            report: InputReport::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of bytes of an incomplete report currently buffered.
+    pub fn pending(&self) -> usize {
+        self.len
+    }
+
+    /// Drop the buffered incomplete report, for example after a reconnection.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn feed<'a>(&'a mut self, data: &'a [u8]) -> ReportStream<'a> {
+        ReportStream { buffer: self, data }
+    }
+}
+
+impl Default for ReportBuffer {
+    fn default() -> Self {
+        ReportBuffer::new()
+    }
+}
+
+/// Iterator over the complete reports contained in a byte buffer.
+///
+/// Reports still incomplete when the buffer is exhausted are kept in the
+/// [`ReportBuffer`] and completed by the next call to `feed`.
+pub struct ReportStream<'a> {
+    buffer: &'a mut ReportBuffer,
+    data: &'a [u8],
+}
+
+impl<'a> ReportStream<'a> {
+    /// Bytes of the buffer not yet consumed.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl<'a> Iterator for ReportStream<'a> {
+    type Item = Result<InputReport, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = if self.buffer.len > 0 {
+            self.buffer.report.as_bytes_mut()[0]
+        } else {
+            *self.data.first()?
+        };
+        let len = match report_len(id) {
+            Some(len) => len,
+            None => {
+                // Resynchronize on the next byte.
+                if self.buffer.len > 0 {
+                    self.buffer.len = 0;
+                } else {
+                    self.data = &self.data[1..];
+                }
+                return Some(Err(StreamError::UnknownReportId(id)));
+            }
+        };
+
+        let start = self.buffer.len;
+        let nb = (len - start).min(self.data.len());
+        self.buffer.report.as_bytes_mut()[start..start + nb].copy_from_slice(&self.data[..nb]);
+        self.buffer.len += nb;
+        self.data = &self.data[nb..];
+        if self.buffer.len < len {
+            return None;
+        }
+
+        let report = self.buffer.report;
+        self.buffer.len = 0;
+        self.buffer.report = InputReport::new();
+        if let Some(reply) = report.subcmd_reply() {
+            if reply.id().try_into().is_none() {
+                return Some(Err(StreamError::InvalidSubcommandReply(reply.id())));
+            }
+        }
+        Some(Ok(report))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum StreamError {
+    /// The first byte of a report is not a known report id. The byte is skipped.
+    UnknownReportId(u8),
+    /// The report contains a reply to an unknown subcommand. The report is dropped.
+    InvalidSubcommandReply(RawId<SubcommandId>),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::UnknownReportId(id) => write!(f, "unknown input report id 0x{:x}", id),
+            StreamError::InvalidSubcommandReply(id) => {
+                write!(f, "invalid subcommand reply id {:?}", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+#[cfg(test)]
+#[test]
+fn split_and_concatenated_reports() {
+    let mut raw = vec![0x3f];
+    raw.extend_from_slice(&[0; 11]);
+    raw.push(0x30);
+    raw.extend_from_slice(&[1; 48]);
+
+    let mut buffer = ReportBuffer::new();
+    let reports: Vec<_> = buffer.feed(&raw[..20]).collect();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].as_ref().unwrap().normal().is_some());
+    assert_eq!(buffer.pending(), 8);
+
+    let reports: Vec<_> = buffer.feed(&raw[20..]).collect();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].as_ref().unwrap().standard_full().is_some());
+    assert_eq!(buffer.pending(), 0);
+
+    let mut stream = buffer.feed(&[0x42, 0x3f]);
+    assert!(matches!(
+        stream.next(),
+        Some(Err(StreamError::UnknownReportId(0x42)))
+    ));
+    assert!(stream.next().is_none());
+    assert_eq!(buffer.pending(), 1);
+}
+
+/// Every report id needs a variant to be parsed into and a size to be split
+/// from a stream, or the reports get dropped as unknown.
+#[cfg(test)]
+#[test]
+fn report_id_registry() {
+    use crate::input::InputReportEnum;
+    use std::mem::size_of;
+
+    let layouts = InputReport::variant_layouts();
+    for raw in 0..=u8::MAX {
+        let id = match RawId::<InputReportId>::new(raw).try_into() {
+            Some(id) => id,
+            None => continue,
+        };
+        let index = InputReportEnum::VARIANTS
+            .iter()
+            .position(|v| *v == id)
+            .unwrap_or_else(|| panic!("no InputReportEnum variant for {:?}", id));
+        // Payloads are sized for the largest reply, only their start has to
+        // be in the report.
+        let (_, offset, _) = layouts[index];
+        match report_len(raw) {
+            Some(len) => {
+                assert!(
+                    offset < len && len <= size_of::<InputReport>(),
+                    "invalid size {} of {:?}",
+                    len,
+                    id
+                );
+                let mut data = vec![0; len];
+                data[0] = raw;
+                let report = InputReport::from_slice(&data).unwrap();
+                assert_eq!(report.len(), len);
+            }
+            // Firmware update reports have no documented size.
+            None => assert_eq!(id, InputReportId::MCUFwUpdate),
+        }
+    }
+}
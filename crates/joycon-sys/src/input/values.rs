@@ -1,3 +1,4 @@
+use crate::common::Wire;
 use num::FromPrimitive;
 use std::fmt;
 
@@ -7,13 +8,69 @@ bitfield::bitfield! {
     pub struct DeviceStatus(u8);
     impl Debug;
 
+    // Powered by the Switch or USB
     pub connected, _: 0;
     pub u8, into DeviceType, device_type, _: 2, 1;
     pub charging, _: 4;
     pub u8, into BatteryLevel, battery_level, _: 7, 5;
 }
 
-#[derive(Debug, Copy, Clone, FromPrimitive)]
+impl From<u8> for DeviceStatus {
+    fn from(raw: u8) -> Self {
+        DeviceStatus(raw)
+    }
+}
+
+impl DeviceStatus {
+    /// How the controller is currently connected, decoded from the
+    /// connection info nibble.
+    pub fn connection_info(&self) -> ConnectionInfo {
+        match (self.connected(), self.device_type()) {
+            (false, _) => ConnectionInfo::Bluetooth,
+            (true, DeviceType::Joycon) => ConnectionInfo::RailAttached,
+            (true, _) => ConnectionInfo::Usb,
+        }
+    }
+
+    /// Battery nibble, `None` for an unknown battery level.
+    pub fn power_info(&self) -> Option<PowerInfo> {
+        Some(PowerInfo {
+            battery_level: BatteryLevel::from_u8(self.0 >> 5)?,
+            charging: self.charging(),
+        })
+    }
+
+    /// Whether the battery level or charging state differs from `previous`.
+    pub fn power_changed(&self, previous: DeviceStatus) -> bool {
+        self.0 >> 4 != previous.0 >> 4
+    }
+
+    pub fn connection_changed(&self, previous: DeviceStatus) -> bool {
+        self.connection_info() != previous.connection_info()
+    }
+}
+
+/// Battery nibble of [`DeviceStatus`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PowerInfo {
+    pub battery_level: BatteryLevel,
+    pub charging: bool,
+}
+
+/// Connection of the controller, changes when a JoyCon is attached to or
+/// detached from the console rail.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ConnectionInfo {
+    /// Wireless and powered by the battery.
+    Bluetooth,
+    /// Powered over USB: Pro Controller plugged with a cable, or JoyCon in the
+    /// charging grip.
+    Usb,
+    /// JoyCon attached to the rail of the console.
+    RailAttached,
+}
+
+#[derive(Debug, Copy, Clone, FromPrimitive, Eq, PartialEq)]
 pub enum DeviceType {
     ProController = 0,
     // Used when the ringcon is plugged, maybe also for the pokeball?
@@ -32,7 +89,7 @@ impl From<u8> for DeviceType {
     }
 }
 
-#[derive(Debug, Copy, Clone, FromPrimitive, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, FromPrimitive, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum BatteryLevel {
     Empty = 0,
     Critical = 1,
@@ -48,6 +105,7 @@ impl From<u8> for BatteryLevel {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Default)]
 pub struct ButtonsStatus {
     pub right: RightButtons,
@@ -55,6 +113,8 @@ pub struct ButtonsStatus {
     pub left: LeftButtons,
 }
 
+unsafe impl Wire for ButtonsStatus {}
+
 impl fmt::Debug for ButtonsStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("ButtonsStatus")
@@ -173,6 +233,9 @@ bitfield::bitfield! {
     pub zl, _: 7;
 }
 
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Button {
     N,
     S,
@@ -188,15 +251,92 @@ pub enum Button {
     DOWN,
     LEFT,
     RIGHT,
+    SL,
+    SR,
+    MINUS,
+    PLUS,
+    HOME,
+    CAPTURE,
+}
+
+impl Button {
+    pub const COUNT: usize = 20;
+
+    pub const ALL: [Button; Button::COUNT] = [
+        Button::N,
+        Button::S,
+        Button::E,
+        Button::W,
+        Button::L,
+        Button::R,
+        Button::ZL,
+        Button::ZR,
+        Button::L3,
+        Button::R3,
+        Button::UP,
+        Button::DOWN,
+        Button::LEFT,
+        Button::RIGHT,
+        Button::SL,
+        Button::SR,
+        Button::MINUS,
+        Button::PLUS,
+        Button::HOME,
+        Button::CAPTURE,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl ButtonsStatus {
+    pub fn is_pressed(&self, button: Button) -> bool {
+        match button {
+            Button::N => self.right.x(),
+            Button::S => self.right.b(),
+            Button::E => self.right.a(),
+            Button::W => self.right.y(),
+            Button::L => self.left.l(),
+            Button::R => self.right.r(),
+            Button::ZL => self.left.zl(),
+            Button::ZR => self.right.zr(),
+            Button::L3 => self.middle.lstick(),
+            Button::R3 => self.middle.rstick(),
+            Button::UP => self.left.up(),
+            Button::DOWN => self.left.down(),
+            Button::LEFT => self.left.left(),
+            Button::RIGHT => self.left.right(),
+            Button::SL => self.left.sl() || self.right.sl(),
+            Button::SR => self.left.sr() || self.right.sr(),
+            Button::MINUS => self.middle.minus(),
+            Button::PLUS => self.middle.plus(),
+            Button::HOME => self.middle.home(),
+            Button::CAPTURE => self.middle.capture(),
+        }
+    }
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 pub struct Stick {
     data: [u8; 3],
 }
 
+unsafe impl Wire for Stick {}
+
 impl Stick {
+    pub fn new(x: u16, y: u16) -> Stick {
+        Stick {
+            data: [
+                x as u8,
+                ((x >> 8) & 0xF) as u8 | ((y & 0xF) << 4) as u8,
+                (y >> 4) as u8,
+            ],
+        }
+    }
+
     pub fn x(self) -> u16 {
         u16::from(self.data[0]) | u16::from(self.data[1] & 0xf) << 8
     }
@@ -214,3 +354,35 @@ impl fmt::Debug for Stick {
             .finish()
     }
 }
+
+#[cfg(test)]
+#[test]
+fn connection_info() {
+    assert_eq!(
+        DeviceStatus(0x8e).connection_info(),
+        ConnectionInfo::Bluetooth
+    );
+    assert_eq!(
+        DeviceStatus(0x8f).connection_info(),
+        ConnectionInfo::RailAttached
+    );
+    assert_eq!(DeviceStatus(0x91).connection_info(), ConnectionInfo::Usb);
+}
+
+#[cfg(test)]
+#[test]
+fn power_info() {
+    let status = DeviceStatus(0x8e);
+    assert_eq!(
+        status.power_info(),
+        Some(PowerInfo {
+            battery_level: BatteryLevel::Full,
+            charging: false,
+        })
+    );
+    assert_eq!(DeviceStatus(0xe0).power_info(), None);
+    assert!(DeviceStatus(0x9e).power_changed(status));
+    assert!(!DeviceStatus(0x8c).power_changed(status));
+    assert!(!DeviceStatus(0x8c).connection_changed(status));
+    assert!(DeviceStatus(0x8f).connection_changed(status));
+}
@@ -0,0 +1,290 @@
+//! JSON export of raw reports, to share captures with tools written in other
+//! languages.
+//!
+//! A report is exported as:
+//!
+//! ```json
+//! {"report": "input", "id": 48, "bytes": "3012...", "decoded": "InputReport { ... }"}
+//! ```
+//!
+//! `bytes` is the hex encoded report as sent on the wire and is the only field
+//! read back by `from_json`. `decoded` is the `Debug` output, for humans.
+//!
+//! # Calibration
+//!
+//! [`CalibrationJson`] moves the stick and sensor calibration between the
+//! tools of this crate. The layout is this crate's own, no other tool reads
+//! it:
+//!
+//! ```json
+//! {
+//!   "sticks": {
+//!     "left": {"min": [520, 610], "center": [2050, 2100], "max": [3560, 3480]},
+//!     "right": {"min": [540, 600], "center": [2010, 1980], "max": [3520, 3370]}
+//!   },
+//!   "sensors": {
+//!     "acc_origin": [-12, 40, 18], "acc_sensitivity": [16384, 16384, 16384],
+//!     "gyro_origin": [3, -7, 1], "gyro_sensitivity": [13371, 13371, 13371]
+//!   }
+//! }
+//! ```
+//!
+//! Stick values are raw 12-bit positions. Both sections are optional.
+
+use crate::{
+    spi::{LeftStickCalibration, RightStickCalibration, SensorCalibration, SticksCalibration},
+    InputReport, OutputReport,
+};
+use cgmath::Vector3;
+use serde_json::{json, Value};
+use std::fmt;
+
+impl InputReport {
+    pub fn to_json(&self) -> String {
+        to_json("input", self.as_bytes(), self)
+    }
+
+    pub fn from_json(json: &str) -> Result<InputReport, JsonError> {
+        let mut report = InputReport::new();
+        from_json(json, "input", report.as_bytes_mut())?;
+        Ok(report)
+    }
+}
+
+impl OutputReport {
+    pub fn to_json(&self) -> String {
+        to_json("output", self.as_bytes(), self)
+    }
+
+    pub fn from_json(json: &str) -> Result<OutputReport, JsonError> {
+        let mut report = OutputReport::new();
+        from_json(json, "output", report.as_bytes_mut())?;
+        Ok(report)
+    }
+}
+
+fn to_json(kind: &str, bytes: &[u8], decoded: &dyn fmt::Debug) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    json!({
+        "report": kind,
+        "id": bytes[0],
+        "bytes": hex,
+        "decoded": format!("{:?}", decoded),
+    })
+    .to_string()
+}
+
+fn from_json(json: &str, kind: &str, out: &mut [u8]) -> Result<(), JsonError> {
+    let value: Value = serde_json::from_str(json).map_err(JsonError::Json)?;
+    if let Some(found) = value.get("report").and_then(Value::as_str) {
+        if found != kind {
+            return Err(JsonError::WrongReportKind);
+        }
+    }
+    let hex = value
+        .get("bytes")
+        .and_then(Value::as_str)
+        .ok_or(JsonError::MissingBytes)?;
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(JsonError::InvalidHex);
+    }
+    let len = hex.len() / 2;
+    if len == 0 || len > out.len() {
+        return Err(JsonError::InvalidLength {
+            max: out.len(),
+            got: len,
+        });
+    }
+    for (i, byte) in out[..len].iter_mut().enumerate() {
+        *byte =
+            u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| JsonError::InvalidHex)?;
+    }
+    Ok(())
+}
+
+/// Stick and sensor calibration in the JSON layout of this crate.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CalibrationJson {
+    pub sticks: Option<SticksCalibration>,
+    pub sensors: Option<SensorCalibration>,
+}
+
+impl CalibrationJson {
+    pub fn to_json(&self) -> String {
+        let mut root = Vec::new();
+        if let Some(sticks) = self.sticks {
+            let (left, right) = (sticks.left, sticks.right);
+            let stick = |min: (u16, u16), center: (u16, u16), max: (u16, u16)| {
+                json!({
+                    "min": vec![min.0, min.1],
+                    "center": vec![center.0, center.1],
+                    "max": vec![max.0, max.1],
+                })
+            };
+            root.push((
+                "sticks".to_string(),
+                json!({
+                    "left": stick(left.min(), left.center(), left.max()),
+                    "right": stick(right.min(), right.center(), right.max()),
+                }),
+            ));
+        }
+        if let Some(sensors) = self.sensors {
+            let axes = |v: Vector3<f64>| vec![v.x as i16, v.y as i16, v.z as i16];
+            root.push((
+                "sensors".to_string(),
+                json!({
+                    "acc_origin": axes(sensors.acc_offset()),
+                    "acc_sensitivity": axes(sensors.acc_factor()),
+                    "gyro_origin": axes(sensors.gyro_offset()),
+                    "gyro_sensitivity": axes(sensors.gyro_factor()),
+                }),
+            ));
+        }
+        Value::Object(root.into_iter().collect()).to_string()
+    }
+
+    pub fn from_json(json: &str) -> Result<CalibrationJson, JsonError> {
+        let value: Value = serde_json::from_str(json).map_err(JsonError::Json)?;
+        let mut export = CalibrationJson::default();
+        if let Some(sticks) = value.get("sticks") {
+            let left = sticks.get("left").ok_or(JsonError::InvalidField("left"))?;
+            let right = sticks
+                .get("right")
+                .ok_or(JsonError::InvalidField("right"))?;
+            export.sticks = Some(SticksCalibration {
+                left: LeftStickCalibration::new(
+                    position(left, "min")?,
+                    position(left, "center")?,
+                    position(left, "max")?,
+                ),
+                right: RightStickCalibration::new(
+                    position(right, "min")?,
+                    position(right, "center")?,
+                    position(right, "max")?,
+                ),
+            });
+        }
+        if let Some(sensors) = value.get("sensors") {
+            let mut calib = SensorCalibration::default();
+            calib.set_acc_offset(axes(sensors, "acc_origin")?);
+            calib.set_acc_factor(axes(sensors, "acc_sensitivity")?);
+            calib.set_gyro_offset(axes(sensors, "gyro_origin")?);
+            calib.set_gyro_factor(axes(sensors, "gyro_sensitivity")?);
+            export.sensors = Some(calib);
+        }
+        Ok(export)
+    }
+}
+
+fn numbers<const N: usize>(value: &Value, field: &'static str) -> Result<[i64; N], JsonError> {
+    let array = value
+        .get(field)
+        .and_then(Value::as_array)
+        .filter(|a| a.len() == N)
+        .ok_or(JsonError::InvalidField(field))?;
+    let mut out = [0; N];
+    for (out, v) in out.iter_mut().zip(array) {
+        *out = v.as_i64().ok_or(JsonError::InvalidField(field))?;
+    }
+    Ok(out)
+}
+
+fn position(value: &Value, field: &'static str) -> Result<(u16, u16), JsonError> {
+    match numbers(value, field)? {
+        [x @ 0..=0xFFF, y @ 0..=0xFFF] => Ok((x as u16, y as u16)),
+        _ => Err(JsonError::InvalidField(field)),
+    }
+}
+
+fn axes(value: &Value, field: &'static str) -> Result<Vector3<f64>, JsonError> {
+    let [x, y, z] = numbers(value, field)?;
+    let range = i16::MIN as i64..=i16::MAX as i64;
+    if [x, y, z].iter().all(|v| range.contains(v)) {
+        Ok(Vector3::new(x as f64, y as f64, z as f64))
+    } else {
+        Err(JsonError::InvalidField(field))
+    }
+}
+
+#[derive(Debug)]
+pub enum JsonError {
+    Json(serde_json::Error),
+    /// The `report` field doesn't match the type being parsed.
+    WrongReportKind,
+    MissingBytes,
+    InvalidHex,
+    InvalidLength {
+        max: usize,
+        got: usize,
+    },
+    /// A calibration field is missing or out of range.
+    InvalidField(&'static str),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::Json(e) => write!(f, "invalid json: {}", e),
+            JsonError::WrongReportKind => f.write_str("wrong report kind"),
+            JsonError::MissingBytes => f.write_str("missing \"bytes\" field"),
+            JsonError::InvalidHex => f.write_str("invalid hex string"),
+            JsonError::InvalidLength { max, got } => {
+                write!(f, "invalid report length {}, max {}", got, max)
+            }
+            JsonError::InvalidField(field) => write!(f, "invalid field \"{}\"", field),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn json_round_trip() {
+    use crate::output::SubcommandRequest;
+
+    let report = OutputReport::from(SubcommandRequest::disable_shipment_mode());
+    let json = report.to_json();
+    let parsed = OutputReport::from_json(&json).unwrap();
+    assert_eq!(parsed.as_bytes(), report.as_bytes());
+    assert!(matches!(
+        InputReport::from_json(&json),
+        Err(JsonError::WrongReportKind)
+    ));
+}
+
+#[cfg(test)]
+#[test]
+fn calibration_round_trip() {
+    let mut sensors = SensorCalibration::default();
+    sensors.set_gyro_offset(Vector3::new(3., -7., 1.));
+    sensors.set_gyro_factor(Vector3::new(13371., 13371., 13371.));
+    let export = CalibrationJson {
+        sticks: Some(SticksCalibration {
+            left: LeftStickCalibration::new((520, 610), (2050, 2100), (3560, 3480)),
+            right: RightStickCalibration::new((540, 600), (2010, 1980), (3520, 3370)),
+        }),
+        sensors: Some(sensors),
+    };
+    let parsed = CalibrationJson::from_json(&export.to_json()).unwrap();
+    let left = parsed.sticks.unwrap().left;
+    assert_eq!(left.min(), (520, 610));
+    assert_eq!(left.max(), (3560, 3480));
+    assert_eq!(parsed.sticks.unwrap().right.center(), (2010, 1980));
+    assert_eq!(
+        parsed.sensors.unwrap().gyro_offset(),
+        Vector3::new(3., -7., 1.)
+    );
+    assert!(matches!(
+        CalibrationJson::from_json(r#"{"sticks": {"left": {}}}"#),
+        Err(JsonError::InvalidField(_))
+    ));
+}
@@ -1,19 +1,82 @@
 //! Helper crate for interacting with a JoyCon and Switch Pro Controller via HID.
 //!
 //! The main structs are [InputReport](input/struct.InputReport.html) and
-//! [OutputReport](output/struct.OutputReport.html).
+//! [OutputReport](output/struct.OutputReport.html). They and the other
+//! commonly needed types are exported by the [prelude](prelude/index.html).
+//!
+//! # Layout
+//!
+//! The wire structs are `#[repr(packed)]` and match the protocol byte for byte.
+//! Rust only guarantees their field order with `repr(C)`, so the `repr-c`
+//! feature adds it to every wire struct and union, for consumers sharing them
+//! across an FFI boundary. The fields are then laid out in declaration order
+//! without padding, as documented in the protocol notes.
+//!
+//! # Logging
+//!
+//! With the `log` or `defmt` feature, the protocol state machines emit
+//! trace-level events: subcommands queued, sent and retried, replies matched,
+//! SPI reads progressing.
 
 #[macro_use]
 extern crate num_derive;
 
+/// Trace-level event, emitted with the `log` or `defmt` feature.
+///
+/// The arguments are formatted with `Debug`, so only `{:?}` placeholders are
+/// allowed.
+macro_rules! trace {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {{
+        #[cfg(feature = "log")]
+        ::log::trace!($fmt $(, $arg)*);
+        #[cfg(feature = "defmt")]
+        ::defmt::trace!($fmt $(, ::defmt::Debug2Format(&$arg))*);
+        #[cfg(not(any(feature = "log", feature = "defmt")))]
+        {
+            $(let _ = &$arg;)*
+        }
+    }};
+}
+
 pub mod accessory;
+#[cfg(any(feature = "ffi", feature = "pyo3", feature = "wasm-bindgen"))]
+mod bindings;
+pub mod capabilities;
+#[cfg(feature = "std")]
+pub mod capture;
+pub mod clock;
+pub mod clone_detect;
 pub mod common;
+pub mod coverage;
+pub mod external;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod grip;
+pub mod identity;
 pub mod imu;
 pub mod input;
+#[cfg(feature = "serde_json")]
+pub mod json;
 pub mod light;
+pub mod link;
+pub mod math;
 pub mod mcu;
+#[cfg(feature = "std")]
+pub mod mock;
+pub mod mode;
 pub mod output;
+pub mod pairing;
+pub mod poll;
+pub mod prelude;
+#[cfg(feature = "pyo3")]
+pub mod python;
+pub mod registry;
 pub mod spi;
+pub mod state;
+pub mod timing;
+pub mod usb;
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
 
 pub use common::*;
 pub use input::InputReport;
@@ -28,12 +91,16 @@ macro_rules! raw_enum {
         #[union: $union:ident]
         #[struct: $struct:ident]
         $(#[raw $rawty:ty])?
+        // Documented offset of the payload and size of the struct, checked at
+        // compile time.
+        $(#[layout offset = $offset:expr, size = $size:expr])?
         $(#[field $field:ident $fieldmut:ident: $fieldty:ty])*
         pub enum $name:ident {
             $($varname:ident $varnamemut:ident: $id:ident = $var:ty),+
         }
     ) => {
         #[repr(packed)]
+        #[cfg_attr(feature = "repr-c", repr(C))]
         #[derive(Copy, Clone)]
         pub struct $struct {
             $($preid: $preidty,)?
@@ -42,6 +109,7 @@ macro_rules! raw_enum {
             u: $union,
         }
         #[repr(packed)]
+        #[cfg_attr(feature = "repr-c", repr(C))]
         #[derive(Copy, Clone)]
         union $union {
             $($varname: $var,)*
@@ -53,6 +121,35 @@ macro_rules! raw_enum {
             $($id($var)),*
         }
 
+        impl $name {
+            /// Ids of all the variants, in declaration order.
+            pub const VARIANTS: &'static [$tyid] = &[$($tyid::$id),*];
+
+            pub fn id(&self) -> $tyid {
+                match self {
+                    $($name::$id(_) => $tyid::$id),*
+                }
+            }
+
+            /// Name of the variant, eg. for debug UIs.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($name::$id(_) => stringify!($id)),*
+                }
+            }
+
+            /// Name of the variant with the id `id`, `None` if there is none.
+            pub fn variant_name(id: $tyid) -> Option<&'static str> {
+                match id {
+                    $($tyid::$id => Some(stringify!($id)),)*
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                }
+            }
+        }
+
+        unsafe impl $crate::common::Wire for $struct {}
+
         impl ::std::convert::TryFrom<$struct> for $name {
             type Error = $struct;
             fn try_from(x: $struct) -> Result<Self, Self::Error> {
@@ -65,11 +162,14 @@ macro_rules! raw_enum {
 
         impl ::std::convert::From<$name> for $struct {
             fn from(x: $name) -> Self {
-                let (id, u) = match x {
-                    $($name::$id(data) => (
-                        $tyid::$id.into(),
-                        $union { $varname: data }
-                    )),*,
+                // Zeroed first so the bytes after a smaller variant are
+                // deterministic.
+                let mut u: $union = unsafe { ::std::mem::zeroed() };
+                let id = match x {
+                    $($name::$id(data) => {
+                        u.$varname = data;
+                        $tyid::$id.into()
+                    }),*,
                 };
                 $struct {
                     $($preid: ::std::default::Default::default(),)?
@@ -81,8 +181,12 @@ macro_rules! raw_enum {
         }
 
         impl $struct {
+            /// Zeroed, except for the `post_id` field set to its default.
             pub fn new() -> Self {
-                unsafe { ::std::mem::zeroed() }
+                #[allow(unused_mut)]
+                let mut x: Self = unsafe { ::std::mem::zeroed() };
+                $(x.$postid = ::std::default::Default::default();)?
+                x
             }
 
             pub fn id(&self) -> RawId<$tyid> {
@@ -135,6 +239,27 @@ macro_rules! raw_enum {
             )*
         }
 
+        #[cfg(test)]
+        impl $struct {
+            /// Name, offset and size of each variant payload.
+            #[allow(unused_unsafe, dead_code)]
+            pub(crate) fn variant_layouts() -> Vec<(&'static str, usize, usize)> {
+                let x = $struct::new();
+                let base = &x as *const _ as usize;
+                vec![$((
+                    stringify!($id),
+                    unsafe { ::std::ptr::addr_of!(x.u.$varname) } as *const u8 as usize - base,
+                    ::std::mem::size_of::<$var>(),
+                )),*]
+            }
+        }
+
+        $(
+            // Documented offset of the payload and size of the whole struct.
+            const _: () = assert!(::std::mem::offset_of!($struct, u) == $offset);
+            const _: () = assert!(::std::mem::size_of::<$struct>() == $size);
+        )?
+
         impl ::std::fmt::Debug for $struct {
             fn fmt(&self, f: &mut ::std::fmt::Formatter) -> std::fmt::Result {
                 let mut out = f.debug_struct(stringify!($struct));
@@ -152,3 +277,27 @@ macro_rules! raw_enum {
         }
     };
 }
+
+#[cfg(test)]
+#[test]
+fn raw_enum_variants() {
+    use input::InputReportEnum;
+
+    assert_eq!(
+        InputReportEnum::VARIANTS,
+        [
+            InputReportId::Normal,
+            InputReportId::StandardAndSubcmd,
+            InputReportId::MCUFwUpdate,
+            InputReportId::StandardFull,
+            InputReportId::StandardFullMCU,
+        ]
+    );
+    let report = InputReportEnum::MCUFwUpdate(());
+    assert_eq!(report.id(), InputReportId::MCUFwUpdate);
+    assert_eq!(report.name(), "MCUFwUpdate");
+    assert_eq!(
+        output::SubcommandRequestEnum::variant_name(SubcommandId::SPIRead),
+        Some("SPIRead")
+    );
+}
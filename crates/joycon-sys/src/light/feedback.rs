@@ -0,0 +1,86 @@
+use super::{HomeLight, PlayerLight, PlayerLights};
+use crate::output::SubcommandRequest;
+
+/// Player light patterns used by the Switch for players 1 to 8.
+const PLAYER_PATTERNS: [u8; 8] = [
+    0b0001, 0b0011, 0b0111, 0b1111, 0b1001, 0b1010, 0b1011, 0b0110,
+];
+
+/// High-level controller state, shown with the player and home lights so
+/// every application uses the same LED language.
+///
+/// ```ignore
+/// for request in LedFeedback::PairedAsPlayer(2).requests().iter() {
+///     joycon.send_subcommand(*request)?;
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LedFeedback {
+    /// Searching for or connecting to the host: all the lights blink.
+    Connecting,
+    /// Connected as player 1 to 8, using the Switch patterns. Other numbers
+    /// are clamped to this range.
+    PairedAsPlayer(u8),
+    /// The first light blinks, the home light pulses slowly.
+    LowBattery,
+    /// The first and last lights blink, the home light flashes.
+    Error,
+    /// Every light off, eg. for users bothered by the LEDs.
+    Dark,
+}
+
+impl LedFeedback {
+    pub fn player_lights(self) -> PlayerLights {
+        use PlayerLight::*;
+        match self {
+            LedFeedback::Connecting => PlayerLights::new(Blinking, Blinking, Blinking, Blinking),
+            LedFeedback::PairedAsPlayer(player) => {
+                let player = player.clamp(1, PLAYER_PATTERNS.len() as u8);
+                PlayerLights::from_bits(PLAYER_PATTERNS[player as usize - 1])
+            }
+            LedFeedback::LowBattery => PlayerLights::new(Blinking, Off, Off, Off),
+            LedFeedback::Error => PlayerLights::new(Blinking, Off, Off, Blinking),
+            LedFeedback::Dark => PlayerLights::off(),
+        }
+    }
+
+    pub fn home_light(self) -> HomeLight {
+        match self {
+            // Breathing.
+            LedFeedback::Connecting => {
+                HomeLight::new(0x8, 0x2, 0x0, &[(0xf, 0xf, 0x0), (0x2, 0xf, 0x0)])
+            }
+            LedFeedback::PairedAsPlayer(_) | LedFeedback::Dark => HomeLight::off(),
+            // Slow pulse, with a long pause.
+            LedFeedback::LowBattery => {
+                HomeLight::new(0xf, 0x0, 0x0, &[(0x8, 0x4, 0x2), (0x0, 0x4, 0xf)])
+            }
+            // Fast flashes.
+            LedFeedback::Error => {
+                HomeLight::new(0x2, 0xf, 0x0, &[(0xf, 0x0, 0x2), (0x0, 0x0, 0x2)])
+            }
+        }
+    }
+
+    /// Subcommands setting the player and home lights.
+    pub fn requests(self) -> [SubcommandRequest; 2] {
+        [self.player_lights().into(), self.home_light().into()]
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn feedback_patterns() {
+    let [player, home] = LedFeedback::PairedAsPlayer(3).requests();
+    assert_eq!(player.set_player_lights().unwrap().0, 0b0111);
+    assert!(home.set_home_light().unwrap().cycles().is_empty());
+    assert_eq!(LedFeedback::Connecting.player_lights().0, 0b1111_0000);
+    let [player, home] = LedFeedback::Dark.requests();
+    assert_eq!(player.set_player_lights().unwrap().0, 0);
+    assert!(home.set_home_light().unwrap().cycles().is_empty());
+    assert_eq!(
+        LedFeedback::PairedAsPlayer(0).player_lights(),
+        LedFeedback::PairedAsPlayer(1).player_lights()
+    );
+    assert_eq!(LedFeedback::PairedAsPlayer(9).player_lights().0, 0b0110);
+}
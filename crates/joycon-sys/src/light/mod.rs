@@ -1,9 +1,17 @@
+use crate::common::Wire;
 use std::fmt;
 
+mod feedback;
+
+pub use feedback::*;
+
 #[repr(packed)]
-#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct PlayerLights(u8);
 
+unsafe impl Wire for PlayerLights {}
+
 impl PlayerLights {
     #[allow(clippy::identity_op)]
     pub fn new(p0: PlayerLight, p1: PlayerLight, p2: PlayerLight, p3: PlayerLight) -> PlayerLights {
@@ -19,6 +27,15 @@ impl PlayerLights {
                 | ((p3 == Blinking) as u8) << 7,
         )
     }
+
+    pub fn off() -> PlayerLights {
+        PlayerLights(0)
+    }
+
+    /// Bits 0 to 3 turn on the lights, bits 4 to 7 make them blink.
+    pub fn from_bits(bits: u8) -> PlayerLights {
+        PlayerLights(bits)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -39,6 +56,7 @@ impl From<bool> for PlayerLight {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 pub struct HomeLight {
     s1: Settings1,
@@ -46,6 +64,8 @@ pub struct HomeLight {
     cycles: [HomeLightCycle; 8],
 }
 
+unsafe impl Wire for HomeLight {}
+
 impl HomeLight {
     pub fn new(
         mini_cycle_duration: u8,
@@ -92,6 +112,11 @@ impl HomeLight {
         HomeLight { s1, s2, cycles }
     }
 
+    /// No cycle, the LED stays off.
+    pub fn off() -> HomeLight {
+        HomeLight::new(0, 0, 0, &[])
+    }
+
     fn cycles(&self) -> &[HomeLightCycle] {
         let nb = self.s1.nb_mini_cycles() as usize;
         &self.cycles[..(nb + 1) / 2]
@@ -126,6 +151,7 @@ bitfield::bitfield! {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug, Default)]
 struct HomeLightCycle {
     intensity: Intensity,
@@ -133,6 +159,8 @@ struct HomeLightCycle {
     second_duration: Durations,
 }
 
+unsafe impl Wire for HomeLightCycle {}
+
 bitfield::bitfield! {
     #[derive(Copy, Clone, Default)]
     struct Intensity(u8);
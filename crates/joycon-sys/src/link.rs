@@ -0,0 +1,136 @@
+//! Estimation of the Bluetooth link quality from the subcommand latency.
+//!
+//! [`LatencyTracker`] measures the time between sending a subcommand and
+//! receiving its reply, and smooths it with an exponentially weighted moving
+//! average. Timestamps are provided by the caller.
+//!
+//! ```ignore
+//! tracker.sent(&report, clock.now());
+//! send(&report)?;
+//! // later
+//! tracker.received(&recv()?, clock.now());
+//! if tracker.quality() == Some(LinkQuality::Poor) {
+//!     warn_user();
+//! }
+//! ```
+
+use crate::{clock::Timestamp, common::SubcommandId, InputReport, OutputReport};
+use std::time::Duration;
+
+/// Above this latency the link is [`LinkQuality::Degraded`].
+pub const DEGRADED_LATENCY: Duration = Duration::from_millis(30);
+/// Above this latency the link is [`LinkQuality::Poor`].
+pub const POOR_LATENCY: Duration = Duration::from_millis(100);
+
+/// Unanswered subcommands older than this are forgotten.
+const MAX_PENDING_AGE: Duration = Duration::from_secs(1);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum LinkQuality {
+    Good,
+    /// Subcommands are slow, the full report modes may drop packets.
+    Degraded,
+    /// The controller is probably too far or the radio is congested.
+    Poor,
+}
+
+#[derive(Clone, Debug)]
+pub struct LatencyTracker {
+    pending: Vec<(SubcommandId, Timestamp)>,
+    average: Option<f64>,
+    smoothing: f64,
+}
+
+impl LatencyTracker {
+    pub fn new() -> LatencyTracker {
+        LatencyTracker::with_smoothing(0.2)
+    }
+
+    /// `smoothing` is the weight of a new sample in the average, between 0
+    /// and 1.
+    pub fn with_smoothing(smoothing: f64) -> LatencyTracker {
+        assert!(0. < smoothing && smoothing <= 1.);
+        LatencyTracker {
+            pending: Vec::new(),
+            average: None,
+            smoothing,
+        }
+    }
+
+    /// Record that `report` is sent at `now`. Reports without subcommand
+    /// are ignored.
+    pub fn sent(&mut self, report: &OutputReport, now: Timestamp) {
+        if let Some(id) = report.rumble_subcmd().and_then(|s| s.id().try_into()) {
+            self.pending
+                .retain(|&(_, sent)| now.saturating_duration_since(sent) < MAX_PENDING_AGE);
+            trace!("subcommand {:?} waiting for its reply", id);
+            self.pending.push((id, now));
+        }
+    }
+
+    /// Record a report received at `now`, returning the latency if it's the
+    /// reply to a pending subcommand.
+    pub fn received(&mut self, report: &InputReport, now: Timestamp) -> Option<Duration> {
+        let id = report.subcmd_reply()?.id().try_into()?;
+        let index = self.pending.iter().position(|&(sent, _)| sent == id)?;
+        let (_, sent) = self.pending.remove(index);
+        let latency = now.saturating_duration_since(sent);
+        trace!("subcommand {:?} acknowledged after {:?}", id, latency);
+        let sample = latency.as_secs_f64();
+        self.average = Some(match self.average {
+            Some(average) => average + self.smoothing * (sample - average),
+            None => sample,
+        });
+        Some(latency)
+    }
+
+    /// Smoothed latency, `None` before the first reply.
+    pub fn latency(&self) -> Option<Duration> {
+        self.average.map(Duration::from_secs_f64)
+    }
+
+    pub fn quality(&self) -> Option<LinkQuality> {
+        let latency = self.latency()?;
+        Some(if latency > POOR_LATENCY {
+            LinkQuality::Poor
+        } else if latency > DEGRADED_LATENCY {
+            LinkQuality::Degraded
+        } else {
+            LinkQuality::Good
+        })
+    }
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        LatencyTracker::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn link_quality() {
+    use crate::{output::SubcommandRequest, InputReportId};
+
+    let mut reply = InputReport::new();
+    let raw = reply.as_bytes_mut();
+    raw[0] = InputReportId::StandardAndSubcmd as u8;
+    raw[13] = 0x80;
+    raw[14] = SubcommandId::SetShipmentMode as u8;
+    let request = OutputReport::from(SubcommandRequest::disable_shipment_mode());
+
+    let mut tracker = LatencyTracker::with_smoothing(0.5);
+    let start = Timestamp::from_micros(0);
+    assert_eq!(tracker.quality(), None);
+    tracker.sent(&request, start);
+    let latency = tracker.received(&reply, start + Duration::from_millis(20));
+    assert_eq!(latency, Some(Duration::from_millis(20)));
+    assert_eq!(tracker.quality(), Some(LinkQuality::Good));
+    // Not a reply to a pending subcommand.
+    assert_eq!(tracker.received(&reply, start), None);
+
+    tracker.sent(&request, start);
+    tracker.received(&reply, start + Duration::from_millis(200));
+    assert_eq!(tracker.latency(), Some(Duration::from_millis(110)));
+    assert_eq!(tracker.quality(), Some(LinkQuality::Poor));
+}
@@ -0,0 +1,125 @@
+//! Backend without float methods, using Q2.30 fixed point.
+
+use std::f64::consts::PI;
+
+const ONE: f64 = (1u64 << 30) as f64;
+const ITERATIONS: usize = 31;
+
+/// `atan(2^-i)` in Q2.30.
+const ATAN_TABLE: [i64; ITERATIONS] = [
+    843314857, 497837829, 263043837, 133525159, 67021687, 33543516, 16775851, 8388437, 4194283,
+    2097149, 1048576, 524288, 262144, 131072, 65536, 32768, 16384, 8192, 4096, 2048, 1024, 512,
+    256, 128, 64, 32, 16, 8, 4, 2, 1,
+];
+
+/// Inverse of the CORDIC gain in Q2.30.
+const CORDIC_GAIN_INV: i64 = 652032874;
+
+fn to_fixed(x: f64) -> i64 {
+    (x * ONE) as i64
+}
+
+fn from_fixed(x: i64) -> f64 {
+    x as f64 / ONE
+}
+
+fn abs(x: f64) -> f64 {
+    if x < 0. {
+        -x
+    } else {
+        x
+    }
+}
+
+/// Sine and cosine of an angle between `-π/2` and `π/2`.
+fn sin_cos_reduced(angle: f64) -> (f64, f64) {
+    let (mut x, mut y, mut z) = (CORDIC_GAIN_INV, 0, to_fixed(angle));
+    for (i, atan) in ATAN_TABLE.iter().enumerate() {
+        let (dx, dy) = (y >> i, x >> i);
+        if z >= 0 {
+            x -= dx;
+            y += dy;
+            z -= atan;
+        } else {
+            x += dx;
+            y -= dy;
+            z += atan;
+        }
+    }
+    (from_fixed(y), from_fixed(x))
+}
+
+fn sin_cos(angle: f64) -> (f64, f64) {
+    let mut angle = angle - 2. * PI * ((angle / (2. * PI)) as i64 as f64);
+    if angle > PI {
+        angle -= 2. * PI;
+    } else if angle < -PI {
+        angle += 2. * PI;
+    }
+    // Mirror onto the right half plane, where the CORDIC converges.
+    if angle > PI / 2. {
+        let (sin, cos) = sin_cos_reduced(PI - angle);
+        (sin, -cos)
+    } else if angle < -PI / 2. {
+        let (sin, cos) = sin_cos_reduced(-PI - angle);
+        (sin, -cos)
+    } else {
+        sin_cos_reduced(angle)
+    }
+}
+
+pub fn sin(x: f64) -> f64 {
+    sin_cos(x).0
+}
+
+pub fn cos(x: f64) -> f64 {
+    sin_cos(x).1
+}
+
+pub fn atan2(y: f64, x: f64) -> f64 {
+    if x < 0. {
+        let angle = atan2(-y, -x);
+        return if y < 0. { angle - PI } else { angle + PI };
+    }
+    let scale = abs(x).max(abs(y));
+    if scale == 0. {
+        return 0.;
+    }
+    let (mut x, mut y, mut z) = (to_fixed(x / scale), to_fixed(y / scale), 0);
+    for (i, atan) in ATAN_TABLE.iter().enumerate() {
+        let (dx, dy) = (y >> i, x >> i);
+        if y > 0 {
+            x += dx;
+            y -= dy;
+            z += atan;
+        } else {
+            x -= dx;
+            y += dy;
+            z -= atan;
+        }
+    }
+    from_fixed(z)
+}
+
+pub fn sqrt(x: f64) -> f64 {
+    if x < 0. {
+        return f64::NAN;
+    }
+    // Q64.64 in, Q32.32 out.
+    let n = (x * (1u128 << 64) as f64) as u128;
+    let (mut root, mut rem) = (0u128, n);
+    let mut bit = 1u128 << 126;
+    while bit > n {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rem >= root + bit {
+            rem -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root as f64 / (1u64 << 32) as f64
+}
@@ -0,0 +1,133 @@
+//! Math backend of the stick normalization and processing helpers.
+//!
+//! The functions have the same signature whatever the backend, selected by
+//! the features, from highest to lowest priority:
+//!
+//! - `fixed-point`: integer CORDIC and square root, for microcontrollers
+//!   without FPU.
+//! - `libm`: the `libm` crate, for targets without the float methods of
+//!   `std`.
+//! - `float32`: the `std` float methods in single precision, faster on
+//!   microcontrollers with a single precision FPU.
+//! - otherwise, the `std` float methods in double precision.
+//!
+//! ```ignore
+//! let angle = math::atan2(stick.y, stick.x);
+//! ```
+
+#[cfg(any(feature = "fixed-point", test))]
+mod fixed;
+
+#[cfg(feature = "fixed-point")]
+use fixed as backend;
+
+#[cfg(all(feature = "libm", not(feature = "fixed-point")))]
+mod backend {
+    pub fn sqrt(x: f64) -> f64 {
+        libm::sqrt(x)
+    }
+
+    pub fn sin(x: f64) -> f64 {
+        libm::sin(x)
+    }
+
+    pub fn cos(x: f64) -> f64 {
+        libm::cos(x)
+    }
+
+    pub fn atan2(y: f64, x: f64) -> f64 {
+        libm::atan2(y, x)
+    }
+}
+
+#[cfg(all(
+    feature = "float32",
+    not(any(feature = "libm", feature = "fixed-point"))
+))]
+mod backend {
+    pub fn sqrt(x: f64) -> f64 {
+        f64::from((x as f32).sqrt())
+    }
+
+    pub fn sin(x: f64) -> f64 {
+        f64::from((x as f32).sin())
+    }
+
+    pub fn cos(x: f64) -> f64 {
+        f64::from((x as f32).cos())
+    }
+
+    pub fn atan2(y: f64, x: f64) -> f64 {
+        f64::from((y as f32).atan2(x as f32))
+    }
+}
+
+#[cfg(not(any(feature = "float32", feature = "libm", feature = "fixed-point")))]
+mod backend {
+    pub fn sqrt(x: f64) -> f64 {
+        x.sqrt()
+    }
+
+    pub fn sin(x: f64) -> f64 {
+        x.sin()
+    }
+
+    pub fn cos(x: f64) -> f64 {
+        x.cos()
+    }
+
+    pub fn atan2(y: f64, x: f64) -> f64 {
+        y.atan2(x)
+    }
+}
+
+/// Square root, `NaN` if `x` is negative.
+pub fn sqrt(x: f64) -> f64 {
+    backend::sqrt(x)
+}
+
+/// Sine of `x` in radians.
+pub fn sin(x: f64) -> f64 {
+    backend::sin(x)
+}
+
+/// Cosine of `x` in radians.
+pub fn cos(x: f64) -> f64 {
+    backend::cos(x)
+}
+
+/// Angle of `(x, y)` in radians, between `-π` and `π`.
+pub fn atan2(y: f64, x: f64) -> f64 {
+    backend::atan2(y, x)
+}
+
+/// Length of `(x, y)`.
+pub fn hypot(x: f64, y: f64) -> f64 {
+    sqrt(x * x + y * y)
+}
+
+#[cfg(test)]
+#[test]
+fn fixed_point_backend() {
+    for i in 0..200 {
+        let x = f64::from(i) * 0.37 - 30.;
+        let y = f64::from(i % 13) * 0.61 - 4.;
+        assert!((fixed::sin(x) - x.sin()).abs() < 1e-6, "sin({})", x);
+        assert!((fixed::cos(x) - x.cos()).abs() < 1e-6, "cos({})", x);
+        assert!(
+            (fixed::atan2(y, x) - y.atan2(x)).abs() < 1e-6,
+            "atan2({}, {})",
+            y,
+            x
+        );
+        let square = f64::from(i) * 41.3;
+        assert!(
+            (fixed::sqrt(square) - square.sqrt()).abs() < 1e-6,
+            "sqrt({})",
+            square
+        );
+    }
+    assert_eq!(fixed::atan2(0., 0.), 0.);
+    assert!(fixed::sqrt(-1.).is_nan());
+    assert_eq!(hypot(3., 4.), 5.);
+}
@@ -0,0 +1,195 @@
+//! Layout of the NTAG215 tags used by amiibos.
+//!
+//! Only the plaintext fields are decoded. The encrypted blocks are exposed as
+//! raw bytes so they can be handed over to an external decryption tool.
+//!
+//! Cf https://www.3dbrew.org/wiki/Amiibo
+
+use crate::common::*;
+use std::fmt;
+
+pub const NTAG215_PAGE_SIZE: usize = 4;
+pub const NTAG215_PAGES: usize = 135;
+pub const NTAG215_SIZE: usize = NTAG215_PAGE_SIZE * NTAG215_PAGES;
+
+/// Fixed value of the first byte of page 4 on a formatted amiibo.
+pub const AMIIBO_MAGIC: u8 = 0xa5;
+
+/// Full content of an amiibo tag, as read page by page.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone)]
+pub struct AmiiboData {
+    uid_start: [u8; 3],
+    bcc0: u8,
+    uid_end: [u8; 4],
+    bcc1: u8,
+    _internal: u8,
+    pub static_lock: [u8; 2],
+    pub capability_container: [u8; 4],
+    pub magic: u8,
+    write_counter: [u8; 2],
+    _unknown_0x13: u8,
+    /// Encrypted tag settings (owner, nickname, ...).
+    pub encrypted_settings: [u8; 0x20],
+    /// HMAC of the locked part of the tag.
+    pub locked_hash: [u8; 0x20],
+    pub model: AmiiboModel,
+    pub keygen_salt: [u8; 0x20],
+    /// HMAC of the application data.
+    pub data_hash: [u8; 0x20],
+    /// Encrypted application data.
+    pub encrypted_app_data: [u8; 0x168],
+    pub dynamic_lock: [u8; 3],
+    _reserved: u8,
+    pub cfg0: [u8; 4],
+    pub cfg1: [u8; 4],
+    pub password: [u8; 4],
+    pub pack: [u8; 2],
+    _reserved2: [u8; 2],
+}
+
+unsafe impl Wire for AmiiboData {}
+
+const _: () = assert!(std::mem::size_of::<AmiiboData>() == NTAG215_SIZE);
+
+impl AmiiboData {
+    pub fn as_bytes(&self) -> &[u8; NTAG215_SIZE] {
+        unsafe { &*(self as *const AmiiboData as *const [u8; NTAG215_SIZE]) }
+    }
+
+    /// 7-byte serial number of the tag.
+    pub fn uid(&self) -> [u8; 7] {
+        let mut uid = [0; 7];
+        uid[..3].copy_from_slice(&self.uid_start);
+        uid[3..].copy_from_slice(&self.uid_end);
+        uid
+    }
+
+    /// Check the two block check characters protecting the UID.
+    pub fn check_uid(&self) -> bool {
+        // The cascade tag 0x88 is part of the first check.
+        let bcc0 = self.uid_start.iter().fold(0x88, |acc, b| acc ^ b);
+        let bcc1 = self.uid_end.iter().fold(0, |acc, b| acc ^ b);
+        self.bcc0 == bcc0 && self.bcc1 == bcc1
+    }
+
+    pub fn is_formatted(&self) -> bool {
+        self.magic == AMIIBO_MAGIC
+    }
+
+    /// Incremented by the console at each write of the application data.
+    pub fn write_counter(&self) -> u16 {
+        u16::from_be_bytes(self.write_counter)
+    }
+
+    /// Read a 4-byte page.
+    pub fn page(&self, page: usize) -> Option<&[u8]> {
+        let start = page * NTAG215_PAGE_SIZE;
+        self.as_bytes().get(start..start + NTAG215_PAGE_SIZE)
+    }
+}
+
+impl From<[u8; NTAG215_SIZE]> for AmiiboData {
+    fn from(raw: [u8; NTAG215_SIZE]) -> Self {
+        unsafe { std::mem::transmute(raw) }
+    }
+}
+
+impl fmt::Debug for AmiiboData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AmiiboData")
+            .field("uid", &format_args!("{:02x?}", self.uid()))
+            .field("write_counter", &self.write_counter())
+            .field("model", &self.model)
+            .finish()
+    }
+}
+
+/// Plaintext identification of the figure.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone)]
+pub struct AmiiboModel {
+    character: [u8; 2],
+    pub variant: u8,
+    figure_type: u8,
+    model_number: [u8; 2],
+    pub series: u8,
+    pub format_version: u8,
+    _unknown: [u8; 4],
+}
+
+unsafe impl Wire for AmiiboModel {}
+
+impl AmiiboModel {
+    /// Id of the character, the high 12 bits being the game series.
+    pub fn character(&self) -> u16 {
+        u16::from_be_bytes(self.character)
+    }
+
+    pub fn game_series(&self) -> u16 {
+        self.character() >> 4
+    }
+
+    pub fn figure_type(&self) -> RawId<AmiiboType> {
+        RawId::new(self.figure_type)
+    }
+
+    pub fn model_number(&self) -> u16 {
+        u16::from_be_bytes(self.model_number)
+    }
+
+    /// 8-byte identifier of the figure as listed by the community databases.
+    pub fn id(&self) -> u64 {
+        let mut id = [0; 8];
+        id[..2].copy_from_slice(&self.character);
+        id[2] = self.variant;
+        id[3] = self.figure_type;
+        id[4..6].copy_from_slice(&self.model_number);
+        id[6] = self.series;
+        id[7] = self.format_version;
+        u64::from_be_bytes(id)
+    }
+}
+
+impl fmt::Debug for AmiiboModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AmiiboModel")
+            .field("id", &format_args!("{:016x}", self.id()))
+            .field("figure_type", &self.figure_type())
+            .finish()
+    }
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum AmiiboType {
+    Figure = 0,
+    Card = 1,
+    Yarn = 2,
+    Band = 3,
+}
+
+#[cfg(test)]
+#[test]
+fn decode_plaintext() {
+    let mut raw = [0; NTAG215_SIZE];
+    raw[..9].copy_from_slice(&[0x04, 0x01, 0x02, 0x8f, 0x10, 0x20, 0x30, 0x40, 0x40]);
+    raw[0x10..0x13].copy_from_slice(&[AMIIBO_MAGIC, 0x00, 0x2a]);
+    raw[0x54..0x5c].copy_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x34, 0x02, 0x02]);
+    let amiibo = AmiiboData::from(raw);
+
+    assert_eq!(amiibo.uid(), [0x04, 0x01, 0x02, 0x10, 0x20, 0x30, 0x40]);
+    assert!(amiibo.check_uid());
+    assert!(amiibo.is_formatted());
+    assert_eq!(amiibo.write_counter(), 0x2a);
+    assert_eq!(amiibo.model.id(), 0x0000_0000_0034_0202);
+    assert_eq!(amiibo.model.model_number(), 0x34);
+    assert_eq!(
+        amiibo.model.figure_type().try_into(),
+        Some(AmiiboType::Figure)
+    );
+    assert_eq!(amiibo.page(4), Some(&[AMIIBO_MAGIC, 0x00, 0x2a, 0x00][..]));
+    assert_eq!(amiibo.page(NTAG215_PAGES), None);
+}
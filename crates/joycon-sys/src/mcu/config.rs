@@ -0,0 +1,137 @@
+use crate::mcu::*;
+use crate::output::SubcommandRequest;
+
+/// MCU firmware version sent by the official firmware when configuring the
+/// IR sensor, `00 05 00 18` on the wire.
+pub const DEFAULT_IR_MCU_FW_VERSION: (u16, u16) = (0x0500, 0x1800);
+
+/// Configuration of the IR sensor, sent once the MCU is in IR mode.
+///
+/// ```ignore
+/// let config = IRConfig::new(MCUIRMode::ImageTransfer)
+///     .fragments(resolution.max_fragment_id())
+///     .mcu_fw_version(status.fw_major_version, status.fw_minor_version);
+/// joycon.call_subcmd_wait(MCUConfig::IR(config))?;
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct IRConfig {
+    mode: MCUIRMode,
+    fragments: u8,
+    mcu_fw_version: (u16, u16),
+}
+
+impl IRConfig {
+    pub fn new(mode: MCUIRMode) -> IRConfig {
+        IRConfig {
+            mode,
+            fragments: 0,
+            mcu_fw_version: DEFAULT_IR_MCU_FW_VERSION,
+        }
+    }
+
+    pub fn mode(&self) -> MCUIRMode {
+        self.mode
+    }
+
+    /// Id of the last fragment of an image, for `ImageTransfer`.
+    pub fn fragments(mut self, fragments: u8) -> IRConfig {
+        self.fragments = fragments;
+        self
+    }
+
+    /// Version reported in `MCUStatus`, instead of the default one.
+    pub fn mcu_fw_version(mut self, major: U16LE, minor: U16LE) -> IRConfig {
+        self.mcu_fw_version = (major.into(), minor.into());
+        self
+    }
+
+    fn data(&self) -> MCUIRModeData {
+        MCUIRModeData {
+            ir_mode: self.mode.into(),
+            no_of_frags: self.fragments,
+            mcu_fw_version: (self.mcu_fw_version.0.into(), self.mcu_fw_version.1.into()),
+        }
+    }
+}
+
+/// Typed payloads of the `SetMCUConf` subcommand, with the CRC computed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MCUConfig {
+    /// Switch the MCU to another mode, required before NFC, IR or ringcon
+    /// use.
+    Mode(MCUMode),
+    /// Configure the IR sensor.
+    IR(IRConfig),
+    /// Put the IR part of the MCU to sleep so the ringcon flex sensor can be
+    /// read, or reset it when disabling the ringcon.
+    Ringcon { enable: bool },
+}
+
+impl MCUConfig {
+    pub fn nfc() -> MCUConfig {
+        MCUConfig::Mode(MCUMode::NFC)
+    }
+
+    pub fn ir() -> MCUConfig {
+        MCUConfig::Mode(MCUMode::IR)
+    }
+
+    pub fn command(self) -> MCUCommand {
+        match self {
+            MCUConfig::Mode(mode) => MCUCommand::set_mcu_mode(mode),
+            MCUConfig::IR(config) => MCUCommand::configure_ir_ir(config.data()),
+            MCUConfig::Ringcon { enable } => MCUCommand::configure_mcu_ir(MCUIRModeData {
+                ir_mode: if enable {
+                    MCUIRMode::IRSensorSleep
+                } else {
+                    MCUIRMode::IRSensorReset
+                }
+                .into(),
+                no_of_frags: 0,
+                mcu_fw_version: (0.into(), 0.into()),
+            }),
+        }
+    }
+}
+
+impl From<MCUConfig> for MCUCommand {
+    fn from(config: MCUConfig) -> Self {
+        config.command()
+    }
+}
+
+impl From<MCUConfig> for SubcommandRequest {
+    fn from(config: MCUConfig) -> Self {
+        config.command().into()
+    }
+}
+
+impl From<IRConfig> for SubcommandRequest {
+    fn from(config: IRConfig) -> Self {
+        MCUConfig::IR(config).into()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn mcu_config_payloads() {
+    let bytes = |config: MCUConfig| {
+        let report = crate::OutputReport::from(SubcommandRequest::from(config));
+        let mut bytes = [0; 39];
+        bytes.copy_from_slice(&report.as_bytes()[10..49]);
+        bytes
+    };
+
+    let nfc = bytes(MCUConfig::nfc());
+    assert_eq!(nfc[..4], [0x21, 0x21, 0x00, 0x04]);
+    assert_eq!(nfc[38], compute_crc8(0, &nfc[3..38]));
+
+    let ir = bytes(MCUConfig::IR(
+        IRConfig::new(MCUIRMode::ImageTransfer).fragments(0x0f),
+    ));
+    assert_eq!(
+        ir[..9],
+        [0x21, 0x23, 0x01, 0x07, 0x0f, 0x00, 0x05, 0x00, 0x18]
+    );
+    assert_eq!(ir[38], compute_crc8(1, &ir[3..38]));
+}
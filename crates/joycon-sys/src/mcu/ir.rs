@@ -1,4 +1,6 @@
 use crate::mcu::*;
+pub use ir_exposure::*;
+pub use ir_hand::*;
 pub use ir_register::*;
 
 #[repr(u8)]
@@ -13,6 +15,7 @@ raw_enum! {
     #[id: IRRequestId]
     #[union: IRRequestUnion]
     #[struct: IRRequest]
+    #[layout offset = 1, size = 5]
     pub enum IRRequestEnum {
         get_sensor_data get_sensor_data_mut: GetSensorData = IRAckRequestPacket,
         get_state get_state_mut: GetState = (),
@@ -43,6 +46,7 @@ impl From<IRReadRegisters> for IRRequest {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug)]
 pub struct IRAckRequestPacket {
     pub packet_missing: RawId<Bool>,
@@ -50,7 +54,10 @@ pub struct IRAckRequestPacket {
     pub ack_packet_id: u8,
 }
 
+unsafe impl Wire for IRAckRequestPacket {}
+
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug)]
 pub struct IRReadRegisters {
     pub unknown_0x01: u8,
@@ -59,6 +66,8 @@ pub struct IRReadRegisters {
     pub nb_registers: u8,
 }
 
+unsafe impl Wire for IRReadRegisters {}
+
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
 pub enum MCUIRMode {
@@ -80,6 +89,7 @@ pub enum MCUIRMode {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Debug, Copy, Clone)]
 pub struct MCUIRModeData {
     pub ir_mode: RawId<MCUIRMode>,
@@ -89,7 +99,21 @@ pub struct MCUIRModeData {
     pub mcu_fw_version: (U16LE, U16LE),
 }
 
+unsafe impl Wire for MCUIRModeData {}
+
+impl MCUIRModeData {
+    pub fn mcu_fw_version(&self) -> (u16, u16) {
+        let (major, minor) = self.mcu_fw_version;
+        (major.into(), minor.into())
+    }
+
+    pub fn set_mcu_fw_version(&mut self, major: u16, minor: u16) {
+        self.mcu_fw_version = (major.into(), minor.into());
+    }
+}
+
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug)]
 pub struct IRStatus {
     _unknown_0x00: u8,
@@ -98,9 +122,21 @@ pub struct IRStatus {
     pub required_fw_minor_version: U16LE,
 }
 
-// TODO: better debug
+unsafe impl Wire for IRStatus {}
+
+impl IRStatus {
+    /// MCU firmware version to send in [`MCUIRModeData`].
+    pub fn required_fw_version(&self) -> (u16, u16) {
+        (
+            self.required_fw_major_version.into(),
+            self.required_fw_minor_version.into(),
+        )
+    }
+}
+
 #[repr(packed)]
-#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone)]
 pub struct IRRegistersSlice {
     _unknown_0x00: u8,
     pub page: u8,
@@ -109,7 +145,29 @@ pub struct IRRegistersSlice {
     pub values: [u8; 0x7f],
 }
 
+unsafe impl Wire for IRRegistersSlice {}
+
+impl IRRegistersSlice {
+    /// Known registers contained in the slice.
+    pub fn registers(&self) -> impl Iterator<Item = Register> + '_ {
+        let len = (self.nb_registers as usize).min(self.values.len());
+        Register::decode_raw(self.page, self.offset, &self.values[..len])
+    }
+}
+
+impl fmt::Debug for IRRegistersSlice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IRRegistersSlice")
+            .field("page", &self.page)
+            .field("offset", &format_args!("0x{:x}", self.offset))
+            .field("nb_registers", &self.nb_registers)
+            .field("registers", &self.registers().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 pub struct IRData {
     _unknown: [u8; 2],
@@ -122,6 +180,18 @@ pub struct IRData {
     pub img_fragment: [u8; 300],
 }
 
+unsafe impl Wire for IRData {}
+
+impl IRData {
+    pub fn white_pixel_count(&self) -> u16 {
+        self.white_pixel_count.into()
+    }
+
+    pub fn ambient_noise_count(&self) -> u16 {
+        self.ambient_noise_count.into()
+    }
+}
+
 impl fmt::Debug for IRData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("IRData")
@@ -134,13 +204,25 @@ impl fmt::Debug for IRData {
 }
 
 #[repr(packed)]
-#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone)]
 pub struct MCURegisters {
     pub len: u8,
     pub regs: [ir_register::Register; 9],
 }
 
+unsafe impl Wire for MCURegisters {}
+
+impl fmt::Debug for MCURegisters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let regs = self.regs;
+        let len = (self.len as usize).min(regs.len());
+        f.debug_list().entries(&regs[..len]).finish()
+    }
+}
+
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 pub struct MCUSetReg {
     pub cmd_id: MCUCommandId,
@@ -162,3 +244,29 @@ fn check_output_layout() {
         );
     }
 }
+
+#[cfg(test)]
+#[test]
+fn registers_debug() {
+    let mut regs = [Register::default(); 9];
+    regs[0] = Register::flip(Flip::Both);
+    let regs = MCURegisters { len: 1, regs };
+    assert_eq!(
+        format!("{:?}", regs),
+        "[ir::Register { name: Flip, value: Both }]"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn registers_slice_page_end() {
+    let slice = IRRegistersSlice {
+        _unknown_0x00: 0,
+        page: 0,
+        offset: 0xf0,
+        nb_registers: 0x7f,
+        values: [0; 0x7f],
+    };
+    assert!(slice.registers().count() <= 0x10);
+    let _ = format!("{:?}", slice);
+}
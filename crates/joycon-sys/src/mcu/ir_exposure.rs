@@ -0,0 +1,193 @@
+//! Automatic exposure of the IR camera.
+//!
+//! The camera doesn't adapt to the ambient light by itself, so a fixed exposure
+//! gives either a black or a saturated image depending on the room. The
+//! brightness statistics sent with each image fragment are used to move the
+//! exposure, then the digital gain, toward a target average intensity.
+
+use crate::mcu::ir::*;
+use crate::output::OutputReport;
+
+/// Maximum exposure supported by the sensor, in µs.
+pub const MAX_EXPOSURE_US: u32 = 600;
+
+#[derive(Copy, Clone, Debug)]
+pub struct IrAutoExposureConfig {
+    /// Average intensity aimed for, between 0 and 255.
+    pub target_intensity: u8,
+    /// No adjustment is done while the average intensity is this close to the target.
+    pub tolerance: u8,
+    pub min_exposure_us: u32,
+    pub max_exposure_us: u32,
+    pub min_gain: u16,
+    pub max_gain: u16,
+    /// Number of frames ignored after a change, the registers only being
+    /// applied on the next frame.
+    pub settle_frames: u8,
+}
+
+impl Default for IrAutoExposureConfig {
+    fn default() -> Self {
+        IrAutoExposureConfig {
+            target_intensity: 0x60,
+            tolerance: 0x10,
+            min_exposure_us: 10,
+            max_exposure_us: MAX_EXPOSURE_US,
+            min_gain: 1,
+            max_gain: 0xff,
+            settle_frames: 2,
+        }
+    }
+}
+
+/// Computes the register writes keeping the IR image correctly exposed.
+///
+/// ```ignore
+/// let mut auto_exposure = IrAutoExposure::new(Default::default(), 200, 1);
+/// if let Some(data) = mcu_report.ir_data() {
+///     if let Some(mut report) = auto_exposure.update(data) {
+///         joycon.send(&mut report)?;
+///     }
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct IrAutoExposure {
+    config: IrAutoExposureConfig,
+    exposure_us: u32,
+    gain: u16,
+    frames_to_skip: u8,
+}
+
+impl IrAutoExposure {
+    /// `exposure_us` and `gain` are the values currently set on the camera.
+    pub fn new(config: IrAutoExposureConfig, exposure_us: u32, gain: u16) -> IrAutoExposure {
+        IrAutoExposure {
+            config,
+            exposure_us: exposure_us
+                .max(config.min_exposure_us)
+                .min(config.max_exposure_us),
+            gain: gain.max(config.min_gain).min(config.max_gain),
+            frames_to_skip: 0,
+        }
+    }
+
+    pub fn config(&self) -> IrAutoExposureConfig {
+        self.config
+    }
+
+    pub fn exposure_us(&self) -> u32 {
+        self.exposure_us
+    }
+
+    pub fn gain(&self) -> u16 {
+        self.gain
+    }
+
+    /// Registers setting the current exposure and gain.
+    pub fn registers(&self) -> [Register; 5] {
+        let [exp_lsb, exp_msb] = Register::exposure_us(self.exposure_us);
+        let [gain_lsb, gain_msb] = Register::digital_gain(self.gain);
+        [exp_lsb, exp_msb, gain_lsb, gain_msb, Register::finish()]
+    }
+
+    /// Handle the statistics of a received fragment.
+    ///
+    /// Only the first fragment of each frame is considered. Returns the report
+    /// to send when the exposure needs to change.
+    pub fn update(&mut self, data: &IRData) -> Option<OutputReport> {
+        if data.frag_number != 0 {
+            return None;
+        }
+        if self.frames_to_skip > 0 {
+            self.frames_to_skip -= 1;
+            return None;
+        }
+        if !self.adjust(data.average_intensity) {
+            return None;
+        }
+        self.frames_to_skip = self.config.settle_frames;
+        let regs = self.registers();
+        let (report, remaining) = OutputReport::set_registers(&regs);
+        debug_assert!(remaining.is_empty());
+        Some(report)
+    }
+
+    /// Move the exposure and gain toward the target, returns true if they changed.
+    fn adjust(&mut self, intensity: u8) -> bool {
+        let target = self.config.target_intensity as u32;
+        let intensity = intensity as u32;
+        if (intensity as i32 - target as i32).abs() <= self.config.tolerance as i32 {
+            return false;
+        }
+        let (old_exposure, old_gain) = (self.exposure_us, self.gain);
+        // Brightness is roughly proportional to exposure * gain, but the
+        // correction is limited to a factor 2 per step to avoid oscillations.
+        let scale = |value: u32| -> u32 {
+            (value.saturating_mul(target) / intensity.max(1))
+                .max(value / 2)
+                .min(value.saturating_mul(2))
+        };
+        if intensity < target {
+            if self.exposure_us < self.config.max_exposure_us {
+                self.exposure_us = scale(self.exposure_us)
+                    .max(self.exposure_us + 1)
+                    .min(self.config.max_exposure_us);
+            } else {
+                self.gain = scale(self.gain as u32)
+                    .max(self.gain as u32 + 1)
+                    .min(self.config.max_gain as u32) as u16;
+            }
+        } else if self.gain > self.config.min_gain {
+            // Lower the gain first as it adds noise.
+            self.gain = (scale(self.gain as u32).min(self.gain as u32 - 1) as u16)
+                .max(self.config.min_gain);
+        } else {
+            self.exposure_us = scale(self.exposure_us)
+                .min(self.exposure_us.saturating_sub(1))
+                .max(self.config.min_exposure_us);
+        }
+        (self.exposure_us, self.gain) != (old_exposure, old_gain)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn converges_toward_target() {
+    let config = IrAutoExposureConfig {
+        settle_frames: 0,
+        ..Default::default()
+    };
+    let mut auto_exposure = IrAutoExposure::new(config, 200, 4);
+
+    // Too bright: the gain goes down before the exposure.
+    assert!(auto_exposure.adjust(0xff));
+    assert_eq!(auto_exposure.exposure_us(), 200);
+    assert_eq!(auto_exposure.gain(), 2);
+    assert!(auto_exposure.adjust(0xff));
+    assert!(auto_exposure.adjust(0xff));
+    assert_eq!(auto_exposure.gain(), 1);
+    assert!(auto_exposure.exposure_us() < 200);
+
+    // Correctly exposed.
+    assert!(!auto_exposure.adjust(0x60));
+
+    // Too dark, up to the maximum exposure then the gain.
+    for _ in 0..10 {
+        auto_exposure.adjust(0);
+    }
+    assert_eq!(auto_exposure.exposure_us(), MAX_EXPOSURE_US);
+    assert!(auto_exposure.gain() > 1);
+
+    // At the limits of the types.
+    let config = IrAutoExposureConfig {
+        min_exposure_us: 0,
+        min_gain: 1,
+        max_gain: u16::MAX,
+        ..config
+    };
+    let mut auto_exposure = IrAutoExposure::new(config, 0, 1);
+    assert!(!auto_exposure.adjust(0xff));
+    let mut auto_exposure = IrAutoExposure::new(config, config.max_exposure_us, u16::MAX);
+    assert!(!auto_exposure.adjust(0));
+    assert_eq!(auto_exposure.gain(), u16::MAX);
+}
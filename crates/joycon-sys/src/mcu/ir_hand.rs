@@ -0,0 +1,120 @@
+//! Hand analysis modes of the IR camera.
+//!
+//! Instead of image fragments, the MCU sends a summary of the hand in front of
+//! the camera, used by some games for gesture detection. Its encoding is
+//! mostly unknown: the header shared with the image transfer is decoded and
+//! the rest is exposed raw, per mode.
+
+use crate::mcu::{ir::*, IRConfig};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum HandAnalysisMode {
+    Silhouette,
+    Image,
+    SilhouetteImage,
+}
+
+impl HandAnalysisMode {
+    pub fn ir_mode(self) -> MCUIRMode {
+        match self {
+            HandAnalysisMode::Silhouette => MCUIRMode::HandAnalysisSilhouette,
+            HandAnalysisMode::Image => MCUIRMode::HandAnalysisImage,
+            HandAnalysisMode::SilhouetteImage => MCUIRMode::HandAnalysisSilhouetteImage,
+        }
+    }
+
+    pub fn from_ir_mode(mode: MCUIRMode) -> Option<HandAnalysisMode> {
+        match mode {
+            MCUIRMode::HandAnalysisSilhouette => Some(HandAnalysisMode::Silhouette),
+            MCUIRMode::HandAnalysisImage => Some(HandAnalysisMode::Image),
+            MCUIRMode::HandAnalysisSilhouetteImage => Some(HandAnalysisMode::SilhouetteImage),
+            _ => None,
+        }
+    }
+
+    /// Configuration of the IR sensor, to send once the MCU is in IR mode.
+    pub fn config(self) -> IRConfig {
+        IRConfig::new(self.ir_mode())
+    }
+}
+
+/// Raw sections of a hand analysis packet.
+#[derive(Copy, Clone, Debug)]
+pub enum HandAnalysisPayload<'a> {
+    Silhouette(&'a [u8]),
+    Image(&'a [u8]),
+    /// Both sections, the boundary between them being unknown.
+    SilhouetteImage(&'a [u8]),
+}
+
+/// Summary sent in the `IRData` reports of the hand analysis modes.
+#[derive(Copy, Clone, Debug)]
+pub struct HandAnalysisPacket<'a> {
+    mode: HandAnalysisMode,
+    data: &'a IRData,
+}
+
+impl<'a> HandAnalysisPacket<'a> {
+    /// Interpret `data` received in `mode`, `None` if it's not a hand
+    /// analysis mode.
+    pub fn new(mode: MCUIRMode, data: &'a IRData) -> Option<HandAnalysisPacket<'a>> {
+        Some(HandAnalysisPacket {
+            mode: HandAnalysisMode::from_ir_mode(mode)?,
+            data,
+        })
+    }
+
+    pub fn mode(&self) -> HandAnalysisMode {
+        self.mode
+    }
+
+    /// Incremented for each packet.
+    pub fn sequence_number(&self) -> u8 {
+        self.data.frag_number
+    }
+
+    pub fn average_intensity(&self) -> u8 {
+        self.data.average_intensity
+    }
+
+    pub fn white_pixel_count(&self) -> u16 {
+        self.data.white_pixel_count.into()
+    }
+
+    pub fn ambient_noise_count(&self) -> u16 {
+        self.data.ambient_noise_count.into()
+    }
+
+    pub fn payload(&self) -> HandAnalysisPayload<'a> {
+        let raw = &self.data.img_fragment[..];
+        match self.mode {
+            HandAnalysisMode::Silhouette => HandAnalysisPayload::Silhouette(raw),
+            HandAnalysisMode::Image => HandAnalysisPayload::Image(raw),
+            HandAnalysisMode::SilhouetteImage => HandAnalysisPayload::SilhouetteImage(raw),
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn hand_analysis_packet() {
+    use crate::common::Wire;
+
+    let mut bytes = [0; 309];
+    bytes[2] = 7;
+    bytes[5..7].copy_from_slice(&[0x34, 0x12]);
+    bytes[9] = 0xaa;
+    let data = IRData::from_bytes(&bytes).unwrap();
+    assert!(HandAnalysisPacket::new(MCUIRMode::ImageTransfer, &data).is_none());
+    let packet = HandAnalysisPacket::new(MCUIRMode::HandAnalysisSilhouette, &data).unwrap();
+    assert_eq!(packet.sequence_number(), 7);
+    assert_eq!(packet.white_pixel_count(), 0x1234);
+    match packet.payload() {
+        HandAnalysisPayload::Silhouette(raw) => assert_eq!(raw[0], 0xaa),
+        payload => panic!("unexpected {:?}", payload),
+    }
+    assert_eq!(
+        HandAnalysisMode::Image.config().mode(),
+        MCUIRMode::HandAnalysisImage
+    );
+}
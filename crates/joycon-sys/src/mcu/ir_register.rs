@@ -1,7 +1,9 @@
+use crate::common::{RawId, Wire};
 use std::convert::TryFrom;
 use std::fmt;
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Default, Eq, PartialEq)]
 pub struct Register {
     page: u8,
@@ -9,6 +11,8 @@ pub struct Register {
     value: u8,
 }
 
+unsafe impl Wire for Register {}
+
 impl Register {
     fn new(address: Address, value: u8) -> Register {
         Register {
@@ -18,6 +22,8 @@ impl Register {
         }
     }
 
+    /// Known registers among `values`, starting at `base_offset`. The values
+    /// past the end of the page are ignored.
     pub fn decode_raw<'a>(
         page: u8,
         base_offset: u8,
@@ -26,9 +32,13 @@ impl Register {
         values
             .iter()
             .enumerate()
+            .map_while(move |(offset, value)| {
+                let offset = u8::try_from(offset).ok()?;
+                Some((base_offset.checked_add(offset)?, *value))
+            })
             .filter_map(move |(offset, value)| {
-                Address::try_from((page, base_offset + offset as u8))
-                    .map(|a| Register::new(a, *value))
+                Address::try_from((page, offset))
+                    .map(|a| Register::new(a, value))
                     .ok()
             })
     }
@@ -127,17 +137,45 @@ impl fmt::Debug for Register {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut out = f.debug_struct("ir::Register");
         match Address::try_from((self.page, self.offset)) {
-            Ok(a) => out.field("name", &a),
-            Err(_) => out.field(
-                "address",
-                &format_args!("0x{:x?}:0x{:x?}", self.page, self.offset),
-            ),
+            Ok(a) => out
+                .field("name", &a)
+                .field("value", &RegisterValue(a, self.value)),
+            Err(_) => out
+                .field(
+                    "address",
+                    &format_args!("0x{:x?}:0x{:x?}", self.page, self.offset),
+                )
+                .field("value", &format_args!("0x{:x?}", self.value)),
         };
-        out.field("value", &format_args!("0x{:x?}", self.value));
         out.finish()
     }
 }
 
+/// Value of a known register, decoded according to its address.
+struct RegisterValue(Address, u8);
+
+impl fmt::Debug for RegisterValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = self.1;
+        match self.0 {
+            Resolution => write!(f, "{:?}", RawId::<Resolution>::new(value)),
+            ExposureMode => write!(f, "{:?}", RawId::<ExposureMode>::new(value)),
+            ExternalLightFilter => write!(f, "{:?}", RawId::<ExternalLightFilter>::new(value)),
+            Flip => write!(f, "{:?}", RawId::<Flip>::new(value)),
+            IRLeds => write!(f, "{:?}", Leds(value)),
+            Denoise | Finish => write!(f, "{}", value != 0),
+            DigitalGainLSB => write!(f, "0x{:x}", value >> 4),
+            DigitalGainMSB => write!(f, "0x{:x}", (value & 0x0f) << 4),
+            WhitePixelThreshold
+            | EdgeSmoothingThreshold
+            | ColorInterpolationThreshold
+            | BufferUpdateTimeLSB => write!(f, "{}", value),
+            IntensityLedsFar12 | IntensityLedsNear34 => write!(f, "{}/15", value),
+            ExposureLSB | ExposureMSB => write!(f, "0x{:02x}", value),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum Address {
     Resolution,
@@ -4,7 +4,13 @@ use crate::raw_enum;
 use ir::*;
 use std::fmt;
 
+pub use config::*;
+
+pub mod amiibo;
+mod config;
 pub mod ir;
+mod ir_exposure;
+mod ir_hand;
 mod ir_register;
 
 #[repr(u8)]
@@ -26,6 +32,7 @@ raw_enum! {
     #[union: MCUReportUnion]
     #[struct: MCUReport]
     #[raw [u8; 312]]
+    #[layout offset = 1, size = 313]
     pub enum MCUReportEnum {
         empty empty_mut: Empty = (),
         state_report state_report_mut: StateReport = MCUStatus,
@@ -58,6 +65,7 @@ impl MCUReport {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug)]
 pub struct MCUStatus {
     _unknown: [u8; 2],
@@ -66,6 +74,15 @@ pub struct MCUStatus {
     pub state: RawId<MCUMode>,
 }
 
+unsafe impl Wire for MCUStatus {}
+
+impl MCUStatus {
+    /// Major and minor version of the MCU firmware.
+    pub fn fw_version(&self) -> (u16, u16) {
+        (self.fw_major_version.into(), self.fw_minor_version.into())
+    }
+}
+
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
 pub enum MCUCommandId {
@@ -82,6 +99,7 @@ pub enum MCUSubCommandId {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 pub struct MCUCommand {
     cmd_id: RawId<MCUCommandId>,
@@ -89,6 +107,8 @@ pub struct MCUCommand {
     u: MCUCommandUnion,
 }
 
+unsafe impl Wire for MCUCommand {}
+
 impl MCUCommand {
     pub fn set_mcu_mode(mode: MCUMode) -> Self {
         let mut u = MCUCommandUnion::new();
@@ -165,6 +185,7 @@ impl fmt::Debug for MCUCommand {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 union MCUCommandUnion {
     mcu_mode: RawId<MCUMode>,
@@ -185,12 +206,15 @@ impl MCUCommandUnion {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 pub struct MCUCommandCRC {
     bytes: [u8; 35],
     crc: u8,
 }
 
+unsafe impl Wire for MCUCommandCRC {}
+
 impl fmt::Debug for MCUCommandCRC {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_tuple("MCUCommandCRC").field(&self.crc).finish()
@@ -228,6 +252,7 @@ raw_enum! {
     #[union: MCURequestUnion]
     #[struct: MCURequest]
     #[raw [u8; 38]]
+    #[layout offset = 1, size = 39]
     #[field crc crc_mut: MCURequestCRC]
     pub enum MCURequestEnum {
         get_mcu_status get_mcu_status_mut: GetMCUStatus = (),
@@ -253,6 +278,7 @@ impl From<IRRequestEnum> for MCURequest {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone)]
 pub struct MCURequestCRC {
     bytes: [u8; 36],
@@ -260,6 +286,8 @@ pub struct MCURequestCRC {
     _padding_0xff: u8,
 }
 
+unsafe impl Wire for MCURequestCRC {}
+
 impl MCURequestCRC {
     pub fn compute_crc8(&mut self, id: IRRequestId) {
         // To simplify the data layout, subcmd_id is outside the byte buffer.
@@ -271,7 +299,7 @@ impl MCURequestCRC {
     }
 }
 
-fn compute_crc8(id: u8, bytes: &[u8]) -> u8 {
+pub(crate) fn compute_crc8(id: u8, bytes: &[u8]) -> u8 {
     // To simplify the data layout, subcmd_id is outside the byte buffer.
     let mut crc = MCU_CRC8_TABLE[id as usize];
     for byte in bytes {
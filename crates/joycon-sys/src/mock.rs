@@ -0,0 +1,306 @@
+//! In-memory controller implementing the device side of the protocol.
+//!
+//! [`MockController`] answers the output reports of a driver like a real
+//! controller would, so the driver can be tested without hardware:
+//!
+//! ```ignore
+//! let mut controller = MockController::new(WhichController::ProController);
+//! controller.send(&SubcommandRequestEnum::RequestDeviceInfo(()).into());
+//! let reply = controller.recv();
+//! assert!(reply.subcmd_reply().unwrap().device_info().is_some());
+//! ```
+
+use crate::{
+    common::*,
+    imu,
+    input::*,
+    output::{SubcommandRequest, SubcommandRequestEnum},
+    spi::{regions::FLASH_SIZE, *},
+    InputReport, OutputReport,
+};
+use cgmath::Vector3;
+use std::{collections::VecDeque, convert::TryFrom};
+
+/// SPI flash of a [`MockController`].
+///
+/// Starts erased, every byte being `0xFF`, except for the colors.
+#[derive(Clone)]
+pub struct VirtualFlash {
+    data: Vec<u8>,
+}
+
+impl VirtualFlash {
+    pub fn new() -> VirtualFlash {
+        VirtualFlash {
+            data: vec![0xFF; FLASH_SIZE as usize],
+        }
+    }
+
+    /// Flash initialized from a dump, eg. `joytk dump`.
+    pub fn from_image(image: &[u8]) -> Option<VirtualFlash> {
+        if image.len() != FLASH_SIZE as usize {
+            return None;
+        }
+        Some(VirtualFlash {
+            data: image.to_vec(),
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Data at `range`, `None` if it doesn't fit in the flash.
+    pub fn read(&self, range: SPIRange) -> Option<&[u8]> {
+        let start = range.offset() as usize;
+        self.data.get(start..start + range.size() as usize)
+    }
+
+    /// Write `data` at `offset`, returning false if it doesn't fit.
+    pub fn write(&mut self, offset: u32, data: &[u8]) -> bool {
+        let start = offset as usize;
+        match self.data.get_mut(start..start + data.len()) {
+            Some(dest) => {
+                dest.copy_from_slice(data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn apply(&mut self, request: &SPIWriteRequest) -> bool {
+        self.write(request.range().offset(), request.data())
+    }
+}
+
+impl Default for VirtualFlash {
+    fn default() -> Self {
+        VirtualFlash::new()
+    }
+}
+
+impl std::fmt::Debug for VirtualFlash {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("VirtualFlash").finish()
+    }
+}
+
+/// Controller answering the output reports in memory.
+///
+/// Subcommands are acknowledged with a `0x21` report, answering device info
+/// requests, IMU register reads with the LSM6DS3 identifier and SPI reads and
+/// writes from the [`VirtualFlash`]. Otherwise the
+/// reports are synthesized from the public fields, in the current input
+/// report mode.
+#[derive(Clone, Debug)]
+pub struct MockController {
+    pub device_info: DeviceInfo,
+    pub status: DeviceStatus,
+    pub buttons: ButtonsStatus,
+    pub left_stick: (u16, u16),
+    pub right_stick: (u16, u16),
+    /// Frames sent in the `0x30` and `0x31` reports.
+    pub imu_frames: [imu::Frame; 3],
+    flash: VirtualFlash,
+    report_mode: RawId<InputReportId>,
+    timer: u8,
+    replies: VecDeque<SubcommandReply>,
+    subcommands: Vec<SubcommandRequest>,
+}
+
+impl MockController {
+    pub fn new(which: WhichController) -> MockController {
+        let mut flash = VirtualFlash::new();
+        let color = ControllerColor {
+            body: Color::new(0x32, 0x32, 0x32),
+            buttons: Color::new(0xff, 0xff, 0xff),
+            left_grip: Color::new(0x32, 0x32, 0x32),
+            right_grip: Color::new(0x32, 0x32, 0x32),
+        };
+        flash.apply(&color.into());
+        let status = match which {
+            WhichController::ProController => 0x80,
+            _ => 0x8e,
+        };
+        // At rest on a table.
+        let frame = imu::Frame::new(Vector3::new(0., 0., 4096.), Vector3::new(0., 0., 0.));
+        MockController {
+            device_info: DeviceInfo::new(
+                FirmwareVersion([0x04, 0x07]),
+                which,
+                MACAddress([0x98, 0xb6, 0xe9, 0x00, 0x00, 0x01]),
+                UseSPIColors::WithoutGrip,
+            ),
+            status: status.into(),
+            buttons: ButtonsStatus::default(),
+            left_stick: (2048, 2048),
+            right_stick: (2048, 2048),
+            imu_frames: [frame; 3],
+            flash,
+            report_mode: InputReportId::Normal.into(),
+            timer: 0,
+            replies: VecDeque::new(),
+            subcommands: Vec::new(),
+        }
+    }
+
+    pub fn flash(&self) -> &VirtualFlash {
+        &self.flash
+    }
+
+    pub fn flash_mut(&mut self) -> &mut VirtualFlash {
+        &mut self.flash
+    }
+
+    pub fn report_mode(&self) -> RawId<InputReportId> {
+        self.report_mode
+    }
+
+    /// Subcommands received so far, in order.
+    pub fn subcommands(&self) -> &[SubcommandRequest] {
+        &self.subcommands
+    }
+
+    /// Receive an output report from the driver.
+    ///
+    /// Rumble and MCU data requests are accepted and ignored.
+    pub fn send(&mut self, report: &OutputReport) {
+        if let Some(subcmd) = report.rumble_subcmd() {
+            self.subcommands.push(*subcmd);
+            let reply = self.reply(subcmd);
+            self.replies.push_back(reply);
+        }
+    }
+
+    /// Next input report for the driver: the pending subcommand replies
+    /// first, then a report in the current mode.
+    pub fn recv(&mut self) -> InputReport {
+        self.timer = self.timer.wrapping_add(3);
+        let standard = StandardInputReport {
+            timer: self.timer,
+            info: self.status,
+            buttons: self.buttons,
+            left_stick: Stick::new(self.left_stick.0, self.left_stick.1),
+            right_stick: Stick::new(self.right_stick.0, self.right_stick.1),
+            vibrator: 0,
+        };
+        if let Some(reply) = self.replies.pop_front() {
+            return InputReportEnum::StandardAndSubcmd((standard, reply)).into();
+        }
+        match self.report_mode.try_into() {
+            Some(InputReportId::StandardFull) => {
+                InputReportEnum::StandardFull((standard, self.imu_frames)).into()
+            }
+            Some(InputReportId::StandardFullMCU) => InputReportEnum::StandardFullMCU((
+                standard,
+                self.imu_frames,
+                crate::mcu::MCUReport::new(),
+            ))
+            .into(),
+            _ => InputReportEnum::Normal(NormalInputReport::new(
+                &self.buttons,
+                standard.left_stick,
+                standard.right_stick,
+            ))
+            .into(),
+        }
+    }
+
+    fn reply(&mut self, subcmd: &SubcommandRequest) -> SubcommandReply {
+        let (ack, mut reply) = match SubcommandRequestEnum::try_from(*subcmd) {
+            Ok(SubcommandRequestEnum::RequestDeviceInfo(())) => (
+                0x02,
+                SubcommandReplyEnum::RequestDeviceInfo(self.device_info).into(),
+            ),
+            Ok(SubcommandRequestEnum::SPIRead(request)) => {
+                let range = request.range();
+                match self.flash.read(range) {
+                    Some(data) if data.len() <= 0x1D => (
+                        0x10,
+                        SubcommandReplyEnum::SPIRead(SPIReadResult::new(range, data)).into(),
+                    ),
+                    // Past the end of the flash, or larger than a reply.
+                    _ => {
+                        let mut reply = SubcommandReply::empty(subcmd.id());
+                        *reply.ack_mut() = Ack::nack();
+                        return reply;
+                    }
+                }
+            }
+            Ok(SubcommandRequestEnum::SPIWrite(request)) => {
+                let success = self.flash.apply(&request);
+                (
+                    0x00,
+                    SubcommandReplyEnum::SPIWrite(SPIWriteResult::new(success)).into(),
+                )
+            }
+            Ok(SubcommandRequestEnum::ReadIMURegisters(read)) => {
+                let mut values = vec![0; read.count.min(0x20) as usize];
+                if let Some(who_am_i) = imu::REG_WHO_AM_I
+                    .checked_sub(read.address)
+                    .and_then(|i| values.get_mut(i as usize))
+                {
+                    *who_am_i = imu::LSM6DS3_ID;
+                }
+                let result = imu::RegistersReadResult::new(read.address, &values);
+                (0x00, SubcommandReplyEnum::ReadIMURegisters(result).into())
+            }
+            Ok(SubcommandRequestEnum::SetInputReportMode(mode)) => {
+                self.report_mode = mode;
+                (0x00, SubcommandReplyEnum::SetInputReportMode(()).into())
+            }
+            _ => (0x00, SubcommandReply::empty(subcmd.id())),
+        };
+        *reply.ack_mut() = Ack::ok(ack);
+        reply
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn mock_controller() {
+    let mut controller = MockController::new(WhichController::LeftJoyCon);
+    let send = |controller: &mut MockController, subcmd: SubcommandRequest| {
+        controller.send(&subcmd.into());
+        *controller.recv().subcmd_reply().unwrap()
+    };
+
+    let reply = send(
+        &mut controller,
+        SubcommandRequestEnum::RequestDeviceInfo(()).into(),
+    );
+    assert!(reply.ack().is_ok());
+    assert_eq!(
+        reply.device_info().unwrap().which_controller,
+        WhichController::LeftJoyCon
+    );
+
+    let calib = UserSticksCalibration::reset();
+    assert!(send(&mut controller, SPIWriteRequest::from(calib).into())
+        .is_spi_write_success()
+        .unwrap());
+    let reply = send(
+        &mut controller,
+        SPIReadRequest::new(UserSticksCalibration::range()).into(),
+    );
+    let read = UserSticksCalibration::try_from(*reply.spi_read_result().unwrap()).unwrap();
+    assert_eq!(read, calib);
+
+    let mode = SubcommandRequestEnum::SetInputReportMode(InputReportId::StandardFull.into());
+    send(&mut controller, mode.into());
+    assert!(controller.recv().imu_frames().is_some());
+    assert_eq!(controller.subcommands().len(), 4);
+
+    // Past the end of the flash.
+    let read = SPIReadRequest::new(unsafe { SPIRange::new(0x7fff0, 0x1d) });
+    assert!(!send(&mut controller, read.into()).ack().is_ok());
+
+    let mode = SubcommandRequestEnum::SetInputReportMode(InputReportId::Normal.into());
+    send(&mut controller, mode.into());
+    controller.buttons.right = RightButtons(0x08);
+    controller.buttons.left = LeftButtons(0x02);
+    let report = controller.recv();
+    let normal = report.normal().unwrap();
+    assert_eq!(normal.buttons_bits(), 0x02);
+    assert_eq!(normal.stick, 0);
+}
@@ -0,0 +1,148 @@
+//! Detection of a controller falling back to another input report mode.
+//!
+//! After a reconnection, the controller often goes back to the simple HID
+//! mode (`0x3F`) without notice. [`ModeWatchdog`] compares the ids of the
+//! incoming reports with the configured mode and gives the reports restoring
+//! it.
+//!
+//! ```ignore
+//! let mut watchdog = ModeWatchdog::new(InputReportId::StandardFull);
+//! let report = recv()?;
+//! if let Some(mismatch) = watchdog.check(&report) {
+//!     log::warn!("{}", mismatch);
+//!     for mut report in watchdog.restore() {
+//!         send(&mut report)?;
+//!     }
+//! }
+//! ```
+
+use crate::{
+    common::{InputReportId, RawId},
+    imu::IMUMode,
+    output::{SubcommandRequest, SubcommandRequestEnum},
+    InputReport, OutputReport,
+};
+use std::fmt;
+
+/// Default number of consecutive reports in the wrong mode before reporting
+/// a mismatch.
+pub const MODE_MISMATCH_REPORTS: u32 = 5;
+
+/// The controller sends reports in another mode than the expected one.
+#[derive(Copy, Clone, Debug)]
+pub struct ModeMismatch {
+    pub expected: InputReportId,
+    pub actual: RawId<InputReportId>,
+}
+
+impl fmt::Display for ModeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expected {:?} reports, got {:?}",
+            self.expected, self.actual
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ModeWatchdog {
+    expected: InputReportId,
+    threshold: u32,
+    mismatches: u32,
+}
+
+impl ModeWatchdog {
+    pub fn new(expected: InputReportId) -> ModeWatchdog {
+        ModeWatchdog {
+            expected,
+            threshold: MODE_MISMATCH_REPORTS,
+            mismatches: 0,
+        }
+    }
+
+    /// Number of consecutive reports in the wrong mode before reporting a
+    /// mismatch, 1 to report it immediately.
+    pub fn with_threshold(mut self, reports: u32) -> ModeWatchdog {
+        self.threshold = reports.max(1);
+        self
+    }
+
+    pub fn expected(&self) -> InputReportId {
+        self.expected
+    }
+
+    /// Change the expected mode, after sending `SetInputReportMode`.
+    pub fn set_expected(&mut self, expected: InputReportId) {
+        self.expected = expected;
+        self.mismatches = 0;
+    }
+
+    /// Check the mode of `report`, returning the mismatch once `threshold`
+    /// consecutive reports are in another mode.
+    ///
+    /// Subcommand replies are sent in every mode and are ignored. After a
+    /// mismatch the count starts again, so it's reported periodically until
+    /// the mode is restored.
+    pub fn check(&mut self, report: &InputReport) -> Option<ModeMismatch> {
+        let actual = report.id();
+        if actual == InputReportId::StandardAndSubcmd {
+            return None;
+        }
+        if actual == self.expected {
+            self.mismatches = 0;
+            return None;
+        }
+        self.mismatches += 1;
+        if self.mismatches < self.threshold {
+            return None;
+        }
+        self.mismatches = 0;
+        trace!("report mode {:?} instead of {:?}", actual, self.expected);
+        Some(ModeMismatch {
+            expected: self.expected,
+            actual,
+        })
+    }
+
+    /// Reports putting the controller back in the expected mode, to be sent
+    /// one after the other.
+    ///
+    /// The IMU is enabled again for the modes containing its frames, since it
+    /// is disabled on reconnection too.
+    pub fn restore(&self) -> Vec<OutputReport> {
+        let mut subcmds: Vec<SubcommandRequest> =
+            vec![SubcommandRequestEnum::SetInputReportMode(self.expected.into()).into()];
+        if self.expected == InputReportId::StandardFull
+            || self.expected == InputReportId::StandardFullMCU
+        {
+            subcmds.push(SubcommandRequestEnum::SetIMUMode(IMUMode::GyroAccel.into()).into());
+        }
+        subcmds.into_iter().map(OutputReport::from).collect()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn detect_downgrade() {
+    let report = |id: InputReportId| {
+        let mut report = InputReport::new();
+        report.as_bytes_mut()[0] = id as u8;
+        report
+    };
+    let mut watchdog = ModeWatchdog::new(InputReportId::StandardFull).with_threshold(2);
+    assert!(watchdog
+        .check(&report(InputReportId::StandardFull))
+        .is_none());
+    assert!(watchdog.check(&report(InputReportId::Normal)).is_none());
+    assert!(watchdog
+        .check(&report(InputReportId::StandardAndSubcmd))
+        .is_none());
+    let mismatch = watchdog.check(&report(InputReportId::Normal)).unwrap();
+    assert!(mismatch.actual == InputReportId::Normal);
+
+    let restore = watchdog.restore();
+    assert_eq!(restore.len(), 2);
+    let subcmd = restore[0].rumble_subcmd().unwrap();
+    assert!(*subcmd.set_input_report_mode().unwrap() == InputReportId::StandardFull);
+}
@@ -0,0 +1,197 @@
+//! Expected length of the arguments of each subcommand.
+//!
+//! The arguments are padded with zeros to [`SUBCOMMAND_ARGS_LEN`], so a
+//! truncated argument still produces a well-formed report, which the
+//! controller silently ignores. [`SubcommandRequest::with_args`] checks the
+//! length against [`args_len`] instead:
+//!
+//! ```ignore
+//! let request = SubcommandRequest::with_args(SubcommandId::SetIMUSens, &[3, 0, 1, 1])?;
+//! ```
+
+use super::{SubcommandRequest, SUBCOMMAND_ARGS_LEN};
+use crate::{
+    accessory::AccessoryCommand,
+    common::SubcommandId,
+    imu::{RegisterWrite, RegistersRead, Sensitivity},
+    light::HomeLight,
+    mcu::MCUCommand,
+    pairing::PairingRequest,
+};
+use std::{fmt, mem::size_of};
+
+/// Size of the address and size header of the SPI read and write arguments.
+const SPI_HEADER_LEN: usize = 5;
+
+/// Accepted lengths of the arguments of a subcommand, both included.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ArgsLen {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl ArgsLen {
+    pub const fn exact(len: usize) -> ArgsLen {
+        ArgsLen { min: len, max: len }
+    }
+
+    pub const fn between(min: usize, max: usize) -> ArgsLen {
+        ArgsLen { min, max }
+    }
+
+    pub fn contains(&self, len: usize) -> bool {
+        self.min <= len && len <= self.max
+    }
+}
+
+impl fmt::Display for ArgsLen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{} to {}", self.min, self.max)
+        }
+    }
+}
+
+/// Length of the arguments of `id`, without the zero padding.
+///
+/// The unknown subcommands accept anything up to [`SUBCOMMAND_ARGS_LEN`].
+/// The length of the data of a SPI write is further checked by
+/// [`check_args`].
+pub const fn args_len(id: SubcommandId) -> ArgsLen {
+    match id {
+        SubcommandId::GetOnlyControllerState => ArgsLen::exact(0),
+        // Only the first step sends the host address.
+        SubcommandId::BluetoothManualPairing => ArgsLen::between(1, size_of::<PairingRequest>()),
+        SubcommandId::RequestDeviceInfo => ArgsLen::exact(0),
+        SubcommandId::SetInputReportMode => ArgsLen::exact(1),
+        SubcommandId::GetTriggerButtonsElapsedTime => ArgsLen::exact(0),
+        SubcommandId::SetHCIState => ArgsLen::exact(1),
+        SubcommandId::ResetPairingInfo => ArgsLen::exact(0),
+        SubcommandId::SetShipmentMode => ArgsLen::exact(1),
+        SubcommandId::SPIRead => ArgsLen::exact(SPI_HEADER_LEN),
+        SubcommandId::SPIWrite => ArgsLen::between(SPI_HEADER_LEN, SPI_HEADER_LEN + 0x1D),
+        SubcommandId::SetMCUConf => ArgsLen::exact(size_of::<MCUCommand>()),
+        SubcommandId::SetMCUState => ArgsLen::exact(1),
+        SubcommandId::SetUnknownData => ArgsLen::between(0, SUBCOMMAND_ARGS_LEN),
+        SubcommandId::SetUnknownMCUData => ArgsLen::between(0, SUBCOMMAND_ARGS_LEN),
+        SubcommandId::GetUnknownMCUData => ArgsLen::exact(0),
+        SubcommandId::SetGPIOPinOutput => ArgsLen::between(0, SUBCOMMAND_ARGS_LEN),
+        SubcommandId::SetPlayerLights => ArgsLen::exact(1),
+        // The cycles are optional.
+        SubcommandId::SetHomeLight => ArgsLen::between(2, size_of::<HomeLight>()),
+        SubcommandId::SetIMUMode => ArgsLen::exact(1),
+        SubcommandId::SetIMUSens => ArgsLen::exact(size_of::<Sensitivity>()),
+        SubcommandId::WriteIMURegister => ArgsLen::exact(size_of::<RegisterWrite>()),
+        SubcommandId::ReadIMURegisters => ArgsLen::exact(size_of::<RegistersRead>()),
+        SubcommandId::EnableVibration => ArgsLen::exact(1),
+        SubcommandId::MaybeAccessory => ArgsLen::between(1, size_of::<AccessoryCommand>()),
+        SubcommandId::Unknown0x59 => ArgsLen::exact(0),
+        SubcommandId::Unknown0x5a => ArgsLen::between(0, SUBCOMMAND_ARGS_LEN),
+        SubcommandId::Unknown0x5b => ArgsLen::exact(0),
+        SubcommandId::Unknown0x5c => ArgsLen::between(0, SUBCOMMAND_ARGS_LEN),
+    }
+}
+
+/// Arguments of the wrong length, see [`check_args`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ArgsLenError {
+    pub id: SubcommandId,
+    /// Length of the arguments, or the length announced by the size byte of
+    /// a SPI write larger than a single write.
+    pub len: usize,
+    pub expected: ArgsLen,
+}
+
+impl fmt::Display for ArgsLenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} takes {} bytes of arguments, got {}",
+            self.id, self.expected, self.len
+        )
+    }
+}
+
+impl std::error::Error for ArgsLenError {}
+
+/// Check the length of the arguments of `id`, including the data of a SPI
+/// write against its size byte.
+pub fn check_args(id: SubcommandId, args: &[u8]) -> Result<(), ArgsLenError> {
+    let table = args_len(id);
+    let (len, expected) = match args.get(SPI_HEADER_LEN - 1) {
+        Some(&size) if id == SubcommandId::SPIWrite => {
+            let len = SPI_HEADER_LEN + size as usize;
+            if table.contains(len) {
+                (args.len(), ArgsLen::exact(len))
+            } else {
+                // More than a single write, whatever the arguments given.
+                (len, table)
+            }
+        }
+        _ => (args.len(), table),
+    };
+    if expected.contains(len) {
+        Ok(())
+    } else {
+        trace!("wrong arguments length for {:?}: {:?}", id, len);
+        Err(ArgsLenError { id, len, expected })
+    }
+}
+
+impl SubcommandRequest {
+    /// Subcommand with raw arguments, checked with [`check_args`].
+    pub fn with_args(id: SubcommandId, args: &[u8]) -> Result<Self, ArgsLenError> {
+        check_args(id, args)?;
+        Ok(SubcommandRequest::build(id as u8, args))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn subcommand_args_len() {
+    use crate::output::SubcommandRequestEnum;
+
+    let request = SubcommandRequest::with_args(SubcommandId::SetIMUSens, &[3, 0, 1, 1]).unwrap();
+    assert!(request.set_imu_sens().is_some());
+    let err = SubcommandRequest::with_args(SubcommandId::SetIMUSens, &[3, 0]).unwrap_err();
+    assert_eq!(err.expected, ArgsLen::exact(4));
+    assert_eq!(
+        err.to_string(),
+        "SetIMUSens takes 4 bytes of arguments, got 2"
+    );
+    assert!(check_args(SubcommandId::RequestDeviceInfo, &[1]).is_err());
+
+    // The size byte of a SPI write must match the data.
+    assert!(check_args(SubcommandId::SPIWrite, &[0x1B, 0x60, 0, 0, 1, 2]).is_ok());
+    assert_eq!(
+        check_args(SubcommandId::SPIWrite, &[0x1B, 0x60, 0, 0, 2, 2]),
+        Err(ArgsLenError {
+            id: SubcommandId::SPIWrite,
+            len: 6,
+            expected: ArgsLen::exact(7),
+        })
+    );
+    // Larger than a single write.
+    let mut write = vec![0; 5 + 0x30];
+    write[4] = 0x30;
+    assert_eq!(
+        check_args(SubcommandId::SPIWrite, &write),
+        Err(ArgsLenError {
+            id: SubcommandId::SPIWrite,
+            len: 5 + 0x30,
+            expected: ArgsLen::between(5, 5 + 0x1D),
+        })
+    );
+    write.truncate(5 + 0x1D);
+    assert_eq!(
+        check_args(SubcommandId::SPIWrite, &write).unwrap_err().len,
+        5 + 0x30
+    );
+
+    assert_eq!(args_len(SubcommandId::SetMCUConf), ArgsLen::exact(38));
+    for &id in SubcommandRequestEnum::VARIANTS {
+        assert!(args_len(id).max <= SUBCOMMAND_ARGS_LEN, "{:?}", id);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::output::{BufferTooSmallError, OutputReport, TransportKind};
+
+/// Fixed-capacity batch of output reports written with a single call, for
+/// the HID backends accepting several queued reports at once.
+///
+/// ```ignore
+/// let mut batch = OutputBatch::<4>::new();
+/// batch.push(OutputReport::set_rumble(left))?;
+/// batch.push(OutputReport::set_rumble(right))?;
+/// batch.stamp(&mut counter);
+/// let lengths = batch.write_into(&mut buf, TransportKind::Usb)?;
+/// write_reports(&buf, lengths)?;
+/// batch.clear();
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct OutputBatch<const N: usize> {
+    reports: [Option<OutputReport>; N],
+    lengths: [usize; N],
+}
+
+impl<const N: usize> OutputBatch<N> {
+    pub fn new() -> OutputBatch<N> {
+        OutputBatch {
+            reports: [None; N],
+            lengths: [0; N],
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.reports.iter().filter(|r| r.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reports.iter().all(Option::is_none)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.reports.iter().all(Option::is_some)
+    }
+
+    pub fn clear(&mut self) {
+        self.reports = [None; N];
+    }
+
+    /// Add a report at the end of the batch, giving it back if the batch is
+    /// full.
+    pub fn push(&mut self, report: OutputReport) -> Result<(), OutputReport> {
+        match self.reports.iter_mut().find(|r| r.is_none()) {
+            Some(slot) => {
+                *slot = Some(report);
+                Ok(())
+            }
+            None => Err(report),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &OutputReport> {
+        self.reports.iter().flatten()
+    }
+
+    /// Give consecutive packet counters to the reports, starting at
+    /// `counter` which is left at the value of the next report.
+    pub fn stamp(&mut self, counter: &mut u8) {
+        for report in self.reports.iter_mut().flatten() {
+            *report.packet_counter() = *counter;
+            *counter = counter.wrapping_add(1) & 0xf;
+        }
+    }
+
+    /// Bytes needed by [`write_into`](#method.write_into).
+    pub fn byte_size(&self, transport: TransportKind) -> usize {
+        self.iter()
+            .map(|r| transport.padded_len(r.as_bytes().len()))
+            .sum()
+    }
+
+    /// Serialize the reports one after the other into `buf`, padded for
+    /// `transport`.
+    ///
+    /// Returns the length of each report in `buf`, in order.
+    pub fn write_into(
+        &mut self,
+        buf: &mut [u8],
+        transport: TransportKind,
+    ) -> Result<&[usize], BufferTooSmallError> {
+        let needed = self.byte_size(transport);
+        if buf.len() < needed {
+            return Err(BufferTooSmallError {
+                needed,
+                got: buf.len(),
+            });
+        }
+        let mut offset = 0;
+        let mut count = 0;
+        for report in self.reports.iter().flatten() {
+            let len = report.write_into(&mut buf[offset..], transport)?;
+            self.lengths[count] = len;
+            offset += len;
+            count += 1;
+        }
+        Ok(&self.lengths[..count])
+    }
+}
+
+impl<const N: usize> Default for OutputBatch<N> {
+    fn default() -> Self {
+        OutputBatch::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn output_batch() {
+    use crate::output::RumbleData;
+
+    let mut batch = OutputBatch::<2>::new();
+    assert!(batch.is_empty());
+    batch.push(OutputReport::keepalive(0)).unwrap();
+    batch
+        .push(OutputReport::set_rumble(RumbleData::default()))
+        .unwrap();
+    assert!(batch.push(OutputReport::keepalive(0)).is_err());
+    assert!(batch.is_full());
+
+    let mut counter = 0xff;
+    batch.stamp(&mut counter);
+    assert_eq!(counter, 1);
+    let mut counter = 15;
+    batch.stamp(&mut counter);
+    assert_eq!(counter, 1);
+
+    let mut buf = [0xff; 128];
+    assert!(batch
+        .write_into(&mut buf[..100], TransportKind::Usb)
+        .is_err());
+    let lengths = batch.write_into(&mut buf, TransportKind::Usb).unwrap();
+    assert_eq!(lengths, [64, 64]);
+    assert_eq!(buf[1], 15);
+    assert_eq!(buf[65], 0);
+    assert_eq!(buf[63], 0);
+
+    let lengths = batch
+        .write_into(&mut buf, TransportKind::Bluetooth)
+        .unwrap()
+        .to_vec();
+    assert_eq!(buf[lengths[0]], batch.iter().nth(1).unwrap().as_bytes()[0]);
+    batch.clear();
+    assert_eq!(batch.len(), 0);
+}
@@ -0,0 +1,275 @@
+use crate::{
+    common::{Bool, InputReportId, RawId, SubcommandId},
+    imu::{self, IMUMode},
+    light::PlayerLights,
+    mcu::MCUMode,
+    output::{SubcommandRequest, SubcommandRequestEnum},
+    InputReport, OutputReport,
+};
+use std::{fmt, str::FromStr};
+
+/// Preset sequence of subcommands putting the controller in a usable state.
+///
+/// Every profile first identifies the controller with `RequestDeviceInfo`,
+/// then configures the reports.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum BringUpProfile {
+    /// Simple HID mode (`0x3F`), the buttons and sticks only.
+    Minimal,
+    /// Standard full mode (`0x30`) with the IMU and the vibration enabled.
+    Gaming,
+    /// Standard full mode with MCU data (`0x31`), with the MCU in standby and
+    /// ready to be configured for the IR camera or NFC.
+    Research,
+}
+
+/// One report of a bring-up sequence.
+#[derive(Copy, Clone, Debug)]
+pub struct BringUpStep {
+    pub report: OutputReport,
+    /// Subcommand whose acknowledgement must be received before the next
+    /// step.
+    pub ack: SubcommandId,
+}
+
+impl BringUpProfile {
+    pub const ALL: [BringUpProfile; 3] = [
+        BringUpProfile::Minimal,
+        BringUpProfile::Gaming,
+        BringUpProfile::Research,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BringUpProfile::Minimal => "minimal",
+            BringUpProfile::Gaming => "gaming",
+            BringUpProfile::Research => "research",
+        }
+    }
+
+    fn subcommands(self) -> Vec<SubcommandRequestEnum> {
+        use SubcommandRequestEnum::*;
+        let mut subcmds = vec![RequestDeviceInfo(())];
+        match self {
+            BringUpProfile::Minimal => {
+                subcmds.push(SetInputReportMode(InputReportId::Normal.into()));
+            }
+            BringUpProfile::Gaming => subcmds.extend_from_slice(&[
+                SetInputReportMode(InputReportId::StandardFull.into()),
+                SetIMUMode(IMUMode::GyroAccel.into()),
+                EnableVibration(Bool::True.into()),
+            ]),
+            BringUpProfile::Research => subcmds.extend_from_slice(&[
+                SetInputReportMode(InputReportId::StandardFullMCU.into()),
+                SetMCUState(MCUMode::Standby.into()),
+            ]),
+        }
+        subcmds
+    }
+
+    /// Reports to send in order, waiting for each acknowledgement.
+    pub fn steps(self) -> impl Iterator<Item = BringUpStep> {
+        self.steps_from(&ObservedState::new())
+    }
+
+    /// Same as [`steps`](#method.steps), without the steps setting a state
+    /// the controller is known to be in, eg. after a reconnection.
+    pub fn steps_from(self, observed: &ObservedState) -> impl Iterator<Item = BringUpStep> {
+        let observed = *observed;
+        self.subcommands()
+            .into_iter()
+            .filter(move |subcmd| !observed.is_redundant(subcmd))
+            .map(|subcmd| {
+                let request = SubcommandRequest::from(subcmd);
+                BringUpStep {
+                    ack: request.id().try_into().expect("known subcommand"),
+                    report: request.into(),
+                }
+            })
+    }
+}
+
+/// Get the player lights, not modeled by [`SubcommandId`], replying with
+/// the bits of [`PlayerLights`].
+const GET_PLAYER_LIGHTS: u8 = 0x31;
+
+/// Current configuration of the controller, inferred from its reports.
+///
+/// The input report mode can't be queried but is known once a report in a
+/// full mode is received. The other states are queried by
+/// [`ObservedState::queries`]:
+///
+/// ```ignore
+/// let mut observed = ObservedState::new();
+/// for query in ObservedState::queries().iter() {
+///     send(query)?;
+///     observed.observe(&recv_reply()?);
+/// }
+/// for step in BringUpProfile::Gaming.steps_from(&observed) {
+///     send(&step.report)?;
+///     wait_ack(step.ack)?;
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ObservedState {
+    pub report_mode: Option<InputReportId>,
+    pub imu_mode: Option<IMUMode>,
+    pub mcu_mode: Option<MCUMode>,
+    pub player_lights: Option<PlayerLights>,
+}
+
+impl ObservedState {
+    pub fn new() -> ObservedState {
+        ObservedState::default()
+    }
+
+    /// Reports querying the IMU configuration and the player lights, whose
+    /// replies are given to [`observe`](#method.observe).
+    pub fn queries() -> [OutputReport; 2] {
+        let imu: SubcommandRequest = imu::RegistersRead {
+            address: imu::REG_CTRL1_XL,
+            count: 2,
+        }
+        .into();
+        [
+            imu.into(),
+            SubcommandRequest::build(GET_PLAYER_LIGHTS, &[]).into(),
+        ]
+    }
+
+    /// Update the state from a received report.
+    pub fn observe(&mut self, report: &InputReport) {
+        match report.id().try_into() {
+            Some(mode @ InputReportId::Normal)
+            | Some(mode @ InputReportId::StandardFull)
+            | Some(mode @ InputReportId::StandardFullMCU) => self.report_mode = Some(mode),
+            _ => {}
+        }
+        if let Some(status) = report.mcu_report().and_then(|r| r.state_report()) {
+            self.mcu_mode = status.state.try_into();
+        }
+        let reply = match report.subcmd_reply() {
+            Some(reply) if reply.ack().is_ok() => reply,
+            _ => return,
+        };
+        if let Some(registers) = reply.imu_registers() {
+            if registers.address == imu::REG_CTRL1_XL {
+                // The output data rate is in the high nibble, 0 when powered down.
+                self.imu_mode = match registers.values() {
+                    [0x00..=0x0f, 0x00..=0x0f, ..] => Some(IMUMode::Disabled),
+                    [0x10..=0xff, 0x10..=0xff, ..] => Some(IMUMode::GyroAccel),
+                    _ => None,
+                };
+            }
+        } else if reply.id() == RawId::new(GET_PLAYER_LIGHTS) {
+            self.player_lights = report
+                .raw_reply()
+                .and_then(|data| data.first())
+                .map(|&bits| PlayerLights::from_bits(bits));
+        }
+    }
+
+    /// Whether sending `subcmd` wouldn't change anything.
+    pub fn is_redundant(&self, subcmd: &SubcommandRequestEnum) -> bool {
+        match *subcmd {
+            SubcommandRequestEnum::SetInputReportMode(mode) => {
+                matches!(self.report_mode, Some(m) if mode == m)
+            }
+            SubcommandRequestEnum::SetIMUMode(mode) => {
+                matches!(self.imu_mode, Some(m) if mode == m)
+            }
+            SubcommandRequestEnum::SetMCUState(mode) => {
+                matches!(self.mcu_mode, Some(m) if mode == m)
+            }
+            SubcommandRequestEnum::SetPlayerLights(lights) => self.player_lights == Some(lights),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for BringUpProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UnknownProfileError;
+
+impl fmt::Display for UnknownProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("unknown bring-up profile")
+    }
+}
+
+impl std::error::Error for UnknownProfileError {}
+
+impl FromStr for BringUpProfile {
+    type Err = UnknownProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BringUpProfile::ALL
+            .iter()
+            .cloned()
+            .find(|profile| profile.name().eq_ignore_ascii_case(s))
+            .ok_or(UnknownProfileError)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn bring_up_profiles() {
+    let acks: Vec<_> = "Gaming"
+        .parse::<BringUpProfile>()
+        .unwrap()
+        .steps()
+        .map(|step| step.ack)
+        .collect();
+    assert_eq!(
+        acks,
+        [
+            SubcommandId::RequestDeviceInfo,
+            SubcommandId::SetInputReportMode,
+            SubcommandId::SetIMUMode,
+            SubcommandId::EnableVibration
+        ]
+    );
+    let last = BringUpProfile::Minimal.steps().last().unwrap();
+    assert_eq!(last.report.as_bytes()[10..12], [0x03, 0x3f]);
+    assert!("turbo".parse::<BringUpProfile>().is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn skip_redundant_steps() {
+    use crate::input::{InputReportEnum, SubcommandReply, SubcommandReplyEnum};
+
+    let mut observed = ObservedState::new();
+    let mut report = InputReport::new();
+    report.as_bytes_mut()[0] = 0x30;
+    observed.observe(&report);
+    assert_eq!(observed.report_mode, Some(InputReportId::StandardFull));
+
+    let registers = imu::RegistersReadResult::new(imu::REG_CTRL1_XL, &[0x7c, 0x7c]);
+    let mut reply: SubcommandReply = SubcommandReplyEnum::ReadIMURegisters(registers).into();
+    *reply.ack_mut() = crate::input::Ack::ok(0);
+    let report = InputReportEnum::StandardAndSubcmd((
+        crate::common::Wire::from_bytes(&[0; 12]).unwrap(),
+        reply,
+    ))
+    .into();
+    observed.observe(&report);
+    assert_eq!(observed.imu_mode, Some(IMUMode::GyroAccel));
+
+    let acks: Vec<_> = BringUpProfile::Gaming
+        .steps_from(&observed)
+        .map(|step| step.ack)
+        .collect();
+    assert_eq!(
+        acks,
+        [
+            SubcommandId::RequestDeviceInfo,
+            SubcommandId::EnableVibration
+        ]
+    );
+}
@@ -0,0 +1,186 @@
+use crate::{clock::Timestamp, timing};
+use std::time::Duration;
+
+pub use crate::timing::{KEEPALIVE_INTERVAL, REPORT_INTERVAL, SUBCOMMAND_SPACING};
+
+/// Kind of output report chosen by the [`RateGovernor`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReportKind {
+    /// A subcommand, which also carries the latest rumble data.
+    Subcommand,
+    /// A rumble-only report.
+    Rumble,
+    /// A [`OutputReport::keepalive`](struct.OutputReport.html#method.keepalive).
+    Keepalive,
+}
+
+/// What the caller has ready to send.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Pending {
+    pub subcommand: bool,
+    pub rumble: bool,
+}
+
+/// Decision of the [`RateGovernor`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Decision {
+    /// Send a report of this kind now.
+    Send(ReportKind),
+    /// Nothing can be sent before this delay.
+    Wait(Duration),
+}
+
+/// Chooses which output report to send next, and when.
+///
+/// Subcommands have priority over rumble updates, and a keepalive is sent
+/// when nothing else was sent for [`KEEPALIVE_INTERVAL`]. The delays are
+/// those of a [`timing::Schedule`], the official one by default.
+///
+/// ```ignore
+/// match governor.next(pending, clock.now()) {
+///     Decision::Send(kind) => {
+///         send(build(kind))?;
+///         governor.sent(kind, clock.now());
+///     }
+///     Decision::Wait(delay) => sleep(delay),
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RateGovernor {
+    last_report: Option<Timestamp>,
+    last_subcommand: Option<Timestamp>,
+    schedule: timing::Schedule,
+}
+
+impl RateGovernor {
+    pub fn new() -> RateGovernor {
+        RateGovernor::default()
+    }
+
+    pub fn with_schedule(mut self, schedule: timing::Schedule) -> RateGovernor {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn schedule(&self) -> timing::Schedule {
+        self.schedule
+    }
+
+    pub fn next(&self, pending: Pending, now: Timestamp) -> Decision {
+        let since = |last: Option<Timestamp>| last.map(|last| now.saturating_duration_since(last));
+        let report_wait = match since(self.last_report) {
+            Some(elapsed) => self.schedule.report_interval.saturating_sub(elapsed),
+            None => Duration::from_secs(0),
+        };
+        let subcommand_wait = match since(self.last_subcommand) {
+            Some(elapsed) => self.schedule.subcommand_spacing.saturating_sub(elapsed),
+            None => Duration::from_secs(0),
+        };
+        let keepalive_wait = match since(self.last_report) {
+            Some(elapsed) => self.schedule.keepalive_interval.saturating_sub(elapsed),
+            None => Duration::from_secs(0),
+        };
+
+        let mut wait = keepalive_wait.max(report_wait);
+        if pending.subcommand {
+            let subcommand_wait = subcommand_wait.max(report_wait);
+            if subcommand_wait == Duration::from_secs(0) {
+                return Decision::Send(ReportKind::Subcommand);
+            }
+            wait = wait.min(subcommand_wait);
+        }
+        if pending.rumble {
+            if report_wait == Duration::from_secs(0) {
+                return Decision::Send(ReportKind::Rumble);
+            }
+            wait = wait.min(report_wait);
+        }
+        if wait == Duration::from_secs(0) {
+            Decision::Send(ReportKind::Keepalive)
+        } else {
+            Decision::Wait(wait)
+        }
+    }
+
+    /// Record that a report was sent at `now`.
+    pub fn sent(&mut self, kind: ReportKind, now: Timestamp) {
+        self.last_report = Some(now);
+        if kind == ReportKind::Subcommand {
+            self.last_subcommand = Some(now);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn governor_schedule() {
+    let ms = Duration::from_millis;
+    let start = Timestamp::from_micros(0);
+    let both = Pending {
+        subcommand: true,
+        rumble: true,
+    };
+    let rumble = Pending {
+        subcommand: false,
+        rumble: true,
+    };
+    let mut governor = RateGovernor::new();
+    assert_eq!(
+        governor.next(both, start),
+        Decision::Send(ReportKind::Subcommand)
+    );
+    governor.sent(ReportKind::Subcommand, start);
+    assert_eq!(governor.next(both, start + ms(5)), Decision::Wait(ms(10)));
+    // The next subcommand waits but the rumble goes through.
+    assert_eq!(
+        governor.next(both, start + ms(15)),
+        Decision::Send(ReportKind::Rumble)
+    );
+    governor.sent(ReportKind::Rumble, start + ms(15));
+    assert_eq!(
+        governor.next(both, start + ms(30)),
+        Decision::Send(ReportKind::Rumble)
+    );
+    assert_eq!(
+        governor.next(both, start + ms(50)),
+        Decision::Send(ReportKind::Subcommand)
+    );
+    assert_eq!(
+        governor.next(Pending::default(), start + ms(15)),
+        Decision::Wait(ms(500))
+    );
+    assert_eq!(
+        governor.next(Pending::default(), start + ms(515)),
+        Decision::Send(ReportKind::Keepalive)
+    );
+    assert_eq!(
+        governor.next(rumble, start + ms(30)),
+        Decision::Send(ReportKind::Rumble)
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn governor_custom_schedule() {
+    // Slower cadence, eg. for a congested Bluetooth link.
+    let schedule = timing::Schedule {
+        report_interval: Duration::from_millis(30),
+        ..timing::Schedule::OFFICIAL
+    };
+    let mut governor = RateGovernor::new().with_schedule(schedule);
+    assert_eq!(governor.schedule(), schedule);
+    let start = Timestamp::from_micros(0);
+    let rumble = Pending {
+        subcommand: false,
+        rumble: true,
+    };
+    governor.sent(ReportKind::Rumble, start);
+    assert_eq!(
+        governor.next(rumble, start + REPORT_INTERVAL),
+        Decision::Wait(Duration::from_millis(15))
+    );
+    assert_eq!(
+        governor.next(rumble, start + Duration::from_millis(30)),
+        Decision::Send(ReportKind::Rumble)
+    );
+}
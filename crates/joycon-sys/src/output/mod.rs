@@ -1,5 +1,17 @@
+mod args;
+mod batch;
+mod bringup;
+mod governor;
+mod preset;
+mod queue;
 mod report;
 mod rumble;
 
+pub use args::*;
+pub use batch::*;
+pub use bringup::*;
+pub use governor::*;
+pub use preset::*;
+pub use queue::*;
 pub use report::*;
 pub use rumble::*;
@@ -0,0 +1,145 @@
+use crate::{
+    input::WhichController,
+    output::{RumbleData, RumbleSide},
+};
+use std::{fmt, str::FromStr, time::Duration};
+
+/// Haptic effect with a consistent feel across controllers.
+///
+/// The actuators of the JoyCon and of the Pro Controller resonate at
+/// different frequencies, so each preset picks the frequencies matching the
+/// controller.
+///
+/// ```ignore
+/// let preset: RumblePreset = "click".parse()?;
+/// joycon.send(OutputReport::set_rumble(preset.rumble(info.which_controller)))?;
+/// sleep(preset.duration());
+/// joycon.send(OutputReport::set_rumble(RumbleData::default()))?;
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum RumblePreset {
+    /// Short and sharp, eg. for a menu selection.
+    Click,
+    /// Short and heavy, eg. for a collision.
+    Thud,
+    /// Sustained vibration, eg. for a notification.
+    Buzz,
+}
+
+/// Frequencies and amplitudes of a preset, see [`RumbleSide::from_freq`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Tone {
+    hi_freq: f32,
+    hi_amp: f32,
+    low_freq: f32,
+    low_amp: f32,
+}
+
+const fn tone(hi_freq: f32, hi_amp: f32, low_freq: f32, low_amp: f32) -> Tone {
+    Tone {
+        hi_freq,
+        hi_amp,
+        low_freq,
+        low_amp,
+    }
+}
+
+// The JoyCon actuators resonate around 160 Hz and 320 Hz.
+const JOYCON_TONES: [Tone; 3] = [
+    tone(320., 0.9, 160., 0.),
+    tone(320., 0., 110., 1.),
+    tone(640., 0.5, 160., 0.5),
+];
+
+// The bigger Pro Controller actuators resonate lower, around 140 Hz and
+// 280 Hz, and need less amplitude.
+const PRO_CONTROLLER_TONES: [Tone; 3] = [
+    tone(280., 0.7, 140., 0.),
+    tone(280., 0., 90., 0.9),
+    tone(560., 0.4, 140., 0.4),
+];
+
+impl RumblePreset {
+    pub const ALL: [RumblePreset; 3] =
+        [RumblePreset::Click, RumblePreset::Thud, RumblePreset::Buzz];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RumblePreset::Click => "click",
+            RumblePreset::Thud => "thud",
+            RumblePreset::Buzz => "buzz",
+        }
+    }
+
+    /// Recommended time to play the preset before stopping the rumble.
+    pub fn duration(self) -> Duration {
+        match self {
+            RumblePreset::Click => Duration::from_millis(15),
+            RumblePreset::Thud => Duration::from_millis(45),
+            RumblePreset::Buzz => Duration::from_millis(200),
+        }
+    }
+
+    pub fn side(self, controller: WhichController) -> RumbleSide {
+        let tones = match controller {
+            WhichController::ProController => &PRO_CONTROLLER_TONES,
+            WhichController::LeftJoyCon | WhichController::RightJoyCon => &JOYCON_TONES,
+        };
+        let tone = tones[self as usize];
+        RumbleSide::from_freq(tone.hi_freq, tone.hi_amp, tone.low_freq, tone.low_amp)
+    }
+
+    /// Same vibration on both sides.
+    pub fn rumble(self, controller: WhichController) -> RumbleData {
+        let side = self.side(controller);
+        RumbleData {
+            left: side,
+            right: side,
+        }
+    }
+}
+
+impl fmt::Display for RumblePreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UnknownPresetError;
+
+impl fmt::Display for UnknownPresetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("unknown rumble preset")
+    }
+}
+
+impl std::error::Error for UnknownPresetError {}
+
+impl FromStr for RumblePreset {
+    type Err = UnknownPresetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RumblePreset::ALL
+            .iter()
+            .cloned()
+            .find(|preset| preset.name().eq_ignore_ascii_case(s))
+            .ok_or(UnknownPresetError)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn rumble_presets() {
+    let click: RumblePreset = "Click".parse().unwrap();
+    assert_eq!(click, RumblePreset::Click);
+    assert_eq!(
+        click.side(WhichController::LeftJoyCon),
+        RumbleSide::from_freq(320., 0.9, 160., 0.)
+    );
+    assert_ne!(
+        click.side(WhichController::LeftJoyCon),
+        click.side(WhichController::ProController)
+    );
+    assert!("rattle".parse::<RumblePreset>().is_err());
+}
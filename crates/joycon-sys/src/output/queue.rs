@@ -0,0 +1,229 @@
+use crate::{common::SubcommandId, output::SubcommandRequest};
+
+/// Number of times a subcommand is sent before giving up.
+pub const MAX_SUBCOMMAND_ATTEMPTS: u8 = 3;
+
+/// Order in which queued subcommands are sent, the first variant first.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum SubcommandPriority {
+    /// Configuration the other subcommands and the reports depend on.
+    BringUp,
+    Normal,
+    /// Lights, only visible to the user.
+    Cosmetic,
+}
+
+impl SubcommandPriority {
+    pub fn of(id: SubcommandId) -> SubcommandPriority {
+        match id {
+            SubcommandId::RequestDeviceInfo
+            | SubcommandId::SetInputReportMode
+            | SubcommandId::SetIMUMode
+            | SubcommandId::SetIMUSens
+            | SubcommandId::EnableVibration
+            | SubcommandId::SetMCUState
+            | SubcommandId::SetMCUConf => SubcommandPriority::BringUp,
+            SubcommandId::SetPlayerLights | SubcommandId::SetHomeLight => {
+                SubcommandPriority::Cosmetic
+            }
+            _ => SubcommandPriority::Normal,
+        }
+    }
+}
+
+/// Subcommands setting a state, where only the last value matters.
+fn is_coalesced(id: SubcommandId) -> bool {
+    matches!(
+        id,
+        SubcommandId::SetInputReportMode
+            | SubcommandId::SetIMUMode
+            | SubcommandId::SetIMUSens
+            | SubcommandId::EnableVibration
+            | SubcommandId::SetPlayerLights
+            | SubcommandId::SetHomeLight
+    )
+}
+
+/// Subcommand waiting in a [`SubcommandQueue`].
+#[derive(Copy, Clone, Debug)]
+pub struct QueuedSubcommand {
+    pub request: SubcommandRequest,
+    /// Number of times it was already sent.
+    pub attempts: u8,
+    id: Option<SubcommandId>,
+    order: u64,
+}
+
+/// Fixed-capacity queue of subcommands waiting to be sent.
+///
+/// Subcommands are sent by [`SubcommandPriority`], then in the order they
+/// were pushed. Pushing a subcommand setting a state, eg. `SetPlayerLights`,
+/// replaces the one already queued.
+///
+/// ```ignore
+/// queue.push(SubcommandRequest::from(lights))?;
+/// if let Some(queued) = queue.pop() {
+///     if send_and_wait_ack(queued.request).is_err() {
+///         let _ = queue.retry(queued);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SubcommandQueue<const N: usize> {
+    entries: [Option<QueuedSubcommand>; N],
+    next_order: u64,
+}
+
+impl<const N: usize> SubcommandQueue<N> {
+    pub fn new() -> SubcommandQueue<N> {
+        SubcommandQueue {
+            entries: [None; N],
+            next_order: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|e| e.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(Option::is_none)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = [None; N];
+    }
+
+    /// Queue a subcommand, giving it back if the queue is full.
+    pub fn push(&mut self, request: SubcommandRequest) -> Result<(), SubcommandRequest> {
+        let order = self.next_order;
+        self.next_order += 1;
+        self.insert(QueuedSubcommand {
+            request,
+            attempts: 0,
+            id: request.id().try_into(),
+            order,
+        })
+        .map_err(|queued| queued.request)
+    }
+
+    /// Queue again a subcommand that wasn't acknowledged, ahead of the other
+    /// subcommands of the same priority.
+    ///
+    /// Gives it back after [`MAX_SUBCOMMAND_ATTEMPTS`] attempts, or if the
+    /// queue is full.
+    pub fn retry(&mut self, mut queued: QueuedSubcommand) -> Result<(), QueuedSubcommand> {
+        queued.attempts += 1;
+        if queued.attempts >= MAX_SUBCOMMAND_ATTEMPTS {
+            trace!(
+                "subcommand {:?} dropped after {:?} attempts",
+                queued.request.id(),
+                queued.attempts
+            );
+            return Err(queued);
+        }
+        trace!(
+            "subcommand {:?} retried, attempt {:?}",
+            queued.request.id(),
+            queued.attempts
+        );
+        self.insert(queued)
+    }
+
+    fn insert(&mut self, queued: QueuedSubcommand) -> Result<(), QueuedSubcommand> {
+        if let Some(id) = queued.id.filter(|&id| is_coalesced(id)) {
+            let existing = self.entries.iter_mut().flatten().find(|e| e.id == Some(id));
+            if let Some(existing) = existing {
+                // Keep the newest value.
+                if queued.order >= existing.order {
+                    trace!("subcommand {:?} coalesced", queued.request.id());
+                    existing.request = queued.request;
+                    existing.attempts = queued.attempts;
+                }
+                return Ok(());
+            }
+        }
+        match self.entries.iter_mut().find(|e| e.is_none()) {
+            Some(slot) => {
+                *slot = Some(queued);
+                Ok(())
+            }
+            None => {
+                trace!("subcommand {:?} rejected, queue full", queued.request.id());
+                Err(queued)
+            }
+        }
+    }
+
+    /// Next subcommand to send.
+    pub fn pop(&mut self) -> Option<QueuedSubcommand> {
+        let slot = self
+            .entries
+            .iter_mut()
+            .filter(|e| e.is_some())
+            .min_by_key(|e| {
+                let e = e.as_ref().unwrap();
+                (
+                    e.id.map_or(SubcommandPriority::Normal, SubcommandPriority::of),
+                    e.order,
+                )
+            })?;
+        let queued = slot.take()?;
+        trace!(
+            "subcommand {:?} sent, attempt {:?}",
+            queued.request.id(),
+            queued.attempts
+        );
+        Some(queued)
+    }
+}
+
+impl<const N: usize> Default for SubcommandQueue<N> {
+    fn default() -> Self {
+        SubcommandQueue::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn subcommand_queue() {
+    use crate::{
+        imu::IMUMode,
+        light::{PlayerLight, PlayerLights},
+        output::SubcommandRequestEnum,
+        OutputReport,
+    };
+
+    let lights = |on| {
+        SubcommandRequest::from(PlayerLights::new(
+            on,
+            PlayerLight::Off,
+            PlayerLight::Off,
+            PlayerLight::Off,
+        ))
+    };
+    let mut queue = SubcommandQueue::<3>::new();
+    queue.push(lights(PlayerLight::On)).unwrap();
+    queue
+        .push(SubcommandRequest::disable_shipment_mode())
+        .unwrap();
+    queue.push(lights(PlayerLight::Blinking)).unwrap();
+    queue
+        .push(SubcommandRequestEnum::SetIMUMode(IMUMode::GyroAccel.into()).into())
+        .unwrap();
+    assert_eq!(queue.len(), 3);
+    assert!(queue
+        .push(SubcommandRequest::erase_pairing_info(
+            crate::output::ConfirmErase::i_want_to_erase_the_pairing_info()
+        ))
+        .is_err());
+
+    let first = queue.pop().unwrap();
+    assert!(first.request.id() == SubcommandId::SetIMUMode);
+    queue.retry(first).unwrap();
+    assert_eq!(queue.pop().unwrap().attempts, 1);
+    assert!(queue.pop().unwrap().request.id() == SubcommandId::SetShipmentMode);
+    let last = queue.pop().unwrap();
+    assert_eq!(OutputReport::from(last.request).as_bytes()[11], 0b0001_0000);
+    assert!(queue.pop().is_none());
+}
@@ -9,10 +9,13 @@ use crate::{
     light,
     mcu::{ir::*, *},
     output::RumbleData,
-    raw_enum,
+    pairing, raw_enum,
     spi::*,
 };
-use std::mem::size_of_val;
+use std::{
+    fmt,
+    mem::{size_of, size_of_val},
+};
 
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
@@ -34,6 +37,7 @@ raw_enum! {
     #[post_id rumble rumble_mut: Rumble]
     #[union: OutputReportUnion]
     #[struct: OutputReport]
+    #[layout offset = 10, size = 49]
     pub enum OutputReportEnum {
         rumble_subcmd rumble_subcmd_mut: RumbleAndSubcmd = SubcommandRequest,
         mcu_fw_update mcu_fw_update_mut: MCUFwUpdate = (),
@@ -43,17 +47,37 @@ raw_enum! {
 }
 
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Rumble {
     pub packet_counter: u8,
     pub rumble_data: RumbleData,
 }
 
+unsafe impl Wire for Rumble {}
+
+/// Bluetooth HIDP header of the output reports, `DATA | Output`.
+pub(crate) const HIDP_OUTPUT_HEADER: u8 = 0xA2;
+
 impl OutputReport {
     pub fn packet_counter(&mut self) -> &mut u8 {
         &mut self.rumble.packet_counter
     }
 
+    /// Zero the serial number and pairing information of a SPI write, and
+    /// the host address of a manual pairing request, before logging or
+    /// sharing the report.
+    pub fn redact(&mut self) {
+        if let Some(subcmd) = self.rumble_subcmd_mut() {
+            if let Some(write) = subcmd.spi_write_mut() {
+                write.redact();
+            }
+            if let Some(request) = subcmd.bluetooth_manual_pairing_mut() {
+                request.redact();
+            }
+        }
+    }
+
     pub fn is_special(&self) -> bool {
         self.id != OutputReportId::RumbleOnly
     }
@@ -90,12 +114,28 @@ impl OutputReport {
         })
     }
 
+    /// Subcommand not modeled by [`SubcommandRequestEnum`], for research.
+    ///
+    /// `args` is padded with zeros, see [`SubcommandRequest::raw`].
+    pub fn raw_subcommand(id: u8, args: &[u8]) -> Result<OutputReport, SubcommandArgsTooLong> {
+        SubcommandRequest::raw(id, args).map(OutputReport::from)
+    }
+
     pub fn set_rumble(rumble_data: RumbleData) -> OutputReport {
         let mut report: OutputReport = OutputReportEnum::RumbleOnly(()).into();
         report.rumble.rumble_data = rumble_data;
         report
     }
 
+    /// Rumble-only report with neutral rumble data, to be sent every
+    /// [`KEEPALIVE_INTERVAL`](super::KEEPALIVE_INTERVAL) when nothing else
+    /// is sent.
+    pub fn keepalive(packet_counter: u8) -> OutputReport {
+        let mut report = OutputReport::set_rumble(RumbleData::default());
+        report.rumble.packet_counter = packet_counter;
+        report
+    }
+
     pub fn byte_size(&self) -> usize {
         match self.id.try_into() {
             Some(OutputReportId::RumbleAndSubcmd) => 49,
@@ -106,6 +146,32 @@ impl OutputReport {
         }
     }
 
+    /// Parse a report from its hexadecimal bytes, see
+    /// [`InputReport::from_hex_str`](../input/struct.InputReport.html#method.from_hex_str).
+    pub fn from_hex_str(s: &str) -> Result<OutputReport, HexError> {
+        let mut buf = [0; size_of::<OutputReport>()];
+        let bytes = parse_hex_report(s, HIDP_OUTPUT_HEADER, &mut buf)?;
+        OutputReport::from_slice(bytes).ok_or(HexError::InvalidReport)
+    }
+
+    /// Copy a report sent as a byte slice, checking its id and length.
+    pub(crate) fn from_slice(data: &[u8]) -> Option<OutputReport> {
+        match RawId::<OutputReportId>::new(*data.first()?).try_into() {
+            Some(OutputReportId::MCUFwUpdate) | None => return None,
+            Some(_) => {}
+        }
+        let mut report = OutputReport::new();
+        let bytes = report.as_bytes_mut();
+        if data.len() > bytes.len() {
+            return None;
+        }
+        bytes[..data.len()].copy_from_slice(data);
+        if data.len() < report.byte_size() {
+            return None;
+        }
+        Some(report)
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         unsafe { std::slice::from_raw_parts(self as *const _ as *const u8, self.byte_size()) }
     }
@@ -114,6 +180,29 @@ impl OutputReport {
         unsafe { std::slice::from_raw_parts_mut(self as *mut _ as *mut u8, size_of_val(self)) }
     }
 
+    /// Serialize the report directly into a HID write buffer.
+    ///
+    /// Returns the number of bytes to send, padding included.
+    pub fn write_into(
+        &self,
+        buf: &mut [u8],
+        transport: TransportKind,
+    ) -> Result<usize, BufferTooSmallError> {
+        let bytes = self.as_bytes();
+        let len = transport.padded_len(bytes.len());
+        if buf.len() < len {
+            return Err(BufferTooSmallError {
+                needed: len,
+                got: buf.len(),
+            });
+        }
+        buf[..bytes.len()].copy_from_slice(bytes);
+        for b in &mut buf[bytes.len()..len] {
+            *b = 0;
+        }
+        Ok(len)
+    }
+
     #[cfg(test)]
     pub(crate) unsafe fn as_mcu_request(&self) -> &MCURequest {
         &self.u.request_mcu_data
@@ -125,6 +214,44 @@ impl OutputReport {
     }
 }
 
+/// Transport used to send the HID reports, which determines their length.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TransportKind {
+    /// Reports are sent with their exact length.
+    Bluetooth,
+    /// Reports are padded with zeroes to the USB interrupt packet size.
+    Usb,
+}
+
+impl TransportKind {
+    pub const USB_REPORT_SIZE: usize = 64;
+
+    pub fn padded_len(self, len: usize) -> usize {
+        match self {
+            TransportKind::Bluetooth => len,
+            TransportKind::Usb => len.max(Self::USB_REPORT_SIZE),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BufferTooSmallError {
+    pub needed: usize,
+    pub got: usize,
+}
+
+impl fmt::Display for BufferTooSmallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "buffer too small: {} bytes needed, got {}",
+            self.needed, self.got
+        )
+    }
+}
+
+impl std::error::Error for BufferTooSmallError {}
+
 impl From<SubcommandRequest> for OutputReport {
     fn from(subcmd: SubcommandRequest) -> Self {
         OutputReportEnum::RumbleAndSubcmd(subcmd).into()
@@ -149,22 +276,30 @@ raw_enum! {
     #[union: SubcommandRequestUnion]
     #[struct: SubcommandRequest]
     #[raw [u8; 38]]
+    #[layout offset = 1, size = 39]
     pub enum SubcommandRequestEnum {
         get_only_controller_state get_only_controller_state_mut: GetOnlyControllerState = (),
-        bluetooth_manual_pairing bluetooth_manual_pairing_mut: BluetoothManualPairing = (),
+        bluetooth_manual_pairing bluetooth_manual_pairing_mut: BluetoothManualPairing = pairing::PairingRequest,
         request_device_info request_device_info_mut: RequestDeviceInfo = (),
         set_input_report_mode set_input_report_mode_mut: SetInputReportMode = RawId<InputReportId>,
         get_trigger_buttons_elapsed_time get_trigger_buttons_elapsed_time_mut: GetTriggerButtonsElapsedTime = (),
+        set_hci_state set_hci_state_mut: SetHCIState = RawId<HCIState>,
+        reset_pairing_info reset_pairing_info_mut: ResetPairingInfo = (),
         set_shipment_mode set_shipment_mode_mut: SetShipmentMode = RawId<Bool>,
         spi_read spi_read_mut: SPIRead = SPIReadRequest,
         spi_write spi_write_mut: SPIWrite = SPIWriteRequest,
         set_mcu_conf set_mcu_conf_mut: SetMCUConf = MCUCommand,
         set_mcu_state set_mcu_state_mut: SetMCUState = RawId<MCUMode>,
         set_unknown_data set_unknown_data_mut: SetUnknownData = [u8; 38],
+        set_unknown_mcu_data set_unknown_mcu_data_mut: SetUnknownMCUData = [u8; 38],
+        get_unknown_mcu_data get_unknown_mcu_data_mut: GetUnknownMCUData = (),
+        set_gpio_pin_output set_gpio_pin_output_mut: SetGPIOPinOutput = [u8; 38],
         set_player_lights set_player_lights_mut: SetPlayerLights = light::PlayerLights,
         set_home_light set_home_light_mut: SetHomeLight = light::HomeLight,
         set_imu_mode set_imu_mode_mut: SetIMUMode = RawId<IMUMode>,
         set_imu_sens set_imu_sens_mut: SetIMUSens = imu::Sensitivity,
+        write_imu_register write_imu_register_mut: WriteIMURegister = imu::RegisterWrite,
+        read_imu_registers read_imu_registers_mut: ReadIMURegisters = imu::RegistersRead,
         enable_vibration enable_vibration_mut: EnableVibration = RawId<Bool>,
         maybe_accessory maybe_accessory_mut: MaybeAccessory = AccessoryCommand,
         unknown0x59 unknown0x59_mut: Unknown0x59 = (),
@@ -174,11 +309,84 @@ raw_enum! {
     }
 }
 
+/// Confirmation required by [`SubcommandRequest::erase_pairing_info`].
+///
+/// Erasing the pairing information can't be undone: the controller will have
+/// to be paired again with every host, including the Switch.
+#[derive(Debug)]
+pub struct ConfirmErase {
+    _private: (),
+}
+
+impl ConfirmErase {
+    pub fn i_want_to_erase_the_pairing_info() -> ConfirmErase {
+        ConfirmErase { _private: () }
+    }
+}
+
+/// Subcommand `0x00`, answered with the current state of the controller
+/// without changing the input report mode.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ControllerStateRequest;
+
+impl From<ControllerStateRequest> for SubcommandRequest {
+    fn from(_: ControllerStateRequest) -> Self {
+        SubcommandRequestEnum::GetOnlyControllerState(()).into()
+    }
+}
+
+/// Size of the subcommand arguments, unused bytes being zero.
+pub const SUBCOMMAND_ARGS_LEN: usize = 38;
+
+/// Arguments larger than [`SUBCOMMAND_ARGS_LEN`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SubcommandArgsTooLong {
+    pub len: usize,
+}
+
+impl fmt::Display for SubcommandArgsTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "subcommand arguments of {} bytes, at most {} fit in a request",
+            self.len, SUBCOMMAND_ARGS_LEN
+        )
+    }
+}
+
+impl std::error::Error for SubcommandArgsTooLong {}
+
 impl SubcommandRequest {
+    /// Subcommand with any id and arguments, padded with zeros.
+    pub fn raw(id: u8, args: &[u8]) -> Result<Self, SubcommandArgsTooLong> {
+        if args.len() > SUBCOMMAND_ARGS_LEN {
+            return Err(SubcommandArgsTooLong { len: args.len() });
+        }
+        Ok(SubcommandRequest::build(id, args))
+    }
+
+    /// Subcommand with arguments known to fit.
+    pub(super) fn build(id: u8, args: &[u8]) -> Self {
+        let mut subcmd = SubcommandRequest::new();
+        subcmd.id = RawId::new(id);
+        unsafe { subcmd.u.raw[..args.len()].copy_from_slice(args) };
+        subcmd
+    }
+
+    /// Arguments as sent, padding included.
+    pub fn raw_args(&self) -> &[u8; SUBCOMMAND_ARGS_LEN] {
+        unsafe { &self.u.raw }
+    }
+
     pub fn disable_shipment_mode() -> Self {
         SubcommandRequestEnum::SetShipmentMode(Bool::False.into()).into()
     }
 
+    /// Erase the pairing information stored in the controller flash.
+    pub fn erase_pairing_info(_confirm: ConfirmErase) -> Self {
+        SubcommandRequestEnum::ResetPairingInfo(()).into()
+    }
+
     pub fn subcmd_0x59() -> Self {
         SubcommandRequestEnum::Unknown0x59(()).into()
     }
@@ -212,6 +420,12 @@ impl SubcommandRequest {
     }
 }
 
+impl From<HCIState> for SubcommandRequest {
+    fn from(hci_state: HCIState) -> Self {
+        SubcommandRequestEnum::SetHCIState(hci_state.into()).into()
+    }
+}
+
 impl From<MCUCommand> for SubcommandRequest {
     fn from(mcu_cmd: MCUCommand) -> Self {
         SubcommandRequestEnum::SetMCUConf(mcu_cmd).into()
@@ -230,6 +444,18 @@ impl From<crate::imu::Sensitivity> for SubcommandRequest {
     }
 }
 
+impl From<crate::imu::RegisterWrite> for SubcommandRequest {
+    fn from(write: crate::imu::RegisterWrite) -> Self {
+        SubcommandRequestEnum::WriteIMURegister(write).into()
+    }
+}
+
+impl From<crate::imu::RegistersRead> for SubcommandRequest {
+    fn from(read: crate::imu::RegistersRead) -> Self {
+        SubcommandRequestEnum::ReadIMURegisters(read).into()
+    }
+}
+
 impl From<SPIReadRequest> for SubcommandRequest {
     fn from(spi_read: SPIReadRequest) -> Self {
         SubcommandRequestEnum::SPIRead(spi_read).into()
@@ -254,6 +480,25 @@ impl From<light::HomeLight> for SubcommandRequest {
     }
 }
 
+#[test]
+fn write_into_buffer() {
+    let report = OutputReport::set_rumble(RumbleData::default());
+    let mut buf = [0xff; 64];
+    assert_eq!(
+        report
+            .write_into(&mut buf, TransportKind::Bluetooth)
+            .unwrap(),
+        10
+    );
+    assert_eq!(&buf[..10], report.as_bytes());
+    assert_eq!(buf[10], 0xff);
+    assert_eq!(report.write_into(&mut buf, TransportKind::Usb).unwrap(), 64);
+    assert!(buf[10..].iter().all(|b| *b == 0));
+    assert!(report
+        .write_into(&mut buf[..9], TransportKind::Bluetooth)
+        .is_err());
+}
+
 #[test]
 pub fn check_layout() {
     unsafe {
@@ -264,3 +509,56 @@ pub fn check_layout() {
         assert_eq!(49, std::mem::size_of_val(&report));
     }
 }
+
+#[test]
+fn keepalive_report() {
+    let report = OutputReport::keepalive(5);
+    assert_eq!(
+        report.as_bytes(),
+        &[0x10, 5, 0x00, 0x01, 0x40, 0x40, 0x00, 0x01, 0x40, 0x40]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn raw_subcommand() {
+    let report = OutputReport::raw_subcommand(0x5d, &[1, 2, 3]).unwrap();
+    let bytes = report.as_bytes();
+    assert_eq!(bytes.len(), 49);
+    assert_eq!(bytes[10..14], [0x5d, 1, 2, 3]);
+    assert!(bytes[14..].iter().all(|&b| b == 0));
+    let subcmd = report.rumble_subcmd().unwrap();
+    assert!(subcmd.id().try_into().is_none());
+    assert_eq!(subcmd.raw_args()[..4], [1, 2, 3, 0]);
+    assert_eq!(
+        SubcommandRequest::raw(0x5d, &[0; 39]).unwrap_err(),
+        SubcommandArgsTooLong { len: 39 }
+    );
+
+    let mut reply = crate::InputReport::new();
+    let raw = reply.as_bytes_mut();
+    raw[..1].copy_from_slice(&[0x21]);
+    raw[13..18].copy_from_slice(&[0x80, 0x5d, 0xaa, 0xbb, 0xcc]);
+    let data = reply.raw_reply().unwrap();
+    assert_eq!(data.len(), 34);
+    assert_eq!(data[..3], [0xaa, 0xbb, 0xcc]);
+}
+
+#[cfg(test)]
+#[test]
+fn default_reports() {
+    // Neutral rumble sent by the Switch, on each side.
+    const NEUTRAL: [u8; 8] = [0x00, 0x01, 0x40, 0x40, 0x00, 0x01, 0x40, 0x40];
+
+    assert_eq!(RumbleData::default().to_bytes(), NEUTRAL);
+    assert_eq!(OutputReport::new().as_bytes()[2..10], NEUTRAL);
+    assert_eq!(
+        OutputReport::keepalive(0).as_bytes(),
+        [&[0x10, 0][..], &NEUTRAL].concat()
+    );
+    let report = OutputReport::from(SubcommandRequestEnum::RequestDeviceInfo(()));
+    assert_eq!(
+        report.as_bytes()[..12],
+        [&[0x01, 0][..], &NEUTRAL, &[0x02, 0]].concat()[..]
+    );
+}
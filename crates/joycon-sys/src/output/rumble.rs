@@ -1,11 +1,68 @@
+use crate::common::Wire;
+
+/// Frequency of the low band in [`RumbleSide::from_dual_motor`], close to
+/// the heavy motor of a standard gamepad.
+pub const DUAL_MOTOR_LOW_FREQ: f32 = 160.;
+/// Frequency of the high band in [`RumbleSide::from_dual_motor`], close to
+/// the light motor of a standard gamepad.
+pub const DUAL_MOTOR_HIGH_FREQ: f32 = 320.;
+
+/// Lowest frequency, in Hz, encoded as `0x41 + i`, ie.
+/// `ceil(10 * 2^((0x41 + i - 0.5) / 32))`.
+///
+/// Lets [`RumbleSide::from_freq_u16`] encode without floating point math.
+const FREQ_THRESHOLDS: [u16; 159] = [
+    41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 58, 59, 60, 62, 63, 64, 66, 67,
+    69, 70, 72, 73, 75, 76, 78, 80, 81, 83, 85, 87, 89, 91, 93, 95, 97, 99, 101, 103, 105, 108,
+    110, 112, 115, 117, 120, 123, 125, 128, 131, 134, 137, 139, 143, 146, 149, 152, 155, 159, 162,
+    166, 169, 173, 177, 181, 185, 189, 193, 197, 201, 206, 210, 215, 220, 224, 229, 234, 239, 245,
+    250, 255, 261, 267, 273, 278, 285, 291, 297, 304, 310, 317, 324, 331, 338, 346, 353, 361, 369,
+    377, 385, 394, 402, 411, 420, 429, 439, 448, 458, 468, 478, 489, 499, 510, 521, 533, 545, 556,
+    569, 581, 594, 607, 620, 634, 647, 662, 676, 691, 706, 721, 737, 753, 770, 787, 804, 822, 840,
+    858, 877, 896, 915, 935, 956, 977, 998, 1020, 1042, 1065, 1089, 1112, 1137, 1162, 1187, 1213,
+    1240,
+];
+
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug, Default)]
 pub struct RumbleData {
     pub left: RumbleSide,
     pub right: RumbleSide,
 }
 
+unsafe impl Wire for RumbleData {}
+
+impl RumbleData {
+    /// Same vibration on both sides, see [`RumbleSide::from_dual_motor`].
+    pub fn from_dual_motor(low: f32, high: f32) -> RumbleData {
+        let side = RumbleSide::from_dual_motor(low, high);
+        RumbleData {
+            left: side,
+            right: side,
+        }
+    }
+
+    /// Magnitudes between 0 and `u16::MAX`, as in SDL and evdev force
+    /// feedback requests.
+    ///
+    /// Encoded without floating point math.
+    pub fn from_dual_motor_u16(low: u16, high: u16) -> RumbleData {
+        let side = RumbleSide::from_freq_u16(
+            DUAL_MOTOR_HIGH_FREQ as u16,
+            high,
+            DUAL_MOTOR_LOW_FREQ as u16,
+            low,
+        );
+        RumbleData {
+            left: side,
+            right: side,
+        }
+    }
+}
+
 #[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[allow(non_snake_case)]
 pub struct RumbleSide {
@@ -15,6 +72,8 @@ pub struct RumbleSide {
     amp_low_lsB: u8,
 }
 
+unsafe impl Wire for RumbleSide {}
+
 impl RumbleSide {
     pub fn from_freq(
         mut hi_freq: f32,
@@ -39,10 +98,44 @@ impl RumbleSide {
         )
     }
 
+    /// Same as [`from_freq`](#method.from_freq) without floating point math,
+    /// eg. for microcontrollers without FPU.
+    ///
+    /// The frequencies are in Hz, the amplitudes between 0 and `u16::MAX`.
+    pub fn from_freq_u16(hi_freq: u16, hi_amp: u16, low_freq: u16, low_amp: u16) -> RumbleSide {
+        let hi_freq = hi_freq.clamp(82, 1253);
+        let low_freq = low_freq.clamp(41, 626);
+        // Truncated percentage, like the float version.
+        let percent = |amp: u16| (u32::from(amp) * 100 / u32::from(u16::MAX)) as u8;
+
+        let hi_freq_hex = (Self::encode_freq_u16(hi_freq) - 0x60) * 4;
+        let low_freq_hex = (Self::encode_freq_u16(low_freq) - 0x40) as u8;
+        let hi_amp_hex = percent(hi_amp) << 1;
+        let low_amp_hex = percent(low_amp) + 0x80;
+        RumbleSide::from_encoded(
+            [hi_freq_hex as u8, (hi_freq_hex >> 8) as u8],
+            hi_amp_hex,
+            low_freq_hex,
+            [(low_amp_hex & 1) << 7, low_amp_hex >> 1],
+        )
+    }
+
+    /// Vibration of a standard gamepad given the magnitudes, between 0 and 1,
+    /// of its low frequency (heavy) and high frequency (light) motors.
+    ///
+    /// Each motor drives one band of the HD rumble at a fixed frequency.
+    pub fn from_dual_motor(low: f32, high: f32) -> RumbleSide {
+        RumbleSide::from_freq(DUAL_MOTOR_HIGH_FREQ, high, DUAL_MOTOR_LOW_FREQ, low)
+    }
+
     fn encode_freq(f: f32) -> u16 {
         ((f / 10.).log2() * 32.).round() as u16
     }
 
+    fn encode_freq_u16(f: u16) -> u16 {
+        0x40 + FREQ_THRESHOLDS.iter().take_while(|&&t| t <= f).count() as u16
+    }
+
     fn from_encoded(
         high_freq: [u8; 2],
         high_amp: u8,
@@ -71,6 +164,14 @@ impl Default for RumbleSide {
     }
 }
 
+#[test]
+fn dual_motor_rumble() {
+    assert_eq!(RumbleSide::from_dual_motor(0., 0.), RumbleSide::default());
+    let full = RumbleData::from_dual_motor_u16(u16::MAX, u16::MAX);
+    assert_eq!(full.left, RumbleSide::from_freq(320., 1., 160., 1.));
+    assert_eq!(full.left, full.right);
+}
+
 #[test]
 fn encode_rumble() {
     let rumble = RumbleSide::from_freq(320., 0., 160., 0.);
@@ -84,3 +185,24 @@ fn encode_rumble() {
         }
     );
 }
+
+#[test]
+fn encode_rumble_u16() {
+    for freq in 0..1300 {
+        assert_eq!(
+            RumbleSide::from_freq_u16(freq, 0, freq, 0),
+            RumbleSide::from_freq(f32::from(freq), 0., f32::from(freq), 0.),
+            "{} Hz",
+            freq
+        );
+    }
+    for amp in (0..=u16::MAX).step_by(97).chain(Some(u16::MAX)) {
+        let scaled = f32::from(amp) / f32::from(u16::MAX);
+        assert_eq!(
+            RumbleSide::from_freq_u16(320, amp, 160, amp),
+            RumbleSide::from_freq(320., scaled, 160., scaled),
+            "amplitude {}",
+            amp
+        );
+    }
+}
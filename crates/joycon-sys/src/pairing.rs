@@ -0,0 +1,466 @@
+//! Conversions of the pairing information between the controller and the
+//! Bluetooth stacks of the hosts.
+//!
+//! The SPI flash stores the host address in big-endian, and the link key in
+//! little-endian XORed with `0xAA`, as does the reply to the manual pairing
+//! (`spi_flash_notes.md` and `bluetooth_hid_subcommands_notes.md` of
+//! <https://github.com/dekuNukem/Nintendo_Switch_Reverse_Engineering>).
+//! BlueZ and Windows use the byte order of the HCI layer, which is
+//! little-endian:
+//!
+//! - BlueZ writes the key as uppercase hexadecimal in the `[LinkKey]` section
+//!   of `/var/lib/bluetooth/<adapter>/<device>/info`,
+//! - Windows stores it as a binary value named after the controller address
+//!   under `HKLM\SYSTEM\CurrentControlSet\Services\BTHPORT\Parameters\Keys\<adapter>`.
+//!
+//! Copying the key read from the controller to the PC lets the controller
+//! reconnect to the PC while staying paired with the Switch.
+//!
+//! The controller can also be paired by a host without going through the
+//! Bluetooth pairing, with the subcommand `0x01`, see [`PairingSequence`].
+
+use crate::{
+    common::{RawId, SubcommandId, Wire},
+    output::SubcommandRequest,
+    InputReport, OutputReport,
+};
+use std::{fmt, str::FromStr};
+
+const SPI_KEY_XOR: u8 = 0xAA;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ParseError;
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid hexadecimal value")
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_hex<const N: usize>(s: &str, separator: Option<char>) -> Result<[u8; N], ParseError> {
+    let mut out = [0; N];
+    let mut bytes = out.iter_mut();
+    let mut rest = s;
+    loop {
+        let byte = bytes.next().ok_or(ParseError)?;
+        let digits = rest.get(..2).ok_or(ParseError)?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseError);
+        }
+        *byte = u8::from_str_radix(digits, 16).map_err(|_| ParseError)?;
+        rest = &rest[2..];
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(sep) = separator {
+            rest = rest.strip_prefix(sep).ok_or(ParseError)?;
+        }
+    }
+    if bytes.next().is_some() {
+        return Err(ParseError);
+    }
+    Ok(out)
+}
+
+/// Bluetooth device address, stored in the HCI (little-endian) byte order.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct BdAddr(pub [u8; 6]);
+
+impl BdAddr {
+    pub fn from_spi(raw: [u8; 6]) -> BdAddr {
+        let mut addr = raw;
+        addr.reverse();
+        BdAddr(addr)
+    }
+
+    pub fn to_spi(self) -> [u8; 6] {
+        let mut raw = self.0;
+        raw.reverse();
+        raw
+    }
+
+    /// Name of the registry value holding the link key on Windows, eg.
+    /// `98b6e9123456`.
+    pub fn to_windows(self) -> String {
+        self.to_spi().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Formatted as `98:B6:E9:12:34:56`, like BlueZ.
+impl fmt::Display for BdAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, b) in self.to_spi().iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02X}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BdAddr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_hex(s, Some(':')).map(BdAddr::from_spi)
+    }
+}
+
+/// Bluetooth BR/EDR link key, stored in the HCI (little-endian) byte order.
+#[derive(Clone, Copy, Default, Eq, PartialEq)]
+pub struct LinkKey(pub [u8; 16]);
+
+/// Undo or apply the XOR of the key stored by the controller.
+fn xor_key(raw: [u8; 16]) -> [u8; 16] {
+    let mut key = raw;
+    for b in &mut key {
+        *b ^= SPI_KEY_XOR;
+    }
+    key
+}
+
+impl LinkKey {
+    /// Decode the key as stored in the SPI flash.
+    pub fn from_spi(raw: [u8; 16]) -> LinkKey {
+        LinkKey(xor_key(raw))
+    }
+
+    pub fn to_spi(self) -> [u8; 16] {
+        xor_key(self.0)
+    }
+
+    /// Parse the `Key` value of a BlueZ `info` file.
+    pub fn from_bluez(key: &str) -> Result<LinkKey, ParseError> {
+        parse_hex(key.trim(), None).map(LinkKey)
+    }
+
+    /// Format as the `Key` value of a BlueZ `info` file.
+    pub fn to_bluez(self) -> String {
+        self.0.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    /// Build from the binary registry value used by Windows.
+    pub fn from_windows(value: [u8; 16]) -> LinkKey {
+        LinkKey(value)
+    }
+
+    /// Binary registry value used by Windows.
+    pub fn to_windows(self) -> [u8; 16] {
+        self.0
+    }
+}
+
+/// The key is a secret and isn't printed.
+impl fmt::Debug for LinkKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LinkKey(..)")
+    }
+}
+
+/// Steps of the manual pairing, the argument of `BluetoothManualPairing`.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, FromPrimitive, ToPrimitive, Eq, PartialEq)]
+pub enum PairingStep {
+    /// Send the host address, replied with the controller address.
+    SendHostAddress = 1,
+    /// Replied with the link key, XORed with `0xAA`.
+    GetLinkKey = 2,
+    /// Save the pairing in the flash.
+    SavePairing = 3,
+}
+
+/// Argument of `BluetoothManualPairing`.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug)]
+pub struct PairingRequest {
+    step: RawId<PairingStep>,
+    /// Only sent with `SendHostAddress`, in the HCI byte order.
+    host_address: [u8; 6],
+}
+
+unsafe impl Wire for PairingRequest {}
+
+impl PairingRequest {
+    pub fn new(step: PairingStep, host_address: BdAddr) -> PairingRequest {
+        PairingRequest {
+            step: step.into(),
+            host_address: if step == PairingStep::SendHostAddress {
+                host_address.0
+            } else {
+                [0; 6]
+            },
+        }
+    }
+
+    pub fn step(&self) -> Option<PairingStep> {
+        self.step.try_into()
+    }
+
+    pub fn host_address(&self) -> BdAddr {
+        BdAddr(self.host_address)
+    }
+
+    /// Zero the host address.
+    pub fn redact(&mut self) {
+        self.host_address = [0; 6];
+    }
+}
+
+impl From<PairingRequest> for SubcommandRequest {
+    fn from(request: PairingRequest) -> Self {
+        crate::output::SubcommandRequestEnum::BluetoothManualPairing(request).into()
+    }
+}
+
+/// Reply data of `BluetoothManualPairing`.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug)]
+pub struct PairingReply {
+    step: RawId<PairingStep>,
+    data: [u8; 38],
+}
+
+unsafe impl Wire for PairingReply {}
+
+impl PairingReply {
+    pub fn step(&self) -> Option<PairingStep> {
+        self.step.try_into()
+    }
+
+    /// Address of the controller, in the reply to `SendHostAddress`.
+    pub fn controller_address(&self) -> Option<BdAddr> {
+        if self.step() != Some(PairingStep::SendHostAddress) {
+            return None;
+        }
+        let mut address = [0; 6];
+        address.copy_from_slice(&self.data[..6]);
+        Some(BdAddr(address))
+    }
+
+    /// Link key, in the reply to `GetLinkKey`.
+    pub fn link_key(&self) -> Option<LinkKey> {
+        if self.step() != Some(PairingStep::GetLinkKey) {
+            return None;
+        }
+        let mut raw = [0; 16];
+        raw.copy_from_slice(&self.data[..16]);
+        Some(LinkKey::from_spi(raw))
+    }
+
+    /// Zero the controller address and the link key.
+    pub fn redact(&mut self) {
+        self.data = [0; 38];
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PairingError {
+    /// The controller refused a step.
+    Nack(PairingStep),
+    /// The reply is for another step than the one sent.
+    UnexpectedStep {
+        expected: PairingStep,
+        got: Option<PairingStep>,
+    },
+}
+
+impl fmt::Display for PairingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PairingError::Nack(step) => write!(f, "pairing step {:?} refused", step),
+            PairingError::UnexpectedStep { expected, got } => write!(
+                f,
+                "unexpected pairing reply: expected {:?}, got {:?}",
+                expected, got
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PairingError {}
+
+/// Sans-io state machine of the manual pairing.
+///
+/// The three steps are sent in order, each after the reply to the previous
+/// one. Once done, the controller reconnects to `host` and the link key can
+/// be installed in its Bluetooth stack:
+///
+/// ```ignore
+/// let mut pairing = PairingSequence::new(adapter_address);
+/// while let Some(mut report) = pairing.next_report() {
+///     send(&mut report)?;
+///     pairing.push(&recv_reply()?)?;
+/// }
+/// install_key(pairing.controller_address().unwrap(), pairing.link_key().unwrap());
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct PairingSequence {
+    host: BdAddr,
+    step: Option<PairingStep>,
+    controller_address: Option<BdAddr>,
+    link_key: Option<LinkKey>,
+}
+
+impl PairingSequence {
+    pub fn new(host: BdAddr) -> PairingSequence {
+        PairingSequence {
+            host,
+            step: Some(PairingStep::SendHostAddress),
+            controller_address: None,
+            link_key: None,
+        }
+    }
+
+    /// Step waiting for its reply, `None` once done.
+    pub fn step(&self) -> Option<PairingStep> {
+        self.step
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.step.is_none()
+    }
+
+    /// Report of the current step, `None` once done.
+    pub fn next_report(&self) -> Option<OutputReport> {
+        let request = PairingRequest::new(self.step?, self.host);
+        Some(SubcommandRequest::from(request).into())
+    }
+
+    /// Handle a report, other reports than the pairing replies being
+    /// ignored.
+    pub fn push(&mut self, report: &InputReport) -> Result<(), PairingError> {
+        let (expected, reply) = match (self.step, report.subcmd_reply()) {
+            (Some(step), Some(reply)) if reply.id() == SubcommandId::BluetoothManualPairing => {
+                (step, reply)
+            }
+            _ => return Ok(()),
+        };
+        if !reply.ack().is_ok() {
+            return Err(PairingError::Nack(expected));
+        }
+        let data = reply.bluetooth_manual_pairing().unwrap();
+        if data.step() != Some(expected) {
+            return Err(PairingError::UnexpectedStep {
+                expected,
+                got: data.step(),
+            });
+        }
+        self.step = match expected {
+            PairingStep::SendHostAddress => {
+                self.controller_address = data.controller_address();
+                Some(PairingStep::GetLinkKey)
+            }
+            PairingStep::GetLinkKey => {
+                self.link_key = data.link_key();
+                Some(PairingStep::SavePairing)
+            }
+            PairingStep::SavePairing => None,
+        };
+        trace!("pairing step {:?} done", expected);
+        Ok(())
+    }
+
+    /// Address of the controller, after the first step.
+    pub fn controller_address(&self) -> Option<BdAddr> {
+        self.controller_address
+    }
+
+    /// Link key shared with the host, after the second step.
+    pub fn link_key(&self) -> Option<LinkKey> {
+        self.link_key
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn link_key_conversions() {
+    let mut spi = [0; 16];
+    for (i, b) in spi.iter_mut().enumerate() {
+        *b = i as u8 ^ SPI_KEY_XOR;
+    }
+    let key = LinkKey::from_spi(spi);
+    assert_eq!(key.0[0], 0);
+    assert_eq!(key.0[15], 15);
+    assert_eq!(key.to_spi(), spi);
+    assert_eq!(key.to_bluez(), "000102030405060708090A0B0C0D0E0F");
+    assert_eq!(LinkKey::from_bluez(&key.to_bluez()), Ok(key));
+    assert_eq!(LinkKey::from_windows(key.to_windows()), key);
+    assert_eq!(LinkKey::from_bluez("0F0E"), Err(ParseError));
+
+    let addr: BdAddr = "98:B6:E9:12:34:56".parse().unwrap();
+    assert_eq!(addr.0, [0x56, 0x34, 0x12, 0xe9, 0xb6, 0x98]);
+    assert_eq!(addr.to_string(), "98:B6:E9:12:34:56");
+    assert_eq!(addr.to_windows(), "98b6e9123456");
+    assert_eq!(BdAddr::from_spi(addr.to_spi()), addr);
+}
+
+#[cfg(test)]
+#[test]
+fn manual_pairing() {
+    use crate::input::{Ack, SubcommandReply, SubcommandReplyEnum};
+
+    let reply = |step: PairingStep, data: &[u8]| {
+        let mut raw = [0; 39];
+        raw[0] = step as u8;
+        raw[1..1 + data.len()].copy_from_slice(data);
+        let mut reply: SubcommandReply =
+            SubcommandReplyEnum::BluetoothManualPairing(PairingReply::from_bytes(&raw).unwrap())
+                .into();
+        *reply.ack_mut() = Ack::ok(1);
+        let mut report = InputReport::new();
+        report.as_bytes_mut()[0] = crate::InputReportId::StandardAndSubcmd as u8;
+        report.as_bytes_mut()[13..13 + 40].copy_from_slice(&reply.to_bytes()[..40]);
+        report
+    };
+
+    let host: BdAddr = "98:B6:E9:12:34:56".parse().unwrap();
+    let mut pairing = PairingSequence::new(host);
+    let report = pairing.next_report().unwrap();
+    assert_eq!(
+        report.as_bytes()[10..18],
+        [0x01, 0x01, 0x56, 0x34, 0x12, 0xe9, 0xb6, 0x98]
+    );
+    assert_eq!(
+        pairing.push(&reply(PairingStep::GetLinkKey, &[])),
+        Err(PairingError::UnexpectedStep {
+            expected: PairingStep::SendHostAddress,
+            got: Some(PairingStep::GetLinkKey),
+        })
+    );
+    pairing
+        .push(&reply(PairingStep::SendHostAddress, &[1, 2, 3, 4, 5, 6]))
+        .unwrap();
+    assert_eq!(
+        pairing.controller_address(),
+        Some(BdAddr([1, 2, 3, 4, 5, 6]))
+    );
+    assert_eq!(pairing.next_report().unwrap().as_bytes()[11..13], [2, 0]);
+    pairing
+        .push(&reply(PairingStep::GetLinkKey, &[SPI_KEY_XOR ^ 0x42; 16]))
+        .unwrap();
+    assert_eq!(pairing.link_key(), Some(LinkKey([0x42; 16])));
+    pairing.push(&reply(PairingStep::SavePairing, &[])).unwrap();
+    assert!(pairing.is_done());
+    assert!(pairing.next_report().is_none());
+
+    // The same key read from the flash and from the pairing reply.
+    let mut stored = [0; 16];
+    for (i, b) in stored.iter_mut().enumerate() {
+        *b = i as u8 ^ SPI_KEY_XOR;
+    }
+    let report = reply(PairingStep::GetLinkKey, &stored);
+    let pairing_reply = report
+        .subcmd_reply()
+        .unwrap()
+        .bluetooth_manual_pairing()
+        .unwrap();
+    let mut spi = [0; 22];
+    spi[6..].copy_from_slice(&stored);
+    let info = crate::spi::PairingInfo::from_bytes(&spi).unwrap();
+    assert_eq!(pairing_reply.link_key(), Some(info.link_key()));
+}
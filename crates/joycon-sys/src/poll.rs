@@ -0,0 +1,281 @@
+//! Reading the controller state without changing the input report mode.
+//!
+//! Switching to the standard full mode (`0x30`) affects every program using
+//! the controller. When the device is shared with another driver,
+//! [`MinimalPoller`] instead queries the state with the subcommand `0x00`,
+//! sending a new query once the previous one is answered.
+//!
+//! ```ignore
+//! let mut poller = MinimalPoller::new(MINIMAL_POLL_INTERVAL);
+//! loop {
+//!     if let Some(mut report) = poller.poll(clock.now()) {
+//!         send(&mut report)?;
+//!     }
+//!     if let Some(state) = poller.handle(&recv()?) {
+//!         handle(state);
+//!     }
+//! }
+//! ```
+//!
+//! [`TriggerTimePoller`] queries the trigger hold times the same way.
+
+use crate::{
+    clock::Timestamp,
+    input::StandardInputReport,
+    output::{ControllerStateRequest, SubcommandRequest, SubcommandRequestEnum},
+    timing, InputReport, OutputReport,
+};
+use std::time::Duration;
+
+/// Default delay between two queries, about the rate of the full mode, one
+/// per output report slot.
+pub const MINIMAL_POLL_INTERVAL: Duration = timing::REPORT_INTERVAL;
+
+/// Delay after which an unanswered query is considered lost.
+pub(crate) const REPLY_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Copy, Clone, Debug)]
+pub struct MinimalPoller {
+    schedule: QuerySchedule,
+}
+
+impl MinimalPoller {
+    pub fn new(interval: Duration) -> MinimalPoller {
+        MinimalPoller {
+            schedule: QuerySchedule::new(interval),
+        }
+    }
+
+    /// The query to send, if one is due at `now`.
+    pub fn poll(&mut self, now: Timestamp) -> Option<OutputReport> {
+        if !self.schedule.is_due(now) {
+            return None;
+        }
+        Some(SubcommandRequest::from(ControllerStateRequest).into())
+    }
+
+    /// The state contained in `report`, if it's the reply to a query.
+    pub fn handle(&mut self, report: &InputReport) -> Option<StandardInputReport> {
+        let state = *report.controller_state()?;
+        self.schedule.replied();
+        Some(state)
+    }
+}
+
+/// One query at a time, sent every `interval`.
+#[derive(Copy, Clone, Debug)]
+struct QuerySchedule {
+    interval: Duration,
+    last_query: Option<Timestamp>,
+    waiting_reply: bool,
+}
+
+impl QuerySchedule {
+    fn new(interval: Duration) -> QuerySchedule {
+        QuerySchedule {
+            interval,
+            last_query: None,
+            waiting_reply: false,
+        }
+    }
+
+    /// Whether a query is due at `now`, in which case it's considered sent.
+    fn is_due(&mut self, now: Timestamp) -> bool {
+        if let Some(last) = self.last_query {
+            let elapsed = now.saturating_duration_since(last);
+            if elapsed < self.interval || (self.waiting_reply && elapsed < REPLY_TIMEOUT) {
+                return false;
+            }
+        }
+        self.last_query = Some(now);
+        self.waiting_reply = true;
+        true
+    }
+
+    fn replied(&mut self) {
+        self.waiting_reply = false;
+    }
+}
+
+impl Default for MinimalPoller {
+    fn default() -> Self {
+        MinimalPoller::new(MINIMAL_POLL_INTERVAL)
+    }
+}
+
+/// Default delay between two elapsed time queries.
+pub const TRIGGER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Unit of the elapsed time counters.
+const TRIGGER_TIME_UNIT: Duration = Duration::from_millis(10);
+
+/// Buttons counted by `GetTriggerButtonsElapsedTime`, in reply order.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TriggerButton {
+    L,
+    R,
+    ZL,
+    ZR,
+    SL,
+    SR,
+    Home,
+}
+
+impl TriggerButton {
+    pub const ALL: [TriggerButton; 7] = [
+        TriggerButton::L,
+        TriggerButton::R,
+        TriggerButton::ZL,
+        TriggerButton::ZR,
+        TriggerButton::SL,
+        TriggerButton::SR,
+        TriggerButton::Home,
+    ];
+}
+
+/// Time each button was held between two replies.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TriggerHoldTimes {
+    times: [Duration; 7],
+}
+
+impl TriggerHoldTimes {
+    pub fn get(&self, button: TriggerButton) -> Duration {
+        self.times[button as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TriggerButton, Duration)> + '_ {
+        TriggerButton::ALL
+            .iter()
+            .cloned()
+            .zip(self.times.iter().cloned())
+    }
+}
+
+/// Periodic `GetTriggerButtonsElapsedTime` queries, turned into hold
+/// durations.
+///
+/// The controller counts the hold time of each button in 16-bit counters of
+/// 10ms units, wrapping after about 11 minutes. The counters are compared
+/// between two replies, so the interval must stay below that.
+///
+/// ```ignore
+/// let mut poller = TriggerTimePoller::new(TRIGGER_POLL_INTERVAL);
+/// loop {
+///     if let Some(mut report) = poller.poll(clock.now()) {
+///         send(&mut report)?;
+///     }
+///     if let Some(held) = poller.handle(&recv()?) {
+///         heatmap.add(held.get(TriggerButton::ZR));
+///     }
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct TriggerTimePoller {
+    schedule: QuerySchedule,
+    last_counters: Option<[u16; 7]>,
+}
+
+impl TriggerTimePoller {
+    pub fn new(interval: Duration) -> TriggerTimePoller {
+        TriggerTimePoller {
+            schedule: QuerySchedule::new(interval),
+            last_counters: None,
+        }
+    }
+
+    /// The query to send, if one is due at `now`.
+    pub fn poll(&mut self, now: Timestamp) -> Option<OutputReport> {
+        if !self.schedule.is_due(now) {
+            return None;
+        }
+        Some(
+            SubcommandRequest::from(SubcommandRequestEnum::GetTriggerButtonsElapsedTime(())).into(),
+        )
+    }
+
+    /// Hold durations since the previous reply, if `report` is a reply.
+    ///
+    /// The first reply only gives the starting counters, so `None` is
+    /// returned for it too.
+    pub fn handle(&mut self, report: &InputReport) -> Option<TriggerHoldTimes> {
+        let counters = report.subcmd_reply()?.trigger_elapsed_times()?;
+        self.schedule.replied();
+        let last = self.last_counters.replace(counters)?;
+        let mut held = TriggerHoldTimes::default();
+        for ((time, now), before) in held.times.iter_mut().zip(&counters).zip(&last) {
+            *time = TRIGGER_TIME_UNIT * u32::from(now.wrapping_sub(*before));
+        }
+        Some(held)
+    }
+
+    /// Forget the previous counters, for example after a reconnection which
+    /// reset them.
+    pub fn reset(&mut self) {
+        self.last_counters = None;
+    }
+}
+
+impl Default for TriggerTimePoller {
+    fn default() -> Self {
+        TriggerTimePoller::new(TRIGGER_POLL_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn alternate_queries() {
+    use crate::{common::SubcommandId, InputReportId};
+
+    let mut reply = InputReport::new();
+    let raw = reply.as_bytes_mut();
+    raw[0] = InputReportId::StandardAndSubcmd as u8;
+    raw[1] = 42;
+    raw[13] = 0x80;
+    raw[14] = SubcommandId::GetOnlyControllerState as u8;
+
+    let start = Timestamp::from_micros(0);
+    let mut poller = MinimalPoller::default();
+    let query = poller.poll(start).unwrap();
+    assert_eq!(query.as_bytes()[10], 0x00);
+    // No new query before the reply.
+    assert!(poller.poll(start + Duration::from_millis(50)).is_none());
+    assert_eq!(poller.handle(&reply).unwrap().timer, 42);
+    assert!(poller.poll(start + Duration::from_millis(60)).is_some());
+    assert!(poller.poll(start + Duration::from_millis(70)).is_none());
+    // Lost reply.
+    assert!(poller.poll(start + Duration::from_millis(160)).is_some());
+}
+
+#[cfg(test)]
+#[test]
+fn trigger_hold_times() {
+    use crate::{common::SubcommandId, InputReportId};
+
+    let reply = |counters: [u16; 7]| {
+        let mut reply = InputReport::new();
+        let raw = reply.as_bytes_mut();
+        raw[0] = InputReportId::StandardAndSubcmd as u8;
+        raw[13] = 0x83;
+        raw[14] = SubcommandId::GetTriggerButtonsElapsedTime as u8;
+        for (i, counter) in counters.iter().enumerate() {
+            raw[15 + 2 * i..17 + 2 * i].copy_from_slice(&counter.to_le_bytes());
+        }
+        reply
+    };
+
+    let start = Timestamp::from_micros(0);
+    let mut poller = TriggerTimePoller::default();
+    let query = poller.poll(start).unwrap();
+    assert_eq!(query.as_bytes()[10], 0x04);
+    assert_eq!(poller.handle(&reply([0xfff0, 0, 0, 0, 0, 0, 0])), None);
+    assert!(poller.poll(start + Duration::from_millis(500)).is_none());
+    assert!(poller.poll(start + TRIGGER_POLL_INTERVAL).is_some());
+    let held = poller.handle(&reply([0x0010, 0, 0, 100, 0, 0, 3])).unwrap();
+    assert_eq!(held.get(TriggerButton::L), Duration::from_millis(320));
+    assert_eq!(held.get(TriggerButton::ZR), Duration::from_secs(1));
+    assert_eq!(
+        held.iter().last(),
+        Some((TriggerButton::Home, Duration::from_millis(30)))
+    );
+}
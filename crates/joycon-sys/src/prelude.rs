@@ -0,0 +1,44 @@
+//! Types needed by most drivers, to glob import instead of spelling out the
+//! module paths.
+//!
+//! ```ignore
+//! use joycon_sys::prelude::*;
+//!
+//! let mut report = OutputReport::from(SubcommandRequest::from(HCIState::Disconnect));
+//! ```
+//!
+//! Only the report types, their builders and the high-level state are
+//! exported here. The protocol details stay in their modules.
+
+pub use crate::{
+    clock::{Clock, Timestamp},
+    common::{Bool, HCIState, InputReportId, RawId, SubcommandId, Wire},
+    identity::ControllerIdentity,
+    imu::IMUMode,
+    input::{
+        Button, DeviceInfo, FirmwareVersion, InputReport, InputReportEnum, MACAddress,
+        SubcommandReply, SubcommandReplyEnum, WhichController,
+    },
+    light::{HomeLight, LedFeedback, PlayerLight, PlayerLights},
+    mcu::MCUMode,
+    output::{
+        BringUpProfile, OutputReport, OutputReportEnum, OutputReportId, RumbleData, RumbleSide,
+        SubcommandQueue, SubcommandRequest, SubcommandRequestEnum, TransportKind,
+    },
+    spi::{
+        ControllerColor, SPIRange, SensorCalibration, StickCalibration, SticksCalibration,
+        UserSensorCalibration, UserSticksCalibration, SPI,
+    },
+    state::{ButtonSet, ControllerState},
+};
+
+#[cfg(test)]
+#[test]
+fn prelude_builds_reports() {
+    let report = OutputReport::from(SubcommandRequest::from(HCIState::Disconnect));
+    assert_eq!(report.id(), OutputReportId::RumbleAndSubcmd);
+    assert_eq!(
+        report.rumble_subcmd().unwrap().id(),
+        SubcommandId::SetHCIState
+    );
+}
@@ -0,0 +1,111 @@
+//! Python bindings, enabled by the `pyo3` feature.
+//!
+//! Exposes the report parsing, the stick calibration and the rumble encoding
+//! for protocol experiments from Python:
+//!
+//! ```python
+//! import joycon_sys
+//! state = joycon_sys.parse_input_report(raw)
+//! calib = joycon_sys.SticksCalibration(spi_data)
+//! x, y = calib.left(*state.left_stick)
+//! ```
+//!
+//! Build the extension module with
+//! `cargo rustc -p joycon-sys --features pyo3 --crate-type cdylib`.
+
+use crate::{
+    bindings::{FlatInputError, FlatInputState},
+    common::Wire,
+    output::RumbleSide,
+    spi,
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use std::mem::size_of;
+
+/// Decoded standard input report.
+#[pyclass(get_all)]
+#[derive(Clone, Debug)]
+pub struct InputState {
+    pub report_id: u8,
+    pub timer: u8,
+    pub battery_level: String,
+    pub charging: bool,
+    pub connection: String,
+    pub buttons: Vec<String>,
+    /// Raw 12-bit values.
+    pub left_stick: (u16, u16),
+    pub right_stick: (u16, u16),
+}
+
+#[pyfunction]
+fn parse_input_report(data: &[u8]) -> PyResult<InputState> {
+    let state = FlatInputState::parse(data).map_err(|e| match e {
+        FlatInputError::Invalid => PyValueError::new_err("invalid report"),
+        FlatInputError::NotStandard => PyValueError::new_err("not a standard input report"),
+    })?;
+    Ok(InputState {
+        report_id: state.report_id,
+        timer: state.timer,
+        battery_level: state.battery_name().into(),
+        charging: state.charging,
+        connection: state.connection_name().into(),
+        buttons: state.buttons.iter().map(|b| format!("{:?}", b)).collect(),
+        left_stick: state.left_stick,
+        right_stick: state.right_stick,
+    })
+}
+
+/// Factory calibration of the sticks, built from the 18 bytes read at
+/// `0x603d` in the SPI flash.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct SticksCalibration(spi::SticksCalibration);
+
+#[pymethods]
+impl SticksCalibration {
+    #[new]
+    fn new(raw: &[u8]) -> PyResult<Self> {
+        spi::SticksCalibration::from_bytes(raw)
+            .map(SticksCalibration)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "expected {} bytes",
+                    size_of::<spi::SticksCalibration>()
+                ))
+            })
+    }
+
+    /// Position of the left stick, between -1 and 1 on each axis.
+    fn left(&self, x: u16, y: u16) -> (f64, f64) {
+        let v = self.0.left.value_from_raw(x, y);
+        (v.x, v.y)
+    }
+
+    /// Position of the right stick, between -1 and 1 on each axis.
+    fn right(&self, x: u16, y: u16) -> (f64, f64) {
+        let v = self.0.right.value_from_raw(x, y);
+        (v.x, v.y)
+    }
+}
+
+/// Encode the 4 bytes of rumble data of one side.
+#[pyfunction]
+fn encode_rumble(
+    py: Python,
+    high_freq: f32,
+    high_amp: f32,
+    low_freq: f32,
+    low_amp: f32,
+) -> PyObject {
+    let side = RumbleSide::from_freq(high_freq, high_amp, low_freq, low_amp);
+    PyBytes::new(py, &side.to_bytes()).into()
+}
+
+#[pymodule]
+fn joycon_sys(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<InputState>()?;
+    m.add_class::<SticksCalibration>()?;
+    m.add_function(wrap_pyfunction!(parse_input_report, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_rumble, m)?)?;
+    Ok(())
+}
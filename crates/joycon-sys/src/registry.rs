@@ -0,0 +1,223 @@
+//! Per-controller state for hosts driving several controllers.
+//!
+//! [`ControllerRegistry`] keeps, for each connected controller, its bring-up
+//! progress, calibration and latest state, in a fixed-capacity table that
+//! doesn't allocate:
+//!
+//! ```ignore
+//! let mut registry = ControllerRegistry::<8>::new();
+//! let entry = registry.insert(info.mac_address, clock.now())?;
+//! entry.calibration = Some(sticks);
+//!
+//! loop {
+//!     // Service the controllers in turn.
+//!     if let Some(entry) = registry.next_slice() {
+//!         let report = recv(entry.mac_address)?;
+//!         entry.update(report.standard().unwrap(), clock.now());
+//!     }
+//!     registry.remove_stale(clock.now(), Duration::from_secs(5));
+//! }
+//! ```
+
+use crate::{
+    clock::Timestamp,
+    identity::ControllerIdentity,
+    input::{MACAddress, StandardInputReport},
+    output::BringUpProfile,
+    spi::SticksCalibration,
+    state::ControllerState,
+};
+use std::time::Duration;
+
+/// Progress of the bring-up sequence of a controller, see
+/// [`BringUpProfile::steps`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BringUpState {
+    Pending,
+    InProgress {
+        profile: BringUpProfile,
+        /// Index of the next step to send.
+        step: usize,
+    },
+    Done(BringUpProfile),
+}
+
+/// What is known about one controller of a [`ControllerRegistry`].
+#[derive(Copy, Clone, Debug)]
+pub struct ControllerEntry {
+    pub mac_address: MACAddress,
+    pub identity: ControllerIdentity,
+    pub bring_up: BringUpState,
+    /// Needed to compute `state`.
+    pub calibration: Option<SticksCalibration>,
+    pub state: Option<ControllerState>,
+    /// Last time a report was received.
+    pub last_seen: Timestamp,
+}
+
+impl ControllerEntry {
+    pub fn new(mac_address: MACAddress, now: Timestamp) -> ControllerEntry {
+        ControllerEntry {
+            mac_address,
+            identity: ControllerIdentity::default(),
+            bring_up: BringUpState::Pending,
+            calibration: None,
+            state: None,
+            last_seen: now,
+        }
+    }
+
+    /// Record a report received at `now`, updating the state if the
+    /// calibration is known.
+    pub fn update(&mut self, report: &StandardInputReport, now: Timestamp) {
+        self.last_seen = now;
+        if let Some(calibration) = self.calibration {
+            self.state = Some(ControllerState::new(report, &calibration));
+        }
+    }
+}
+
+/// Fixed-capacity table of controllers, keyed by their MAC address.
+#[derive(Clone, Debug)]
+pub struct ControllerRegistry<const N: usize> {
+    entries: [Option<ControllerEntry>; N],
+    cursor: usize,
+}
+
+impl<const N: usize> ControllerRegistry<N> {
+    pub fn new() -> ControllerRegistry<N> {
+        ControllerRegistry {
+            entries: [None; N],
+            cursor: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|e| e.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(Option::is_none)
+    }
+
+    fn position(&self, mac_address: MACAddress) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|e| matches!(e, Some(e) if e.mac_address == mac_address))
+    }
+
+    pub fn get(&self, mac_address: MACAddress) -> Option<&ControllerEntry> {
+        self.entries[self.position(mac_address)?].as_ref()
+    }
+
+    pub fn get_mut(&mut self, mac_address: MACAddress) -> Option<&mut ControllerEntry> {
+        let index = self.position(mac_address)?;
+        self.entries[index].as_mut()
+    }
+
+    /// Entry of the controller, created if it's not known yet, or the
+    /// address back if the registry is full.
+    pub fn insert(
+        &mut self,
+        mac_address: MACAddress,
+        now: Timestamp,
+    ) -> Result<&mut ControllerEntry, MACAddress> {
+        let index = match self.position(mac_address) {
+            Some(index) => index,
+            None => {
+                let index = self
+                    .entries
+                    .iter()
+                    .position(Option::is_none)
+                    .ok_or(mac_address)?;
+                trace!("controller {:?} registered", mac_address);
+                self.entries[index] = Some(ControllerEntry::new(mac_address, now));
+                index
+            }
+        };
+        Ok(self.entries[index].as_mut().unwrap())
+    }
+
+    pub fn remove(&mut self, mac_address: MACAddress) -> Option<ControllerEntry> {
+        let index = self.position(mac_address)?;
+        self.entries[index].take()
+    }
+
+    /// Remove the controllers without reports for `timeout`, returning how
+    /// many were removed.
+    pub fn remove_stale(&mut self, now: Timestamp, timeout: Duration) -> usize {
+        let mut removed = 0;
+        for entry in self.entries.iter_mut() {
+            if let Some(e) = entry {
+                if now.saturating_duration_since(e.last_seen) >= timeout {
+                    trace!("controller {:?} stale", e.mac_address);
+                    *entry = None;
+                    removed += 1;
+                }
+            }
+        }
+        removed
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ControllerEntry> {
+        self.entries.iter().flatten()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut ControllerEntry> {
+        self.entries.iter_mut().flatten()
+    }
+
+    /// Next controller to service, going through all of them in turn so a
+    /// busy controller doesn't starve the others.
+    pub fn next_slice(&mut self) -> Option<&mut ControllerEntry> {
+        let index = (0..N)
+            .map(|i| (self.cursor + i) % N)
+            .find(|&i| self.entries[i].is_some())?;
+        self.cursor = (index + 1) % N;
+        self.entries[index].as_mut()
+    }
+}
+
+impl<const N: usize> Default for ControllerRegistry<N> {
+    fn default() -> Self {
+        ControllerRegistry::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn controller_registry() {
+    use crate::common::Wire;
+
+    let mac = |last| MACAddress([0x98, 0xb6, 0xe9, 0, 0, last]);
+    let t0 = Timestamp::from_micros(0);
+    let mut registry = ControllerRegistry::<2>::new();
+    registry.insert(mac(1), t0).unwrap().bring_up = BringUpState::Done(BringUpProfile::Gaming);
+    registry.insert(mac(2), t0).unwrap();
+    assert_eq!(registry.insert(mac(3), t0).unwrap_err(), mac(3));
+    assert_eq!(
+        registry.insert(mac(1), t0).unwrap().bring_up,
+        BringUpState::Done(BringUpProfile::Gaming)
+    );
+
+    let order: Vec<_> = (0..3)
+        .map(|_| registry.next_slice().unwrap().mac_address)
+        .collect();
+    assert_eq!(order, [mac(1), mac(2), mac(1)]);
+
+    let bytes = vec![0; std::mem::size_of::<StandardInputReport>()];
+    let report = StandardInputReport::from_bytes(&bytes).unwrap();
+    let entry = registry.get_mut(mac(2)).unwrap();
+    entry.update(&report, t0 + Duration::from_secs(2));
+    assert!(entry.state.is_none());
+    assert_eq!(
+        registry.remove_stale(t0 + Duration::from_secs(3), Duration::from_secs(2)),
+        1
+    );
+    assert!(registry.get(mac(1)).is_none());
+    assert_eq!(registry.len(), 1);
+}
@@ -0,0 +1,155 @@
+//! Typed data spread over several SPI ranges or larger than a single range.
+//!
+//! [`Chunked`] splits the data into ranges fitting in a single SPI read or
+//! write, from which the request sequences are built:
+//!
+//! ```ignore
+//! let profile = ColorProfile {
+//!     colors,
+//!     use_spi_colors: UseSPIColors::IncludingGrip,
+//! };
+//! for request in unsafe { profile.write_requests() } {
+//!     send_and_wait_reply(request)?;
+//! }
+//! ```
+
+use super::*;
+use std::mem::size_of;
+
+/// Maximum size of a chunk, that of a single SPI read or write.
+const MAX_CHUNK_SIZE: usize = 0x1D;
+
+pub trait Chunked {
+    /// Ranges of the data with their content, in writing order, each
+    /// fitting in a single SPI read or write.
+    fn chunks(&self) -> impl Iterator<Item = (SPIRange, &[u8])>;
+
+    /// Requests reading back every chunk, eg. to verify a write.
+    fn read_requests(&self) -> Vec<SPIReadRequest> {
+        self.chunks()
+            .map(|(range, _)| SPIReadRequest::new(range))
+            .collect()
+    }
+
+    /// Requests writing every chunk.
+    ///
+    /// # Safety
+    ///
+    /// Writing some parts of the flash can brick the controller, the ranges
+    /// must be checked beforehand, eg. with [`regions::is_writable`].
+    unsafe fn write_requests(&self) -> Vec<SPIWriteRequest> {
+        self.chunks()
+            .map(|(range, data)| SPIWriteRequest::new(range, data))
+            .collect()
+    }
+}
+
+fn bytes_of<T: Wire>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) }
+}
+
+/// Chunks of `data` stored at `offset`.
+fn split(offset: u32, data: &[u8]) -> impl Iterator<Item = (SPIRange, &[u8])> {
+    data.chunks(MAX_CHUNK_SIZE)
+        .enumerate()
+        .map(move |(i, chunk)| {
+            let start = offset + (i * MAX_CHUNK_SIZE) as u32;
+            (SPIRange(start, chunk.len() as u8), chunk)
+        })
+}
+
+impl Chunked for PairingInfo {
+    fn chunks(&self) -> impl Iterator<Item = (SPIRange, &[u8])> {
+        split(PairingInfo::range().0, bytes_of(self))
+    }
+}
+
+/// Colors of the controller with the flag telling how to use them.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ColorProfile {
+    pub colors: ControllerColor,
+    pub use_spi_colors: UseSPIColors,
+}
+
+/// The colors first, so that the flag is only set once they are written.
+impl Chunked for ColorProfile {
+    fn chunks(&self) -> impl Iterator<Item = (SPIRange, &[u8])> {
+        // `UseSPIColors` is a `u8`.
+        let flag = unsafe {
+            std::slice::from_raw_parts(&self.use_spi_colors as *const UseSPIColors as *const u8, 1)
+        };
+        split(ControllerColor::range().0, bytes_of(&self.colors))
+            .chain(split(UseSPIColors::range().0, flag))
+    }
+}
+
+/// Factory calibration of the sensors and the sticks.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct FactoryCalibration {
+    pub sensors: SensorCalibration,
+    pub sticks: SticksCalibration,
+}
+
+impl Chunked for FactoryCalibration {
+    fn chunks(&self) -> impl Iterator<Item = (SPIRange, &[u8])> {
+        split(SensorCalibration::range().0, bytes_of(&self.sensors))
+            .chain(split(SticksCalibration::range().0, bytes_of(&self.sticks)))
+    }
+}
+
+/// User calibration of the sticks and the sensors, stored contiguously.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UserCalibration {
+    pub sticks: UserSticksCalibration,
+    pub sensors: UserSensorCalibration,
+}
+
+unsafe impl Wire for UserCalibration {}
+
+impl UserCalibration {
+    /// Both calibrations erased, the factory ones being used.
+    pub fn reset() -> UserCalibration {
+        UserCalibration {
+            sticks: UserSticksCalibration::reset(),
+            sensors: UserSensorCalibration::reset(),
+        }
+    }
+}
+
+impl Chunked for UserCalibration {
+    fn chunks(&self) -> impl Iterator<Item = (SPIRange, &[u8])> {
+        split(UserSticksCalibration::range().0, bytes_of(self))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn chunked_requests() {
+    let calib = UserCalibration::reset();
+    let ranges: Vec<_> = calib.chunks().map(|(range, _)| range).collect();
+    assert_eq!(ranges, [SPIRange(0x8010, 0x1D), SPIRange(0x802D, 0x13)]);
+    let data: Vec<u8> = calib.chunks().flat_map(|(_, data)| data.to_vec()).collect();
+    assert_eq!(data, calib.to_bytes());
+
+    let profile = ColorProfile {
+        colors: "#323232/#0f0f0f/#0ab9e6/#ff3c28".parse().unwrap(),
+        use_spi_colors: UseSPIColors::IncludingGrip,
+    };
+    let writes = unsafe { profile.write_requests() };
+    assert_eq!(writes.len(), 2);
+    assert_eq!(writes[0].range(), ControllerColor::range());
+    assert_eq!(writes[0].data()[..3], [0x32, 0x32, 0x32]);
+    assert_eq!(writes[1].range(), SPIRange(0x601B, 1));
+    assert_eq!(writes[1].data(), [2]);
+    let reads = profile.read_requests();
+    assert_eq!(reads[1].range(), SPIRange(0x601B, 1));
+
+    let ranges: Vec<_> = FactoryCalibration::default()
+        .chunks()
+        .map(|(range, _)| range)
+        .collect();
+    assert_eq!(ranges, [SPIRange(0x6020, 0x18), SPIRange(0x603D, 0x12)]);
+    assert_eq!(PairingInfo::default().chunks().count(), 1);
+}
@@ -0,0 +1,286 @@
+//! Change of the controller colors, as done by color editors.
+//!
+//! The colors are only read by the controller at boot, so after writing them
+//! and the flag telling to use them, [`ColorWrite`] reads both back and
+//! reboots the controller. The application is asked for confirmation before
+//! the write and before the reboot, which disconnects the controller:
+//!
+//! ```ignore
+//! let mut write = ColorWrite::new(colors, UseSPIColors::IncludingGrip);
+//! while let Some(step) = write.next_step() {
+//!     match step {
+//!         ColorWriteStep::Confirm(point) => {
+//!             if ask_user(point) {
+//!                 write.confirm();
+//!             } else {
+//!                 write.cancel();
+//!             }
+//!         }
+//!         ColorWriteStep::Reboot(request) => {
+//!             send(request)?;
+//!             write.reboot_sent();
+//!         }
+//!         step => write.push(&send_and_wait_reply(step.request().unwrap())?)?,
+//!     }
+//! }
+//! ```
+
+use super::*;
+use crate::{common::HCIState, input::SubcommandReply, output::SubcommandRequest};
+
+/// Point where the application must confirm before continuing.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorConfirmation {
+    /// Before writing to the flash.
+    Write,
+    /// Colors written and verified, before rebooting the controller.
+    Reboot,
+}
+
+/// What to do next, see [`ColorWrite::next_step`].
+#[derive(Copy, Clone, Debug)]
+pub enum ColorWriteStep {
+    Confirm(ColorConfirmation),
+    Write(SPIWriteRequest),
+    /// Read back a write.
+    Verify(SPIReadRequest),
+    /// Not replied to, the controller disconnects.
+    Reboot(SubcommandRequest),
+}
+
+impl ColorWriteStep {
+    /// Subcommand to send, `None` for a confirmation.
+    pub fn request(self) -> Option<SubcommandRequest> {
+        match self {
+            ColorWriteStep::Confirm(_) => None,
+            ColorWriteStep::Write(write) => Some(write.into()),
+            ColorWriteStep::Verify(read) => Some(read.into()),
+            ColorWriteStep::Reboot(request) => Some(request),
+        }
+    }
+}
+
+/// How the controller comes back after the reboot of a [`ColorWrite`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorReboot {
+    /// Reconnect to the last paired host.
+    Reconnect,
+    /// Enter pairing mode, eg. to pair with another host.
+    Pair,
+    /// Reconnect to the last paired host in home mode.
+    ReconnectHome,
+}
+
+impl From<ColorReboot> for HCIState {
+    fn from(reboot: ColorReboot) -> HCIState {
+        match reboot {
+            ColorReboot::Reconnect => HCIState::RebootAndReconnect,
+            ColorReboot::Pair => HCIState::RebootAndPair,
+            ColorReboot::ReconnectHome => HCIState::RebootAndReconnectHome,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorWriteState {
+    /// Waiting for [`ColorWrite::confirm`] or [`ColorWrite::cancel`].
+    Confirming(ColorConfirmation),
+    Writing,
+    Verifying,
+    /// The reboot subcommand is to be sent.
+    Rebooting,
+    /// Rebooted, the new colors are shown after the reconnection.
+    Done,
+    /// Cancelled at a confirmation point.
+    Cancelled,
+    /// A write wasn't acknowledged.
+    WriteFailed,
+    /// The content read back doesn't match the write.
+    VerifyFailed,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Phase {
+    Confirm(ColorConfirmation),
+    Write(usize),
+    Verify(usize),
+    Reboot,
+    Done,
+    Cancelled,
+    WriteFailed,
+    VerifyFailed,
+}
+
+/// Sans-io state machine writing the colors, verifying them and rebooting.
+#[derive(Copy, Clone, Debug)]
+pub struct ColorWrite {
+    writes: [SPIWriteRequest; 2],
+    reboot: ColorReboot,
+    phase: Phase,
+}
+
+impl ColorWrite {
+    /// Write `colors`, shown according to `use_spi_colors`.
+    pub fn new(colors: ControllerColor, use_spi_colors: UseSPIColors) -> ColorWrite {
+        let profile = ColorProfile {
+            colors,
+            use_spi_colors,
+        };
+        // Both regions are writable.
+        let writes = unsafe { profile.write_requests() };
+        ColorWrite {
+            writes: [writes[0], writes[1]],
+            reboot: ColorReboot::Reconnect,
+            phase: Phase::Confirm(ColorConfirmation::Write),
+        }
+    }
+
+    /// Come back as `reboot` says instead of reconnecting to the last host.
+    pub fn with_reboot(mut self, reboot: ColorReboot) -> ColorWrite {
+        self.reboot = reboot;
+        self
+    }
+
+    pub fn state(&self) -> ColorWriteState {
+        match self.phase {
+            Phase::Confirm(point) => ColorWriteState::Confirming(point),
+            Phase::Write(_) => ColorWriteState::Writing,
+            Phase::Verify(_) => ColorWriteState::Verifying,
+            Phase::Reboot => ColorWriteState::Rebooting,
+            Phase::Done => ColorWriteState::Done,
+            Phase::Cancelled => ColorWriteState::Cancelled,
+            Phase::WriteFailed => ColorWriteState::WriteFailed,
+            Phase::VerifyFailed => ColorWriteState::VerifyFailed,
+        }
+    }
+
+    /// Next step, `None` once finished.
+    pub fn next_step(&self) -> Option<ColorWriteStep> {
+        Some(match self.phase {
+            Phase::Confirm(point) => ColorWriteStep::Confirm(point),
+            Phase::Write(i) => ColorWriteStep::Write(self.writes[i]),
+            Phase::Verify(i) => ColorWriteStep::Verify(SPIReadRequest::new(self.writes[i].range())),
+            Phase::Reboot => ColorWriteStep::Reboot(HCIState::from(self.reboot).into()),
+            Phase::Done | Phase::Cancelled | Phase::WriteFailed | Phase::VerifyFailed => {
+                return None
+            }
+        })
+    }
+
+    /// Continue after a confirmation point.
+    pub fn confirm(&mut self) {
+        self.phase = match self.phase {
+            Phase::Confirm(ColorConfirmation::Write) => Phase::Write(0),
+            Phase::Confirm(ColorConfirmation::Reboot) => Phase::Reboot,
+            phase => phase,
+        };
+    }
+
+    /// Stop at a confirmation point.
+    ///
+    /// At the reboot confirmation, the colors are already written and will
+    /// be used at the next reboot of the controller.
+    pub fn cancel(&mut self) {
+        if let Phase::Confirm(_) = self.phase {
+            self.phase = Phase::Cancelled;
+        }
+    }
+
+    /// The reboot subcommand was sent.
+    pub fn reboot_sent(&mut self) {
+        if self.phase == Phase::Reboot {
+            self.phase = Phase::Done;
+        }
+    }
+
+    /// Handle the reply to a write or verification.
+    ///
+    /// Replies to other subcommands are ignored.
+    pub fn push(&mut self, reply: &SubcommandReply) -> Result<(), WrongRangeError> {
+        let last = self.writes.len() - 1;
+        self.phase = match (
+            self.phase,
+            reply.spi_read_result(),
+            reply.is_spi_write_success(),
+        ) {
+            (Phase::Write(i), _, Some(true)) if i == last => Phase::Verify(0),
+            (Phase::Write(i), _, Some(true)) => Phase::Write(i + 1),
+            (Phase::Write(_), _, Some(false)) => Phase::WriteFailed,
+            (Phase::Verify(i), Some(result), _) => {
+                let write = &self.writes[i];
+                if result.range() != write.range() {
+                    return Err(WrongRangeError {
+                        expected: write.range(),
+                        got: result.range(),
+                    });
+                }
+                if result.raw()[..write.data().len()] != *write.data() {
+                    trace!("color verification failed: {:?}", result);
+                    Phase::VerifyFailed
+                } else if i == last {
+                    Phase::Confirm(ColorConfirmation::Reboot)
+                } else {
+                    Phase::Verify(i + 1)
+                }
+            }
+            (phase, _, _) => phase,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn color_write_sequence() {
+    use crate::{input::WhichController, mock::MockController};
+
+    let colors = ControllerColor {
+        body: Color::new(0x32, 0x32, 0x32),
+        buttons: Color::new(0x0f, 0x0f, 0x0f),
+        left_grip: Color::new(0x0a, 0xb9, 0xe6),
+        right_grip: Color::new(0xff, 0x3c, 0x28),
+    };
+    let mut controller = MockController::new(WhichController::ProController);
+    let mut write = ColorWrite::new(colors, UseSPIColors::IncludingGrip);
+    let mut confirmations = Vec::new();
+    let mut rebooted = false;
+    while let Some(step) = write.next_step() {
+        match step {
+            ColorWriteStep::Confirm(point) => {
+                confirmations.push(point);
+                write.confirm();
+            }
+            ColorWriteStep::Reboot(request) => {
+                assert_eq!(
+                    request.set_hci_state().unwrap().try_into(),
+                    Some(HCIState::RebootAndReconnect)
+                );
+                rebooted = true;
+                write.reboot_sent();
+            }
+            step => {
+                controller.send(&step.request().unwrap().into());
+                write
+                    .push(controller.recv().subcmd_reply().unwrap())
+                    .unwrap();
+            }
+        }
+    }
+    assert_eq!(write.state(), ColorWriteState::Done);
+    assert_eq!(
+        confirmations,
+        [ColorConfirmation::Write, ColorConfirmation::Reboot]
+    );
+    assert!(rebooted);
+    assert_eq!(
+        controller.flash().read(ControllerColor::range()).unwrap(),
+        &colors.to_bytes()[..]
+    );
+    assert_eq!(controller.flash().read(UseSPIColors::range()).unwrap(), [2]);
+
+    let mut write = ColorWrite::new(colors, UseSPIColors::No).with_reboot(ColorReboot::Pair);
+    assert_eq!(write.reboot, ColorReboot::Pair);
+    write.cancel();
+    assert_eq!(write.state(), ColorWriteState::Cancelled);
+    assert!(write.next_step().is_none());
+}
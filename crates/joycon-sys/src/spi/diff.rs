@@ -0,0 +1,98 @@
+//! Differences between two SPI flash images.
+//!
+//! Before restoring a backup, the changes can be listed by region:
+//!
+//! ```ignore
+//! for change in diff_images(&current, &backup) {
+//!     println!(
+//!         "{} at 0x{:05x}: {:02x?} -> {:02x?}",
+//!         change.region_name(),
+//!         change.offset,
+//!         change.old,
+//!         change.new,
+//!     );
+//! }
+//! ```
+
+use super::regions::{region_at, SpiRegion};
+
+/// Run of consecutive changed bytes within a region.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ImageChange<'a> {
+    /// Most specific region of the bytes, `None` outside the known regions.
+    pub region: Option<&'static SpiRegion>,
+    /// Address of the first changed byte.
+    pub offset: u32,
+    pub old: &'a [u8],
+    pub new: &'a [u8],
+}
+
+impl ImageChange<'_> {
+    /// Name of the region, `unknown` outside the known regions.
+    pub fn region_name(&self) -> &'static str {
+        self.region.map_or("unknown", |r| r.name)
+    }
+}
+
+/// Iterator returned by [`diff_images`].
+#[derive(Clone, Debug)]
+pub struct ImageDiff<'a> {
+    old: &'a [u8],
+    new: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for ImageDiff<'a> {
+    type Item = ImageChange<'a>;
+
+    fn next(&mut self) -> Option<ImageChange<'a>> {
+        let len = self.old.len().min(self.new.len());
+        let start = (self.position..len).find(|&i| self.old[i] != self.new[i])?;
+        let region = region_at(start as u32);
+        let end = (start..len)
+            .find(|&i| self.old[i] == self.new[i] || region_at(i as u32) != region)
+            .unwrap_or(len);
+        self.position = end;
+        Some(ImageChange {
+            region,
+            offset: start as u32,
+            old: &self.old[start..end],
+            new: &self.new[start..end],
+        })
+    }
+}
+
+/// Changes from the flash image `old` to `new`, both starting at address 0.
+///
+/// A run of changed bytes is split where the most specific region changes.
+/// Only the length common to both images is compared.
+pub fn diff_images<'a>(old: &'a [u8], new: &'a [u8]) -> ImageDiff<'a> {
+    ImageDiff {
+        old,
+        new,
+        position: 0,
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn image_diff() {
+    let old = vec![0xff; 0x6100];
+    let mut new = old.clone();
+    new[0x5000] = 0;
+    // Across the end of the colors.
+    new[0x605a..0x6060].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+
+    let changes: Vec<_> = diff_images(&old, &new).collect();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0].region_name(), "shipment_flag");
+    assert_eq!(changes[0].offset, 0x5000);
+    assert_eq!(changes[0].new, [0]);
+    assert_eq!(changes[1].region_name(), "colors");
+    assert_eq!(changes[1].offset, 0x605a);
+    assert_eq!(changes[1].old, [0xff, 0xff]);
+    assert_eq!(changes[1].new, [1, 2]);
+    assert_eq!(changes[2].region_name(), "factory");
+    assert_eq!(changes[2].offset, 0x605c);
+    assert_eq!(diff_images(&old, &old).count(), 0);
+}
@@ -0,0 +1,1341 @@
+use crate::{
+    common::*,
+    input::{UseSPIColors, WhichController},
+    pairing::{BdAddr, LinkKey},
+};
+use cgmath::{vec2, Vector2, Vector3};
+use std::{convert::TryFrom, fmt, marker::PhantomData, str::FromStr};
+
+mod chunked;
+mod color_write;
+mod diff;
+mod multi_read;
+pub mod regions;
+mod validate;
+mod write_plan;
+
+pub use chunked::*;
+pub use color_write::*;
+pub use diff::*;
+pub use multi_read::*;
+pub use validate::*;
+pub use write_plan::*;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SPIRange(u32, u8);
+
+impl SPIRange {
+    pub unsafe fn new(offset: u32, size: u8) -> SPIRange {
+        assert!(size <= 0x1D);
+        SPIRange(offset, size)
+    }
+
+    pub fn offset(self) -> u32 {
+        self.0
+    }
+
+    pub fn size(self) -> u8 {
+        self.1
+    }
+}
+
+const RANGE_PAIRING_INFO: SPIRange = SPIRange(0x2004, 0x16);
+const RANGE_SERIAL_NUMBER: SPIRange = SPIRange(0x6000, 0x10);
+const RANGE_HARDWARE_INFO: SPIRange = SPIRange(0x6012, 0xA);
+const RANGE_FACTORY_CALIBRATION_SENSORS: SPIRange = SPIRange(0x6020, 0x18);
+const RANGE_FACTORY_CALIBRATION_STICKS: SPIRange = SPIRange(0x603D, 0x12);
+const RANGE_USER_CALIBRATION_STICKS: SPIRange = SPIRange(0x8010, 0x16);
+const RANGE_USER_CALIBRATION_LEFT_STICK: SPIRange = SPIRange(0x8010, 0xB);
+const RANGE_USER_CALIBRATION_RIGHT_STICK: SPIRange = SPIRange(0x801B, 0xB);
+const RANGE_USER_CALIBRATION_SENSORS: SPIRange = SPIRange(0x8026, 0x1A);
+
+const RANGE_CONTROLLER_COLOR_USE_SPI: SPIRange = SPIRange(0x601B, 1);
+const RANGE_CONTROLLER_COLOR: SPIRange = SPIRange(0x6050, 12);
+
+pub trait SPI: TryFrom<SPIReadResult, Error = WrongRangeError> {
+    fn range() -> SPIRange;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WrongRangeError {
+    expected: SPIRange,
+    got: SPIRange,
+}
+
+impl fmt::Display for WrongRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "wrong SPI range: expected {:?}, got {:?}",
+            self.expected, self.got
+        )
+    }
+}
+
+impl std::error::Error for WrongRangeError {}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug)]
+#[allow(dead_code)]
+pub struct SPIReadRequest {
+    offset: U32LE,
+    size: u8,
+}
+
+unsafe impl Wire for SPIReadRequest {}
+
+impl SPIReadRequest {
+    pub fn new(range: SPIRange) -> SPIReadRequest {
+        assert!(range.1 <= 0x1d);
+        SPIReadRequest {
+            offset: range.0.into(),
+            size: range.1,
+        }
+    }
+
+    pub fn range(&self) -> SPIRange {
+        SPIRange(self.offset.into(), self.size)
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone)]
+pub struct SPIWriteRequest {
+    address: U32LE,
+    size: u8,
+    data: SPIData,
+}
+
+unsafe impl Wire for SPIWriteRequest {}
+
+impl SPIWriteRequest {
+    pub unsafe fn new(range: SPIRange, data: &[u8]) -> SPIWriteRequest {
+        assert_eq!(range.1 as usize, data.len());
+        let mut raw = [0; 0x1D];
+        raw[..range.1 as usize].copy_from_slice(data);
+        SPIWriteRequest {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData { raw },
+        }
+    }
+
+    pub fn range(&self) -> SPIRange {
+        SPIRange(self.address.into(), self.size)
+    }
+
+    /// The bytes to write.
+    pub fn data(&self) -> &[u8] {
+        let raw = unsafe { &self.data.raw };
+        &raw[..(self.size as usize).min(raw.len())]
+    }
+
+    /// Zero the serial number and pairing information in the data, see
+    /// [`regions::redact`].
+    pub fn redact(&mut self) {
+        redact_spi_data(self.range(), &mut self.data);
+    }
+}
+
+impl From<ControllerColor> for SPIWriteRequest {
+    fn from(color: ControllerColor) -> SPIWriteRequest {
+        let range = ControllerColor::range();
+        assert!(range.1 <= 0x1d);
+        SPIWriteRequest {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData { color },
+        }
+    }
+}
+
+impl SPI for UseSPIColors {
+    fn range() -> SPIRange {
+        RANGE_CONTROLLER_COLOR_USE_SPI
+    }
+}
+
+impl From<UseSPIColors> for SPIWriteRequest {
+    fn from(use_spi_colors: UseSPIColors) -> SPIWriteRequest {
+        let range = UseSPIColors::range();
+        assert!(range.1 <= 0x1d);
+        SPIWriteRequest {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData {
+                use_spi_colors: use_spi_colors.into(),
+            },
+        }
+    }
+}
+
+impl TryFrom<SPIReadResult> for UseSPIColors {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.use_spi_colors.try_into().unwrap() })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+impl fmt::Debug for SPIWriteRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = f.debug_struct("SPIWriteRequest");
+        dbg_spi_data(&mut out, self.address, self.size, &self.data);
+        out.finish()
+    }
+}
+
+fn dbg_spi_data(out: &mut fmt::DebugStruct, address: U32LE, size: u8, data: &SPIData) {
+    let range = SPIRange(address.into(), size);
+    let raw = unsafe { &data.raw[..(size as usize).min(0x1D)] };
+    out.field("address", &address).field("size", &size);
+    let region = match regions::region_of(range) {
+        Some(region) => region,
+        None => {
+            // Annotate with the enclosing region, if any.
+            if let Some(region) = regions::region_at(range.0) {
+                out.field("in", &region.name);
+            }
+            out.field("raw", &HexBytes(raw));
+            return;
+        }
+    };
+    unsafe {
+        match range {
+            RANGE_PAIRING_INFO => out.field(region.name, &data.pairing_info),
+            RANGE_SERIAL_NUMBER => out.field(region.name, &data.serial_number),
+            RANGE_HARDWARE_INFO => out.field(region.name, &data.hardware_info),
+            RANGE_CONTROLLER_COLOR_USE_SPI => out.field(region.name, &data.use_spi_colors),
+            RANGE_FACTORY_CALIBRATION_SENSORS => out.field(region.name, &data.imu_factory_calib),
+            RANGE_FACTORY_CALIBRATION_STICKS => out.field(region.name, &data.sticks_factory_calib),
+            RANGE_CONTROLLER_COLOR => out.field(region.name, &data.color),
+            RANGE_USER_CALIBRATION_STICKS => out.field(region.name, &data.sticks_user_calib),
+            RANGE_USER_CALIBRATION_LEFT_STICK => {
+                out.field(region.name, &data.left_stick_user_calib)
+            }
+            RANGE_USER_CALIBRATION_RIGHT_STICK => {
+                out.field(region.name, &data.right_stick_user_calib)
+            }
+            RANGE_USER_CALIBRATION_SENSORS => out.field(region.name, &data.imu_user_calib),
+            _ => out.field(region.name, &HexBytes(raw)),
+        };
+    }
+}
+
+/// Bytes printed as a compact hex string.
+struct HexBytes<'a>(&'a [u8]);
+
+impl fmt::Debug for HexBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone)]
+pub struct SPIReadResult {
+    address: U32LE,
+    size: u8,
+    data: SPIData,
+}
+
+unsafe impl Wire for SPIReadResult {}
+
+impl SPIReadResult {
+    /// Result of reading `data` at `range`, as sent by the controller.
+    pub fn new(range: SPIRange, data: &[u8]) -> SPIReadResult {
+        assert_eq!(range.1 as usize, data.len());
+        let mut raw = [0; 0x1D];
+        raw[..data.len()].copy_from_slice(data);
+        SPIReadResult {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData { raw },
+        }
+    }
+
+    pub fn range(&self) -> SPIRange {
+        SPIRange(self.address.into(), self.size)
+    }
+
+    pub fn raw(&self) -> [u8; 0x1D] {
+        unsafe { self.data.raw }
+    }
+
+    /// Zero the serial number and pairing information in the data, see
+    /// [`regions::redact`].
+    pub fn redact(&mut self) {
+        redact_spi_data(self.range(), &mut self.data);
+    }
+}
+
+fn redact_spi_data(range: SPIRange, data: &mut SPIData) {
+    let raw = unsafe { &mut data.raw };
+    let len = (range.size() as usize).min(raw.len());
+    regions::redact(range.offset(), &mut raw[..len]);
+}
+
+impl fmt::Debug for SPIReadResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = f.debug_struct("SPIReadResult");
+        dbg_spi_data(&mut out, self.address, self.size, &self.data);
+        out.finish()
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug)]
+pub struct SPIWriteResult {
+    status: u8,
+}
+
+unsafe impl Wire for SPIWriteResult {}
+
+impl SPIWriteResult {
+    pub fn new(success: bool) -> SPIWriteResult {
+        SPIWriteResult {
+            status: if success { 0 } else { 1 },
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.status == 0
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone)]
+union SPIData {
+    pairing_info: PairingInfo,
+    serial_number: SerialNumber,
+    hardware_info: HardwareInfo,
+    sticks_factory_calib: SticksCalibration,
+    sticks_user_calib: UserSticksCalibration,
+    left_stick_user_calib: UserStickCalibration<LeftOrder>,
+    right_stick_user_calib: UserStickCalibration<RightOrder>,
+    imu_factory_calib: SensorCalibration,
+    imu_user_calib: UserSensorCalibration,
+    color: ControllerColor,
+    use_spi_colors: RawId<UseSPIColors>,
+    raw: [u8; 0x1D],
+}
+
+// TODO: clean
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SticksCalibration {
+    pub left: LeftStickCalibration,
+    pub right: RightStickCalibration,
+}
+
+unsafe impl Wire for SticksCalibration {}
+
+impl SPI for SticksCalibration {
+    fn range() -> SPIRange {
+        RANGE_FACTORY_CALIBRATION_STICKS
+    }
+}
+
+impl TryFrom<SPIReadResult> for SticksCalibration {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.sticks_factory_calib })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UserSticksCalibration {
+    pub left: UserStickCalibration<LeftOrder>,
+    pub right: UserStickCalibration<RightOrder>,
+}
+
+unsafe impl Wire for UserSticksCalibration {}
+
+/// Pack two 12-bit values like the stick calibration does.
+fn encode_axes(x: u16, y: u16) -> [u8; 3] {
+    [
+        x as u8,
+        ((x >> 8) & 0xF) as u8 | ((y & 0xF) << 4) as u8,
+        (y >> 4) as u8,
+    ]
+}
+
+/// Order of the max, center and min values in a [`StickCalibration`].
+pub trait StickOrder: Copy + Default {
+    /// Offsets of the max, center and min values.
+    const OFFSETS: [usize; 3];
+    /// Range of the user calibration of the stick using this order.
+    const USER_RANGE: SPIRange;
+}
+
+/// Order of the left stick calibration: max, center, min.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct LeftOrder;
+
+impl StickOrder for LeftOrder {
+    const OFFSETS: [usize; 3] = [0, 3, 6];
+    const USER_RANGE: SPIRange = RANGE_USER_CALIBRATION_LEFT_STICK;
+}
+
+/// Order of the right stick calibration: center, min, max.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct RightOrder;
+
+impl StickOrder for RightOrder {
+    const OFFSETS: [usize; 3] = [6, 0, 3];
+    const USER_RANGE: SPIRange = RANGE_USER_CALIBRATION_RIGHT_STICK;
+}
+
+/// Calibration of a stick, the extremes being stored relative to the center.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct StickCalibration<O> {
+    raw: [u8; 9],
+    order: PhantomData<O>,
+}
+
+unsafe impl<O: Copy> Wire for StickCalibration<O> {}
+
+pub type LeftStickCalibration = StickCalibration<LeftOrder>;
+pub type RightStickCalibration = StickCalibration<RightOrder>;
+
+impl<O: StickOrder> StickCalibration<O> {
+    /// Build a calibration from the extreme and center raw positions.
+    pub fn new(min: (u16, u16), center: (u16, u16), max: (u16, u16)) -> StickCalibration<O> {
+        let mut calib = StickCalibration {
+            raw: [0; 9],
+            order: PhantomData,
+        };
+        calib.set(
+            0,
+            max.0.saturating_sub(center.0),
+            max.1.saturating_sub(center.1),
+        );
+        calib.set(1, center.0, center.1);
+        calib.set(
+            2,
+            center.0.saturating_sub(min.0),
+            center.1.saturating_sub(min.1),
+        );
+        calib
+    }
+
+    fn set(&mut self, index: usize, x: u16, y: u16) {
+        let offset = O::OFFSETS[index];
+        self.raw[offset..offset + 3].copy_from_slice(&encode_axes(x, y));
+    }
+
+    /// Unpack the two 12-bit values of the max, center or min.
+    fn get(&self, index: usize) -> (u16, u16) {
+        let offset = O::OFFSETS[index];
+        let raw = &self.raw[offset..offset + 3];
+        (
+            (((raw[1] as u16) << 8) & 0xF00) | raw[0] as u16,
+            ((raw[2] as u16) << 4) | (raw[1] >> 4) as u16,
+        )
+    }
+
+    pub fn max(&self) -> (u16, u16) {
+        let center = self.center();
+        let max = self.get(0);
+        ((center.0 + max.0).min(0xFFF), (center.1 + max.1).min(0xFFF))
+    }
+
+    pub fn center(&self) -> (u16, u16) {
+        self.get(1)
+    }
+
+    pub fn min(&self) -> (u16, u16) {
+        let center = self.center();
+        let min = self.get(2);
+        (
+            center.0.saturating_sub(min.0),
+            center.1.saturating_sub(min.1),
+        )
+    }
+
+    pub fn value_from_raw(&self, x: u16, y: u16) -> Vector2<f64> {
+        let min = self.min();
+        let center = self.center();
+        let max = self.max();
+        let rel_x = x.max(min.0).min(max.0) as f64 - center.0 as f64;
+        let rel_y = y.max(min.1).min(max.1) as f64 - center.1 as f64;
+
+        vec2(
+            if rel_x >= 0. {
+                rel_x / (max.0 as f64 - center.0 as f64)
+            } else {
+                rel_x / (center.0 as f64 - min.0 as f64)
+            },
+            if rel_y >= 0. {
+                rel_y / (max.1 as f64 - center.1 as f64)
+            } else {
+                rel_y / (center.1 as f64 - min.1 as f64)
+            },
+        )
+    }
+}
+
+impl<O: StickOrder> fmt::Debug for StickCalibration<O> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StickCalibration")
+            .field("min", &self.min())
+            .field("center", &self.center())
+            .field("max", &self.max())
+            .finish()
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct UserStickCalibration<O> {
+    magic: [u8; 2],
+    calib: StickCalibration<O>,
+}
+
+unsafe impl<O: Copy> Wire for UserStickCalibration<O> {}
+impl SPI for UserSticksCalibration {
+    fn range() -> SPIRange {
+        RANGE_USER_CALIBRATION_STICKS
+    }
+}
+
+impl TryFrom<SPIReadResult> for UserSticksCalibration {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.sticks_user_calib })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+/// Only one side, for the Joy-Cons which have a single stick.
+impl<O: StickOrder> SPI for UserStickCalibration<O> {
+    fn range() -> SPIRange {
+        O::USER_RANGE
+    }
+}
+
+impl<O: StickOrder> TryFrom<SPIReadResult> for UserStickCalibration<O> {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            let raw = unsafe { value.data.raw };
+            Ok(unsafe { std::ptr::read_unaligned(raw.as_ptr() as *const Self) })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+impl<O: StickOrder> UserStickCalibration<O> {
+    /// Remove the user calibration, the factory one being used instead.
+    pub fn reset() -> UserStickCalibration<O> {
+        UserStickCalibration {
+            magic: USER_NO_CALIB_MAGIC,
+            calib: StickCalibration::default(),
+        }
+    }
+
+    pub fn calib(&self) -> Option<StickCalibration<O>> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib)
+        } else {
+            None
+        }
+    }
+
+    pub fn max(&self) -> Option<(u16, u16)> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib.max())
+        } else {
+            None
+        }
+    }
+
+    pub fn center(&self) -> Option<(u16, u16)> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib.center())
+        } else {
+            None
+        }
+    }
+
+    pub fn min(&self) -> Option<(u16, u16)> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib.min())
+        } else {
+            None
+        }
+    }
+}
+
+impl<O: StickOrder> From<StickCalibration<O>> for UserStickCalibration<O> {
+    fn from(calib: StickCalibration<O>) -> Self {
+        UserStickCalibration {
+            magic: USER_CALIB_MAGIC,
+            calib,
+        }
+    }
+}
+
+/// Write only one side, leaving the other one untouched.
+impl<O: StickOrder> From<UserStickCalibration<O>> for SPIWriteRequest {
+    fn from(calib: UserStickCalibration<O>) -> Self {
+        let range = O::USER_RANGE;
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &calib as *const _ as *const u8,
+                std::mem::size_of_val(&calib),
+            )
+        };
+        assert_eq!(bytes.len(), range.1 as usize);
+        unsafe { SPIWriteRequest::new(range, bytes) }
+    }
+}
+
+impl UserSticksCalibration {
+    pub fn reset() -> UserSticksCalibration {
+        UserSticksCalibration {
+            left: UserStickCalibration::reset(),
+            right: UserStickCalibration::reset(),
+        }
+    }
+}
+
+impl From<UserSticksCalibration> for SPIWriteRequest {
+    fn from(calib: UserSticksCalibration) -> Self {
+        let range = UserSticksCalibration::range();
+        SPIWriteRequest {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData {
+                sticks_user_calib: calib,
+            },
+        }
+    }
+}
+
+impl<O: StickOrder> fmt::Debug for UserStickCalibration<O> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.magic == USER_CALIB_MAGIC {
+            f.write_fmt(format_args!("{:?}", self.calib))
+        } else {
+            f.write_str("NoUserStickCalibration")
+        }
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SensorCalibration {
+    acc_orig: [I16LE; 3],
+    acc_sens: [I16LE; 3],
+    gyro_orig: [I16LE; 3],
+    gyro_sens: [I16LE; 3],
+}
+
+unsafe impl Wire for SensorCalibration {}
+
+impl SensorCalibration {
+    pub fn reset() -> SensorCalibration {
+        let zero = [I16LE([0; 2]); 3];
+        SensorCalibration {
+            acc_orig: zero,
+            acc_sens: zero,
+            gyro_orig: zero,
+            gyro_sens: zero,
+        }
+    }
+
+    pub fn acc_offset(&self) -> Vector3<f64> {
+        vector_from_raw(self.acc_orig)
+    }
+
+    pub fn set_acc_offset(&mut self, offset: Vector3<f64>) {
+        self.acc_orig = raw_from_vector(offset);
+    }
+
+    pub fn acc_factor(&self) -> Vector3<f64> {
+        vector_from_raw(self.acc_sens)
+    }
+
+    pub fn set_acc_factor(&mut self, factor: Vector3<f64>) {
+        self.acc_sens = raw_from_vector(factor);
+    }
+
+    pub fn gyro_offset(&self) -> Vector3<f64> {
+        vector_from_raw(self.gyro_orig)
+    }
+
+    pub fn set_gyro_offset(&mut self, offset: Vector3<f64>) {
+        self.gyro_orig = raw_from_vector(offset);
+    }
+
+    pub fn gyro_factor(&self) -> Vector3<f64> {
+        vector_from_raw(self.gyro_sens)
+    }
+
+    pub fn set_gyro_factor(&mut self, factor: Vector3<f64>) {
+        self.gyro_sens = raw_from_vector(factor);
+    }
+
+    /// Accelerometer offset as stored, without conversion.
+    pub fn acc_offset_i16(&self) -> [i16; 3] {
+        i16s_from_raw(self.acc_orig)
+    }
+
+    pub fn set_acc_offset_i16(&mut self, offset: [i16; 3]) {
+        self.acc_orig = raw_from_i16s(offset);
+    }
+
+    pub fn acc_factor_i16(&self) -> [i16; 3] {
+        i16s_from_raw(self.acc_sens)
+    }
+
+    pub fn set_acc_factor_i16(&mut self, factor: [i16; 3]) {
+        self.acc_sens = raw_from_i16s(factor);
+    }
+
+    pub fn gyro_offset_i16(&self) -> [i16; 3] {
+        i16s_from_raw(self.gyro_orig)
+    }
+
+    pub fn set_gyro_offset_i16(&mut self, offset: [i16; 3]) {
+        self.gyro_orig = raw_from_i16s(offset);
+    }
+
+    pub fn gyro_factor_i16(&self) -> [i16; 3] {
+        i16s_from_raw(self.gyro_sens)
+    }
+
+    pub fn set_gyro_factor_i16(&mut self, factor: [i16; 3]) {
+        self.gyro_sens = raw_from_i16s(factor);
+    }
+
+    /// Same as [`acc_offset`](#method.acc_offset), for callers not using
+    /// cgmath.
+    pub fn acc_offset_f32(&self) -> [f32; 3] {
+        f32s_from_raw(self.acc_orig)
+    }
+
+    pub fn acc_factor_f32(&self) -> [f32; 3] {
+        f32s_from_raw(self.acc_sens)
+    }
+
+    pub fn gyro_offset_f32(&self) -> [f32; 3] {
+        f32s_from_raw(self.gyro_orig)
+    }
+
+    pub fn gyro_factor_f32(&self) -> [f32; 3] {
+        f32s_from_raw(self.gyro_sens)
+    }
+}
+
+impl SPI for SensorCalibration {
+    fn range() -> SPIRange {
+        RANGE_FACTORY_CALIBRATION_SENSORS
+    }
+}
+
+impl TryFrom<SPIReadResult> for SensorCalibration {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.imu_factory_calib })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+const USER_CALIB_MAGIC: [u8; 2] = [0xB2, 0xA1];
+const USER_NO_CALIB_MAGIC: [u8; 2] = [0xFF; 2];
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct UserSensorCalibration {
+    magic: [u8; 2],
+    calib: SensorCalibration,
+}
+
+unsafe impl Wire for UserSensorCalibration {}
+
+impl UserSensorCalibration {
+    pub fn reset() -> UserSensorCalibration {
+        UserSensorCalibration {
+            magic: USER_NO_CALIB_MAGIC,
+            calib: SensorCalibration::reset(),
+        }
+    }
+}
+
+impl SPI for UserSensorCalibration {
+    fn range() -> SPIRange {
+        RANGE_USER_CALIBRATION_SENSORS
+    }
+}
+impl From<SensorCalibration> for UserSensorCalibration {
+    fn from(calib: SensorCalibration) -> Self {
+        UserSensorCalibration {
+            magic: USER_CALIB_MAGIC,
+            calib,
+        }
+    }
+}
+
+impl From<UserSensorCalibration> for SPIWriteRequest {
+    fn from(calib: UserSensorCalibration) -> Self {
+        let range = UserSensorCalibration::range();
+        SPIWriteRequest {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData {
+                imu_user_calib: calib,
+            },
+        }
+    }
+}
+
+impl TryFrom<SPIReadResult> for UserSensorCalibration {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.imu_user_calib })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+impl UserSensorCalibration {
+    pub fn calib(&self) -> Option<SensorCalibration> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib)
+        } else {
+            None
+        }
+    }
+    pub fn acc_offset(&self) -> Option<Vector3<f64>> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib.acc_offset())
+        } else {
+            None
+        }
+    }
+
+    pub fn acc_factor(&self) -> Option<Vector3<f64>> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib.acc_factor())
+        } else {
+            None
+        }
+    }
+
+    pub fn gyro_offset(&self) -> Option<Vector3<f64>> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib.gyro_offset())
+        } else {
+            None
+        }
+    }
+
+    pub fn gyro_factor(&self) -> Option<Vector3<f64>> {
+        if self.magic == USER_CALIB_MAGIC {
+            Some(self.calib.gyro_factor())
+        } else {
+            None
+        }
+    }
+
+    pub fn acc_offset_f32(&self) -> Option<[f32; 3]> {
+        self.calib().map(|c| c.acc_offset_f32())
+    }
+
+    pub fn acc_factor_f32(&self) -> Option<[f32; 3]> {
+        self.calib().map(|c| c.acc_factor_f32())
+    }
+
+    pub fn gyro_offset_f32(&self) -> Option<[f32; 3]> {
+        self.calib().map(|c| c.gyro_offset_f32())
+    }
+
+    pub fn gyro_factor_f32(&self) -> Option<[f32; 3]> {
+        self.calib().map(|c| c.gyro_factor_f32())
+    }
+}
+
+/// Host the controller was last paired with.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct PairingInfo {
+    host_address: [u8; 6],
+    link_key: [u8; 16],
+}
+
+unsafe impl Wire for PairingInfo {}
+
+impl PairingInfo {
+    pub fn new(host_address: BdAddr, link_key: LinkKey) -> PairingInfo {
+        PairingInfo {
+            host_address: host_address.to_spi(),
+            link_key: link_key.to_spi(),
+        }
+    }
+
+    pub fn host_address(&self) -> BdAddr {
+        BdAddr::from_spi(self.host_address)
+    }
+
+    pub fn link_key(&self) -> LinkKey {
+        LinkKey::from_spi(self.link_key)
+    }
+}
+
+impl fmt::Debug for PairingInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PairingInfo")
+            .field("host_address", &self.host_address())
+            .field("link_key", &self.link_key())
+            .finish()
+    }
+}
+
+impl SPI for PairingInfo {
+    fn range() -> SPIRange {
+        RANGE_PAIRING_INFO
+    }
+}
+
+impl TryFrom<SPIReadResult> for PairingInfo {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.pairing_info })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+/// Serial number of the controller, as printed on its back.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SerialNumber([u8; 0x10]);
+
+unsafe impl Wire for SerialNumber {}
+
+impl SerialNumber {
+    /// `None` if the controller has no serial number.
+    pub fn as_str(&self) -> Option<&str> {
+        // No serial number if the first byte isn't ASCII.
+        if self.0[0] >= 0x80 {
+            return None;
+        }
+        // Padded with NUL bytes, and at most 15 characters are used.
+        let raw = &self.0[..0xf];
+        let start = raw.iter().position(|&b| b != 0)?;
+        let end = raw.iter().rposition(|&b| b != 0)? + 1;
+        std::str::from_utf8(&raw[start..end])
+            .ok()
+            .filter(|s| s.is_ascii())
+    }
+}
+
+impl fmt::Debug for SerialNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SerialNumber").field(&self.as_str()).finish()
+    }
+}
+
+impl SPI for SerialNumber {
+    fn range() -> SPIRange {
+        RANGE_SERIAL_NUMBER
+    }
+}
+
+impl TryFrom<SPIReadResult> for SerialNumber {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.serial_number })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+/// Hardware information stored after the serial number.
+///
+/// Only the device type and the color flag are documented, the other bytes
+/// are kept for inventory tools.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct HardwareInfo {
+    device_type: RawId<WhichController>,
+    unknown: [u8; 8],
+    use_spi_colors: RawId<UseSPIColors>,
+}
+
+unsafe impl Wire for HardwareInfo {}
+
+impl HardwareInfo {
+    pub fn device_type(&self) -> Option<WhichController> {
+        self.device_type.try_into()
+    }
+
+    /// Undocumented bytes between the device type and the color flag,
+    /// `0xA0` then `0xFF` on official controllers.
+    pub fn unknown(&self) -> [u8; 8] {
+        self.unknown
+    }
+
+    pub fn use_spi_colors(&self) -> Option<UseSPIColors> {
+        self.use_spi_colors.try_into()
+    }
+}
+
+impl fmt::Debug for HardwareInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HardwareInfo")
+            .field("device_type", &self.device_type)
+            .field("unknown", &HexBytes(&self.unknown))
+            .field("use_spi_colors", &self.use_spi_colors)
+            .finish()
+    }
+}
+
+impl SPI for HardwareInfo {
+    fn range() -> SPIRange {
+        RANGE_HARDWARE_INFO
+    }
+}
+
+impl TryFrom<SPIReadResult> for HardwareInfo {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.hardware_info })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Color(u8, u8, u8);
+
+unsafe impl Wire for Color {}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Color {
+        Color(r, g, b)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// Parsed from `rrggbb`, with an optional leading `#`.
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('#').unwrap_or(s);
+        if s.len() != 6 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseColorError::InvalidColor);
+        }
+        let channel = |i: usize| u8::from_str_radix(&s[i..i + 2], 16).unwrap();
+        Ok(Color(channel(0), channel(2), channel(4)))
+    }
+}
+
+/// Error parsing a [`Color`] or a [`ControllerColor`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ParseColorError {
+    /// A color isn't 6 hex digits.
+    InvalidColor,
+    /// A profile doesn't have exactly 4 colors.
+    WrongColorCount(usize),
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseColorError::InvalidColor => f.write_str("expected a color as rrggbb"),
+            ParseColorError::WrongColorCount(n) => write!(
+                f,
+                "expected 4 colors as body/buttons/leftgrip/rightgrip, got {}",
+                n
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct ControllerColor {
+    pub body: Color,
+    pub buttons: Color,
+    pub left_grip: Color,
+    pub right_grip: Color,
+}
+
+unsafe impl Wire for ControllerColor {}
+
+/// Formatted as `#body/#buttons/#leftgrip/#rightgrip`, eg.
+/// `#323232/#0f0f0f/#0ab9e6/#ff3c28`.
+impl fmt::Display for ControllerColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ControllerColor {
+            body,
+            buttons,
+            left_grip,
+            right_grip,
+        } = *self;
+        write!(f, "{}/{}/{}/{}", body, buttons, left_grip, right_grip)
+    }
+}
+
+/// Parsed from the [`Display`](#impl-Display) syntax, the `#` being optional.
+impl FromStr for ControllerColor {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colors = s
+            .split('/')
+            .map(|c| c.trim().parse())
+            .collect::<Result<Vec<Color>, _>>()?;
+        match colors[..] {
+            [body, buttons, left_grip, right_grip] => Ok(ControllerColor {
+                body,
+                buttons,
+                left_grip,
+                right_grip,
+            }),
+            _ => Err(ParseColorError::WrongColorCount(colors.len())),
+        }
+    }
+}
+
+impl SPI for ControllerColor {
+    fn range() -> SPIRange {
+        RANGE_CONTROLLER_COLOR
+    }
+}
+
+impl TryFrom<SPIReadResult> for ControllerColor {
+    type Error = WrongRangeError;
+
+    fn try_from(value: SPIReadResult) -> Result<Self, Self::Error> {
+        if value.range() == Self::range() {
+            Ok(unsafe { value.data.color })
+        } else {
+            Err(WrongRangeError {
+                expected: Self::range(),
+                got: value.range(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn spi_debug_by_region() {
+    let read = |range: SPIRange| {
+        let mut raw = [0; 0x1D];
+        raw[0] = 0x58;
+        let result = SPIReadResult {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData { raw },
+        };
+        format!("{:?}", result)
+    };
+    assert!(read(RANGE_SERIAL_NUMBER).contains("serial_number: SerialNumber(Some(\"X\"))"));
+    assert!(read(SPIRange(0x5000, 1)).contains("shipment_flag: 58"));
+    assert!(read(SPIRange(0x6010, 2)).contains("in: \"factory\", raw: 5800"));
+}
+
+#[cfg(test)]
+#[test]
+fn stick_calibration() {
+    fn check<O: StickOrder>() {
+        let calib = StickCalibration::<O>::new((500, 600), (2000, 2100), (3500, 3400));
+        assert_eq!(calib.min(), (500, 600));
+        assert_eq!(calib.center(), (2000, 2100));
+        assert_eq!(calib.max(), (3500, 3400));
+        assert_eq!(calib.value_from_raw(2000, 2100), vec2(0., 0.));
+        assert_eq!(calib.value_from_raw(3500, 600), vec2(1., -1.));
+        assert_eq!(calib.value_from_raw(1250, 4000), vec2(-0.5, 1.));
+    }
+    check::<LeftOrder>();
+    check::<RightOrder>();
+
+    let left = LeftStickCalibration::new((0, 0), (0x123, 0x456), (0, 0));
+    assert_eq!(left.raw[3..6], [0x23, 0x61, 0x45]);
+    let right = RightStickCalibration::new((0, 0), (0x123, 0x456), (0, 0));
+    assert_eq!(right.raw[..3], [0x23, 0x61, 0x45]);
+}
+
+#[cfg(test)]
+#[test]
+fn user_stick_writes() {
+    let calib = RightStickCalibration::new((500, 600), (2000, 2100), (3500, 3400));
+    let request = SPIWriteRequest::from(UserStickCalibration::from(calib));
+    assert_eq!(u32::from(request.address), 0x801B);
+    assert_eq!(request.size, 11);
+    let raw = unsafe { request.data.raw };
+    assert_eq!(raw[..2], USER_CALIB_MAGIC);
+    assert_eq!(raw[2..11], calib.raw);
+
+    let both = SPIWriteRequest::from(UserSticksCalibration::reset());
+    assert_eq!(
+        SPIRange(both.address.into(), both.size),
+        RANGE_USER_CALIBRATION_STICKS
+    );
+    let raw = unsafe { both.data.raw };
+    assert_eq!(raw[11..13], USER_NO_CALIB_MAGIC);
+}
+
+#[cfg(test)]
+#[test]
+fn user_stick_read() {
+    let calib = LeftStickCalibration::new((500, 600), (2000, 2100), (3500, 3400));
+    let write = SPIWriteRequest::from(UserStickCalibration::from(calib));
+    let read = SPIReadResult {
+        address: write.address,
+        size: write.size,
+        data: write.data,
+    };
+    let user = UserStickCalibration::<LeftOrder>::try_from(read).unwrap();
+    assert!(user == UserStickCalibration::from(calib));
+    assert!(UserStickCalibration::<RightOrder>::try_from(read).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn hardware_info() {
+    let bytes = [0x03, 0xa0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    let result = SPIReadResult::new(HardwareInfo::range(), &bytes);
+    let info = HardwareInfo::try_from(result).unwrap();
+    assert_eq!(info.device_type(), Some(WhichController::ProController));
+    assert_eq!(info.unknown()[0], 0xa0);
+    assert_eq!(info.use_spi_colors(), Some(UseSPIColors::WithoutGrip));
+    assert!(HardwareInfo::try_from(SPIReadResult::new(SerialNumber::range(), &[0; 16])).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn controller_color_str() {
+    let color = ControllerColor {
+        body: Color::new(0x32, 0x32, 0x32),
+        buttons: Color::new(0x0f, 0x0f, 0x0f),
+        left_grip: Color::new(0x0a, 0xb9, 0xe6),
+        right_grip: Color::new(0xff, 0x3c, 0x28),
+    };
+    assert_eq!(color.to_string(), "#323232/#0f0f0f/#0ab9e6/#ff3c28");
+    assert_eq!(color.to_string().parse(), Ok(color));
+    assert_eq!("323232/0F0F0F/0ab9e6/ff3c28".parse(), Ok(color));
+    assert_eq!(
+        "323232/0f0f0f".parse::<ControllerColor>(),
+        Err(ParseColorError::WrongColorCount(2))
+    );
+    assert_eq!(
+        "323232/0f0f0f/0ab9e6/ff3c2".parse::<ControllerColor>(),
+        Err(ParseColorError::InvalidColor)
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn sensor_calibration_i16() {
+    let mut calib = SensorCalibration::from_bytes(&[
+        0x01, 0x00, 0xff, 0xff, 0x00, 0x80, 0x00, 0x40, 0x00, 0x40, 0x00, 0x40, 0, 0, 0, 0, 0, 0,
+        0x3b, 0x34, 0x3b, 0x34, 0x3b, 0x34,
+    ])
+    .unwrap();
+    assert_eq!(calib.acc_offset_i16(), [1, -1, i16::MIN]);
+    assert_eq!(calib.acc_factor_i16(), [0x4000; 3]);
+    assert_eq!(calib.gyro_factor_i16(), [0x343b; 3]);
+    calib.set_gyro_offset_i16([-2, 0, 0x1234]);
+    assert_eq!(calib.gyro_offset_i16(), [-2, 0, 0x1234]);
+    assert_eq!(calib.to_bytes()[12..18], [0xfe, 0xff, 0, 0, 0x34, 0x12]);
+    assert_eq!(calib.gyro_offset_f32(), [-2., 0., 4660.]);
+    assert_eq!(calib.acc_offset_f32(), [1., -1., -32768.]);
+
+    assert_eq!(UserSensorCalibration::reset().gyro_factor_f32(), None);
+    let user = UserSensorCalibration::from(calib);
+    assert_eq!(user.gyro_factor_f32(), Some([13371.; 3]));
+}
@@ -0,0 +1,236 @@
+//! Reading of SPI blobs larger than a single read.
+//!
+//! A [`SPIReadRequest`] returns at most `0x1D` bytes. [`MultiRangeRead`] splits
+//! a logical [`SpiBlock`] into as many requests as needed and reassembles the
+//! results into a [`SpiBlockData`].
+//!
+//! ```ignore
+//! let mut read = MultiRangeRead::new(SpiBlock::ControllerColors);
+//! while let Some(request) = read.next_request() {
+//!     let result = send_and_wait_reply(request)?;
+//!     read.push(&result)?;
+//! }
+//! let color: ControllerColor = read.finish().unwrap().get().unwrap();
+//! ```
+
+use super::*;
+
+/// Maximum size of a single SPI read.
+const MAX_READ_SIZE: u32 = 0x1D;
+
+/// Logical blobs of the SPI flash, possibly spread over several ranges.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SpiBlock {
+    /// Pairing information of the last paired host.
+    PairingInfo,
+    /// Colors of the controller and whether they should be used.
+    ControllerColors,
+    /// Factory calibration of the sensors and the sticks, with the stick
+    /// parameters.
+    FactoryCalibration,
+    /// User calibration of the sticks and the sensors.
+    UserCalibration,
+    /// The whole 512KB flash.
+    Full,
+}
+
+impl SpiBlock {
+    /// `(offset, size)` of the contiguous segments making up the block.
+    pub fn segments(self) -> &'static [(u32, u32)] {
+        match self {
+            SpiBlock::PairingInfo => &[(0x2000, 0x26)],
+            SpiBlock::ControllerColors => &[(0x601B, 1), (0x6050, 0xC)],
+            SpiBlock::FactoryCalibration => &[
+                (0x6020, 0x18),
+                (0x603D, 0x12),
+                (0x6080, 0x18),
+                (0x6098, 0x12),
+            ],
+            SpiBlock::UserCalibration => &[(0x8010, 0x34)],
+            SpiBlock::Full => &[(0, 0x80000)],
+        }
+    }
+
+    /// Ranges of the single reads covering the block, for callers handling
+    /// the data as it arrives instead of through [`MultiRangeRead`].
+    pub fn ranges(self) -> impl Iterator<Item = SPIRange> {
+        self.segments().iter().flat_map(|&(offset, size)| {
+            (0..size)
+                .step_by(MAX_READ_SIZE as usize)
+                .map(move |done| SPIRange(offset + done, (size - done).min(MAX_READ_SIZE) as u8))
+        })
+    }
+}
+
+/// Planner and accumulator of the reads of a [`SpiBlock`].
+#[derive(Clone, Debug)]
+pub struct MultiRangeRead {
+    segments: Vec<(u32, Vec<u8>)>,
+    sizes: Vec<u32>,
+    segment: usize,
+}
+
+impl MultiRangeRead {
+    pub fn new(block: SpiBlock) -> MultiRangeRead {
+        MultiRangeRead::from_segments(block.segments())
+    }
+
+    /// Read arbitrary `(offset, size)` segments.
+    pub fn from_segments(segments: &[(u32, u32)]) -> MultiRangeRead {
+        MultiRangeRead {
+            segments: segments
+                .iter()
+                .map(|&(offset, size)| (offset, Vec::with_capacity(size as usize)))
+                .collect(),
+            sizes: segments.iter().map(|&(_, size)| size).collect(),
+            segment: 0,
+        }
+    }
+
+    /// Range of the next read, `None` once the block is complete.
+    pub fn next_range(&self) -> Option<SPIRange> {
+        let (offset, data) = self.segments.get(self.segment)?;
+        let done = data.len() as u32;
+        let size = (self.sizes[self.segment] - done).min(MAX_READ_SIZE);
+        Some(SPIRange(offset + done, size as u8))
+    }
+
+    pub fn next_request(&self) -> Option<SPIReadRequest> {
+        self.next_range().map(SPIReadRequest::new)
+    }
+
+    /// Store the result of the request returned by
+    /// [`next_request`](Self::next_request).
+    pub fn push(&mut self, result: &SPIReadResult) -> Result<(), WrongRangeError> {
+        self.push_raw(result.range(), &result.raw())
+    }
+
+    /// Store the `raw` data read from `range`, which must be the range
+    /// returned by [`next_range`](Self::next_range).
+    pub fn push_raw(&mut self, range: SPIRange, raw: &[u8]) -> Result<(), WrongRangeError> {
+        let expected = match self.next_range() {
+            Some(expected) if expected == range && raw.len() >= range.1 as usize => expected,
+            expected => {
+                trace!(
+                    "SPI read of {:?} unexpected, waiting for {:?}",
+                    range,
+                    expected
+                );
+                return Err(WrongRangeError {
+                    expected: expected.unwrap_or(SPIRange(0, 0)),
+                    got: range,
+                });
+            }
+        };
+        let data = &mut self.segments[self.segment].1;
+        data.extend_from_slice(&raw[..expected.1 as usize]);
+        if data.len() as u32 == self.sizes[self.segment] {
+            trace!("SPI segment {:?} read", self.segment);
+            self.segment += 1;
+        }
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.segment == self.segments.len()
+    }
+
+    /// Number of bytes read and total number of bytes to read.
+    pub fn progress(&self) -> (u32, u32) {
+        let read = self
+            .segments
+            .iter()
+            .map(|(_, data)| data.len() as u32)
+            .sum();
+        (read, self.sizes.iter().sum())
+    }
+
+    /// The reassembled data, `None` if some reads are missing.
+    pub fn finish(self) -> Option<SpiBlockData> {
+        if self.is_complete() {
+            Some(SpiBlockData {
+                segments: self.segments,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Data of a complete [`MultiRangeRead`].
+#[derive(Clone, Debug)]
+pub struct SpiBlockData {
+    segments: Vec<(u32, Vec<u8>)>,
+}
+
+impl SpiBlockData {
+    /// `(offset, data)` of each segment.
+    pub fn segments(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.segments
+            .iter()
+            .map(|(offset, data)| (*offset, data.as_slice()))
+    }
+
+    /// The `size` bytes at `offset`, if they were read.
+    pub fn bytes(&self, offset: u32, size: u32) -> Option<&[u8]> {
+        self.segments.iter().find_map(|(start, data)| {
+            let begin = offset.checked_sub(*start)? as usize;
+            data.get(begin..begin + size as usize)
+        })
+    }
+
+    /// Decode a typed value from the data read.
+    pub fn get<S: SPI>(&self) -> Option<S> {
+        let range = S::range();
+        let bytes = self.bytes(range.0, range.1 as u32)?;
+        let mut raw = [0; MAX_READ_SIZE as usize];
+        raw[..bytes.len()].copy_from_slice(bytes);
+        let result = SPIReadResult {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData { raw },
+        };
+        S::try_from(result).ok()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn multi_range_read() {
+    let mut read = MultiRangeRead::new(SpiBlock::ControllerColors);
+    let mut nb_reads = 0;
+    while let Some(range) = read.next_range() {
+        let mut raw = [0; 0x1D];
+        for (i, b) in raw[..range.1 as usize].iter_mut().enumerate() {
+            *b = range.0.wrapping_sub(0x6050) as u8 + i as u8;
+        }
+        let result = SPIReadResult {
+            address: range.0.into(),
+            size: range.1,
+            data: SPIData { raw },
+        };
+        read.push(&result).unwrap();
+        nb_reads += 1;
+    }
+    assert_eq!(nb_reads, 2);
+    let extra = SPIReadResult {
+        address: 0x605C.into(),
+        size: 1,
+        data: SPIData { raw: [0; 0x1D] },
+    };
+    assert!(read.push(&extra).is_err());
+    let data = read.finish().unwrap();
+    let color: ControllerColor = data.get().unwrap();
+    assert_eq!(color.buttons, Color::new(3, 4, 5));
+
+    let full = MultiRangeRead::new(SpiBlock::Full);
+    assert_eq!(full.progress(), (0, 0x80000));
+    assert_eq!(full.next_range(), Some(SPIRange(0, 0x1D)));
+    let ranges: Vec<_> = SpiBlock::Full.ranges().collect();
+    assert_eq!(ranges.len(), 0x80000 / 0x1D + 1);
+    assert_eq!(ranges.last(), Some(&SPIRange(0x7FFE6, 26)));
+    assert_eq!(
+        SpiBlock::ControllerColors.ranges().collect::<Vec<_>>(),
+        [SPIRange(0x601B, 1), SPIRange(0x6050, 0xC)]
+    );
+}
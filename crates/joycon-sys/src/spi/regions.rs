@@ -0,0 +1,160 @@
+//! Map of the SPI flash address space.
+//!
+//! Sections (eg. the factory configuration) contain finer regions (eg. the
+//! serial number). Lookups return the most specific region.
+//!
+//! <https://github.com/dekuNukem/Nintendo_Switch_Reverse_Engineering/blob/master/spi_flash_notes.md>
+
+use super::SPIRange;
+
+/// Size of the flash.
+pub const FLASH_SIZE: u32 = 0x80000;
+
+/// Known area of the SPI flash.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SpiRegion {
+    pub offset: u32,
+    pub len: u32,
+    /// Short identifier, eg. `serial_number`.
+    pub name: &'static str,
+    /// Whether this crate considers it safe to write. Writing outside these
+    /// regions can brick the controller.
+    pub writable: bool,
+    pub description: &'static str,
+}
+
+impl SpiRegion {
+    pub fn end(&self) -> u32 {
+        self.offset + self.len
+    }
+
+    pub fn contains(&self, address: u32) -> bool {
+        self.offset <= address && address < self.end()
+    }
+
+    /// The range to read the whole region at once, if it's small enough.
+    pub fn range(&self) -> Option<SPIRange> {
+        if self.len <= 0x1D {
+            Some(SPIRange(self.offset, self.len as u8))
+        } else {
+            None
+        }
+    }
+}
+
+const fn region(
+    offset: u32,
+    len: u32,
+    name: &'static str,
+    writable: bool,
+    description: &'static str,
+) -> SpiRegion {
+    SpiRegion {
+        offset,
+        len,
+        name,
+        writable,
+        description,
+    }
+}
+
+/// Every known region, sorted by offset, sections before their content.
+#[rustfmt::skip]
+pub const REGIONS: &[SpiRegion] = &[
+    region(0x0000, 0x1000, "patch_ram", false, "Initial PatchRAM"),
+    region(0x1000, 0x1000, "failsafe", false, "Failsafe firmware update data"),
+    region(0x2000, 0x1000, "pairing", false, "Pairing information"),
+    region(0x2000, 0x26, "pairing_entry", false, "Last paired host"),
+    region(0x2004, 0x16, "pairing_host", false, "Host address and link key"),
+    region(0x5000, 0x1000, "shipment", false, "Shipment section"),
+    region(0x5000, 0x1, "shipment_flag", true, "Shipment mode flag"),
+    region(0x6000, 0x1000, "factory", false, "Factory configuration and calibration"),
+    region(0x6000, 0x10, "serial_number", false, "Serial number"),
+    region(0x6012, 0xA, "hardware_info", false, "Device type and color flag"),
+    region(0x6012, 0x1, "device_type", false, "Device type"),
+    region(0x601B, 0x1, "use_spi_colors", true, "Whether to use the colors"),
+    region(0x6020, 0x18, "sensors_factory", false, "Factory sensor calibration"),
+    region(0x603D, 0x12, "sticks_factory", false, "Factory stick calibration"),
+    region(0x6050, 0xC, "colors", true, "Body, buttons and grip colors"),
+    region(0x6080, 0x6, "sensors_offset", false, "Sensor horizontal offset"),
+    region(0x6086, 0x12, "stick_parameters_1", false, "Stick device parameters 1"),
+    region(0x6098, 0x12, "stick_parameters_2", false, "Stick device parameters 2"),
+    region(0x8000, 0x1000, "user", false, "User calibration"),
+    region(0x8010, 0x16, "sticks_user", true, "User stick calibration"),
+    region(0x8010, 0xB, "left_stick_user", true, "User left stick calibration"),
+    region(0x801B, 0xB, "right_stick_user", true, "User right stick calibration"),
+    region(0x8026, 0x1A, "sensors_user", true, "User sensor calibration"),
+    region(0x10000, 0x70000, "firmware", false, "Firmware"),
+];
+
+/// The most specific region containing `address`.
+pub fn region_at(address: u32) -> Option<&'static SpiRegion> {
+    REGIONS
+        .iter()
+        .filter(|r| r.contains(address))
+        .min_by_key(|r| r.len)
+}
+
+/// The region exactly matching `range`.
+pub fn region_of(range: SPIRange) -> Option<&'static SpiRegion> {
+    REGIONS
+        .iter()
+        .find(|r| r.offset == range.0 && r.len == range.1 as u32)
+}
+
+/// Whether `range` only covers writable regions.
+pub fn is_writable(range: SPIRange) -> bool {
+    (range.0..range.0 + range.1 as u32)
+        .all(|address| matches!(region_at(address), Some(r) if r.writable))
+}
+
+/// Regions identifying the controller or its hosts: the serial number, and
+/// the addresses and link keys of the paired hosts.
+const SENSITIVE_REGIONS: &[&str] = &["pairing", "serial_number"];
+
+/// Whether `address` holds an identifier, to hide before sharing a dump.
+pub fn is_sensitive(address: u32) -> bool {
+    REGIONS
+        .iter()
+        .any(|r| r.contains(address) && SENSITIVE_REGIONS.contains(&r.name))
+}
+
+/// Zero the sensitive bytes of `data`, read from the flash at `offset`.
+pub fn redact(offset: u32, data: &mut [u8]) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        // No region past the end of the address space.
+        let address = match offset.checked_add(i as u32) {
+            Some(address) => address,
+            None => break,
+        };
+        if is_sensitive(address) {
+            *byte = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn region_lookup() {
+    assert_eq!(region_at(0x6005).unwrap().name, "serial_number");
+    assert_eq!(region_at(0x6010).unwrap().name, "factory");
+    assert_eq!(region_at(0x90000), None);
+    assert_eq!(
+        region_of(super::RANGE_CONTROLLER_COLOR).unwrap().name,
+        "colors"
+    );
+    assert!(is_writable(super::RANGE_USER_CALIBRATION_STICKS));
+    assert!(!is_writable(super::RANGE_FACTORY_CALIBRATION_STICKS));
+    assert!(!is_writable(SPIRange(0x804E, 2)));
+    for pair in REGIONS.windows(2) {
+        assert!(pair[0].offset <= pair[1].offset);
+    }
+
+    let mut dump = [0xff; 4];
+    redact(0x5FFE, &mut dump);
+    assert_eq!(dump, [0xff, 0xff, 0, 0]);
+    // Offset sent by the controller.
+    redact(0xFFFF_FFF0, &mut [0xff; 0x1D]);
+    assert!(is_sensitive(0x2010));
+    assert!(!is_sensitive(0x6050));
+}
@@ -0,0 +1,218 @@
+//! Sanity checks of a SPI flash image, eg. a backup before restoring it.
+//!
+//! A corrupt dump written back can leave the controller with unusable sticks
+//! or sensors. [`validate_image`] checks the magics and flags of the
+//! writable regions and the plausibility of the calibrations:
+//!
+//! ```ignore
+//! let issues = validate_image(&backup);
+//! if !issues.is_empty() {
+//!     for issue in &issues {
+//!         eprintln!("{}", issue);
+//!     }
+//!     return Err("corrupt backup");
+//! }
+//! ```
+
+use super::{regions::region_at, *};
+
+/// Lowest and highest plausible stick center.
+const STICK_CENTER_RANGE: (u16, u16) = (0x400, 0xC00);
+/// Lowest plausible distance from the center to the extremes.
+const STICK_MIN_EXTENT: u16 = 0x100;
+
+/// Problem found in an image.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ImageIssue {
+    /// The image doesn't cover the checked regions.
+    TooShort { len: usize },
+    /// A user calibration starts with neither the calibration magic nor the
+    /// erased one.
+    BadMagic { offset: u32, magic: [u8; 2] },
+    /// A flag byte has a value the firmware doesn't use.
+    InvalidFlag { offset: u32, value: u8 },
+    /// A calibration has values no controller would have.
+    ImplausibleCalibration { offset: u32 },
+}
+
+impl ImageIssue {
+    /// Address of the problem, `None` for [`ImageIssue::TooShort`].
+    pub fn offset(&self) -> Option<u32> {
+        match *self {
+            ImageIssue::TooShort { .. } => None,
+            ImageIssue::BadMagic { offset, .. }
+            | ImageIssue::InvalidFlag { offset, .. }
+            | ImageIssue::ImplausibleCalibration { offset } => Some(offset),
+        }
+    }
+}
+
+impl fmt::Display for ImageIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let region = self
+            .offset()
+            .and_then(region_at)
+            .map_or("unknown", |r| r.name);
+        match self {
+            ImageIssue::TooShort { len } => write!(f, "image of {} bytes is too short", len),
+            ImageIssue::BadMagic { offset, magic } => write!(
+                f,
+                "{} at 0x{:05x}: bad magic {:02x}{:02x}",
+                region, offset, magic[0], magic[1]
+            ),
+            ImageIssue::InvalidFlag { offset, value } => write!(
+                f,
+                "{} at 0x{:05x}: invalid value 0x{:02x}",
+                region, offset, value
+            ),
+            ImageIssue::ImplausibleCalibration { offset } => {
+                write!(f, "{} at 0x{:05x}: implausible calibration", region, offset)
+            }
+        }
+    }
+}
+
+fn read<T: Wire>(image: &[u8], range: SPIRange) -> T {
+    let start = range.offset() as usize;
+    T::from_bytes(&image[start..start + range.size() as usize]).unwrap()
+}
+
+fn is_erased(bytes: &[u8]) -> bool {
+    bytes.iter().all(|&b| b == 0xFF)
+}
+
+/// Whether a stick calibration is plausible. JoyCons have only one stick,
+/// the calibration of the other being left erased.
+fn stick_plausible<O: StickOrder>(calib: &StickCalibration<O>) -> bool {
+    if is_erased(&calib.raw) {
+        return true;
+    }
+    let (min, center, max) = (calib.get(2), calib.get(1), calib.get(0));
+    let (low, high) = STICK_CENTER_RANGE;
+    [center.0, center.1].iter().all(|c| low <= *c && *c <= high)
+        && [min.0, min.1, max.0, max.1]
+            .iter()
+            .all(|&extent| extent >= STICK_MIN_EXTENT)
+}
+
+fn sensors_plausible(calib: &SensorCalibration) -> bool {
+    calib
+        .acc_factor_i16()
+        .iter()
+        .chain(calib.gyro_factor_i16().iter())
+        .all(|&factor| factor > 0)
+}
+
+/// Check the image of a whole flash, returning the problems found.
+pub fn validate_image(image: &[u8]) -> Vec<ImageIssue> {
+    let checked_end = RANGE_USER_CALIBRATION_SENSORS.offset() as usize
+        + RANGE_USER_CALIBRATION_SENSORS.size() as usize;
+    if image.len() < checked_end {
+        return vec![ImageIssue::TooShort { len: image.len() }];
+    }
+    let mut issues = Vec::new();
+    let mut flag = |offset: u32, valid: &[u8]| {
+        let value = image[offset as usize];
+        if !valid.contains(&value) {
+            issues.push(ImageIssue::InvalidFlag { offset, value });
+        }
+    };
+    // Shipment mode flag.
+    flag(0x5000, &[0, 1]);
+    flag(RANGE_CONTROLLER_COLOR_USE_SPI.offset(), &[0, 1, 2]);
+
+    let mut calibration = |range: SPIRange, plausible: bool| {
+        if !plausible {
+            issues.push(ImageIssue::ImplausibleCalibration {
+                offset: range.offset(),
+            });
+        }
+    };
+    let sticks: SticksCalibration = read(image, RANGE_FACTORY_CALIBRATION_STICKS);
+    calibration(
+        RANGE_FACTORY_CALIBRATION_STICKS,
+        stick_plausible(&sticks.left) && stick_plausible(&sticks.right),
+    );
+    let sensors: SensorCalibration = read(image, RANGE_FACTORY_CALIBRATION_SENSORS);
+    calibration(
+        RANGE_FACTORY_CALIBRATION_SENSORS,
+        sensors_plausible(&sensors),
+    );
+
+    let mut user = |range: SPIRange, plausible: &dyn Fn(&[u8]) -> bool| {
+        let start = range.offset() as usize;
+        let bytes = &image[start..start + range.size() as usize];
+        let magic = [bytes[0], bytes[1]];
+        if magic == USER_CALIB_MAGIC {
+            if !plausible(&bytes[2..]) {
+                issues.push(ImageIssue::ImplausibleCalibration {
+                    offset: range.offset(),
+                });
+            }
+        } else if magic != USER_NO_CALIB_MAGIC {
+            issues.push(ImageIssue::BadMagic {
+                offset: range.offset(),
+                magic,
+            });
+        }
+    };
+    user(RANGE_USER_CALIBRATION_LEFT_STICK, &|bytes| {
+        stick_plausible(&LeftStickCalibration::from_bytes(bytes).unwrap())
+    });
+    user(RANGE_USER_CALIBRATION_RIGHT_STICK, &|bytes| {
+        stick_plausible(&RightStickCalibration::from_bytes(bytes).unwrap())
+    });
+    user(RANGE_USER_CALIBRATION_SENSORS, &|bytes| {
+        sensors_plausible(&SensorCalibration::from_bytes(bytes).unwrap())
+    });
+    issues
+}
+
+#[cfg(test)]
+#[test]
+fn image_validation() {
+    let mut image = vec![0xFF; regions::FLASH_SIZE as usize];
+    image[0x5000] = 0;
+    image[0x601B] = 1;
+    let sticks = SticksCalibration {
+        left: LeftStickCalibration::new((0x200, 0x200), (0x800, 0x800), (0xE00, 0xE00)),
+        // Erased on a left JoyCon.
+        right: RightStickCalibration::from_bytes(&[0xFF; 9]).unwrap(),
+    };
+    let mut sensors = SensorCalibration::reset();
+    sensors.set_acc_factor_i16([0x4000; 3]);
+    sensors.set_gyro_factor_i16([0x343b; 3]);
+    image[0x603D..0x603D + 18].copy_from_slice(&sticks.to_bytes());
+    image[0x6020..0x6020 + 24].copy_from_slice(&sensors.to_bytes());
+    assert_eq!(validate_image(&image), []);
+    assert_eq!(
+        validate_image(&image[..0x1000]),
+        [ImageIssue::TooShort { len: 0x1000 }]
+    );
+
+    // Zeroed right stick, bad user stick magic, erased user sensor values.
+    image[0x603D + 9..0x603D + 18].copy_from_slice(&[0; 9]);
+    image[0x601B] = 7;
+    image[0x801B..0x801D].copy_from_slice(&[0, 0]);
+    image[0x8026..0x8028].copy_from_slice(&USER_CALIB_MAGIC);
+    let issues = validate_image(&image);
+    assert_eq!(
+        issues,
+        [
+            ImageIssue::InvalidFlag {
+                offset: 0x601B,
+                value: 7
+            },
+            ImageIssue::ImplausibleCalibration { offset: 0x603D },
+            ImageIssue::BadMagic {
+                offset: 0x801B,
+                magic: [0, 0]
+            },
+            ImageIssue::ImplausibleCalibration { offset: 0x8026 },
+        ]
+    );
+    assert_eq!(
+        issues[0].to_string(),
+        "use_spi_colors at 0x0601b: invalid value 0x07"
+    );
+}
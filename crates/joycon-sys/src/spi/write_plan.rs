@@ -0,0 +1,266 @@
+//! SPI writes larger than a single write, restoring the previous content if
+//! one of them fails.
+//!
+//! A [`WritePlan`] first reads back the whole range to keep a backup, then
+//! writes and verifies each chunk. If a write isn't acknowledged or its
+//! verification doesn't match, the chunks already written are restored from
+//! the backup:
+//!
+//! ```ignore
+//! let mut plan = unsafe { WritePlan::new(0x8010, &user_calibration) };
+//! while let Some(step) = plan.next_step() {
+//!     let reply = send_and_wait_reply(SubcommandRequest::from(step))?;
+//!     plan.push(&reply)?;
+//! }
+//! match plan.state() {
+//!     WritePlanState::Done => {}
+//!     state => eprintln!("write failed: {:?}", state),
+//! }
+//! ```
+
+use super::*;
+use crate::{input::SubcommandReply, output::SubcommandRequest};
+
+/// Maximum size of a single SPI write.
+const MAX_WRITE_SIZE: u32 = 0x1D;
+
+/// Request to send, see [`WritePlan::next_step`].
+#[derive(Copy, Clone, Debug)]
+pub enum WriteStep {
+    /// Read the current content, for the backup.
+    Backup(SPIReadRequest),
+    Write(SPIWriteRequest),
+    /// Read back a written chunk.
+    Verify(SPIReadRequest),
+}
+
+impl From<WriteStep> for SubcommandRequest {
+    fn from(step: WriteStep) -> Self {
+        match step {
+            WriteStep::Backup(read) | WriteStep::Verify(read) => read.into(),
+            WriteStep::Write(write) => write.into(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WritePlanState {
+    /// Reading the backup, nothing written yet.
+    Backup,
+    Writing,
+    /// A write failed, restoring the backup.
+    RollingBack,
+    Done,
+    /// A write failed and the previous content was restored.
+    RolledBack,
+    /// The restoration failed too: the range holds a mix of the old and new
+    /// content, see [`WritePlan::backup`].
+    RollbackFailed,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Phase {
+    Backup(usize),
+    Write(usize),
+    Verify(usize),
+    RestoreWrite(usize),
+    RestoreVerify(usize),
+    Done,
+    RolledBack,
+    RollbackFailed,
+}
+
+/// Sans-io state machine of a verified write with rollback.
+#[derive(Clone, Debug)]
+pub struct WritePlan {
+    offset: u32,
+    data: Vec<u8>,
+    backup: Vec<u8>,
+    phase: Phase,
+    /// Number of chunks to restore on rollback.
+    touched: usize,
+}
+
+impl WritePlan {
+    /// Plan the write of `data` at `offset`.
+    ///
+    /// # Safety
+    ///
+    /// Writing some parts of the flash can brick the controller, the range
+    /// must be checked beforehand, eg. with [`regions::is_writable`].
+    pub unsafe fn new(offset: u32, data: &[u8]) -> WritePlan {
+        WritePlan {
+            offset,
+            data: data.to_vec(),
+            backup: Vec::with_capacity(data.len()),
+            phase: if data.is_empty() {
+                Phase::Done
+            } else {
+                Phase::Backup(0)
+            },
+            touched: 0,
+        }
+    }
+
+    fn chunk_count(&self) -> usize {
+        self.data.len().div_ceil(MAX_WRITE_SIZE as usize)
+    }
+
+    fn chunk(&self, index: usize) -> (SPIRange, std::ops::Range<usize>) {
+        let start = index * MAX_WRITE_SIZE as usize;
+        let end = (start + MAX_WRITE_SIZE as usize).min(self.data.len());
+        (
+            SPIRange(self.offset + start as u32, (end - start) as u8),
+            start..end,
+        )
+    }
+
+    pub fn state(&self) -> WritePlanState {
+        match self.phase {
+            Phase::Backup(_) => WritePlanState::Backup,
+            Phase::Write(_) | Phase::Verify(_) => WritePlanState::Writing,
+            Phase::RestoreWrite(_) | Phase::RestoreVerify(_) => WritePlanState::RollingBack,
+            Phase::Done => WritePlanState::Done,
+            Phase::RolledBack => WritePlanState::RolledBack,
+            Phase::RollbackFailed => WritePlanState::RollbackFailed,
+        }
+    }
+
+    /// Content of the range before the write, once read.
+    pub fn backup(&self) -> Option<&[u8]> {
+        if self.backup.len() == self.data.len() {
+            Some(&self.backup)
+        } else {
+            None
+        }
+    }
+
+    /// Next request to send, `None` once finished.
+    pub fn next_step(&self) -> Option<WriteStep> {
+        let write = |range, data: &[u8]| unsafe { SPIWriteRequest::new(range, data) };
+        Some(match self.phase {
+            Phase::Backup(i) => WriteStep::Backup(SPIReadRequest::new(self.chunk(i).0)),
+            Phase::Write(i) => {
+                let (range, bytes) = self.chunk(i);
+                WriteStep::Write(write(range, &self.data[bytes]))
+            }
+            Phase::RestoreWrite(i) => {
+                let (range, bytes) = self.chunk(i);
+                WriteStep::Write(write(range, &self.backup[bytes]))
+            }
+            Phase::Verify(i) | Phase::RestoreVerify(i) => {
+                WriteStep::Verify(SPIReadRequest::new(self.chunk(i).0))
+            }
+            Phase::Done | Phase::RolledBack | Phase::RollbackFailed => return None,
+        })
+    }
+
+    /// Handle the reply to the request of [`next_step`](#method.next_step).
+    ///
+    /// Replies to other subcommands are ignored.
+    pub fn push(&mut self, reply: &SubcommandReply) -> Result<(), WrongRangeError> {
+        let step = match self.next_step() {
+            Some(step) => step,
+            None => return Ok(()),
+        };
+        match (step, reply.spi_read_result(), reply.is_spi_write_success()) {
+            (WriteStep::Backup(read), Some(result), _)
+            | (WriteStep::Verify(read), Some(result), _) => {
+                if result.range() != read.range() {
+                    return Err(WrongRangeError {
+                        expected: read.range(),
+                        got: result.range(),
+                    });
+                }
+                let raw = result.raw();
+                self.read_done(&raw[..read.range().size() as usize]);
+            }
+            (WriteStep::Write(_), _, Some(success)) => self.write_done(success),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn read_done(&mut self, read: &[u8]) {
+        let last = self.chunk_count() - 1;
+        self.phase = match self.phase {
+            Phase::Backup(i) => {
+                self.backup.extend_from_slice(read);
+                if i == last {
+                    Phase::Write(0)
+                } else {
+                    Phase::Backup(i + 1)
+                }
+            }
+            Phase::Verify(i) if read != &self.data[self.chunk(i).1] => {
+                trace!("SPI chunk {:?} verification failed", i);
+                self.rollback()
+            }
+            Phase::Verify(i) if i == last => Phase::Done,
+            Phase::Verify(i) => Phase::Write(i + 1),
+            Phase::RestoreVerify(i) if read != &self.backup[self.chunk(i).1] => {
+                Phase::RollbackFailed
+            }
+            Phase::RestoreVerify(i) if i + 1 == self.touched => Phase::RolledBack,
+            Phase::RestoreVerify(i) => Phase::RestoreWrite(i + 1),
+            phase => phase,
+        };
+    }
+
+    fn write_done(&mut self, success: bool) {
+        self.phase = match self.phase {
+            Phase::Write(i) => {
+                self.touched = i + 1;
+                if success {
+                    Phase::Verify(i)
+                } else {
+                    trace!("SPI chunk {:?} write failed", i);
+                    self.rollback()
+                }
+            }
+            Phase::RestoreWrite(i) if success => Phase::RestoreVerify(i),
+            Phase::RestoreWrite(_) => Phase::RollbackFailed,
+            phase => phase,
+        };
+    }
+
+    fn rollback(&self) -> Phase {
+        if self.touched == 0 {
+            Phase::RolledBack
+        } else {
+            Phase::RestoreWrite(0)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn write_plan_rollback() {
+    use crate::{input::WhichController, mock::MockController};
+
+    let mut controller = MockController::new(WhichController::ProController);
+    let old: Vec<u8> = (0..40).collect();
+    controller.flash_mut().write(0x8010, &old);
+    let new = [0xAB; 40];
+    let mut plan = unsafe { WritePlan::new(0x8010, &new) };
+    let mut writes = 0;
+    while let Some(step) = plan.next_step() {
+        controller.send(&SubcommandRequest::from(step).into());
+        let reply = *controller.recv().subcmd_reply().unwrap();
+        if let WriteStep::Write(_) = step {
+            writes += 1;
+            // Corrupt the second chunk after it is written.
+            if writes == 2 {
+                controller.flash_mut().write(0x8010 + 0x1D, &[0]);
+            }
+        }
+        plan.push(&reply).unwrap();
+    }
+    assert_eq!(plan.state(), WritePlanState::RolledBack);
+    assert_eq!(plan.backup(), Some(&old[..]));
+    assert_eq!(
+        controller.flash().read(SPIRange(0x8010, 0x1D)).unwrap(),
+        &old[..0x1D]
+    );
+    assert_eq!(writes, 4);
+}
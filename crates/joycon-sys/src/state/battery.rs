@@ -0,0 +1,162 @@
+use super::{ControllerState, TimerTicks};
+use crate::input::BatteryLevel;
+
+/// Default number of report timer ticks a new battery level must be reported
+/// before being accepted, about 1 second.
+pub const BATTERY_DEBOUNCE_TICKS: u32 = 200;
+
+/// Battery transition reported by a [`BatteryMonitor`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum BatteryEvent {
+    /// The level dropped to `Low` or below.
+    EnteredLow,
+    /// The level dropped to `Critical` or below.
+    EnteredCritical,
+    /// The level went back above `Low`.
+    LeftLow,
+    StartedCharging,
+    StoppedCharging,
+}
+
+/// Tracks the battery across reports and reports its transitions.
+///
+/// The level reported by the controller oscillates around the thresholds,
+/// so a new level is only accepted once it has been reported for
+/// `debounce_ticks` report timer ticks.
+///
+/// ```ignore
+/// let mut battery = BatteryMonitor::new();
+/// for event in battery.update(&state) {
+///     if event == BatteryEvent::EnteredLow {
+///         notify("Controller battery low");
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BatteryMonitor {
+    debounce_ticks: u32,
+    level: Option<BatteryLevel>,
+    charging: Option<bool>,
+    pending: Option<(BatteryLevel, u32)>,
+    timer: TimerTicks,
+}
+
+impl BatteryMonitor {
+    pub fn new() -> BatteryMonitor {
+        BatteryMonitor::with_debounce(BATTERY_DEBOUNCE_TICKS)
+    }
+
+    pub fn with_debounce(debounce_ticks: u32) -> BatteryMonitor {
+        BatteryMonitor {
+            debounce_ticks,
+            level: None,
+            charging: None,
+            pending: None,
+            timer: TimerTicks::default(),
+        }
+    }
+
+    /// Debounced battery level, `None` before the first report.
+    pub fn level(&self) -> Option<BatteryLevel> {
+        self.level
+    }
+
+    pub fn charging(&self) -> Option<bool> {
+        self.charging
+    }
+
+    pub fn is_low(&self) -> bool {
+        matches!(self.level, Some(level) if level <= BatteryLevel::Low)
+    }
+
+    pub fn reset(&mut self) {
+        *self = BatteryMonitor::with_debounce(self.debounce_ticks);
+    }
+
+    /// Transitions caused by this state.
+    ///
+    /// The first state is accepted as is and only reports `EnteredLow` and
+    /// `EnteredCritical` if the battery is already low.
+    pub fn update(&mut self, state: &ControllerState) -> Vec<BatteryEvent> {
+        let delta = self.timer.advance(state.timer);
+
+        let mut events = Vec::new();
+        match self.charging {
+            Some(false) if state.charging => events.push(BatteryEvent::StartedCharging),
+            Some(true) if !state.charging => events.push(BatteryEvent::StoppedCharging),
+            _ => {}
+        }
+        self.charging = Some(state.charging);
+
+        let previous = match self.level {
+            Some(level) if level == state.battery_level => {
+                self.pending = None;
+                return events;
+            }
+            Some(level) => level,
+            None => {
+                self.level = Some(state.battery_level);
+                level_events(BatteryLevel::Full, state.battery_level, &mut events);
+                return events;
+            }
+        };
+        let ticks = match self.pending {
+            Some((level, ticks)) if level == state.battery_level => ticks.saturating_add(delta),
+            _ => 0,
+        };
+        if ticks >= self.debounce_ticks {
+            self.pending = None;
+            self.level = Some(state.battery_level);
+            level_events(previous, state.battery_level, &mut events);
+        } else {
+            self.pending = Some((state.battery_level, ticks));
+        }
+        events
+    }
+}
+
+impl Default for BatteryMonitor {
+    fn default() -> Self {
+        BatteryMonitor::new()
+    }
+}
+
+fn level_events(from: BatteryLevel, to: BatteryLevel, events: &mut Vec<BatteryEvent>) {
+    if from > BatteryLevel::Low && to <= BatteryLevel::Low {
+        events.push(BatteryEvent::EnteredLow);
+    }
+    if from > BatteryLevel::Critical && to <= BatteryLevel::Critical {
+        events.push(BatteryEvent::EnteredCritical);
+    }
+    if from <= BatteryLevel::Low && to > BatteryLevel::Low {
+        events.push(BatteryEvent::LeftLow);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn battery_transitions() {
+    use BatteryLevel::{Low, Medium};
+
+    let mut battery = BatteryMonitor::with_debounce(6);
+    let steps: [(BatteryLevel, bool, &[BatteryEvent]); 8] = [
+        (Medium, false, &[]),
+        // Flickering around the threshold is ignored.
+        (Low, false, &[]),
+        (Medium, false, &[]),
+        (Low, false, &[]),
+        (Low, false, &[]),
+        (Low, false, &[BatteryEvent::EnteredLow]),
+        (Low, true, &[BatteryEvent::StartedCharging]),
+        (Low, true, &[]),
+    ];
+    // Starts right before the timer wraps around.
+    for (i, &(battery_level, charging, expected)) in steps.iter().enumerate() {
+        let state = ControllerState {
+            battery_level,
+            charging,
+            ..ControllerState::neutral(250u8.wrapping_add(3 * i as u8))
+        };
+        assert_eq!(battery.update(&state), expected, "step {}", i);
+    }
+}
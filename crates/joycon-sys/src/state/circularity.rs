@@ -0,0 +1,95 @@
+use super::ControllerState;
+use crate::math;
+use cgmath::Vector2;
+use std::f64::consts::PI;
+
+/// Maps the range of the calibrated sticks onto the unit circle.
+///
+/// The calibration scales each axis independently between its outer points,
+/// so the reachable range is closer to a square: diagonals go beyond 1 and
+/// feel faster than the axes. The square is mapped onto the unit disc, then
+/// an optional gain per octant compensates for the shape of a particular
+/// stick gate.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CircularityCorrection {
+    /// Gain at the center of each octant, counterclockwise from +X. The gain
+    /// is interpolated between two octants.
+    pub octant_gains: [f64; 8],
+}
+
+impl CircularityCorrection {
+    pub fn new() -> CircularityCorrection {
+        CircularityCorrection::with_octant_gains([1.; 8])
+    }
+
+    pub fn with_octant_gains(octant_gains: [f64; 8]) -> CircularityCorrection {
+        CircularityCorrection { octant_gains }
+    }
+
+    /// Corrected position of a stick, inside the unit circle.
+    pub fn correct(&self, position: Vector2<f64>) -> Vector2<f64> {
+        let x = position.x.clamp(-1., 1.);
+        let y = position.y.clamp(-1., 1.);
+        let disc = Vector2::new(
+            x * math::sqrt(1. - y * y / 2.),
+            y * math::sqrt(1. - x * x / 2.),
+        );
+        let corrected = disc * self.gain(math::atan2(disc.y, disc.x));
+        let length = math::hypot(corrected.x, corrected.y);
+        if length > 1. {
+            corrected / length
+        } else {
+            corrected
+        }
+    }
+
+    /// Correct both sticks of `state`.
+    pub fn apply(&self, state: &mut ControllerState) {
+        state.left_stick = self.correct(state.left_stick);
+        state.right_stick = self.correct(state.right_stick);
+    }
+
+    fn gain(&self, angle: f64) -> f64 {
+        // Position in octants, the center of octant 0 being at 0.
+        let octant = angle.rem_euclid(2. * PI) / (PI / 4.);
+        let index = octant.floor() as usize % 8;
+        let t = octant.fract();
+        self.octant_gains[index] * (1. - t) + self.octant_gains[(index + 1) % 8] * t
+    }
+}
+
+impl Default for CircularityCorrection {
+    fn default() -> Self {
+        CircularityCorrection::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn circular_sticks() {
+    // Single precision backend.
+    let eps = if cfg!(feature = "float32") {
+        1e-6
+    } else {
+        1e-9
+    };
+    let correction = CircularityCorrection::new();
+    let corner = correction.correct(Vector2::new(1., 1.));
+    assert!((corner.x.hypot(corner.y) - 1.).abs() < eps);
+    assert!((corner.x - corner.y).abs() < eps);
+    assert_eq!(
+        correction.correct(Vector2::new(1., 0.)),
+        Vector2::new(1., 0.)
+    );
+    assert_eq!(
+        correction.correct(Vector2::new(0., 0.)),
+        Vector2::new(0., 0.)
+    );
+
+    let mut gains = [1.; 8];
+    gains[2] = 0.5;
+    let correction = CircularityCorrection::with_octant_gains(gains);
+    let up = correction.correct(Vector2::new(0., 0.8));
+    assert!((up.y - 0.4).abs() < eps);
+}
@@ -0,0 +1,159 @@
+use super::{ButtonSet, ControllerState, TimerTicks};
+use crate::input::Button;
+
+/// Buttons to hold together for some time.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Chord {
+    pub buttons: ButtonSet,
+    /// Number of report timer ticks the buttons must be held before the
+    /// chord fires. 0 fires as soon as they are all pressed.
+    pub hold_ticks: u32,
+}
+
+impl Chord {
+    pub fn new(buttons: &[Button], hold_ticks: u32) -> Chord {
+        let mut set = ButtonSet::new();
+        for &button in buttons {
+            set.insert(button);
+        }
+        Chord {
+            buttons: set,
+            hold_ticks,
+        }
+    }
+
+    fn is_held(&self, buttons: ButtonSet) -> bool {
+        !self.buttons.is_empty() && buttons.bits() & self.buttons.bits() == self.buttons.bits()
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct ChordState<A> {
+    chord: Chord,
+    action: A,
+    held_ticks: Option<u32>,
+    fired: bool,
+}
+
+/// Detects button chords, eg. Home+Plus held for 2 seconds, and returns the
+/// action associated to each chord when it fires.
+///
+/// Other buttons may be pressed during a chord. A chord fires once per press
+/// and is rearmed when one of its buttons is released. The hold is counted in
+/// report timer ticks, so reports dropped during the hold still count towards
+/// it.
+///
+/// ```ignore
+/// let mut combos = ComboDetector::new();
+/// combos.add(Chord::new(&[Button::HOME, Button::PLUS], 400), Action::Quit);
+/// for action in combos.update(&state) {
+///     run(action);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ComboDetector<A> {
+    chords: Vec<ChordState<A>>,
+    timer: TimerTicks,
+}
+
+impl<A: Copy> ComboDetector<A> {
+    pub fn new() -> ComboDetector<A> {
+        ComboDetector {
+            chords: Vec::new(),
+            timer: TimerTicks::default(),
+        }
+    }
+
+    pub fn add(&mut self, chord: Chord, action: A) {
+        self.chords.push(ChordState {
+            chord,
+            action,
+            held_ticks: None,
+            fired: false,
+        });
+    }
+
+    pub fn reset(&mut self) {
+        self.timer.reset();
+        for state in &mut self.chords {
+            state.held_ticks = None;
+            state.fired = false;
+        }
+    }
+
+    /// Actions of the chords firing with this state, in the order they were
+    /// added.
+    pub fn update(&mut self, state: &ControllerState) -> Vec<A> {
+        let delta = self.timer.advance(state.timer);
+
+        let mut fired = Vec::new();
+        for chord in &mut self.chords {
+            if !chord.chord.is_held(state.buttons) {
+                chord.held_ticks = None;
+                chord.fired = false;
+                continue;
+            }
+            let ticks = match chord.held_ticks {
+                Some(ticks) => ticks.saturating_add(delta),
+                None => 0,
+            };
+            chord.held_ticks = Some(ticks);
+            if !chord.fired && ticks >= chord.chord.hold_ticks {
+                chord.fired = true;
+                fired.push(chord.action);
+            }
+        }
+        fired
+    }
+}
+
+impl<A: Copy> Default for ComboDetector<A> {
+    fn default() -> Self {
+        ComboDetector::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn detect_chords() {
+    let quit = Chord::new(&[Button::HOME, Button::PLUS], 4);
+    let screenshot = Chord::new(&[Button::L, Button::R], 0);
+    let mut combos = ComboDetector::new();
+    combos.add(quit, "quit");
+    combos.add(screenshot, "screenshot");
+
+    let home = Chord::new(&[Button::HOME], 0).buttons;
+    let mut quit_and_s = quit.buttons;
+    quit_and_s.insert(Button::S);
+    let fired: Vec<Vec<&str>> = [
+        home,
+        quit_and_s,
+        quit.buttons,
+        quit.buttons,
+        // Fires once per press.
+        quit.buttons,
+        screenshot.buttons,
+        quit.buttons,
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, &buttons)| {
+        combos.update(&ControllerState {
+            buttons,
+            ..ControllerState::neutral(3 * i as u8)
+        })
+    })
+    .collect();
+    assert_eq!(
+        fired,
+        [
+            vec![],
+            vec![],
+            vec![],
+            vec!["quit"],
+            vec![],
+            vec!["screenshot"],
+            vec![]
+        ]
+    );
+}
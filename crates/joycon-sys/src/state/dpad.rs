@@ -0,0 +1,202 @@
+use super::ControllerState;
+use crate::{input::Button, math};
+use cgmath::{vec2, Vector2};
+
+/// Which stick is used by the dpad synthesis.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StickSide {
+    Left,
+    Right,
+}
+
+impl StickSide {
+    fn get(self, state: &ControllerState) -> Vector2<f64> {
+        match self {
+            StickSide::Left => state.left_stick,
+            StickSide::Right => state.right_stick,
+        }
+    }
+
+    fn get_mut(self, state: &mut ControllerState) -> &mut Vector2<f64> {
+        match self {
+            StickSide::Left => &mut state.left_stick,
+            StickSide::Right => &mut state.right_stick,
+        }
+    }
+}
+
+/// Configuration of the stick to dpad conversion.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StickToDpadConfig {
+    pub stick: StickSide,
+    /// Distance from the center at which a direction gets pressed.
+    pub press_threshold: f64,
+    /// Distance from the center under which the directions get released.
+    ///
+    /// Should be lower than `press_threshold` to avoid flickering.
+    pub release_threshold: f64,
+    /// Angle in degrees of the sector around each cardinal direction.
+    ///
+    /// 90° gives a 4-way dpad, anything higher adds diagonals: at 135° each
+    /// diagonal covers 45°.
+    pub sector_angle: f64,
+    /// Additional angle in degrees a sector is extended by while its direction
+    /// is pressed.
+    pub angle_hysteresis: f64,
+    /// Whether the original stick position is kept in the state.
+    pub keep_stick: bool,
+}
+
+impl Default for StickToDpadConfig {
+    fn default() -> Self {
+        StickToDpadConfig {
+            stick: StickSide::Left,
+            press_threshold: 0.5,
+            release_threshold: 0.4,
+            sector_angle: 135.,
+            angle_hysteresis: 5.,
+            keep_stick: true,
+        }
+    }
+}
+
+const DIRECTIONS: [(Button, f64); 4] = [
+    (Button::RIGHT, 0.),
+    (Button::UP, 90.),
+    (Button::LEFT, 180.),
+    (Button::DOWN, 270.),
+];
+
+/// Presses dpad buttons from the position of a stick.
+#[derive(Clone, Debug)]
+pub struct StickToDpad {
+    config: StickToDpadConfig,
+    pressed: [bool; 4],
+}
+
+impl StickToDpad {
+    pub fn new(config: StickToDpadConfig) -> StickToDpad {
+        StickToDpad {
+            config,
+            pressed: [false; 4],
+        }
+    }
+
+    pub fn apply(&mut self, state: &mut ControllerState) {
+        let stick = self.config.stick.get(state);
+        let distance = math::hypot(stick.x, stick.y);
+        let active = self.pressed.iter().any(|p| *p);
+        let threshold = if active {
+            self.config.release_threshold
+        } else {
+            self.config.press_threshold
+        };
+
+        if distance < threshold {
+            self.pressed = [false; 4];
+        } else {
+            let angle = math::atan2(stick.y, stick.x).to_degrees();
+            for (pressed, (_, direction)) in self.pressed.iter_mut().zip(DIRECTIONS.iter()) {
+                let mut half_sector = self.config.sector_angle / 2.;
+                if *pressed {
+                    half_sector += self.config.angle_hysteresis;
+                }
+                *pressed = angle_distance(angle, *direction) <= half_sector;
+            }
+        }
+
+        for (pressed, (button, _)) in self.pressed.iter().zip(DIRECTIONS.iter()) {
+            if *pressed {
+                state.buttons.insert(*button);
+            }
+        }
+        if !self.config.keep_stick {
+            *self.config.stick.get_mut(state) = vec2(0., 0.);
+        }
+    }
+}
+
+fn angle_distance(a: f64, b: f64) -> f64 {
+    let d = (a - b).rem_euclid(360.);
+    d.min(360. - d)
+}
+
+/// Configuration of the dpad to stick conversion.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DpadToStickConfig {
+    pub stick: StickSide,
+    /// Distance from the center of the produced stick position.
+    pub magnitude: f64,
+    /// Whether the dpad buttons are kept pressed in the state.
+    pub keep_dpad: bool,
+}
+
+impl Default for DpadToStickConfig {
+    fn default() -> Self {
+        DpadToStickConfig {
+            stick: StickSide::Left,
+            magnitude: 1.,
+            keep_dpad: false,
+        }
+    }
+}
+
+/// Moves a stick from the dpad buttons.
+///
+/// The stick is only overwritten when at least one direction is pressed.
+#[derive(Copy, Clone, Debug)]
+pub struct DpadToStick {
+    config: DpadToStickConfig,
+}
+
+impl DpadToStick {
+    pub fn new(config: DpadToStickConfig) -> DpadToStick {
+        DpadToStick { config }
+    }
+
+    pub fn apply(&self, state: &mut ControllerState) {
+        let mut dir = vec2(0., 0.);
+        for (button, angle) in DIRECTIONS.iter() {
+            if state.is_pressed(*button) {
+                let angle = angle.to_radians();
+                dir += vec2(math::cos(angle), math::sin(angle));
+            }
+            if !self.config.keep_dpad {
+                state.buttons.remove(*button);
+            }
+        }
+        // Opposite directions cancel out.
+        let length = math::hypot(dir.x, dir.y);
+        if length > 0.5 {
+            *self.config.stick.get_mut(state) = dir * (self.config.magnitude / length);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn stick_to_dpad_hysteresis() {
+    use crate::state::ButtonSet;
+
+    let mut state = ControllerState::neutral(0);
+    let mut conv = StickToDpad::new(StickToDpadConfig::default());
+    let mut pressed = |conv: &mut StickToDpad, x: f64, y: f64| {
+        state.buttons = ButtonSet::new();
+        state.left_stick = vec2(x, y);
+        conv.apply(&mut state);
+        state.buttons.iter().collect::<Vec<_>>()
+    };
+
+    assert_eq!(pressed(&mut conv, 0.3, 0.), []);
+    assert_eq!(pressed(&mut conv, 0.6, 0.), [Button::RIGHT]);
+    // Under the press threshold but over the release one
+    assert_eq!(pressed(&mut conv, 0.45, 0.), [Button::RIGHT]);
+    assert_eq!(pressed(&mut conv, 0.35, 0.), []);
+    assert_eq!(pressed(&mut conv, 0.6, 0.6), [Button::UP, Button::RIGHT]);
+    // 69°, still in the diagonal thanks to the hysteresis
+    assert_eq!(pressed(&mut conv, 0.25, 0.65), [Button::UP, Button::RIGHT]);
+    assert_eq!(pressed(&mut conv, 0.1, 0.7), [Button::UP]);
+}
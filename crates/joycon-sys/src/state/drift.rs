@@ -0,0 +1,154 @@
+use super::ButtonSet;
+use crate::{
+    input::Button,
+    spi::{StickCalibration, StickOrder},
+};
+
+/// When the stick is expected to be at rest, so its samples can be used to
+/// measure the drift.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DriftTrigger {
+    /// While the user holds a button, having been asked to release the stick.
+    WhileHeld(Button),
+    /// While the raw position is within `radius` of the calibrated center on
+    /// both axes and no button is pressed.
+    NearCenter { radius: u16 },
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct AxisStats {
+    mean: f64,
+    m2: f64,
+}
+
+impl AxisStats {
+    // Welford's online algorithm.
+    fn push(&mut self, value: f64, count: u32) {
+        let delta = value - self.mean;
+        self.mean += delta / f64::from(count);
+        self.m2 += delta * (value - self.mean);
+    }
+
+    fn std_dev(&self, count: u32) -> f64 {
+        crate::math::sqrt(self.m2 / f64::from(count))
+    }
+}
+
+/// Measures the offset of a stick at rest compared to its calibration, and
+/// suggests a recentered calibration to write as the user calibration.
+///
+/// ```ignore
+/// let mut drift = DriftDetector::new(factory.left, DriftTrigger::WhileHeld(Button::L));
+/// // For each report, while asking the user to release the stick and hold L:
+/// let stick = report.left_stick;
+/// drift.sample(stick.x(), stick.y(), report.buttons.into());
+/// if let Some(calib) = drift.suggested_calibration(200) {
+///     joycon.write_spi(UserStickCalibration::from(calib))?;
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DriftDetector<O: StickOrder> {
+    calibration: StickCalibration<O>,
+    trigger: DriftTrigger,
+    count: u32,
+    x: AxisStats,
+    y: AxisStats,
+}
+
+impl<O: StickOrder> DriftDetector<O> {
+    pub fn new(calibration: StickCalibration<O>, trigger: DriftTrigger) -> DriftDetector<O> {
+        DriftDetector {
+            calibration,
+            trigger,
+            count: 0,
+            x: AxisStats::default(),
+            y: AxisStats::default(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = DriftDetector::new(self.calibration, self.trigger);
+    }
+
+    /// Record a raw stick position, returning whether it was used.
+    pub fn sample(&mut self, x: u16, y: u16, buttons: ButtonSet) -> bool {
+        let at_rest = match self.trigger {
+            DriftTrigger::WhileHeld(button) => buttons.contains(button),
+            DriftTrigger::NearCenter { radius } => {
+                let center = self.calibration.center();
+                buttons.is_empty()
+                    && (x as i32 - center.0 as i32).abs() <= i32::from(radius)
+                    && (y as i32 - center.1 as i32).abs() <= i32::from(radius)
+            }
+        };
+        if at_rest {
+            self.count += 1;
+            self.x.push(f64::from(x), self.count);
+            self.y.push(f64::from(y), self.count);
+        }
+        at_rest
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.count
+    }
+
+    /// Average raw position at rest minus the calibrated center.
+    pub fn offset(&self) -> Option<(f64, f64)> {
+        if self.count == 0 {
+            return None;
+        }
+        let center = self.calibration.center();
+        Some((
+            self.x.mean - f64::from(center.0),
+            self.y.mean - f64::from(center.1),
+        ))
+    }
+
+    /// Standard deviation of the raw position at rest, a lower bound for the
+    /// deadzone.
+    pub fn noise(&self) -> Option<(f64, f64)> {
+        if self.count == 0 {
+            return None;
+        }
+        Some((self.x.std_dev(self.count), self.y.std_dev(self.count)))
+    }
+
+    /// Calibration with the center moved to the measured rest position, once
+    /// at least `min_samples` samples were recorded.
+    ///
+    /// The min and max positions are kept.
+    pub fn suggested_calibration(&self, min_samples: u32) -> Option<StickCalibration<O>> {
+        if self.count == 0 || self.count < min_samples {
+            return None;
+        }
+        let center = (self.x.mean.round() as u16, self.y.mean.round() as u16);
+        Some(StickCalibration::new(
+            self.calibration.min(),
+            center,
+            self.calibration.max(),
+        ))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn detect_drift() {
+    use crate::spi::LeftStickCalibration;
+
+    let calib = LeftStickCalibration::new((500, 500), (2000, 2000), (3500, 3500));
+    let mut drift = DriftDetector::new(calib, DriftTrigger::NearCenter { radius: 100 });
+    assert!(!drift.sample(3000, 2000, ButtonSet::new()));
+    for &(x, y) in &[(2040, 1990), (2060, 1990), (2040, 2010), (2060, 2010)] {
+        assert!(drift.sample(x, y, ButtonSet::new()));
+    }
+    assert_eq!(drift.offset(), Some((50., 0.)));
+    let (noise_x, noise_y) = drift.noise().unwrap();
+    assert!((noise_x - 10.).abs() < 1e-9 && (noise_y - 10.).abs() < 1e-9);
+    assert!(drift.suggested_calibration(10).is_none());
+    let suggested = drift.suggested_calibration(4).unwrap();
+    assert_eq!(suggested.center(), (2050, 2000));
+    assert_eq!(suggested.min(), (500, 500));
+    assert_eq!(suggested.max(), (3500, 3500));
+}
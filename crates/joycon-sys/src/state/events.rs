@@ -0,0 +1,193 @@
+use super::{ControllerState, StickSide};
+use crate::input::{BatteryLevel, Button, ConnectionInfo};
+use cgmath::{MetricSpace, Vector2};
+
+/// Change between two consecutive [`ControllerState`]s.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    ButtonDown(Button),
+    ButtonUp(Button),
+    StickMoved {
+        stick: StickSide,
+        position: Vector2<f64>,
+    },
+    BatteryChanged {
+        level: BatteryLevel,
+        charging: bool,
+    },
+    /// A JoyCon was attached to or detached from the console rail, or a cable
+    /// was plugged.
+    ConnectionChanged(ConnectionInfo),
+}
+
+/// Fixed-capacity queue of [`Event`]s generated from state updates.
+///
+/// Doesn't allocate, so it can be filled from an interrupt handler and
+/// drained from the main loop. When full, new events are dropped and counted
+/// in [`EventQueue::dropped`].
+#[derive(Clone, Debug)]
+pub struct EventQueue<const N: usize> {
+    events: [Option<Event>; N],
+    head: usize,
+    len: usize,
+    dropped: usize,
+    stick_threshold: f64,
+    previous: Option<ControllerState>,
+    left_stick: Vector2<f64>,
+    right_stick: Vector2<f64>,
+}
+
+impl<const N: usize> EventQueue<N> {
+    /// `stick_threshold` is the distance a stick has to move since the last
+    /// `StickMoved` event to generate a new one.
+    pub fn new(stick_threshold: f64) -> EventQueue<N> {
+        EventQueue {
+            events: [None; N],
+            head: 0,
+            len: 0,
+            dropped: 0,
+            stick_threshold,
+            previous: None,
+            left_stick: Vector2::new(0., 0.),
+            right_stick: Vector2::new(0., 0.),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Number of events dropped because the queue was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn push(&mut self, event: Event) -> Result<(), Event> {
+        if self.is_full() {
+            self.dropped += 1;
+            return Err(event);
+        }
+        self.events[(self.head + self.len) % N] = Some(event);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<Event> {
+        if self.is_empty() {
+            return None;
+        }
+        let event = self.events[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        event
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    /// Push the events describing the changes since the previous state.
+    ///
+    /// The first state only initializes the comparison point.
+    pub fn update(&mut self, state: &ControllerState) {
+        let previous = match self.previous.replace(*state) {
+            Some(previous) => previous,
+            None => {
+                self.left_stick = state.left_stick;
+                self.right_stick = state.right_stick;
+                return;
+            }
+        };
+
+        for button in Button::ALL.iter().cloned() {
+            match (previous.is_pressed(button), state.is_pressed(button)) {
+                (false, true) => {
+                    let _ = self.push(Event::ButtonDown(button));
+                }
+                (true, false) => {
+                    let _ = self.push(Event::ButtonUp(button));
+                }
+                _ => {}
+            }
+        }
+
+        if self.left_stick.distance(state.left_stick) >= self.stick_threshold {
+            self.left_stick = state.left_stick;
+            let _ = self.push(Event::StickMoved {
+                stick: StickSide::Left,
+                position: state.left_stick,
+            });
+        }
+        if self.right_stick.distance(state.right_stick) >= self.stick_threshold {
+            self.right_stick = state.right_stick;
+            let _ = self.push(Event::StickMoved {
+                stick: StickSide::Right,
+                position: state.right_stick,
+            });
+        }
+
+        if previous.battery_level != state.battery_level || previous.charging != state.charging {
+            let _ = self.push(Event::BatteryChanged {
+                level: state.battery_level,
+                charging: state.charging,
+            });
+        }
+
+        if previous.connection != state.connection {
+            let _ = self.push(Event::ConnectionChanged(state.connection));
+        }
+    }
+}
+
+impl<const N: usize> Iterator for EventQueue<N> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.pop()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn state_diff_events() {
+    let mut queue = EventQueue::<3>::new(0.1);
+    let mut state = ControllerState::neutral(0);
+    queue.update(&state);
+    assert!(queue.is_empty());
+
+    state.buttons.insert(Button::E);
+    state.left_stick = Vector2::new(0.05, 0.);
+    queue.update(&state);
+    assert_eq!(queue.pop(), Some(Event::ButtonDown(Button::E)));
+    assert!(queue.is_empty());
+
+    state.buttons.remove(Button::E);
+    state.buttons.insert(Button::HOME);
+    state.left_stick = Vector2::new(0.1, 0.);
+    state.charging = true;
+    queue.update(&state);
+    assert_eq!(queue.dropped(), 1);
+    assert_eq!(
+        queue.collect::<Vec<_>>(),
+        [
+            Event::ButtonUp(Button::E),
+            Event::ButtonDown(Button::HOME),
+            Event::StickMoved {
+                stick: StickSide::Left,
+                position: Vector2::new(0.1, 0.)
+            },
+        ]
+    );
+}
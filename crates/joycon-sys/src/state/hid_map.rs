@@ -0,0 +1,235 @@
+use super::ControllerState;
+use crate::input::Button;
+use cgmath::Vector2;
+
+/// Size of a boot protocol keyboard report: modifiers, a reserved byte and
+/// six key codes.
+pub const KEYBOARD_REPORT_SIZE: usize = 8;
+/// Size of a boot protocol mouse report with a wheel: buttons, X, Y and
+/// wheel.
+pub const MOUSE_REPORT_SIZE: usize = 4;
+
+/// Key code reported in every slot when more than six keys are pressed.
+const KEY_ERROR_ROLL_OVER: u8 = 0x01;
+
+/// What a button does on the USB HID side.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HidAction {
+    /// Key of the keyboard/keypad usage page, eg. `0x04` for A.
+    Key(u8),
+    /// Bits of the modifier byte, eg. `0x02` for left shift.
+    Modifiers(u8),
+    /// Bits of the mouse buttons, `0x01` being the left button.
+    MouseButtons(u8),
+}
+
+/// What a stick does on the USB HID side.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StickAction {
+    None,
+    /// Move the pointer, up to `speed` units per report.
+    Pointer {
+        speed: f64,
+    },
+    /// Vertical wheel, up to `speed` units per report.
+    Wheel {
+        speed: f64,
+    },
+    /// Keys pressed past `threshold` in each direction, eg. arrows or WASD.
+    Keys {
+        up: u8,
+        down: u8,
+        left: u8,
+        right: u8,
+        threshold: f64,
+    },
+}
+
+/// Mapping of a [`ControllerState`] to USB HID boot keyboard and mouse
+/// reports, to present the controller as a keyboard and a mouse, eg. from a
+/// USB gadget.
+///
+/// ```ignore
+/// let mut map = HidMap::new();
+/// map.set_button(Button::E, Some(HidAction::Key(0x28)));
+/// map.set_right_stick(StickAction::Pointer { speed: 10. });
+/// gadget.write_keyboard(&map.keyboard_report(&state))?;
+/// gadget.write_mouse(&map.mouse_report(&state))?;
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HidMap {
+    buttons: [Option<HidAction>; Button::COUNT],
+    left_stick: StickAction,
+    right_stick: StickAction,
+}
+
+impl HidMap {
+    /// Nothing mapped.
+    pub fn new() -> HidMap {
+        HidMap {
+            buttons: [None; Button::COUNT],
+            left_stick: StickAction::None,
+            right_stick: StickAction::None,
+        }
+    }
+
+    pub fn button(&self, button: Button) -> Option<HidAction> {
+        self.buttons[button.index()]
+    }
+
+    pub fn set_button(&mut self, button: Button, action: Option<HidAction>) {
+        self.buttons[button.index()] = action;
+    }
+
+    pub fn set_left_stick(&mut self, action: StickAction) {
+        self.left_stick = action;
+    }
+
+    pub fn set_right_stick(&mut self, action: StickAction) {
+        self.right_stick = action;
+    }
+
+    fn actions<'a>(&'a self, state: &'a ControllerState) -> impl Iterator<Item = HidAction> + 'a {
+        let mut stick_keys = keys_of(self.left_stick, state.left_stick);
+        stick_keys.extend(keys_of(self.right_stick, state.right_stick));
+        state
+            .buttons
+            .iter()
+            .filter_map(move |b| self.button(b))
+            .chain(stick_keys.into_iter().map(HidAction::Key))
+    }
+
+    /// Boot protocol keyboard report of `state`.
+    pub fn keyboard_report(&self, state: &ControllerState) -> [u8; KEYBOARD_REPORT_SIZE] {
+        let mut report = [0; KEYBOARD_REPORT_SIZE];
+        let mut keys = 0;
+        for action in self.actions(state) {
+            match action {
+                HidAction::Modifiers(bits) => report[0] |= bits,
+                HidAction::Key(key) if report[2..2 + keys].contains(&key) => {}
+                HidAction::Key(key) if keys < 6 => {
+                    report[2 + keys] = key;
+                    keys += 1;
+                }
+                HidAction::Key(_) => {
+                    report[2..].copy_from_slice(&[KEY_ERROR_ROLL_OVER; 6]);
+                    break;
+                }
+                HidAction::MouseButtons(_) => {}
+            }
+        }
+        report
+    }
+
+    /// Boot protocol mouse report of `state`, with a wheel.
+    pub fn mouse_report(&self, state: &ControllerState) -> [u8; MOUSE_REPORT_SIZE] {
+        let mut report = [0; MOUSE_REPORT_SIZE];
+        for action in self.actions(state) {
+            if let HidAction::MouseButtons(bits) = action {
+                report[0] |= bits;
+            }
+        }
+        let (mut x, mut y, mut wheel) = (0., 0., 0.);
+        for &(action, position) in &[
+            (self.left_stick, state.left_stick),
+            (self.right_stick, state.right_stick),
+        ] {
+            match action {
+                StickAction::Pointer { speed } => {
+                    x += position.x * speed;
+                    // HID Y goes down.
+                    y -= position.y * speed;
+                }
+                StickAction::Wheel { speed } => wheel += position.y * speed,
+                StickAction::None | StickAction::Keys { .. } => {}
+            }
+        }
+        report[1] = relative(x);
+        report[2] = relative(y);
+        report[3] = relative(wheel);
+        report
+    }
+}
+
+impl Default for HidMap {
+    fn default() -> Self {
+        HidMap::new()
+    }
+}
+
+/// Keys pressed by a stick mapped to [`StickAction::Keys`].
+fn keys_of(action: StickAction, position: Vector2<f64>) -> Vec<u8> {
+    match action {
+        StickAction::Keys {
+            up,
+            down,
+            left,
+            right,
+            threshold,
+        } => [
+            (position.y >= threshold, up),
+            (position.y <= -threshold, down),
+            (position.x <= -threshold, left),
+            (position.x >= threshold, right),
+        ]
+        .iter()
+        .filter(|(pressed, _)| *pressed)
+        .map(|(_, key)| *key)
+        .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Relative axis value of a report, a signed byte.
+fn relative(value: f64) -> u8 {
+    value.round().clamp(-127., 127.) as i8 as u8
+}
+
+#[cfg(test)]
+#[test]
+fn hid_reports() {
+    use crate::input::{BatteryLevel, ConnectionInfo};
+
+    let mut map = HidMap::new();
+    map.set_button(Button::E, Some(HidAction::Key(0x28)));
+    map.set_button(Button::ZR, Some(HidAction::MouseButtons(0x01)));
+    map.set_button(Button::L, Some(HidAction::Modifiers(0x02)));
+    map.set_left_stick(StickAction::Keys {
+        up: 0x1a,
+        down: 0x16,
+        left: 0x04,
+        right: 0x07,
+        threshold: 0.5,
+    });
+    map.set_right_stick(StickAction::Pointer { speed: 10. });
+
+    let mut state = ControllerState {
+        timer: 0,
+        buttons: Default::default(),
+        left_stick: Vector2::new(0.7, 0.6),
+        right_stick: Vector2::new(0.5, 1.),
+        left_trigger: 0.,
+        right_trigger: 0.,
+        battery_level: BatteryLevel::Full,
+        charging: false,
+        connection: ConnectionInfo::Bluetooth,
+    };
+    state.buttons.insert(Button::E);
+    state.buttons.insert(Button::L);
+    state.buttons.insert(Button::ZR);
+
+    assert_eq!(
+        map.keyboard_report(&state),
+        [0x02, 0, 0x28, 0x1a, 0x07, 0, 0, 0]
+    );
+    assert_eq!(map.mouse_report(&state), [0x01, 5, -10i8 as u8, 0]);
+
+    for (i, button) in Button::ALL.iter().enumerate() {
+        map.set_button(*button, Some(HidAction::Key(0x04 + i as u8)));
+        state.buttons.insert(*button);
+    }
+    assert_eq!(map.keyboard_report(&state)[2..], [KEY_ERROR_ROLL_OVER; 6]);
+}
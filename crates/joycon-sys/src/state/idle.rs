@@ -0,0 +1,119 @@
+use super::ControllerState;
+use crate::{clock::Timestamp, common::HCIState, output::SubcommandRequest, OutputReport};
+use cgmath::Vector2;
+use std::time::Duration;
+
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Stick movement ignored as noise, on each axis.
+pub const DEFAULT_IDLE_STICK_THRESHOLD: f64 = 0.1;
+
+/// Puts the controller to sleep after a period without input, to save its
+/// battery.
+///
+/// The sleep report disconnects the controller. A button press wakes it up
+/// and makes it reconnect to the last paired host, the stick movements
+/// don't. The reconnection is a new connection for the host, which has to
+/// bring the controller up again.
+///
+/// ```ignore
+/// let mut idle = IdleTracker::new(DEFAULT_IDLE_TIMEOUT);
+/// if let Some(report) = idle.update(&state, clock.now()) {
+///     send(&report)?;
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct IdleTracker {
+    timeout: Duration,
+    stick_threshold: f64,
+    last_input: Option<Timestamp>,
+    sticks: Option<(Vector2<f64>, Vector2<f64>)>,
+    asleep: bool,
+}
+
+impl IdleTracker {
+    pub fn new(timeout: Duration) -> IdleTracker {
+        IdleTracker {
+            timeout,
+            stick_threshold: DEFAULT_IDLE_STICK_THRESHOLD,
+            last_input: None,
+            sticks: None,
+            asleep: false,
+        }
+    }
+
+    pub fn with_stick_threshold(mut self, threshold: f64) -> IdleTracker {
+        self.stick_threshold = threshold;
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Time since the last input.
+    pub fn idle_for(&self, now: Timestamp) -> Duration {
+        self.last_input.map_or(Duration::from_secs(0), |last| {
+            now.saturating_duration_since(last)
+        })
+    }
+
+    /// Whether the sleep report was already given.
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+
+    /// Consider the controller active at `now`, eg. after a reconnection.
+    pub fn wake(&mut self, now: Timestamp) {
+        self.last_input = Some(now);
+        self.sticks = None;
+        self.asleep = false;
+    }
+
+    /// Check the state received at `now`, giving the sleep report once the
+    /// controller is idle for the timeout.
+    pub fn update(&mut self, state: &ControllerState, now: Timestamp) -> Option<OutputReport> {
+        let moved = |rest: Vector2<f64>, stick: Vector2<f64>| {
+            (stick.x - rest.x).abs() > self.stick_threshold
+                || (stick.y - rest.y).abs() > self.stick_threshold
+        };
+        let active = !state.buttons.is_empty()
+            || match self.sticks {
+                Some((left, right)) => {
+                    moved(left, state.left_stick) || moved(right, state.right_stick)
+                }
+                None => true,
+            };
+        if active || self.last_input.is_none() {
+            self.wake(now);
+            self.sticks = Some((state.left_stick, state.right_stick));
+            return None;
+        }
+        if self.asleep || self.idle_for(now) < self.timeout {
+            return None;
+        }
+        trace!("idle for {:?}, going to sleep", self.idle_for(now));
+        self.asleep = true;
+        Some(SubcommandRequest::from(HCIState::Disconnect).into())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn idle_tracker() {
+    use crate::input::Button;
+
+    let mut state = ControllerState::neutral(0);
+    let at = |secs: u64| Timestamp::from_micros(secs * 1_000_000);
+    let mut idle = IdleTracker::new(Duration::from_secs(60));
+    assert!(idle.update(&state, at(0)).is_none());
+    state.left_stick.x = 0.05;
+    assert!(idle.update(&state, at(30)).is_none());
+    state.buttons.insert(Button::E);
+    assert!(idle.update(&state, at(40)).is_none());
+    state.buttons.remove(Button::E);
+    assert!(idle.update(&state, at(99)).is_none());
+    let report = idle.update(&state, at(100)).unwrap();
+    assert_eq!(report.as_bytes()[10..12], [0x06, 0x00]);
+    assert!(idle.is_asleep());
+    assert!(idle.update(&state, at(200)).is_none());
+}
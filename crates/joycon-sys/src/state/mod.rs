@@ -0,0 +1,197 @@
+//! High-level controller state derived from the standard input reports.
+//!
+//! [`ControllerState`] is the calibrated, remapped view of a
+//! [`StandardInputReport`](../input/struct.StandardInputReport.html) that the
+//! processing helpers of this module operate on.
+
+use crate::{
+    input::{BatteryLevel, Button, ButtonsStatus, ConnectionInfo, StandardInputReport},
+    spi::SticksCalibration,
+};
+use cgmath::Vector2;
+use std::fmt;
+
+mod battery;
+mod circularity;
+mod combo;
+mod dpad;
+mod drift;
+mod events;
+mod hid_map;
+mod idle;
+mod orientation;
+mod recorder;
+mod remap;
+mod stats;
+mod trigger;
+mod turbo;
+
+pub use battery::*;
+pub use circularity::*;
+pub use combo::*;
+pub use dpad::*;
+pub use drift::*;
+pub use events::*;
+pub use hid_map::*;
+pub use idle::*;
+pub use orientation::*;
+pub use recorder::*;
+pub use remap::*;
+pub use stats::*;
+pub use trigger::*;
+pub use turbo::*;
+
+/// Set of buttons, stored as a bitmask indexed by [`Button::index`].
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct ButtonSet(u32);
+
+impl ButtonSet {
+    pub fn new() -> ButtonSet {
+        ButtonSet(0)
+    }
+
+    pub fn contains(self, button: Button) -> bool {
+        self.0 & (1 << button.index()) != 0
+    }
+
+    pub fn set(&mut self, button: Button, pressed: bool) {
+        if pressed {
+            self.0 |= 1 << button.index();
+        } else {
+            self.0 &= !(1 << button.index());
+        }
+    }
+
+    pub fn insert(&mut self, button: Button) {
+        self.set(button, true);
+    }
+
+    pub fn remove(&mut self, button: Button) {
+        self.set(button, false);
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Button> {
+        Button::ALL
+            .iter()
+            .cloned()
+            .filter(move |b| self.contains(*b))
+    }
+}
+
+impl From<ButtonsStatus> for ButtonSet {
+    fn from(status: ButtonsStatus) -> ButtonSet {
+        let mut set = ButtonSet::new();
+        for button in Button::ALL.iter().cloned() {
+            set.set(button, status.is_pressed(button));
+        }
+        set
+    }
+}
+
+impl fmt::Debug for ButtonSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Calibrated state of the controller at one point in time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ControllerState {
+    /// Report timer, incremented by the controller for each report.
+    pub timer: u8,
+    /// Logical buttons, after remapping.
+    pub buttons: ButtonSet,
+    /// Calibrated stick position, between -1 and 1 on each axis.
+    pub left_stick: Vector2<f64>,
+    /// Calibrated stick position, between -1 and 1 on each axis.
+    pub right_stick: Vector2<f64>,
+    /// Analog value of ZL between 0 and 1.
+    ///
+    /// The triggers are digital so this is either 0 or 1, unless modified by
+    /// an [`AnalogTrigger`].
+    pub left_trigger: f64,
+    /// Analog value of ZR, see `left_trigger`.
+    pub right_trigger: f64,
+    pub battery_level: BatteryLevel,
+    pub charging: bool,
+    pub connection: ConnectionInfo,
+}
+
+impl ControllerState {
+    pub fn new(report: &StandardInputReport, sticks: &SticksCalibration) -> ControllerState {
+        ControllerState::with_remap(report, sticks, &ButtonRemap::identity())
+    }
+
+    pub fn with_remap(
+        report: &StandardInputReport,
+        sticks: &SticksCalibration,
+        remap: &ButtonRemap,
+    ) -> ControllerState {
+        let left_stick = report.left_stick;
+        let right_stick = report.right_stick;
+        let buttons = remap.apply(report.buttons.into());
+        ControllerState {
+            timer: report.timer,
+            buttons,
+            left_stick: sticks.left.value_from_raw(left_stick.x(), left_stick.y()),
+            right_stick: sticks
+                .right
+                .value_from_raw(right_stick.x(), right_stick.y()),
+            left_trigger: if buttons.contains(Button::ZL) { 1. } else { 0. },
+            right_trigger: if buttons.contains(Button::ZR) { 1. } else { 0. },
+            battery_level: report.info.battery_level(),
+            charging: report.info.charging(),
+            connection: report.info.connection_info(),
+        }
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.buttons.contains(button)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn neutral(timer: u8) -> ControllerState {
+        ControllerState {
+            timer,
+            buttons: ButtonSet::new(),
+            left_stick: cgmath::vec2(0., 0.),
+            right_stick: cgmath::vec2(0., 0.),
+            left_trigger: 0.,
+            right_trigger: 0.,
+            battery_level: BatteryLevel::Full,
+            charging: false,
+            connection: ConnectionInfo::Bluetooth,
+        }
+    }
+}
+
+/// Report timer ticks elapsed between consecutive states, across the
+/// wraparound of the timer.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct TimerTicks {
+    last: Option<u8>,
+}
+
+impl TimerTicks {
+    /// Ticks since the previous state, 0 for the first one.
+    pub fn advance(&mut self, timer: u8) -> u32 {
+        let delta = self
+            .last
+            .map(|last| u32::from(timer.wrapping_sub(last)))
+            .unwrap_or(0);
+        self.last = Some(timer);
+        delta
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
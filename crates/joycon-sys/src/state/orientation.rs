@@ -0,0 +1,107 @@
+use super::ControllerState;
+use cgmath::Vector2;
+
+/// Axis changes of a calibrated stick, for controllers mounted sideways or in
+/// a custom housing.
+///
+/// The axes are swapped first, then inverted: `invert_x` inverts the X axis
+/// of the result.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StickOrientation {
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub swap_axes: bool,
+}
+
+impl StickOrientation {
+    /// Orientation of a JoyCon held sideways, SL and SR on top, the stick
+    /// being on the left for a left JoyCon.
+    pub fn sideways_left() -> StickOrientation {
+        StickOrientation {
+            invert_x: true,
+            invert_y: false,
+            swap_axes: true,
+        }
+    }
+
+    /// Orientation of a JoyCon held sideways, SL and SR on top, the stick
+    /// being on the right for a right JoyCon.
+    pub fn sideways_right() -> StickOrientation {
+        StickOrientation {
+            invert_x: false,
+            invert_y: true,
+            swap_axes: true,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == StickOrientation::default()
+    }
+
+    pub fn correct(&self, position: Vector2<f64>) -> Vector2<f64> {
+        let mut out = if self.swap_axes {
+            Vector2::new(position.y, position.x)
+        } else {
+            position
+        };
+        if self.invert_x {
+            out.x = -out.x;
+        }
+        if self.invert_y {
+            out.y = -out.y;
+        }
+        out
+    }
+}
+
+/// Orientation of both sticks, applied after the calibration.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SticksOrientation {
+    pub left: StickOrientation,
+    pub right: StickOrientation,
+}
+
+impl SticksOrientation {
+    pub fn apply(&self, state: &mut ControllerState) {
+        state.left_stick = self.left.correct(state.left_stick);
+        state.right_stick = self.right.correct(state.right_stick);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn stick_orientation() {
+    let up = Vector2::new(0., 1.);
+    let right = Vector2::new(1., 0.);
+    assert_eq!(StickOrientation::default().correct(up), up);
+
+    let inverted = StickOrientation {
+        invert_y: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        inverted.correct(Vector2::new(0.5, 0.25)),
+        Vector2::new(0.5, -0.25)
+    );
+
+    // Left JoyCon sideways: pushing towards the SL/SR rail is up.
+    let left = StickOrientation::sideways_left();
+    assert_eq!(left.correct(right), up);
+    assert_eq!(left.correct(up), -right);
+    // Right JoyCon sideways: the rail is on the other side.
+    let right_joycon = StickOrientation::sideways_right();
+    assert_eq!(right_joycon.correct(-right), up);
+
+    let mut state = ControllerState::neutral(0);
+    state.left_stick = right;
+    state.right_stick = right;
+    SticksOrientation {
+        left,
+        right: StickOrientation::default(),
+    }
+    .apply(&mut state);
+    assert_eq!(state.left_stick, up);
+    assert_eq!(state.right_stick, right);
+}
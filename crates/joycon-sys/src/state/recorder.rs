@@ -0,0 +1,109 @@
+use super::{ControllerState, TimerTicks};
+
+/// A [`ControllerState`] stored by a [`RingRecorder`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Recorded {
+    /// Report timer ticks since the first recorded state. Unlike the report
+    /// timer, doesn't wrap around.
+    pub ticks: u32,
+    pub state: ControllerState,
+}
+
+/// Keeps the last `N` states, to capture what happened right before a bug.
+///
+/// Doesn't allocate, so it can run on embedded devices and be dumped when
+/// something goes wrong. Older states are overwritten once full.
+///
+/// ```ignore
+/// let mut recorder = RingRecorder::<256>::new();
+/// recorder.record(&state);
+/// if bug_detected {
+///     for Recorded { ticks, state } in recorder.iter() {
+///         log(ticks, state);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RingRecorder<const N: usize> {
+    states: [Option<Recorded>; N],
+    head: usize,
+    len: usize,
+    ticks: u32,
+    timer: TimerTicks,
+}
+
+impl<const N: usize> RingRecorder<N> {
+    pub fn new() -> RingRecorder<N> {
+        RingRecorder {
+            states: [None; N],
+            head: 0,
+            len: 0,
+            ticks: 0,
+            timer: TimerTicks::default(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        *self = RingRecorder::new();
+    }
+
+    pub fn record(&mut self, state: &ControllerState) {
+        if N == 0 {
+            return;
+        }
+        self.ticks = self.ticks.saturating_add(self.timer.advance(state.timer));
+        let recorded = Recorded {
+            ticks: self.ticks,
+            state: *state,
+        };
+        if self.len < N {
+            self.states[(self.head + self.len) % N] = Some(recorded);
+            self.len += 1;
+        } else {
+            self.states[self.head] = Some(recorded);
+            self.head = (self.head + 1) % N;
+        }
+    }
+
+    /// Most recent state.
+    pub fn latest(&self) -> Option<&Recorded> {
+        self.iter().next_back()
+    }
+
+    /// Recorded states, from the oldest to the newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Recorded> + ExactSizeIterator {
+        (0..self.len).map(move |i| self.states[(self.head + i) % N].as_ref().expect("recorded"))
+    }
+}
+
+impl<const N: usize> Default for RingRecorder<N> {
+    fn default() -> Self {
+        RingRecorder::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn ring_recorder() {
+    let mut recorder = RingRecorder::<3>::new();
+    assert!(recorder.latest().is_none());
+    for timer in [250u8, 253, 0, 3, 6].iter() {
+        recorder.record(&ControllerState::neutral(*timer));
+    }
+    assert_eq!(recorder.len(), 3);
+    let ticks: Vec<_> = recorder.iter().map(|r| r.ticks).collect();
+    assert_eq!(ticks, [6, 9, 12]);
+    assert_eq!(recorder.latest().unwrap().state.timer, 6);
+}
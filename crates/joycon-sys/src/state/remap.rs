@@ -0,0 +1,78 @@
+use super::ButtonSet;
+use crate::input::Button;
+
+/// Mapping from the physical buttons to the logical buttons reported in
+/// [`ControllerState`](struct.ControllerState.html).
+///
+/// Several physical buttons can be mapped to the same logical button, and a
+/// physical button can be disabled by mapping it to `None`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ButtonRemap {
+    map: [Option<Button>; Button::COUNT],
+}
+
+impl ButtonRemap {
+    /// Each button is mapped to itself.
+    pub fn identity() -> ButtonRemap {
+        let mut map = [None; Button::COUNT];
+        for button in Button::ALL.iter().cloned() {
+            map[button.index()] = Some(button);
+        }
+        ButtonRemap { map }
+    }
+
+    pub fn get(&self, physical: Button) -> Option<Button> {
+        self.map[physical.index()]
+    }
+
+    pub fn set(&mut self, physical: Button, logical: Option<Button>) {
+        self.map[physical.index()] = logical;
+    }
+
+    /// Exchange the mappings of two physical buttons.
+    pub fn swap(&mut self, a: Button, b: Button) {
+        self.map.swap(a.index(), b.index());
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == ButtonRemap::identity()
+    }
+
+    pub fn apply(&self, physical: ButtonSet) -> ButtonSet {
+        let mut logical = ButtonSet::new();
+        for button in physical.iter() {
+            if let Some(mapped) = self.get(button) {
+                logical.insert(mapped);
+            }
+        }
+        logical
+    }
+}
+
+impl Default for ButtonRemap {
+    fn default() -> Self {
+        ButtonRemap::identity()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn remap_buttons() {
+    let mut remap = ButtonRemap::identity();
+    remap.swap(Button::E, Button::S);
+    remap.set(Button::CAPTURE, None);
+    remap.set(Button::SL, Some(Button::L));
+
+    let mut physical = ButtonSet::new();
+    physical.insert(Button::E);
+    physical.insert(Button::CAPTURE);
+    physical.insert(Button::SL);
+    let logical = remap.apply(physical);
+
+    assert!(logical.contains(Button::S));
+    assert!(logical.contains(Button::L));
+    assert!(!logical.contains(Button::E));
+    assert!(!logical.contains(Button::CAPTURE));
+    assert_eq!(logical.iter().count(), 2);
+}
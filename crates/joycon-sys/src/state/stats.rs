@@ -0,0 +1,132 @@
+use super::ButtonSet;
+use crate::input::{Button, StandardInputReport, Stick};
+
+/// Largest raw stick value, the sticks being 12 bits.
+const STICK_MAX: u16 = 0xfff;
+
+/// Usage counters accumulated over the input reports.
+///
+/// The sticks are counted on a `G`×`G` grid over their raw range, without
+/// calibration, so the counters are comparable between controllers and wear
+/// of the stick gate shows up as cells never reached. No allocation is done,
+/// the counters are plain arrays:
+///
+/// ```ignore
+/// let mut stats = InputStats::<16>::new();
+/// loop {
+///     stats.update(report.standard().unwrap());
+/// }
+/// save(stats.press_counts(), stats.left_stick_histogram());
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct InputStats<const G: usize> {
+    reports: u32,
+    pressed: ButtonSet,
+    press_counts: [u32; Button::COUNT],
+    left_stick: [[u32; G]; G],
+    right_stick: [[u32; G]; G],
+}
+
+impl<const G: usize> InputStats<G> {
+    pub fn new() -> InputStats<G> {
+        assert!(G > 0, "empty stick grid");
+        InputStats {
+            reports: 0,
+            pressed: ButtonSet::new(),
+            press_counts: [0; Button::COUNT],
+            left_stick: [[0; G]; G],
+            right_stick: [[0; G]; G],
+        }
+    }
+
+    /// Count a report: the buttons pressed since the previous one and the
+    /// cells of the sticks.
+    pub fn update(&mut self, report: &StandardInputReport) {
+        let pressed = ButtonSet::from(report.buttons);
+        for button in pressed.iter() {
+            if !self.pressed.contains(button) {
+                self.press_counts[button.index()] =
+                    self.press_counts[button.index()].saturating_add(1);
+            }
+        }
+        self.pressed = pressed;
+        let (x, y) = InputStats::<G>::cell(report.left_stick);
+        self.left_stick[y][x] = self.left_stick[y][x].saturating_add(1);
+        let (x, y) = InputStats::<G>::cell(report.right_stick);
+        self.right_stick[y][x] = self.right_stick[y][x].saturating_add(1);
+        self.reports = self.reports.saturating_add(1);
+    }
+
+    /// Number of reports counted.
+    pub fn reports(&self) -> u32 {
+        self.reports
+    }
+
+    pub fn press_count(&self, button: Button) -> u32 {
+        self.press_counts[button.index()]
+    }
+
+    /// Press counts indexed by [`Button::index`].
+    pub fn press_counts(&self) -> &[u32; Button::COUNT] {
+        &self.press_counts
+    }
+
+    /// Reports per cell of the left stick, indexed by `[y][x]`, `[0][0]`
+    /// being the bottom left corner.
+    pub fn left_stick_histogram(&self) -> &[[u32; G]; G] {
+        &self.left_stick
+    }
+
+    pub fn right_stick_histogram(&self) -> &[[u32; G]; G] {
+        &self.right_stick
+    }
+
+    pub fn reset(&mut self) {
+        *self = InputStats::new();
+    }
+
+    /// Cell of the grid containing the raw position of `stick`.
+    pub fn cell(stick: Stick) -> (usize, usize) {
+        let cell = |v: u16| usize::from(v.min(STICK_MAX)) * G / (usize::from(STICK_MAX) + 1);
+        (cell(stick.x()), cell(stick.y()))
+    }
+}
+
+impl<const G: usize> Default for InputStats<G> {
+    fn default() -> Self {
+        InputStats::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn input_stats() {
+    use crate::common::Wire;
+
+    let report = |a_pressed: bool, left: Stick| {
+        let mut raw = [0; 12];
+        // A in the right buttons.
+        raw[2] = if a_pressed { 0x08 } else { 0 };
+        let mut report = StandardInputReport::from_bytes(&raw).unwrap();
+        report.left_stick = left;
+        report.right_stick = Stick::new(0xfff, 0);
+        report
+    };
+    let center = Stick::new(0x800, 0x800);
+
+    let mut stats = InputStats::<4>::new();
+    stats.update(&report(false, center));
+    stats.update(&report(true, center));
+    stats.update(&report(true, center));
+    stats.update(&report(false, center));
+    stats.update(&report(true, Stick::new(0x7ff, 0)));
+
+    assert_eq!(stats.reports(), 5);
+    assert_eq!(stats.press_count(Button::E), 2);
+    assert_eq!(stats.press_count(Button::S), 0);
+    assert_eq!(stats.left_stick_histogram()[2][2], 4);
+    assert_eq!(stats.left_stick_histogram()[0][1], 1);
+    assert_eq!(stats.right_stick_histogram()[0][3], 5);
+    stats.reset();
+    assert_eq!(stats, InputStats::default());
+}
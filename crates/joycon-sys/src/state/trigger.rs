@@ -0,0 +1,154 @@
+use super::{ControllerState, TimerTicks};
+use crate::input::Button;
+
+/// How the analog value of a trigger is synthesized from its digital state.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TriggerMode {
+    /// The value rises linearly while the trigger is held, reaching 1 after
+    /// `ramp_ticks` report timer ticks, and goes back to 0 on release.
+    Hold { ramp_ticks: u16 },
+    /// Each press starting less than `chain_ticks` after the previous release
+    /// raises the level by one, up to `levels`. The value is
+    /// `level / levels` while held and 0 when released.
+    Clicks { levels: u8, chain_ticks: u16 },
+}
+
+impl Default for TriggerMode {
+    fn default() -> Self {
+        TriggerMode::Hold { ramp_ticks: 30 }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct TriggerState {
+    held: bool,
+    ticks: u32,
+    level: u8,
+}
+
+impl TriggerState {
+    fn update(&mut self, mode: TriggerMode, pressed: bool, delta: u32) -> f64 {
+        let was_held = self.held;
+        self.held = pressed;
+        match mode {
+            TriggerMode::Hold { ramp_ticks } => {
+                if !pressed {
+                    self.ticks = 0;
+                    return 0.;
+                }
+                if was_held {
+                    self.ticks = self.ticks.saturating_add(delta);
+                }
+                if ramp_ticks == 0 {
+                    1.
+                } else {
+                    (self.ticks as f64 / ramp_ticks as f64).min(1.)
+                }
+            }
+            TriggerMode::Clicks {
+                levels,
+                chain_ticks,
+            } => {
+                let levels = levels.max(1);
+                if pressed != was_held {
+                    if pressed {
+                        let chained = self.ticks.saturating_add(delta) < u32::from(chain_ticks);
+                        self.level = if chained && self.level > 0 {
+                            (self.level + 1).min(levels)
+                        } else {
+                            1
+                        };
+                    }
+                    self.ticks = 0;
+                } else {
+                    self.ticks = self.ticks.saturating_add(delta);
+                }
+                if pressed {
+                    f64::from(self.level) / f64::from(levels)
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+}
+
+/// Synthesizes analog values for the ZL and ZR triggers.
+///
+/// Updates `left_trigger` and `right_trigger` of [`ControllerState`]. The
+/// timer advances once per IMU sample, so a `ramp_ticks` of 200 takes about a
+/// second to reach full pull.
+#[derive(Clone, Debug)]
+pub struct AnalogTrigger {
+    mode: TriggerMode,
+    timer: TimerTicks,
+    left: TriggerState,
+    right: TriggerState,
+}
+
+impl AnalogTrigger {
+    pub fn new(mode: TriggerMode) -> AnalogTrigger {
+        AnalogTrigger {
+            mode,
+            timer: TimerTicks::default(),
+            left: TriggerState::default(),
+            right: TriggerState::default(),
+        }
+    }
+
+    pub fn mode(&self) -> TriggerMode {
+        self.mode
+    }
+
+    pub fn reset(&mut self) {
+        *self = AnalogTrigger::new(self.mode);
+    }
+
+    pub fn apply(&mut self, state: &mut ControllerState) {
+        let delta = self.timer.advance(state.timer);
+        state.left_trigger = self
+            .left
+            .update(self.mode, state.is_pressed(Button::ZL), delta);
+        state.right_trigger = self
+            .right
+            .update(self.mode, state.is_pressed(Button::ZR), delta);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn analog_trigger_modes() {
+    let run = |mode, presses: &[bool]| {
+        let mut trigger = AnalogTrigger::new(mode);
+        presses
+            .iter()
+            .enumerate()
+            .map(|(timer, pressed)| {
+                let mut state = ControllerState::neutral(timer as u8);
+                state.buttons.set(Button::ZR, *pressed);
+                trigger.apply(&mut state);
+                assert_eq!(state.left_trigger, 0.);
+                state.right_trigger
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        run(
+            TriggerMode::Hold { ramp_ticks: 2 },
+            &[true, true, true, true, false, true]
+        ),
+        [0., 0.5, 1., 1., 0., 0.]
+    );
+    assert_eq!(
+        run(
+            TriggerMode::Clicks {
+                levels: 2,
+                chain_ticks: 2
+            },
+            &[true, false, true, false, true, false, false, false, true]
+        ),
+        [0.5, 0., 1., 0., 1., 0., 0., 0., 0.5]
+    );
+}
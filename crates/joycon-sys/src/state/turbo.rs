@@ -0,0 +1,137 @@
+use super::{ButtonSet, ControllerState, TimerTicks};
+use crate::input::Button;
+
+/// Turbo rate of one button, in report timer ticks.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TurboRate {
+    /// Number of ticks the button is reported pressed.
+    pub pressed_ticks: u8,
+    /// Number of ticks the button is reported released.
+    pub released_ticks: u8,
+}
+
+impl TurboRate {
+    /// Symmetric rate with a full press/release cycle lasting `period_ticks`.
+    pub fn with_period(period_ticks: u8) -> TurboRate {
+        let pressed_ticks = (period_ticks / 2).max(1);
+        TurboRate {
+            pressed_ticks,
+            released_ticks: period_ticks.saturating_sub(pressed_ticks).max(1),
+        }
+    }
+
+    fn period(self) -> u32 {
+        u32::from(self.pressed_ticks.max(1)) + u32::from(self.released_ticks.max(1))
+    }
+
+    fn is_pressed_at(self, elapsed: u32) -> bool {
+        elapsed % self.period() < u32::from(self.pressed_ticks.max(1))
+    }
+}
+
+/// Which logical buttons have turbo enabled, and at which rate.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TurboConfig {
+    rates: [Option<TurboRate>; Button::COUNT],
+}
+
+impl TurboConfig {
+    pub fn get(&self, button: Button) -> Option<TurboRate> {
+        self.rates[button.index()]
+    }
+
+    pub fn set(&mut self, button: Button, rate: Option<TurboRate>) {
+        self.rates[button.index()] = rate;
+    }
+
+    pub fn buttons(&self) -> ButtonSet {
+        let mut set = ButtonSet::new();
+        for button in Button::ALL.iter().cloned() {
+            set.set(button, self.get(button).is_some());
+        }
+        set
+    }
+}
+
+/// Toggles the held buttons configured in a [`TurboConfig`].
+///
+/// Durations are measured with the report timer so the result only depends on
+/// the reports received, not on when the host processes them.
+#[derive(Clone, Debug)]
+pub struct Turbo {
+    config: TurboConfig,
+    timer: TimerTicks,
+    held_ticks: [u32; Button::COUNT],
+}
+
+impl Turbo {
+    pub fn new(config: TurboConfig) -> Turbo {
+        Turbo {
+            config,
+            timer: TimerTicks::default(),
+            held_ticks: [0; Button::COUNT],
+        }
+    }
+
+    pub fn config(&self) -> &TurboConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: TurboConfig) {
+        self.config = config;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.timer.reset();
+        self.held_ticks = [0; Button::COUNT];
+    }
+
+    /// Update the turbo state with a new report and toggle the held turbo buttons.
+    pub fn apply(&mut self, state: &mut ControllerState) {
+        let delta = self.timer.advance(state.timer);
+
+        for button in Button::ALL.iter().cloned() {
+            let rate = match self.config.get(button) {
+                Some(rate) => rate,
+                None => continue,
+            };
+            let held = &mut self.held_ticks[button.index()];
+            if state.buttons.contains(button) {
+                *held = held.saturating_add(delta);
+                state.buttons.set(button, rate.is_pressed_at(*held));
+            } else {
+                // Always start a new press with the button pressed.
+                *held = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn turbo_toggle() {
+    let mut config = TurboConfig::default();
+    config.set(Button::E, Some(TurboRate::with_period(4)));
+    let mut turbo = Turbo::new(config);
+
+    let mut pressed = ButtonSet::new();
+    pressed.insert(Button::E);
+    pressed.insert(Button::S);
+    let mut out = vec![];
+    for timer in (250..=255).chain(0..4) {
+        let mut state = ControllerState {
+            buttons: pressed,
+            ..ControllerState::neutral(timer)
+        };
+        turbo.apply(&mut state);
+        assert!(state.is_pressed(Button::S));
+        out.push(state.is_pressed(Button::E));
+    }
+    assert_eq!(
+        out,
+        [true, true, false, false, true, true, false, false, true, true]
+    );
+}
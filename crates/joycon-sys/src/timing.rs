@@ -0,0 +1,78 @@
+//! Cadence of the output reports expected by the controller.
+//!
+//! The official drivers send an output report every 15ms, subcommands being
+//! spaced further apart. Going faster makes the controller drop reports,
+//! going slower makes the rumble stutter. [`Schedule`] groups these delays,
+//! for the [`RateGovernor`](../output/struct.RateGovernor.html) or for drivers
+//! timing the reports themselves:
+//!
+//! ```ignore
+//! let schedule = Schedule::OFFICIAL;
+//! loop {
+//!     send(&next_rumble_report())?;
+//!     sleep(schedule.report_interval);
+//! }
+//! ```
+
+use std::time::Duration;
+
+/// Minimum delay between two output reports. Rumble updates are usually sent
+/// at this cadence.
+pub const REPORT_INTERVAL: Duration = Duration::from_millis(15);
+
+/// Minimum delay between two subcommands. The controller drops subcommands
+/// sent faster, even though it acknowledges some of them.
+pub const SUBCOMMAND_SPACING: Duration = Duration::from_millis(50);
+
+/// Delay between two MCU requests while streaming IR images or NFC data, one
+/// per output report slot.
+pub const MCU_POLL_INTERVAL: Duration = REPORT_INTERVAL;
+
+/// Maximum delay between two output reports.
+///
+/// Without output reports the controller eventually falls back from the full
+/// report modes to the simple HID mode, so a driver with nothing to send must
+/// send a [`OutputReport::keepalive`](../output/struct.OutputReport.html#method.keepalive)
+/// at least this often.
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Delays between the output reports sent to a controller.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Schedule {
+    /// Minimum delay between two output reports.
+    pub report_interval: Duration,
+    /// Minimum delay between two subcommands.
+    pub subcommand_spacing: Duration,
+    /// Delay between two MCU requests.
+    pub mcu_poll_interval: Duration,
+    /// Maximum delay without any output report.
+    pub keepalive_interval: Duration,
+}
+
+impl Schedule {
+    /// Cadence of the official drivers.
+    pub const OFFICIAL: Schedule = Schedule {
+        report_interval: REPORT_INTERVAL,
+        subcommand_spacing: SUBCOMMAND_SPACING,
+        mcu_poll_interval: MCU_POLL_INTERVAL,
+        keepalive_interval: KEEPALIVE_INTERVAL,
+    };
+
+    /// Output reports per second at most.
+    pub fn report_rate(&self) -> f64 {
+        1. / self.report_interval.as_secs_f64()
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule::OFFICIAL
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn official_schedule() {
+    assert!((Schedule::OFFICIAL.report_rate() - 66.67).abs() < 0.01);
+    assert_eq!(Schedule::default().mcu_poll_interval, REPORT_INTERVAL);
+}
@@ -0,0 +1,293 @@
+//! USB framing of the Pro Controller.
+//!
+//! Over USB, the HID reports travel in 64-byte packets. Packets starting with
+//! [`USB_COMMAND`] are commands to the USB bridge of the controller, replied
+//! with [`USB_REPLY`]. With [`UsbCommand::Wrapped`], the Switch sends its
+//! output reports after an 8-byte header, `80 92 00 31 00 00 xx xx` in the
+//! captures, the length of the report being big endian. A report longer than
+//! [`MAX_FRAME_LEN`], like the MCU ones carrying the NFC and IR data,
+//! continues in the following packets without header, and is reassembled by
+//! a [`FrameAssembler`]:
+//!
+//! ```ignore
+//! for packet in wrap_report(&OutputReport::from(subcommand)) {
+//!     write(&packet)?;
+//! }
+//! let mut assembler = FrameAssembler::new();
+//! loop {
+//!     let packet = read()?;
+//!     match assembler.push(&packet) {
+//!         Ok(Some(report)) => handle(report),
+//!         Ok(None) => {}
+//!         Err(FrameError::NotAFrame) => handle_plain(&packet),
+//!         Err(e) => eprintln!("{}", e),
+//!     }
+//! }
+//! ```
+
+use crate::{common::*, InputReport, OutputReport};
+use std::{fmt, mem::size_of};
+
+pub const USB_PACKET_SIZE: usize = 64;
+/// First byte of the packets sent to the USB bridge.
+pub const USB_COMMAND: u8 = 0x80;
+/// First byte of the packets received from the USB bridge.
+pub const USB_REPLY: u8 = 0x81;
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum UsbCommand {
+    /// Replied with the controller type and MAC address.
+    Status = 0x01,
+    Handshake = 0x02,
+    /// Switch the UART between the bridge and the controller to 3Mbit/s.
+    HighSpeed = 0x03,
+    /// Keep using USB instead of Bluetooth, without timeout.
+    ForceUsb = 0x04,
+    /// Let the controller fall back to Bluetooth.
+    DisableForceUsb = 0x05,
+    Reset = 0x06,
+    /// Report after a [`FrameHeader`].
+    Wrapped = 0x92,
+}
+
+impl UsbCommand {
+    /// Packet sending the command, `Wrapped` being built by [`wrap_report`].
+    pub fn packet(self) -> [u8; USB_PACKET_SIZE] {
+        let mut packet = [0; USB_PACKET_SIZE];
+        packet[0] = USB_COMMAND;
+        packet[1] = self as u8;
+        packet
+    }
+}
+
+/// Start of a [`UsbCommand::Wrapped`] packet, followed by the report.
+#[repr(packed)]
+#[cfg_attr(feature = "repr-c", repr(C))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FrameHeader {
+    /// [`USB_COMMAND`] or [`USB_REPLY`].
+    direction: u8,
+    command: RawId<UsbCommand>,
+    /// Big endian.
+    len: [u8; 2],
+    /// Zero, then two bytes varying between the packets of a capture.
+    _unknown: [u8; 4],
+}
+
+unsafe impl Wire for FrameHeader {}
+
+impl FrameHeader {
+    pub const SIZE: usize = size_of::<FrameHeader>();
+
+    fn new(direction: u8, payload: &[u8]) -> FrameHeader {
+        FrameHeader {
+            direction,
+            command: UsbCommand::Wrapped.into(),
+            len: (payload.len() as u16).to_be_bytes(),
+            _unknown: [0; 4],
+        }
+    }
+
+    /// Length of the payload, the report.
+    pub fn payload_len(&self) -> usize {
+        u16::from_be_bytes(self.len) as usize
+    }
+}
+
+/// Largest report fitting in a frame.
+pub const MAX_FRAME_LEN: usize = USB_PACKET_SIZE - FrameHeader::SIZE;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameError {
+    /// The packet isn't a frame, it's a plain report or a command reply.
+    NotAFrame,
+    /// The report doesn't fit in a single packet, given to
+    /// [`unwrap_report`], or is larger than any input report.
+    TooLong(usize),
+    /// The payload isn't a valid input report.
+    InvalidReport,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::NotAFrame => f.write_str("packet outside of a USB frame"),
+            FrameError::TooLong(len) => write!(f, "USB frame of {} bytes is too long", len),
+            FrameError::InvalidReport => f.write_str("USB frame with an invalid report"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// Packets carrying `payload` after a [`FrameHeader`], the part not fitting
+/// in the first packet continuing in the next ones.
+fn split_frame(direction: u8, payload: &[u8]) -> Vec<[u8; USB_PACKET_SIZE]> {
+    let (first, rest) = payload.split_at(payload.len().min(MAX_FRAME_LEN));
+    let mut packet = [0; USB_PACKET_SIZE];
+    packet[..FrameHeader::SIZE].copy_from_slice(&FrameHeader::new(direction, payload).to_bytes());
+    packet[FrameHeader::SIZE..][..first.len()].copy_from_slice(first);
+    let mut packets = vec![packet];
+    for chunk in rest.chunks(USB_PACKET_SIZE) {
+        let mut packet = [0; USB_PACKET_SIZE];
+        packet[..chunk.len()].copy_from_slice(chunk);
+        packets.push(packet);
+    }
+    packets
+}
+
+/// Packets wrapping `report` in a [`UsbCommand::Wrapped`] frame.
+pub fn wrap_report(report: &OutputReport) -> Vec<[u8; USB_PACKET_SIZE]> {
+    split_frame(USB_COMMAND, report.as_bytes())
+}
+
+fn frame_header(packet: &[u8]) -> Result<FrameHeader, FrameError> {
+    packet
+        .get(..FrameHeader::SIZE)
+        .and_then(FrameHeader::from_bytes)
+        .filter(|h| h.direction == USB_REPLY && h.command == UsbCommand::Wrapped)
+        .ok_or(FrameError::NotAFrame)
+}
+
+/// Input report of a received [`UsbCommand::Wrapped`] packet, the frame
+/// fitting in it.
+pub fn unwrap_report(packet: &[u8]) -> Result<InputReport, FrameError> {
+    let header = frame_header(packet)?;
+    let payload = &packet[FrameHeader::SIZE..];
+    if header.payload_len() > payload.len() {
+        return Err(FrameError::TooLong(header.payload_len()));
+    }
+    InputReport::from_slice(&payload[..header.payload_len()]).ok_or(FrameError::InvalidReport)
+}
+
+/// Reassembly of the [`UsbCommand::Wrapped`] frames spanning several
+/// packets.
+#[derive(Copy, Clone, Debug)]
+pub struct FrameAssembler {
+    report: InputReport,
+    /// Length of the frame being reassembled, 0 between two frames.
+    len: usize,
+    received: usize,
+}
+
+impl FrameAssembler {
+    pub fn new() -> FrameAssembler {
+        FrameAssembler {
+            report: InputReport::new(),
+            len: 0,
+            received: 0,
+        }
+    }
+
+    /// Drop the frame being reassembled, for example after a reconnection.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.received = 0;
+    }
+
+    /// Handle a received packet, returning the report once its frame is
+    /// complete.
+    ///
+    /// A packet is taken as the continuation of the current frame until it
+    /// is complete, whatever its first bytes.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<InputReport>, FrameError> {
+        let data = if self.received < self.len {
+            packet
+        } else {
+            let header = frame_header(packet)?;
+            let len = header.payload_len();
+            if len > self.report.as_bytes_mut().len() {
+                return Err(FrameError::TooLong(len));
+            }
+            self.len = len;
+            self.received = 0;
+            &packet[FrameHeader::SIZE..]
+        };
+        let data = &data[..data.len().min(self.len - self.received)];
+        self.report.as_bytes_mut()[self.received..][..data.len()].copy_from_slice(data);
+        self.received += data.len();
+        if self.received < self.len {
+            return Ok(None);
+        }
+        let len = self.len;
+        self.reset();
+        InputReport::from_slice(&self.report.as_bytes_mut()[..len])
+            .map(Some)
+            .ok_or(FrameError::InvalidReport)
+    }
+}
+
+impl Default for FrameAssembler {
+    fn default() -> Self {
+        FrameAssembler::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn usb_frames() {
+    use crate::output::SubcommandRequest;
+
+    let request = OutputReport::from(SubcommandRequest::from(crate::common::HCIState::Disconnect));
+    let packets = wrap_report(&request);
+    assert_eq!(packets.len(), 1);
+    let packet = packets[0];
+    assert_eq!(packet[..8], [0x80, 0x92, 0x00, 0x31, 0, 0, 0, 0]);
+    assert_eq!(packet[8..8 + 0x31], *request.as_bytes());
+
+    let mut report = InputReport::new();
+    report.as_bytes_mut()[0] = 0x30;
+    report.as_bytes_mut()[48] = 0x42;
+    let payload = &report.as_bytes()[..49];
+    let mut packet = FrameHeader::new(USB_REPLY, payload).to_bytes();
+    packet.extend_from_slice(payload);
+    assert_eq!(unwrap_report(&packet).unwrap().as_bytes()[48], 0x42);
+
+    // Plain reports are left alone.
+    assert_eq!(
+        unwrap_report(&[0x30; 64]).unwrap_err(),
+        FrameError::NotAFrame
+    );
+    packet[3] = 0xff;
+    assert_eq!(
+        unwrap_report(&packet).unwrap_err(),
+        FrameError::TooLong(0xff)
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn usb_mcu_frames() {
+    let mut report = InputReport::new();
+    let raw = report.as_bytes_mut();
+    raw[0] = 0x31;
+    for (i, b) in raw[1..362].iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let packets = split_frame(USB_REPLY, &report.as_bytes()[..362]);
+    // 56 bytes in the first packet, then 4 full packets and 50 bytes.
+    assert_eq!(packets.len(), 6);
+    assert_eq!(packets[0][..4], [0x81, 0x92, 0x01, 0x6a]);
+    assert_eq!(
+        unwrap_report(&packets[0]).unwrap_err(),
+        FrameError::TooLong(362)
+    );
+
+    let mut assembler = FrameAssembler::new();
+    for packet in &packets[..5] {
+        assert!(assembler.push(packet).unwrap().is_none());
+    }
+    let assembled = assembler.push(&packets[5]).unwrap().unwrap();
+    assert_eq!(assembled.as_bytes(), &report.as_bytes()[..362]);
+
+    // Back to single packet frames and plain reports.
+    let mut small = FrameHeader::new(USB_REPLY, &[0; 49]).to_bytes();
+    small.extend_from_slice(&report.as_bytes()[..49]);
+    small[8] = 0x30;
+    assert!(assembler.push(&small).unwrap().is_some());
+    assert_eq!(
+        assembler.push(&[0x30; 64]).unwrap_err(),
+        FrameError::NotAFrame
+    );
+}
@@ -0,0 +1,125 @@
+//! JavaScript bindings for WebHID applications, enabled by the `wasm-bindgen`
+//! feature.
+//!
+//! Byte slices are exchanged as `Uint8Array`:
+//!
+//! ```js
+//! device.addEventListener("inputreport", (event) => {
+//!     // WebHID strips the report id from the data.
+//!     const data = new Uint8Array(event.data.byteLength + 1);
+//!     data[0] = event.reportId;
+//!     data.set(new Uint8Array(event.data.buffer), 1);
+//!     const state = joycon.parse_input_report(data);
+//! });
+//! const report = joycon.set_player_lights(counter++, 0b0001);
+//! await device.sendReport(report[0], report.subarray(1));
+//! ```
+
+use crate::{
+    bindings::{FlatInputError, FlatInputState},
+    imu::IMUMode,
+    light::PlayerLights,
+    output::{RumbleData, RumbleSide, SubcommandRequest, SubcommandRequestEnum},
+    InputReportId, OutputReport, RawId,
+};
+use wasm_bindgen::prelude::*;
+
+/// Decoded standard input report.
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug)]
+pub struct InputState {
+    pub report_id: u8,
+    pub timer: u8,
+    /// 0 (empty) to 4 (full).
+    pub battery_level: u8,
+    pub charging: bool,
+    /// 0: Bluetooth, 1: USB, 2: attached to the console rail.
+    pub connection: u8,
+    /// Pressed buttons, bit `n` being the button of index `n` in `Button::ALL`.
+    pub buttons: u32,
+    /// Raw 12-bit stick values, to be calibrated.
+    pub left_stick_x: u16,
+    pub left_stick_y: u16,
+    pub right_stick_x: u16,
+    pub right_stick_y: u16,
+}
+
+/// Parse a raw input report, report id included.
+#[wasm_bindgen]
+pub fn parse_input_report(data: &[u8]) -> Result<InputState, JsError> {
+    let state = FlatInputState::parse(data).map_err(|e| match e {
+        FlatInputError::Invalid => JsError::new("invalid report"),
+        FlatInputError::NotStandard => JsError::new("not a standard input report"),
+    })?;
+    Ok(InputState {
+        report_id: state.report_id,
+        timer: state.timer,
+        battery_level: state.battery_code(),
+        charging: state.charging,
+        connection: state.connection_code(),
+        buttons: state.buttons.bits(),
+        left_stick_x: state.left_stick.0,
+        left_stick_y: state.left_stick.1,
+        right_stick_x: state.right_stick.0,
+        right_stick_y: state.right_stick.1,
+    })
+}
+
+fn subcmd_bytes(subcmd: impl Into<SubcommandRequest>, packet_counter: u8) -> Vec<u8> {
+    let mut report = OutputReport::from(subcmd.into());
+    *report.packet_counter() = packet_counter;
+    report.as_bytes().to_vec()
+}
+
+/// Rumble-only report playing the same vibration on both sides.
+#[wasm_bindgen]
+pub fn rumble(
+    packet_counter: u8,
+    high_freq: f32,
+    high_amp: f32,
+    low_freq: f32,
+    low_amp: f32,
+) -> Vec<u8> {
+    let side = RumbleSide::from_freq(high_freq, high_amp, low_freq, low_amp);
+    let mut report = OutputReport::set_rumble(RumbleData {
+        left: side,
+        right: side,
+    });
+    *report.packet_counter() = packet_counter;
+    report.as_bytes().to_vec()
+}
+
+#[wasm_bindgen]
+pub fn set_input_report_mode(packet_counter: u8, mode: u8) -> Result<Vec<u8>, JsError> {
+    let mode = RawId::<InputReportId>::new(mode)
+        .try_into()
+        .ok_or_else(|| JsError::new("unknown input report mode"))?;
+    Ok(subcmd_bytes(
+        SubcommandRequestEnum::SetInputReportMode(mode.into()),
+        packet_counter,
+    ))
+}
+
+/// Bits 0 to 3 turn on the lights, bits 4 to 7 make them blink.
+#[wasm_bindgen]
+pub fn set_player_lights(packet_counter: u8, lights: u8) -> Vec<u8> {
+    subcmd_bytes(PlayerLights::from_bits(lights), packet_counter)
+}
+
+#[wasm_bindgen]
+pub fn enable_imu(packet_counter: u8, enabled: bool) -> Vec<u8> {
+    let mode = if enabled {
+        IMUMode::GyroAccel
+    } else {
+        IMUMode::Disabled
+    };
+    subcmd_bytes(
+        SubcommandRequestEnum::SetIMUMode(mode.into()),
+        packet_counter,
+    )
+}
+
+#[wasm_bindgen]
+pub fn request_device_info(packet_counter: u8) -> Vec<u8> {
+    subcmd_bytes(SubcommandRequestEnum::RequestDeviceInfo(()), packet_counter)
+}
@@ -9,7 +9,7 @@ use joycon_sys::spi::*;
 use joycon_sys::*;
 use joycon_sys::{imu::IMUMode, mcu::ir::*};
 use joycon_sys::{input::*, light};
-use tracing::{field::debug, instrument, trace, Span};
+use tracing::{field::debug, instrument, trace, warn, Span};
 
 const WAIT_TIMEOUT: u32 = 200;
 
@@ -47,7 +47,11 @@ impl JoyCon {
             JOYCON_L_BT => WhichController::LeftJoyCon,
             JOYCON_R_BT => WhichController::RightJoyCon,
             PRO_CONTROLLER => WhichController::ProController,
-            JOYCON_CHARGING_GRIP => panic!("unsupported charging grip"),
+            JOYCON_CHARGING_GRIP => {
+                grip::GripSlot::from_interface(info.interface_number(), info.usage_page())
+                    .context("unknown charging grip interface")?
+                    .which_controller()
+            }
             _ => panic!("unknown controller type"),
         };
         let mut joycon = JoyCon {
@@ -93,7 +97,20 @@ impl JoyCon {
     pub fn recv(&mut self) -> Result<InputReport> {
         let mut report = InputReport::new();
         let nb_read = self.device.read(report.as_bytes_mut())?;
-        assert!(nb_read >= report.len(), "{} < {}", nb_read, report.len());
+        let raw = &report.as_bytes_mut()[..nb_read];
+        ensure!(!raw.is_empty(), "empty report");
+        if let Err(warning) = check_reply_len(raw) {
+            warn!("{}", warning);
+        }
+        // Reports of unknown size are kept whatever their length.
+        if let Some(len) = report_len(raw[0]) {
+            ensure!(
+                nb_read >= len,
+                "truncated report: {} < {} bytes",
+                nb_read,
+                len
+            );
+        }
         Span::current()
             .record("special", &report.is_special())
             .record("report", &debug(report));
@@ -173,6 +190,15 @@ impl JoyCon {
         Ok(*reply.device_info().unwrap())
     }
 
+    /// Gather the model, color and serial number of the controller.
+    #[instrument(level = "info", skip(self), err)]
+    pub fn identity(&mut self) -> Result<identity::ControllerIdentity> {
+        let info = self.get_dev_info()?;
+        let color = self.read_spi::<ControllerColor>().ok();
+        let serial_number = self.read_spi::<SerialNumber>().ok();
+        Ok(identity::ControllerIdentity::new(&info, color, serial_number))
+    }
+
     #[instrument(level = "info", skip(self), err)]
     pub fn set_home_light(&mut self, home_light: light::HomeLight) -> Result<()> {
         self.call_subcmd_wait(home_light)?;
@@ -185,6 +211,23 @@ impl JoyCon {
         Ok(())
     }
 
+    /// Put the controller to sleep, or reboot it to reconnect or pair.
+    ///
+    /// The controller disconnects without replying, so this doesn't wait
+    /// for an acknowledgement.
+    #[instrument(level = "info", skip(self), err)]
+    pub fn set_hci_state(&mut self, hci_state: HCIState) -> Result<()> {
+        self.send(&mut OutputReport::from(SubcommandRequest::from(hci_state)))?;
+        Ok(())
+    }
+
+    /// Erase the pairing information of the controller.
+    #[instrument(level = "info", skip(self), err)]
+    pub fn reset_pairing_info(&mut self, confirm: ConfirmErase) -> Result<()> {
+        self.call_subcmd_wait(SubcommandRequest::erase_pairing_info(confirm))?;
+        Ok(())
+    }
+
     #[instrument(level = "info", skip(self), err)]
     fn set_report_mode_standard(&mut self) -> Result<()> {
         self.call_subcmd_wait(SubcommandRequestEnum::SetInputReportMode(
@@ -230,6 +273,17 @@ impl JoyCon {
         Ok(result.raw())
     }
 
+    /// Read a blob larger than a single SPI read.
+    #[instrument(level = "info", skip(self), err)]
+    pub fn read_spi_block(&mut self, block: SpiBlock) -> Result<SpiBlockData> {
+        let mut read = MultiRangeRead::new(block);
+        while let Some(request) = read.next_request() {
+            let reply = self.call_subcmd_wait(request)?;
+            read.push(reply.spi_read_result().unwrap())?;
+        }
+        Ok(read.finish().unwrap())
+    }
+
     #[instrument(level = "info", skip(self), err)]
     pub fn write_spi<S: SPI + Into<SPIWriteRequest> + std::fmt::Debug>(
         &mut self,
@@ -241,6 +295,11 @@ impl JoyCon {
 
     #[instrument(level = "info", skip(self), err)]
     pub unsafe fn write_spi_raw(&mut self, range: SPIRange, data: &[u8]) -> Result<bool> {
+        ensure!(
+            regions::is_writable(range),
+            "refusing to write outside of the writable SPI regions: {:?}",
+            range
+        );
         let reply = self.call_subcmd_wait(SPIWriteRequest::new(range, data))?;
         Ok(reply.is_spi_write_success().unwrap())
     }
@@ -296,7 +355,7 @@ impl JoyCon {
 
     #[instrument(level = "info", skip(self), err)]
     fn set_mcu_mode_ir(&mut self) -> Result<()> {
-        self.call_subcmd_wait(MCUCommand::set_mcu_mode(MCUMode::IR))?;
+        self.call_subcmd_wait(MCUConfig::ir())?;
         self.wait_mcu_status(MCUMode::IR)
             .context("set_mcu_mode_ir")?;
         self.enable_ir_loop = true;
@@ -314,12 +373,10 @@ impl JoyCon {
                 false
             }
         })?;
-        let mcu_cmd = MCUCommand::configure_ir_ir(MCUIRModeData {
-            ir_mode: ir_mode.into(),
-            no_of_frags: frags,
-            mcu_fw_version,
-        });
-        self.call_subcmd_wait(mcu_cmd)?;
+        let config = IRConfig::new(ir_mode)
+            .fragments(frags)
+            .mcu_fw_version(mcu_fw_version.0, mcu_fw_version.1);
+        self.call_subcmd_wait(config)?;
 
         self.wait_mcu_cond(IRRequestEnum::GetState(()), |r| {
             r.ir_status()
@@ -404,12 +461,9 @@ impl JoyCon {
                 false
             }
         })?;
-        let mcu_cmd = MCUCommand::configure_ir_ir(MCUIRModeData {
-            ir_mode: MCUIRMode::IRSensorReset.into(),
-            no_of_frags: 0,
-            mcu_fw_version,
-        });
-        self.call_subcmd_wait(mcu_cmd)?;
+        let config = IRConfig::new(MCUIRMode::IRSensorReset)
+            .mcu_fw_version(mcu_fw_version.0, mcu_fw_version.1);
+        self.call_subcmd_wait(config)?;
 
         self.wait_mcu_cond(IRRequestEnum::GetState(()), |r| {
             r.ir_status()
@@ -484,6 +538,17 @@ impl JoyCon {
         self.accel_sens = accel_sens;*/
         Ok(())
     }
+
+    /// Write the IMU registers of a predefined profile.
+    #[instrument(level = "info", skip(self), err)]
+    pub fn set_imu_profile(&mut self, profile: imu::IMUProfile) -> Result<()> {
+        for subcmd in profile.subcommands() {
+            self.call_subcmd_wait(subcmd)?;
+        }
+        let (gyro_sens, accel_sens) = profile.sensitivity();
+        self.imu_handler.set_sensitivity(gyro_sens, accel_sens);
+        Ok(())
+    }
 }
 
 /// Ringcon handling
@@ -492,16 +557,12 @@ impl JoyCon {
     pub fn enable_ringcon(&mut self) -> Result<()> {
         self.call_subcmd_wait(SubcommandRequestEnum::SetMCUState(MCUMode::Standby.into()))?;
         loop {
-            let out = self.call_subcmd_wait(MCUCommand::set_mcu_mode(MCUMode::MaybeRingcon))?;
+            let out = self.call_subcmd_wait(MCUConfig::Mode(MCUMode::MaybeRingcon))?;
             if out.mcu_report().unwrap().state_report().unwrap().state == MCUMode::MaybeRingcon {
                 break;
             }
         }
-        self.call_subcmd_wait(MCUCommand::configure_mcu_ir(MCUIRModeData {
-            ir_mode: MCUIRMode::IRSensorSleep.into(),
-            no_of_frags: 0,
-            mcu_fw_version: (0.into(), 0.into()),
-        }))?;
+        self.call_subcmd_wait(MCUConfig::Ringcon { enable: true })?;
         self.call_subcmd_wait(SubcommandRequest::subcmd_0x59())?;
         self.call_subcmd_wait(SubcommandRequestEnum::SetIMUMode(
             IMUMode::MaybeRingcon.into(),
@@ -518,11 +579,7 @@ impl JoyCon {
             IMUMode::_Unknown0x02.into(),
         ))?;
         self.call_subcmd_wait(SubcommandRequest::subcmd_0x5c_0())?;
-        self.call_subcmd_wait(MCUCommand::configure_mcu_ir(MCUIRModeData {
-            ir_mode: MCUIRMode::IRSensorReset.into(),
-            no_of_frags: 0,
-            mcu_fw_version: (0.into(), 0.into()),
-        }))?;
+        self.call_subcmd_wait(MCUConfig::Ringcon { enable: false })?;
         Ok(())
     }
 
@@ -56,6 +56,12 @@ impl Handler {
         self.user_calibration = calib;
     }
 
+    /// Full scale currently configured on the IMU.
+    pub fn set_sensitivity(&mut self, gyro_sens: imu::GyroSens, accel_sens: imu::AccSens) {
+        self.gyro_sens = gyro_sens;
+        self.accel_sens = accel_sens;
+    }
+
     fn acc_calib(&self) -> Vector3<f64> {
         self.user_calibration
             .acc_offset()
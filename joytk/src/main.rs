@@ -11,8 +11,8 @@ use joycon::{
         mcu::ir::Resolution,
         output::OutputReportEnum,
         spi::{
-            ControllerColor, SPIRange, SensorCalibration, SticksCalibration, UserSensorCalibration,
-            UserSticksCalibration,
+            regions, ControllerColor, SensorCalibration, SpiBlock, SticksCalibration,
+            UserSensorCalibration, UserSticksCalibration,
         },
         InputReport, OutputReport, HID_IDS, NINTENDO_VENDOR_ID,
     },
@@ -171,23 +171,18 @@ fn dump(joycon: &mut JoyCon) -> Result<()> {
         .write(true)
         .truncate(true)
         .open("/tmp/out.raw")?;
-    let mut offset = 0;
-    let size = 0x1D;
-    // TODO: why value
+    let (_, total) = SpiBlock::Full.segments()[0];
     let mut last_percent = 0;
-    while offset + (size as u32) < 0x80000 {
-        let percent = offset * 100 / 0x80000;
+    for range in SpiBlock::Full.ranges() {
+        let percent = range.0 * 100 / total;
         if last_percent != percent {
-            println!("{}%", percent);
+            let region = regions::region_at(range.0).map_or("unknown", |r| r.name);
+            println!("{}% ({})", percent, region);
             last_percent = percent;
         }
-        let slice = joycon.read_spi_raw(unsafe { SPIRange::new(offset, size) })?;
-        out.write(&slice)?;
-        offset += size as u32;
+        let slice = joycon.read_spi_raw(range)?;
+        out.write_all(&slice[..range.1 as usize])?;
     }
-    let last_size = (0x80000 - offset) as u8;
-    let slice = joycon.read_spi_raw(unsafe { SPIRange::new(offset, last_size) })?;
-    out.write(&slice)?;
     Ok(())
 }
 